@@ -26,12 +26,13 @@ fn main() {
                 variables: vec![country, target, count],
                 plan: Box::new(Plan::Join(Join {
                     variables: vec![e],
-                    left_plan: Box::new(Plan::MatchA(e, "country".to_string(), country)),
-                    right_plan: Box::new(Plan::MatchA(e, "target".to_string(), target)),
+                    left_plan: Box::new(Plan::MatchA(e, "country".into(), country)),
+                    right_plan: Box::new(Plan::MatchA(e, "target".into(), target)),
                 })),
                 aggregation_fns: vec![AggregationFn::COUNT],
                 key_variables: vec![country, target],
                 with_variables: vec![],
+                count_empty: false,
             }),
         }];
 
@@ -26,12 +26,12 @@ fn main() {
                 variables: vec![e],
                 left_plan: Box::new(Plan::MatchAV(
                     e,
-                    "target".to_string(),
+                    "target".into(),
                     Value::String("Russian".to_string()),
                 )),
                 right_plan: Box::new(Plan::MatchAV(
                     e,
-                    "guess".to_string(),
+                    "guess".into(),
                     Value::String("Russian".to_string()),
                 )),
             }),
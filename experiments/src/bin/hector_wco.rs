@@ -58,7 +58,7 @@ fn main() {
                     graph
                         .edges(index)
                         .iter()
-                        .map(|y| TxData(1, index as u64, "edge".to_string(), Eid(*y as u64)))
+                        .map(|y| TxData(1, index as u64, "edge".into(), Eid(*y as u64)))
                         .collect(),
                     0,
                     0,
@@ -0,0 +1,58 @@
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{AttributeConfig, InputSemantics, TxData, Value};
+
+/// Resident set size of this process in pages, as reported by Linux.
+fn resident_pages() -> u64 {
+    let statm = std::fs::read_to_string("/proc/self/statm").unwrap();
+    statm.split_whitespace().nth(1).unwrap().parse().unwrap()
+}
+
+fn main() {
+    let datoms = std::env::args().nth(1).unwrap().parse::<u64>().unwrap();
+    let attributes = std::env::args().nth(2).unwrap().parse::<u64>().unwrap();
+
+    timely::execute_from_args(std::env::args().skip(2), move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for a in 0..attributes {
+                server
+                    .context
+                    .internal
+                    .create_attribute(
+                        &format!(":attribute/{}", a),
+                        AttributeConfig::tx_time(InputSemantics::Raw),
+                        scope,
+                    )
+                    .unwrap();
+            }
+        });
+
+        let before = resident_pages();
+
+        let tx_data: Vec<TxData> = (0..datoms)
+            .map(|e| {
+                TxData(
+                    1,
+                    e,
+                    format!(":attribute/{}", e % attributes).into(),
+                    Value::Number(e as i64),
+                )
+            })
+            .collect();
+
+        server.transact(tx_data, 0, 0).unwrap();
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let after = resident_pages();
+
+        println!(
+            "indexed {} datoms over {} attributes (forward + reverse): {} pages resident",
+            datoms,
+            attributes,
+            after - before
+        );
+    })
+    .unwrap();
+}
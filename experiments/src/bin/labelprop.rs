@@ -26,10 +26,10 @@ fn main() {
                 plan: Plan::Union(Union {
                     variables: vec![x, y],
                     plans: vec![
-                        Plan::MatchA(x, ":node".to_string(), y),
+                        Plan::MatchA(x, ":node".into(), y),
                         Plan::Join(Join {
                             variables: vec![z],
-                            left_plan: Box::new(Plan::MatchA(z, ":edge".to_string(), y)),
+                            left_plan: Box::new(Plan::MatchA(z, ":edge".into(), y)),
                             right_plan: Box::new(Plan::NameExpr(vec![x, z], "label".to_string())),
                         }),
                     ],
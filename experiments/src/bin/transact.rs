@@ -0,0 +1,64 @@
+use std::time::Instant;
+
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{AttributeConfig, InputSemantics, TxData, Value};
+
+fn main() {
+    let datoms = std::env::args().nth(1).unwrap().parse::<u64>().unwrap();
+    let attributes = std::env::args().nth(2).unwrap().parse::<u64>().unwrap();
+    let rounds = std::env::args().nth(3).unwrap().parse::<u64>().unwrap();
+
+    timely::execute_from_args(std::env::args().skip(3), move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for a in 0..attributes {
+                server
+                    .context
+                    .internal
+                    .create_attribute(
+                        &format!(":attribute/{}", a),
+                        AttributeConfig::tx_time(InputSemantics::Raw),
+                        scope,
+                    )
+                    .unwrap();
+            }
+        });
+
+        // Interleave attributes, which is the worst case for per-datom
+        // input lookups.
+        let batch: Vec<TxData> = (0..datoms)
+            .map(|e| {
+                TxData(
+                    1,
+                    e,
+                    format!(":attribute/{}", e % attributes).into(),
+                    Value::Number(e as i64),
+                )
+            })
+            .collect();
+
+        let mut elapsed = std::time::Duration::from_secs(0);
+
+        for round in 0..rounds {
+            let tx_data = batch.clone();
+
+            let timer = Instant::now();
+            server.transact(tx_data, 0, 0).unwrap();
+            elapsed += timer.elapsed();
+
+            server.advance_domain(None, round + 1).unwrap();
+            worker.step_while(|| server.is_any_outdated());
+        }
+
+        println!(
+            "transacted {} datoms over {} attributes, {} rounds: {:?} total, {:?} per round",
+            datoms,
+            attributes,
+            rounds,
+            elapsed,
+            elapsed / rounds as u32
+        );
+    })
+    .unwrap();
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "jemalloc")]
 #[global_allocator]
 static ALLOCATOR: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
@@ -20,9 +21,11 @@ extern crate env_logger;
 extern crate abomonation_derive;
 extern crate abomonation;
 
-use std::collections::{HashSet, VecDeque};
-use std::io::BufRead;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use std::{thread, usize};
 
@@ -30,18 +33,22 @@ use getopts::Options;
 
 use timely::dataflow::channels::pact::{Exchange, Pipeline};
 use timely::dataflow::operators::generic::OutputHandle;
-use timely::dataflow::operators::{Operator, Probe};
+use timely::dataflow::operators::{Inspect, Operator, Probe};
+use timely::dataflow::ProbeHandle;
 use timely::synchronization::Sequencer;
 
-use mio::net::TcpListener;
+use differential_dataflow::operators::Consolidate;
+
+use mio::net::{TcpListener, TcpStream};
 use mio::*;
 
 use slab::Slab;
 
 use ws::connection::{ConnEvent, Connection};
 
-use declarative_dataflow::server::{Config, CreateAttribute, Request, Server, TxId};
-use declarative_dataflow::{Error, ImplContext, ResultDiff};
+use declarative_dataflow::plan::{graphql_to_json, order_nested, GraphQl};
+use declarative_dataflow::server::{debug_name, Affinity, Config, Delivery, Format, Request, Server, TxId};
+use declarative_dataflow::{Eid, Error, ErrorKind, ImplContext, ResultDiff, TxData, Value};
 
 /// Server timestamp type.
 #[cfg(not(feature = "real-time"))]
@@ -56,6 +63,16 @@ const RESULTS: Token = Token(usize::MAX - 2);
 const ERRORS: Token = Token(usize::MAX - 3);
 const SYSTEM: Token = Token(usize::MAX - 4);
 const CLI: Token = Token(usize::MAX - 5);
+const SNAPSHOTS: Token = Token(usize::MAX - 6);
+const ACKS: Token = Token(usize::MAX - 7);
+const GRAPHQL: Token = Token(usize::MAX - 8);
+const DEBUG: Token = Token(usize::MAX - 9);
+const HANDOFF: Token = Token(usize::MAX - 10);
+const HEALTH: Token = Token(usize::MAX - 11);
+
+/// How long the server probe may lag behind without advancing,
+/// before liveness probes are answered as unhealthy.
+const HEALTH_STALL: Duration = Duration::from_secs(30);
 
 /// A mutation of server state.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize, Debug)]
@@ -70,11 +87,252 @@ pub struct Command {
     pub requests: Vec<Request>,
 }
 
+/// An interest dataflow that has to catch up with the domain before
+/// its deadline, or else be torn down.
+struct Deadline {
+    /// The worker holding the interested client's connection.
+    owner: usize,
+    /// The interested client.
+    token: Token,
+    /// The name of the relation of interest.
+    name: String,
+    /// Probe on the results of the dataflow.
+    probe: ProbeHandle<T>,
+    /// The domain time as of the interest, which results have to
+    /// move past.
+    at: T,
+    /// The instant by which they have to.
+    deadline: Instant,
+    /// Sequence number of the interest, for reporting the timeout.
+    last_tx: TxId,
+    /// Whether the interest has already been dropped.
+    expired: bool,
+}
+
+/// Results channels of all workers in this process, by worker
+/// index. Used to hand serialized results directly to the worker
+/// owning the interested connection.
+type ResultSenders = Arc<Mutex<HashMap<usize, mio::channel::Sender<(String, Frame)>>>>;
+
+/// The workers in this process that may own client connections.
+#[derive(Default)]
+struct LocalWorkers {
+    /// Channels for handing accepted connections to a worker, by
+    /// worker index.
+    handoffs: HashMap<usize, mio::channel::Sender<(TcpStream, SocketAddr)>>,
+    /// Number of open connections owned by each worker.
+    loads: HashMap<usize, usize>,
+    /// Number of connections dealt so far, for round-robin affinity.
+    dealt: usize,
+}
+
+/// Chooses the worker that will own a connection accepted by the
+/// given worker, and accounts for it.
+fn choose_owner(workers: &Mutex<LocalWorkers>, affinity: Affinity, accepting: usize) -> usize {
+    let mut workers = workers.lock().expect("local workers poisoned");
+
+    let owner = match affinity {
+        Affinity::Accepting => accepting,
+        Affinity::RoundRobin => {
+            let mut indices: Vec<usize> = workers.handoffs.keys().cloned().collect();
+            indices.sort();
+
+            let owner = indices[workers.dealt % indices.len()];
+            workers.dealt += 1;
+
+            owner
+        }
+        Affinity::LeastLoaded => workers
+            .loads
+            .iter()
+            .min_by_key(|&(index, load)| (*load, *index))
+            .map(|(index, _load)| *index)
+            .unwrap_or(accepting),
+    };
+
+    *workers.loads.entry(owner).or_insert(0) += 1;
+
+    owner
+}
+
+/// The consolidated changes to a result set at a single time, as
+/// delivered to clients that asked for deltas.
+#[derive(Serialize)]
+struct Delta<'a> {
+    time: &'a T,
+    added: Vec<&'a Vec<Value>>,
+    removed: Vec<&'a Vec<Value>>,
+}
+
+/// The consolidated net changes to the counts of tuples in a result
+/// set at a single time, as delivered to clients that asked for
+/// counts.
+#[derive(Serialize)]
+struct Counts<'a> {
+    time: &'a T,
+    counts: Vec<(&'a Vec<Value>, isize)>,
+}
+
+/// A marker telling clients that they have seen all results at times
+/// not in advance of the given frontier.
+#[derive(Serialize)]
+struct Progress<'a> {
+    frontier: &'a [T],
+}
+
+/// A serialized message, in the format its recipients asked for.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Frame {
+    /// Serializes the given data in the given format.
+    fn encode<D: serde::Serialize>(format: Format, data: &D) -> Frame {
+        match format {
+            Format::Json => Frame::Text(serde_json::to_string(data).expect("failed to serialize as json")),
+            Format::MessagePack => Frame::Binary(rmp_serde::to_vec_named(data).expect("failed to serialize as msgpack")),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match *self {
+            Frame::Text(ref text) => text.len(),
+            Frame::Binary(ref bytes) => bytes.len(),
+        }
+    }
+
+    fn into_message(self) -> ws::Message {
+        match self {
+            Frame::Text(text) => ws::Message::text(text),
+            Frame::Binary(bytes) => ws::Message::binary(bytes),
+        }
+    }
+}
+
+/// Sends a serialized message to the specified client, or prints it
+/// if the request was issued by the system itself.
+fn send_frame(client: usize, frame: &Frame, connections: &mut Slab<Connection>, poll: &Poll) {
+    if client == SYSTEM.0 {
+        match *frame {
+            Frame::Text(ref text) => println!("{}", text),
+            Frame::Binary(ref bytes) => println!("{:?}", bytes),
+        }
+    } else if let Some(conn) = connections.get_mut(client) {
+        conn.send_message(frame.clone().into_message())
+            .expect("failed to send message");
+
+        poll.reregister(
+            conn.socket(),
+            conn.token(),
+            conn.events(),
+            PollOpt::edge() | PollOpt::oneshot(),
+        ).unwrap();
+    }
+}
+
+/// Accounts for another `size` bytes queued for the given token,
+/// returning true iff it thereby fell further behind than allowed.
+/// Only open connections are accounted for, as nothing is ever
+/// written on behalf of the system or of replayed clients.
+fn falls_behind(
+    pending_bytes: &mut HashMap<Token, usize>,
+    connections: &Slab<Connection>,
+    token: Token,
+    size: usize,
+    max_pending_bytes: Option<usize>,
+) -> bool {
+    if !connections.contains(token.into()) {
+        return false;
+    }
+
+    let pending = pending_bytes.entry(token).or_insert(0);
+    *pending += size;
+
+    max_pending_bytes.map(|max| *pending > max).unwrap_or(false)
+}
+
+/// Returns the format results of the named interest are serialized
+/// in, JSON if no client asked for anything else.
+fn format_of(deliveries: &HashMap<String, (Delivery, Format)>, name: &str) -> Format {
+    deliveries.get(name).map(|(_delivery, format)| *format).unwrap_or_default()
+}
+
+/// Sends a reply of the form (tag, payload) to the specified client,
+/// or prints it if the request was issued by the system itself.
+fn reply<P: serde::Serialize>(client: usize, reply: &(&str, P), connections: &mut Slab<Connection>, poll: &Poll) {
+    send_frame(client, &Frame::encode(Format::Json, reply), connections, poll);
+}
+
+/// Serializes consolidated results into one counts message per time.
+fn serialize_counts(name: &str, results: &mut [ResultDiff<T>], format: Format) -> Vec<Frame> {
+    results.sort_by(|x, y| x.1.cmp(&y.1));
+
+    let mut messages = Vec::new();
+    let mut start = 0;
+    while start < results.len() {
+        let time = &results[start].1;
+        let end = start + results[start..].iter().take_while(|x| &x.1 == time).count();
+
+        let counts = Counts {
+            time,
+            counts: results[start..end].iter().map(|(tuple, _time, diff)| (tuple, *diff)).collect(),
+        };
+
+        messages.push(Frame::encode::<(&str, Counts)>(format, &(name, counts)));
+
+        start = end;
+    }
+
+    messages
+}
+
+/// Serializes consolidated results into one delta message per time.
+fn serialize_deltas(name: &str, results: &mut [ResultDiff<T>], format: Format) -> Vec<Frame> {
+    results.sort_by(|x, y| x.1.cmp(&y.1));
+
+    let mut messages = Vec::new();
+    let mut start = 0;
+    while start < results.len() {
+        let time = &results[start].1;
+        let end = start + results[start..].iter().take_while(|x| &x.1 == time).count();
+
+        let mut delta = Delta {
+            time,
+            added: Vec::new(),
+            removed: Vec::new(),
+        };
+
+        for (tuple, _time, diff) in results[start..end].iter() {
+            let changes = if *diff > 0 { &mut delta.added } else { &mut delta.removed };
+            for _ in 0..diff.abs() {
+                changes.push(tuple);
+            }
+        }
+
+        messages.push(Frame::encode::<(&str, Delta)>(format, &(name, delta)));
+
+        start = end;
+    }
+
+    messages
+}
+
 fn main() {
     env_logger::init();
 
     let mut opts = Options::new();
     opts.optopt("", "port", "server port", "PORT");
+    opts.optopt(
+        "",
+        "max-pending-bytes",
+        "result bytes a connection may fall behind before its interests are dropped",
+        "BYTES",
+    );
+    opts.optopt("", "record", "append all sequenced commands to a log", "FILE");
+    opts.optopt("", "replay", "replay commands from a log, instead of accepting connections", "FILE");
+    opts.optopt("", "eid-offset", "first entity id handed out by AllocateEids", "EID");
     opts.optflag(
         "",
         "manual-advance",
@@ -82,12 +340,23 @@ fn main() {
     );
     opts.optflag("", "enable-cli", "enable the CLI interface");
     opts.optflag("", "enable-history", "enable historical queries");
-    opts.optflag("", "enable-optimizer", "enable WCO queries");
+    opts.optflag("", "enable-optimizer", "enable WCO queries and cost-based join ordering");
     opts.optflag("", "enable-meta", "enable queries on the query graph");
+    opts.optflag("", "intern-aids", "intern attribute identifiers in value position");
+    opts.optopt("", "health-port", "port answering liveness probes", "PORT");
+    opts.optopt(
+        "",
+        "affinity",
+        "which worker owns a new connection: accepting, round-robin, or least-loaded",
+        "POLICY",
+    );
 
     let args: Vec<String> = std::env::args().collect();
     let timely_args = std::env::args().take_while(|ref arg| *arg != "--");
 
+    let result_senders: ResultSenders = Arc::new(Mutex::new(HashMap::new()));
+    let local_workers: Arc<Mutex<LocalWorkers>> = Arc::new(Mutex::new(Default::default()));
+
     timely::execute_from_args(timely_args, move |worker| {
         // read configuration
         let server_args = args.iter().rev().take_while(|arg| *arg != "--");
@@ -106,6 +375,25 @@ fn main() {
                     enable_cli: matches.opt_present("enable-cli"),
                     enable_optimizer: matches.opt_present("enable-optimizer"),
                     enable_meta: matches.opt_present("enable-meta"),
+                    enable_history: matches.opt_present("enable-history"),
+                    max_pending_bytes: matches
+                        .opt_str("max-pending-bytes")
+                        .map(|x| x.parse().expect("invalid max-pending-bytes")),
+                    record: matches.opt_str("record"),
+                    replay: matches.opt_str("replay"),
+                    peers: worker.peers(),
+                    eid_offset: matches
+                        .opt_str("eid-offset")
+                        .map(|x| x.parse().expect("invalid eid-offset"))
+                        .unwrap_or(default_config.eid_offset),
+                    intern_aids: matches.opt_present("intern-aids"),
+                    affinity: matches
+                        .opt_str("affinity")
+                        .map(|x| x.parse().expect("invalid affinity"))
+                        .unwrap_or(default_config.affinity),
+                    health_port: matches
+                        .opt_str("health-port")
+                        .map(|x| x.parse::<u16>().expect("invalid health-port") + (worker.index() as u16)),
                 }
             }
         };
@@ -117,16 +405,49 @@ fn main() {
         // setting-up built-in arrangements. We serialize those here
         // and pre-load the sequencer with them, such that they will
         // flow through the regular request handling.
-        let builtins = Server::<T, Token>::builtins();
+        let builtins = Server::<T, Token>::builtins(&config);
         let preload_command = Command {
             owner: worker.index(),
             client: SYSTEM.0,
             requests: builtins,
         };
 
+        // A replayed log already contains the built-in commands, as
+        // they were sequenced originally. All workers read the full
+        // log, s.t. they agree on the sequence without coordination.
+        let mut replay: Option<VecDeque<(TxId, Command)>> = config.replay.as_ref().map(|path| {
+            let log = File::open(path).expect("failed to open replay log");
+
+            BufReader::new(log)
+                .lines()
+                .map(|line| {
+                    let line = line.expect("failed to read replay log");
+                    serde_json::from_str::<(TxId, Command)>(&line).expect("malformed replay log")
+                })
+                .collect()
+        });
+
+        let preloaded = match replay {
+            None => VecDeque::from(vec![preload_command]),
+            Some(_) => VecDeque::new(),
+        };
+
         // setup serialized command queue (shared between all workers)
         let mut sequencer: Sequencer<Command> =
-            Sequencer::preloaded(worker, Instant::now(), VecDeque::from(vec![preload_command]));
+            Sequencer::preloaded(worker, Instant::now(), preloaded);
+
+        // All workers see the same sequence, so one of them suffices
+        // to record it.
+        let mut record = match config.record {
+            Some(ref path) if worker.index() == 0 => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .expect("failed to open record log"),
+            ),
+            _ => None,
+        };
 
         // configure websocket server
         let ws_settings = ws::Settings {
@@ -138,16 +459,46 @@ fn main() {
         let (send_cli, recv_cli) = mio::channel::channel();
 
         // setup results channel
-        let (send_results, recv_results) = mio::channel::channel::<(String, Vec<ResultDiff<T>>)>();
+        let (send_results, recv_results) = mio::channel::channel::<(String, Frame)>();
+        result_senders
+            .lock()
+            .expect("result senders poisoned")
+            .insert(worker.index(), send_results.clone());
+
+        // setup connection handoff channel
+        let (send_handoff, recv_handoff) = mio::channel::channel::<(TcpStream, SocketAddr)>();
+        {
+            let mut local_workers = local_workers.lock().expect("local workers poisoned");
+            local_workers.handoffs.insert(worker.index(), send_handoff);
+            local_workers.loads.entry(worker.index()).or_insert(0);
+        }
 
         // setup errors channel
         let (send_errors, recv_errors) = mio::channel::channel::<(Vec<Token>, Vec<(Error, TxId)>)>();
 
+        // setup transaction acknowledgements channel
+        let (send_acks, recv_acks) = mio::channel::channel::<(Vec<Token>, Vec<(TxId, T, Vec<(Eid, Eid)>)>)>();
+
+        // setup snapshots channel
+        let (send_snapshots, recv_snapshots) = mio::channel::channel::<(Token, String, Vec<ResultDiff<T>>)>();
+
+        // setup GraphQL results channel
+        let (send_graphql, recv_graphql) = mio::channel::channel::<(String, serde_json::Value)>();
+
+        // setup debugging output channel
+        let (send_debug, recv_debug) = mio::channel::channel::<(String, Vec<ResultDiff<T>>)>();
+
         // setup server socket
         // let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), config.port);
         let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0,0,0,0)), config.port);
         let server_socket = TcpListener::bind(&addr).unwrap();
         let mut connections = Slab::with_capacity(ws_settings.max_connections);
+
+        // setup health socket
+        let health_socket = config.health_port.map(|port| {
+            let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0,0,0,0)), port);
+            TcpListener::bind(&addr).unwrap()
+        });
         let mut next_connection_id: u32 = 0;
 
         // setup event loop
@@ -188,8 +539,63 @@ fn main() {
             PollOpt::edge() | PollOpt::oneshot(),
         ).unwrap();
 
-        poll.register(&server_socket, SERVER, Ready::readable(), PollOpt::level())
-            .unwrap();
+        poll.register(
+            &recv_acks,
+            ACKS,
+            Ready::readable(),
+            PollOpt::edge() | PollOpt::oneshot(),
+        ).unwrap();
+
+        poll.register(
+            &recv_snapshots,
+            SNAPSHOTS,
+            Ready::readable(),
+            PollOpt::edge() | PollOpt::oneshot(),
+        ).unwrap();
+
+        poll.register(
+            &recv_graphql,
+            GRAPHQL,
+            Ready::readable(),
+            PollOpt::edge() | PollOpt::oneshot(),
+        ).unwrap();
+
+        poll.register(
+            &recv_debug,
+            DEBUG,
+            Ready::readable(),
+            PollOpt::edge() | PollOpt::oneshot(),
+        ).unwrap();
+
+        poll.register(
+            &recv_handoff,
+            HANDOFF,
+            Ready::readable(),
+            PollOpt::edge() | PollOpt::oneshot(),
+        ).unwrap();
+
+        if replay.is_none() {
+            poll.register(&server_socket, SERVER, Ready::readable(), PollOpt::level())
+                .unwrap();
+        }
+
+        if let Some(ref health_socket) = health_socket {
+            poll.register(health_socket, HEALTH, Ready::readable(), PollOpt::level())
+                .unwrap();
+        }
+
+        // The probe frontier as of the last time it was seen
+        // advancing, or not lagging behind at all.
+        let mut last_frontier: Vec<T> = Vec::new();
+        let mut last_progress = Instant::now();
+
+        // Connections this worker has been chosen to own, but has not
+        // opened yet.
+        let mut incoming: Vec<(TcpStream, SocketAddr)> = Vec::new();
+
+        // Snapshots of materialized relations this worker owes its
+        // clients, waiting for the materialization to catch up.
+        let mut pending_snapshots: Vec<(Token, String, T, TxId)> = Vec::new();
 
         info!(
             "[WORKER {}] running with config {:?}",
@@ -200,6 +606,21 @@ fn main() {
         // Sequence counter for commands.
         let mut next_tx: TxId = 0;
 
+        // Delivery forms of all active interest dataflows, and the
+        // workers owning them.
+        let mut deliveries: HashMap<String, (Delivery, Format)> = HashMap::new();
+        let mut owners: HashMap<String, usize> = HashMap::new();
+
+        // Result bytes not yet written to each connection, and the
+        // interests dropped because a connection fell too far behind.
+        let mut pending_bytes: HashMap<Token, usize> = HashMap::new();
+        let mut dropped: HashSet<(Token, String)> = HashSet::new();
+
+        // Interest dataflows that have yet to catch up with the domain
+        // before their deadline. Every worker tracks them, s.t. none
+        // of them keeps stepping a runaway dataflow indefinitely.
+        let mut deadlines: Vec<Deadline> = Vec::new();
+
         let mut shutdown = false;
 
         while !shutdown {
@@ -236,7 +657,7 @@ fn main() {
                             match serde_json::from_str::<Vec<Request>>(&cli_input) {
                                 Err(serde_error) => {
                                     let error = Error {
-                                        category: "df.error.category/incorrect",
+                                        category: ErrorKind::Incorrect,
                                         message: serde_error.to_string(),
                                     };
 
@@ -275,42 +696,61 @@ fn main() {
                                         addr
                                     );
 
-                                    // @TODO to nagle or not to nagle?
-                                    // sock.set_nodelay(true)
+                                    let owner = choose_owner(&local_workers, config.affinity, worker.index());
 
-                                    let token = {
-                                        let entry = connections.vacant_entry();
-                                        let token = Token(entry.key());
-                                        let connection_id = next_connection_id;
-                                        next_connection_id = next_connection_id.wrapping_add(1);
+                                    if owner == worker.index() {
+                                        incoming.push((socket, addr));
+                                    } else {
+                                        let handoff = local_workers
+                                            .lock()
+                                            .expect("local workers poisoned")
+                                            .handoffs[&owner]
+                                            .clone();
 
-                                        entry.insert(Connection::new(
-                                            token,
-                                            socket,
-                                            ws_settings,
-                                            connection_id,
-                                        ));
-
-                                        token
-                                    };
-
-                                    let conn = &mut connections[token.into()];
-
-                                    conn.as_server().unwrap();
-
-                                    poll.register(
-                                        conn.socket(),
-                                        conn.token(),
-                                        conn.events(),
-                                        PollOpt::edge() | PollOpt::oneshot(),
-                                    ).unwrap();
+                                        handoff.send((socket, addr)).unwrap();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    HEALTH => {
+                        if let Some(ref health_socket) = health_socket {
+                            while let Ok((mut socket, _addr)) = health_socket.accept() {
+                                let response = if last_progress.elapsed() < HEALTH_STALL {
+                                    "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK"
+                                } else {
+                                    "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 7\r\nConnection: close\r\n\r\nSTALLED"
+                                };
+
+                                // Plain TCP probes only connect, so we respond
+                                // without waiting for a request.
+                                if let Err(err) = socket.write_all(response.as_bytes()) {
+                                    debug!("[WORKER {}] error while answering health probe: {}", worker.index(), err);
                                 }
                             }
                         }
                     }
+                    HANDOFF => {
+                        while let Ok((socket, addr)) = recv_handoff.try_recv() {
+                            info!(
+                                "[WORKER {}] took over tcp connection from {}",
+                                worker.index(),
+                                addr
+                            );
+
+                            incoming.push((socket, addr));
+                        }
+
+                        poll.reregister(
+                            &recv_handoff,
+                            HANDOFF,
+                            Ready::readable(),
+                            PollOpt::edge() | PollOpt::oneshot(),
+                        ).unwrap();
+                    }
                     RESULTS => {
-                        while let Ok((query_name, results)) = recv_results.try_recv() {
-                            info!("[WORKER {}] {} {} results", worker.index(), query_name, results.len());
+                        while let Ok((query_name, serialized)) = recv_results.try_recv() {
+                            info!("[WORKER {}] {} results", worker.index(), query_name);
 
                             match server.interests.get(&query_name) {
                                 None => {
@@ -318,24 +758,49 @@ fn main() {
                                     warn!("NO INTEREST FOR THIS RESULT");
                                 }
                                 Some(tokens) => {
-                                    let serialized = serde_json::to_string::<(String, Vec<ResultDiff<T>>)>(
-                                        &(query_name, results),
-                                    ).expect("failed to serialize outputs");
-                                    let msg = ws::Message::text(serialized);
+                                    let size = serialized.len();
+                                    let msg = serialized.into_message();
 
                                     for &token in tokens.iter() {
-                                        // @TODO check whether connection still exists
-                                        let conn = &mut connections[token.into()];
-
-                                        conn.send_message(msg.clone())
-                                            .expect("failed to send message");
-
-                                        poll.reregister(
-                                            conn.socket(),
-                                            conn.token(),
-                                            conn.events(),
-                                            PollOpt::edge() | PollOpt::oneshot(),
-                                        ).unwrap();
+                                        if dropped.contains(&(token, query_name.clone())) {
+                                            continue;
+                                        }
+
+                                        if falls_behind(&mut pending_bytes, &connections, token, size, config.max_pending_bytes) {
+                                            // Rather than buffering indefinitely for a
+                                            // slow client, we drop its interest.
+                                            warn!("[WORKER {}] {:?} fell behind on {}", worker.index(), token, query_name);
+
+                                            dropped.insert((token, query_name.clone()));
+
+                                            let error = Error {
+                                                category: ErrorKind::Fault,
+                                                message: format!("Too many pending results, dropping interest in {}", query_name),
+                                            };
+
+                                            send_errors.send((vec![token], vec![(error, next_tx - 1)])).unwrap();
+
+                                            sequencer.push(Command {
+                                                owner: worker.index(),
+                                                client: token.into(),
+                                                requests: vec![Request::Uninterest(query_name.clone())],
+                                            });
+
+                                            continue;
+                                        }
+
+                                        // Replayed commands have no connection.
+                                        if let Some(conn) = connections.get_mut(token.into()) {
+                                            conn.send_message(msg.clone())
+                                                .expect("failed to send message");
+
+                                            poll.reregister(
+                                                conn.socket(),
+                                                conn.token(),
+                                                conn.events(),
+                                                PollOpt::edge() | PollOpt::oneshot(),
+                                            ).unwrap();
+                                        }
                                     }
                                 }
                             }
@@ -366,18 +831,18 @@ fn main() {
                             let msg = ws::Message::text(serialized);
 
                             for &token in tokens.iter() {
-                                // @TODO check whether connection still exists
-                                let conn = &mut connections[token.into()];
-
-                                conn.send_message(msg.clone())
-                                    .expect("failed to send message");
+                                // Replayed commands have no connection.
+                                if let Some(conn) = connections.get_mut(token.into()) {
+                                    conn.send_message(msg.clone())
+                                        .expect("failed to send message");
 
-                                poll.reregister(
-                                    conn.socket(),
-                                    conn.token(),
-                                    conn.events(),
-                                    PollOpt::edge() | PollOpt::oneshot(),
-                                ).unwrap();
+                                    poll.reregister(
+                                        conn.socket(),
+                                        conn.token(),
+                                        conn.events(),
+                                        PollOpt::edge() | PollOpt::oneshot(),
+                                    ).unwrap();
+                                }
                             }
                         }
 
@@ -388,6 +853,145 @@ fn main() {
                             PollOpt::edge() | PollOpt::oneshot(),
                         ).unwrap();
                     }
+                    ACKS => {
+                        while let Ok((tokens, acks)) = recv_acks.try_recv() {
+                            trace!("[WORKER {}] acknowledging {:?}", worker.index(), acks);
+
+                            let frame = Frame::encode::<(&str, Vec<(TxId, T, Vec<(Eid, Eid)>)>)>(
+                                Format::Json,
+                                &("df.tx/ack", acks),
+                            );
+
+                            for &token in tokens.iter() {
+                                send_frame(token.into(), &frame, &mut connections, &poll);
+                            }
+                        }
+
+                        poll.reregister(
+                            &recv_acks,
+                            ACKS,
+                            Ready::readable(),
+                            PollOpt::edge() | PollOpt::oneshot(),
+                        ).unwrap();
+                    }
+                    SNAPSHOTS => {
+                        while let Ok((token, name, results)) = recv_snapshots.try_recv() {
+                            info!("[WORKER {}] {} snapshot of {} tuples", worker.index(), name, results.len());
+
+                            reply(token.into(), &(name.as_str(), results), &mut connections, &poll);
+                        }
+
+                        poll.reregister(
+                            &recv_snapshots,
+                            SNAPSHOTS,
+                            Ready::readable(),
+                            PollOpt::edge() | PollOpt::oneshot(),
+                        ).unwrap();
+                    }
+                    GRAPHQL => {
+                        while let Ok((query_name, nested)) = recv_graphql.try_recv() {
+                            info!("[WORKER {}] {} GraphQL results", worker.index(), query_name);
+
+                            match server.interests.get(&query_name) {
+                                None => {
+                                    warn!("NO INTEREST FOR THIS RESULT");
+                                }
+                                Some(tokens) => {
+                                    let frame = Frame::encode::<(&str, serde_json::Value)>(
+                                        format_of(&deliveries, &query_name),
+                                        &(query_name.as_str(), nested),
+                                    );
+
+                                    for &token in tokens.iter() {
+                                        if dropped.contains(&(token, query_name.clone())) {
+                                            continue;
+                                        }
+
+                                        if falls_behind(&mut pending_bytes, &connections, token, frame.len(), config.max_pending_bytes) {
+                                            warn!("[WORKER {}] {:?} fell behind on {}", worker.index(), token, query_name);
+
+                                            dropped.insert((token, query_name.clone()));
+
+                                            let error = Error {
+                                                category: ErrorKind::Fault,
+                                                message: format!("Too many pending results, dropping interest in {}", query_name),
+                                            };
+
+                                            send_errors.send((vec![token], vec![(error, next_tx - 1)])).unwrap();
+
+                                            sequencer.push(Command {
+                                                owner: worker.index(),
+                                                client: token.into(),
+                                                requests: vec![Request::Uninterest(query_name.clone())],
+                                            });
+
+                                            continue;
+                                        }
+
+                                        send_frame(token.into(), &frame, &mut connections, &poll);
+                                    }
+                                }
+                            }
+                        }
+
+                        poll.reregister(
+                            &recv_graphql,
+                            GRAPHQL,
+                            Ready::readable(),
+                            PollOpt::edge() | PollOpt::oneshot(),
+                        ).unwrap();
+                    }
+                    DEBUG => {
+                        while let Ok((name, updates)) = recv_debug.try_recv() {
+                            trace!("[WORKER {}] {} debugging output", worker.index(), name);
+
+                            let debug_name = debug_name(&name);
+                            if let Some(tokens) = server.interests.get(&debug_name) {
+                                // Debugging output is sent in the same format as
+                                // results of the relation itself.
+                                let frame = Frame::encode::<(&str, Vec<ResultDiff<T>>)>(
+                                    format_of(&deliveries, &name),
+                                    &(debug_name.as_str(), updates),
+                                );
+
+                                for &token in tokens.iter() {
+                                    if dropped.contains(&(token, debug_name.clone())) {
+                                        continue;
+                                    }
+
+                                    if falls_behind(&mut pending_bytes, &connections, token, frame.len(), config.max_pending_bytes) {
+                                        warn!("[WORKER {}] {:?} fell behind on {}", worker.index(), token, debug_name);
+
+                                        dropped.insert((token, debug_name.clone()));
+
+                                        let error = Error {
+                                            category: ErrorKind::Fault,
+                                            message: format!("Too many pending updates, no longer debugging {}", name),
+                                        };
+
+                                        send_errors.send((vec![token], vec![(error, next_tx - 1)])).unwrap();
+
+                                        sequencer.push(Command {
+                                            owner: worker.index(),
+                                            client: token.into(),
+                                            requests: vec![Request::Undebug(name.clone())],
+                                        });
+
+                                        continue;
+                                    }
+
+                                    send_frame(token.into(), &frame, &mut connections, &poll);
+                                }
+                            }
+                        }
+
+                        poll.reregister(
+                            &recv_debug,
+                            DEBUG,
+                            Ready::readable(),
+                            PollOpt::edge() | PollOpt::oneshot(),
+                        ).unwrap();
+                    }
                     _ => {
                         let token = event.token();
                         let active = {
@@ -418,7 +1022,7 @@ fn main() {
                                                             match serde_json::from_str::<Vec<Request>>(&string) {
                                                                 Err(serde_error) => {
                                                                     let error = Error {
-                                                                        category: "df.error.category/incorrect",
+                                                                        category: ErrorKind::Incorrect,
                                                                         message: serde_error.to_string(),
                                                                     };
 
@@ -439,7 +1043,7 @@ fn main() {
                                                             match rmp_serde::decode::from_slice::<Vec<Request>>(&bytes) {
                                                                 Err(rmp_error) => {
                                                                     let error = Error {
-                                                                        category: "df.error.category/incorrect",
+                                                                        category: ErrorKind::Incorrect,
                                                                         message: rmp_error.to_string(),
                                                                     };
 
@@ -482,6 +1086,11 @@ fn main() {
                                 }
                             }
 
+                            // once everything is written, nothing is pending anymore
+                            if !connections[token.into()].events().is_writable() {
+                                pending_bytes.remove(&token);
+                            }
+
                             // connection events may have changed
                             connections[token.into()].events().is_readable()
                                 || connections[token.into()].events().is_writable()
@@ -497,6 +1106,15 @@ fn main() {
                                 trace!("WebSocket connection to token={:?} disconnected.", token);
                             }
                             connections.remove(token.into());
+
+                            if let Some(load) = local_workers
+                                .lock()
+                                .expect("local workers poisoned")
+                                .loads
+                                .get_mut(&worker.index())
+                            {
+                                *load -= 1;
+                            }
                         } else {
                             let conn = &connections[token.into()];
                             poll.reregister(
@@ -510,9 +1128,76 @@ fn main() {
                 }
             }
 
+            // open connections owned by this worker
+            for (socket, addr) in incoming.drain(..) {
+                trace!("[WORKER {}] opening connection from {}", worker.index(), addr);
+
+                // @TODO to nagle or not to nagle?
+                // sock.set_nodelay(true)
+
+                let token = {
+                    let entry = connections.vacant_entry();
+                    let token = Token(entry.key());
+                    let connection_id = next_connection_id;
+                    next_connection_id = next_connection_id.wrapping_add(1);
+
+                    entry.insert(Connection::new(
+                        token,
+                        socket,
+                        ws_settings,
+                        connection_id,
+                    ));
+
+                    token
+                };
+
+                let conn = &mut connections[token.into()];
+
+                conn.as_server().unwrap();
+
+                poll.register(
+                    conn.socket(),
+                    conn.token(),
+                    conn.events(),
+                    PollOpt::edge() | PollOpt::oneshot(),
+                ).unwrap();
+            }
+
             // handle commands
 
-            while let Some(mut command) = sequencer.next() {
+            loop {
+                let mut command = match replay {
+                    None => match sequencer.next() {
+                        None => break,
+                        Some(command) => command,
+                    },
+                    Some(ref mut log) => match log.pop_front() {
+                        None => {
+                            info!("[WORKER {}] replay complete", worker.index());
+
+                            if let Err(error) = server.shutdown() {
+                                error!("[WORKER {}] {:?}", worker.index(), error);
+                            }
+
+                            shutdown = true;
+                            break;
+                        }
+                        Some((tx, command)) => {
+                            if tx != next_tx {
+                                warn!("[WORKER {}] replaying {} as {}", worker.index(), tx, next_tx);
+                            }
+
+                            command
+                        }
+                    },
+                };
+
+                if let Some(ref mut log) = record {
+                    let serialized = serde_json::to_string::<(TxId, &Command)>(&(next_tx, &command))
+                        .expect("failed to serialize command");
+
+                    writeln!(log, "{}", serialized).expect("failed to record command");
+                }
 
                 // Count-up sequence numbers.
                 next_tx += 1;
@@ -523,17 +1208,102 @@ fn main() {
                 let client = command.client;
                 let last_tx = next_tx - 1;
 
+                // Acknowledgements for all transactions in this command.
+                let mut acks = Vec::new();
+
                 for req in command.requests.drain(..) {
 
+                    if let Err(error) = server.authorize(owner, &Token(client), &req) {
+                        if owner == worker.index() {
+                            send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                        }
+
+                        continue;
+                    }
+
                     // @TODO only create a single dataflow, but only if req != Transact
 
                     match req {
                         Request::Transact(req) => {
-                            if let Err(error) = server.transact(req, owner, worker.index()) {
-                                send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                            // Inputs are introduced at the current time of the
+                            // domains they are written to.
+                            let tx_time = server.tx_time(req.iter().map(|TxData(_op, _e, a, _v)| a.as_str()));
+
+                            match server.transact(req, owner, worker.index()) {
+                                Err(error) => {
+                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                }
+                                Ok(tempids) => {
+                                    if owner == worker.index() {
+                                        acks.push((last_tx, tx_time, tempids));
+                                    }
+                                }
+                            }
+                        }
+                        Request::TransactBatch(batch) => {
+                            for (tx_time, req) in batch.into_iter() {
+                                let tx_time: T = tx_time.into();
+
+                                match server.transact_at(tx_time.clone(), req, owner, worker.index()) {
+                                    Err(error) => {
+                                        send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                        break;
+                                    }
+                                    Ok(tempids) => {
+                                        if owner == worker.index() {
+                                            acks.push((last_tx, tx_time, tempids));
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        Request::RetractCurrent(req) => {
+                            // Retractions are introduced at the current time of
+                            // the domains they are written to.
+                            let tx_time = server.tx_time(req.iter().map(|(_e, a)| a.as_str()));
+
+                            match server.retract_current(req, owner, worker.index()) {
+                                Err(error) => {
+                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                }
+                                Ok(()) => {
+                                    if owner == worker.index() {
+                                        acks.push((last_tx, tx_time, Vec::new()));
+                                    }
+                                }
+                            }
+                        }
+                        Request::RetractEntities(req) => {
+                            // Retractions are introduced into all domains, at
+                            // their respective current times.
+                            let attributes = server.context.attributes();
+                            let tx_time = server.tx_time(attributes.iter().map(|a| a.as_str()));
+
+                            server.retract_entities(req, owner, worker.index());
+
+                            if owner == worker.index() {
+                                acks.push((last_tx, tx_time, Vec::new()));
                             }
                         }
                         Request::Interest(req) => {
+                            let conflicting = deliveries
+                                .get(&req.name)
+                                .map(|delivery| *delivery != (req.delivery, req.format))
+                                .unwrap_or(false);
+
+                            if conflicting {
+                                if owner == worker.index() {
+                                    let error = Error {
+                                        category: ErrorKind::Conflict,
+                                        message: format!("{} is already delivered as {:?}", req.name, deliveries[&req.name]),
+                                    };
+
+                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                }
+
+                                continue;
+                            }
+
                             // All workers keep track of every client's interests, s.t. they
                             // know when to clean up unused dataflows.
 
@@ -546,33 +1316,128 @@ fn main() {
                             if server.context.global_arrangement(&req.name).is_none() {
 
                                 let send_results_handle = send_results.clone();
+                                let result_senders = result_senders.clone();
+                                let delivery = req.delivery;
+                                let format = req.format;
+
+                                deliveries.insert(req.name.clone(), (delivery, format));
+                                owners.insert(req.name.clone(), owner);
+
+                                let worker_index = worker.index();
+
+                                // The deadline covers implementing the dataflow
+                                // as well as catching up with the domain.
+                                let started = Instant::now();
+                                let at = server.relation_time(&req.name);
 
                                 worker.dataflow::<T, _, _>(|scope| {
                                     let name = req.name.clone();
+                                    let remote_name = req.name.clone();
 
                                     match server.interest(&req.name, scope) {
                                         Err(error) => {
                                             send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
                                         }
                                         Ok(relation) => {
-                                            relation
-                                                .inner
-                                                .unary_notify(
-                                                    Exchange::new(move |_| owner as u64),
-                                                    "ResultsRecv",
-                                                    vec![],
-                                                    move |input, _output: &mut OutputHandle<_, (), _>, _notificator| {
+                                            let relation = match req.timeout_ms {
+                                                None => relation,
+                                                Some(timeout_ms) => {
+                                                    let mut probe = ProbeHandle::new();
+                                                    let relation = relation.probe_with(&mut probe);
+
+                                                    deadlines.push(Deadline {
+                                                        owner,
+                                                        token: Token(client),
+                                                        name: req.name.clone(),
+                                                        probe,
+                                                        at,
+                                                        deadline: started + Duration::from_millis(timeout_ms),
+                                                        last_tx,
+                                                        expired: false,
+                                                    });
+
+                                                    relation
+                                                }
+                                            };
+
+                                            // deltas are consolidated per time, which
+                                            // requires all updates to a tuple to meet
+                                            let results = match delivery {
+                                                Delivery::Raw => relation.inner,
+                                                Delivery::Delta | Delivery::Counts => relation.consolidate().inner,
+                                            };
+
+                                            let mut vector = Vec::new();
+                                            let mut messages = Vec::new();
+                                            let mut frontier = vec![T::default()];
+
+                                            results
+                                                .unary(Pipeline, "ResultsSerialize", move |_cap, _info| {
+                                                    move |input, output| {
+
+                                                        // results are serialized on the worker that
+                                                        // produced them, s.t. no single worker has
+                                                        // to serialize everything
+
+                                                        input.for_each(|time, data| {
+                                                            data.swap(&mut vector);
+
+                                                            let messages = match delivery {
+                                                                Delivery::Raw => vec![
+                                                                    Frame::encode::<(&str, &Vec<ResultDiff<T>>)>(format, &(&name, &vector))
+                                                                ],
+                                                                Delivery::Delta => serialize_deltas(&name, &mut vector, format),
+                                                                Delivery::Counts => serialize_counts(&name, &mut vector, format),
+                                                            };
+
+                                                            vector.clear();
+
+                                                            // owners within this process are reached directly,
+                                                            // all others via the exchange below
+                                                            match result_senders.lock().expect("result senders poisoned").get(&owner) {
+                                                                Some(sender) => {
+                                                                    for serialized in messages.into_iter() {
+                                                                        sender.send((name.clone(), serialized)).unwrap();
+                                                                    }
+                                                                }
+                                                                None => output.session(&time).give_iterator(messages.into_iter()),
+                                                            }
+                                                        });
+                                                    }
+                                                })
+                                                .probe_with(&mut server.probe)
+                                                .sink(Exchange::new(move |_| owner as u64), "ResultsRecv", move |input| {
+                                                    input.for_each(|_time, data| {
+                                                        data.swap(&mut messages);
+
+                                                        for serialized in messages.drain(..) {
+                                                            send_results_handle
+                                                                .send((remote_name.clone(), serialized))
+                                                                .unwrap();
+                                                        }
+                                                    });
+
+                                                    // results from all workers pass through here
+                                                    // or have been handed over already, so once
+                                                    // the frontier moves the owner can tell its
+                                                    // clients which times are complete
+                                                    if worker_index == owner {
+                                                        let current = input.frontier().frontier().to_vec();
+
+                                                        if current != frontier {
+                                                            frontier = current;
 
-                                                        // due to the exchange pact, this closure is only
-                                                        // executed by the owning worker
+                                                            let serialized = Frame::encode::<(&str, Progress)>(
+                                                                format,
+                                                                &(&remote_name, Progress { frontier: &frontier }),
+                                                            );
 
-                                                        input.for_each(|_time, data| {
                                                             send_results_handle
-                                                                .send((name.clone(), data.to_vec()))
+                                                                .send((remote_name.clone(), serialized))
                                                                 .unwrap();
-                                                        });
-                                                    })
-                                                .probe_with(&mut server.probe);
+                                                        }
+                                                    }
+                                                });
                                         }
                                     }
                                 });
@@ -582,49 +1447,277 @@ fn main() {
                             // All workers keep track of every client's interests, s.t. they
                             // know when to clean up unused dataflows.
                             let client_token = Token(command.client);
+                            if owner == worker.index() {
+                                dropped.remove(&(client_token, name.clone()));
+                            }
+
+                            deadlines.retain(|deadline| deadline.token != client_token || deadline.name != name);
+
                             if let Some(entry) = server.interests.get_mut(&name) {
                                 entry.remove(&client_token);
 
                                 if entry.is_empty() {
                                     info!("Shutting down {}", name);
                                     server.interests.remove(&name);
-                                    server.shutdown_handles.remove(&name);
+                                    server.uninterest(&name);
+                                    deliveries.remove(&name);
+                                    owners.remove(&name);
+                                }
+                            }
+                        }
+                        Request::Debug(name) => {
+                            // All workers keep track of every client's debugging
+                            // interests, s.t. they know when to clean up.
+                            let debug_name = debug_name(&name);
+                            let already_debugging = server.shutdown_handles.contains_key(&debug_name);
+
+                            server.interests
+                                .entry(debug_name.clone())
+                                .or_insert_with(HashSet::new)
+                                .insert(Token(client));
+
+                            if !already_debugging {
+                                let send_debug_handle = send_debug.clone();
+
+                                worker.dataflow::<T, _, _>(|scope| {
+                                    match server.debug(&name, scope) {
+                                        Err(error) => {
+                                            send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                        }
+                                        Ok(relation) => {
+                                            relation
+                                                .inner
+                                                .inspect_batch(move |time, data| {
+                                                    trace!("[DEBUG] {:?} {:?}", time, data);
+                                                })
+                                                .sink(Exchange::new(move |_| owner as u64), "DebugRecv", move |input| {
+
+                                                    // due to the exchange pact, only the owning
+                                                    // worker will ever see any data
+
+                                                    input.for_each(|_time, data| {
+                                                        send_debug_handle
+                                                            .send((name.clone(), data.to_vec()))
+                                                            .unwrap();
+                                                    });
+                                                });
+                                        }
+                                    }
+                                });
+                            }
+                        }
+                        Request::Undebug(name) => {
+                            let debug_name = debug_name(&name);
+                            if owner == worker.index() {
+                                dropped.remove(&(Token(client), debug_name.clone()));
+                            }
+
+                            if let Some(entry) = server.interests.get_mut(&debug_name) {
+                                entry.remove(&Token(client));
+
+                                if entry.is_empty() {
+                                    info!("Shutting down {}", debug_name);
+                                    server.interests.remove(&debug_name);
+                                    server.uninterest(&debug_name);
                                 }
                             }
                         }
-                        Request::Flow(source, sink) => {
+                        Request::Snapshot(name) if server.materializations.contains_key(&name) => {
+                            // Only the owning worker holds the client's connection.
+                            if owner == worker.index() {
+                                let at = server.relation_time(&name);
+                                pending_snapshots.push((Token(client), name, at, last_tx));
+                            }
+                        }
+                        Request::Snapshot(name) => {
+                            let send_snapshots_handle = send_snapshots.clone();
+                            let worker_index = worker.index();
+
+                            worker.dataflow::<T, _, _>(|scope| {
+                                let at = server.relation_time(&name);
+
+                                match server.snapshot(&name, scope) {
+                                    Err(error) => {
+                                        if owner == worker_index {
+                                            send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                        }
+                                    }
+                                    Ok(snapshot) => {
+                                        let mut buffer = Vec::new();
+                                        let mut sent = false;
+
+                                        snapshot
+                                            .inner
+                                            .sink(Exchange::new(move |_| owner as u64), "SnapshotRecv", move |input| {
+
+                                                // due to the exchange pact, only the owning
+                                                // worker will ever see any data
+
+                                                input.for_each(|_time, data| {
+                                                    buffer.append(&mut data.to_vec());
+                                                });
+
+                                                if !sent && !input.frontier().less_equal(&at) {
+                                                    sent = true;
+
+                                                    if owner == worker_index {
+                                                        send_snapshots_handle
+                                                            .send((Token(client), name.clone(), buffer.drain(..).collect()))
+                                                            .unwrap();
+                                                    }
+                                                }
+                                            });
+                                    }
+                                }
+                            });
+                        }
+                        Request::QueryAt(name, time) => {
+                            let send_snapshots_handle = send_snapshots.clone();
+                            let worker_index = worker.index();
+
+                            worker.dataflow::<T, _, _>(|scope| {
+                                let at: T = time.into();
+
+                                match server.query_at(&name, at.clone(), scope) {
+                                    Err(error) => {
+                                        if owner == worker_index {
+                                            send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                        }
+                                    }
+                                    Ok(relation) => {
+                                        let mut buffer = Vec::new();
+                                        let mut sent = false;
+
+                                        relation
+                                            .inner
+                                            .sink(Exchange::new(move |_| owner as u64), "QueryAtRecv", move |input| {
+
+                                                // due to the exchange pact, only the owning
+                                                // worker will ever see any data
+
+                                                input.for_each(|_time, data| {
+                                                    buffer.append(&mut data.to_vec());
+                                                });
+
+                                                if !sent && !input.frontier().less_equal(&at) {
+                                                    sent = true;
+
+                                                    if owner == worker_index {
+                                                        send_snapshots_handle
+                                                            .send((Token(client), name.clone(), buffer.drain(..).collect()))
+                                                            .unwrap();
+                                                    }
+                                                }
+                                            });
+                                    }
+                                }
+                            });
+                        }
+                        Request::GraphQl(name, query) => {
+                            // All workers keep track of every client's interests, s.t. they
+                            // know when to clean up unused dataflows.
+
+                            let client_token = Token(command.client);
+                            server.interests
+                                .entry(name.clone())
+                                .or_insert_with(HashSet::new)
+                                .insert(client_token);
+                            owners.entry(name.clone()).or_insert(owner);
+
+                            let send_graphql_handle = send_graphql.clone();
+                            let query = GraphQl { query };
+
+                            // results are ordered as requested on serialization,
+                            // invalid queries are reported below
+                            let levels = query.paths().unwrap_or_default();
+
+                            worker.dataflow::<T, _, _>(|scope| {
+                                match server.graphql(&name, query, scope) {
+                                    Err(error) => {
+                                        send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                    }
+                                    Ok(paths) => {
+                                        let mut buffers = std::collections::HashMap::new();
+
+                                        paths
+                                            .inner
+                                            .unary_notify(
+                                                Exchange::new(move |_| owner as u64),
+                                                "GraphQlRecv",
+                                                vec![],
+                                                move |input, _output: &mut OutputHandle<_, (), _>, notificator| {
+
+                                                    // due to the exchange pact, this closure is only
+                                                    // executed by the owning worker
+
+                                                    input.for_each(|cap, data| {
+                                                        buffers
+                                                            .entry(cap.time().clone())
+                                                            .or_insert_with(Vec::new)
+                                                            .append(&mut data.to_vec());
+
+                                                        notificator.notify_at(cap.retain());
+                                                    });
+
+                                                    // only send complete results for each time
+                                                    notificator.for_each(|cap, _count, _notificator| {
+                                                        if let Some(paths) = buffers.remove(cap.time()) {
+                                                            send_graphql_handle
+                                                                .send((name.clone(), order_nested(graphql_to_json(&paths), &levels)))
+                                                                .unwrap();
+                                                        }
+                                                    });
+                                                })
+                                            .probe_with(&mut server.probe);
+                                    }
+                                }
+                            });
+                        }
+                        Request::Flow(source, sinks) => {
                             // @TODO?
                             // We treat sinks as single-use right now.
-                            match server.context.internal.sinks.remove(&sink) {
-                                None => {
-                                    let error = Error {
-                                        category: "df.error.category/not-found",
-                                        message: format!("Unknown sink {}", sink),
-                                    };
-                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                            let mut sink_handles = Vec::new();
+                            for sink in sinks.iter() {
+                                match server.context.internal.sinks.remove(sink) {
+                                    None => {
+                                        // Unknown sinks don't keep the others from
+                                        // being fed.
+                                        let error = Error {
+                                            category: ErrorKind::NotFound,
+                                            message: format!("Unknown sink {}", sink),
+                                        };
+                                        send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                    }
+                                    Some(sink_handle) => sink_handles.push(sink_handle),
                                 }
-                                Some(mut sink_handle) => {
-                                    let server_handle = &mut server;
-                                    let send_errors_handle = &send_errors;
+                            }
 
-                                    worker.dataflow::<T, _, _>(move |scope| {
-                                        match server_handle.interest(&source, scope) {
-                                            Err(error) => {
-                                                send_errors_handle.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
-                                            }
-                                            Ok(relation) => {
-                                                // @TODO Ideally we only ever want to "send" references
-                                                // to local trace batches. 
-                                                relation
-                                                    .inner
-                                                    .sink(Pipeline, "Flow", move |input| {
-                                                        input.for_each(|_time, data| {
-                                                            for (tuple, time, diff) in data.to_vec().drain(..) {
-                                                                sink_handle.update_at(tuple, time, diff);
+                            if !sink_handles.is_empty() {
+                                let server_handle = &mut server;
+                                let send_errors_handle = &send_errors;
+
+                                // All sinks are fed from a single dataflow.
+                                worker.dataflow::<T, _, _>(move |scope| {
+                                    match server_handle.interest(&source, scope) {
+                                        Err(error) => {
+                                            send_errors_handle.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                        }
+                                        Ok(relation) => {
+                                            // @TODO Ideally we only ever want to "send" references
+                                            // to local trace batches. 
+                                            relation
+                                                .inner
+                                                .sink(Pipeline, "Flow", move |input| {
+                                                    input.for_each(|_time, data| {
+                                                        for (tuple, time, diff) in data.to_vec().drain(..) {
+                                                            for sink_handle in sink_handles.iter_mut() {
+                                                                sink_handle.update_at(tuple.clone(), time, diff);
                                                             }
-                                                        });
+                                                        }
+                                                    });
 
-                                                        let frontier = input.frontier().frontier();
+                                                    let frontier = input.frontier().frontier();
+                                                    for sink_handle in sink_handles.iter_mut() {
                                                         if frontier.is_empty() {
                                                             // @TODO
                                                             // sink_handle.close();
@@ -633,11 +1726,11 @@ fn main() {
                                                             sink_handle.advance_to(frontier[0]);
                                                             sink_handle.flush();
                                                         }
-                                                    });
-                                            }
+                                                    }
+                                                });
                                         }
-                                    });
-                                }
+                                    }
+                                });
                             }
                         }
                         Request::Register(req) => {
@@ -652,6 +1745,17 @@ fn main() {
                                 }
                             });
                         }
+                        Request::Materialize(req) => {
+                            let worker_index = worker.index();
+
+                            worker.dataflow::<T, _, _>(|scope| {
+                                if let Err(error) = server.materialize(req, scope) {
+                                    if owner == worker_index {
+                                        send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                    }
+                                }
+                            });
+                        }
                         Request::RegisterSink(req) => {
                             worker.dataflow::<T, _, _>(|scope| {
                                 if let Err(error) = server.register_sink(req, scope) {
@@ -659,9 +1763,9 @@ fn main() {
                                 }
                             });
                         }
-                        Request::CreateAttribute(CreateAttribute { name, config }) => {
+                        Request::CreateAttribute(req) => {
                             worker.dataflow::<T, _, _>(|scope| {
-                                if let Err(error) = server.context.internal.create_attribute(&name, config, scope) {
+                                if let Err(error) = server.create_attribute(req, scope) {
                                     send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
                                 }
                             });
@@ -671,17 +1775,148 @@ fn main() {
                                 send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
                             }
                         }
+                        Request::SetCompaction(name, compaction) => {
+                            if let Err(error) = server.set_compaction(&name, compaction) {
+                                if owner == worker.index() {
+                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                }
+                            }
+                        }
+                        Request::SetIterationLimit(name, limit) => {
+                            if let Err(error) = server.set_iteration_limit(&name, limit) {
+                                if owner == worker.index() {
+                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                }
+                            }
+                        }
+                        Request::FailRule(name) => {
+                            for (interest, tokens) in server.fail_rule(&name) {
+                                info!("Shutting down {}", interest);
+
+                                deliveries.remove(&interest);
+                                deadlines.retain(|deadline| deadline.name != interest);
+
+                                // Only the owner holds the connections of its clients.
+                                if owners.remove(&interest) == Some(worker.index()) {
+                                    let error = Error {
+                                        category: ErrorKind::Fault,
+                                        message: format!("{} depends on rule {}, which exceeded its iteration limit", interest, name),
+                                    };
+
+                                    send_errors.send((tokens.into_iter().collect(), vec![(error, last_tx)])).unwrap();
+                                }
+                            }
+                        }
+                        Request::SetRuleSemantics(name, semantics) => {
+                            if let Err(error) = server.set_rule_semantics(&name, semantics) {
+                                send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                            }
+                        }
+                        Request::CreateAlias(alias, target) => {
+                            if let Err(error) = server.create_alias(&alias, &target) {
+                                send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                            }
+                        }
+                        Request::MigrateAttribute(name, input_semantics) => {
+                            worker.dataflow::<T, _, _>(|scope| {
+                                if let Err(error) = server.migrate_attribute(&name, input_semantics, scope) {
+                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                }
+                            });
+                        }
                         Request::CloseInput(name) => {
-                            if let Err(error) = server.context.internal.close_input(name) {
+                            if let Err(error) = server.close_input(name) {
                                 send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
                             }
                         }
                         Request::Shutdown => {
-                            shutdown = true
+                            if let Err(error) = server.shutdown() {
+                                send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                            }
+
+                            shutdown = true;
+
+                            // Anything after a shutdown request is discarded.
+                            break;
+                        }
+                        Request::Status => {
+                            // Only the owning worker holds the client's connection.
+                            if owner == worker.index() {
+                                reply(client, &("df.status", server.status()), &mut connections, &poll);
+                            }
+                        }
+                        Request::ListRules => {
+                            // Only the owning worker holds the client's connection.
+                            if owner == worker.index() {
+                                reply(client, &("df.rules", server.list_rules()), &mut connections, &poll);
+                            }
+                        }
+                        Request::Dependencies(name) => {
+                            // Only the owning worker holds the client's connection.
+                            if owner == worker.index() {
+                                match server.dependencies(&name) {
+                                    Err(error) => {
+                                        send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                    }
+                                    Ok(closure) => {
+                                        reply(client, &("df.dependencies", closure), &mut connections, &poll);
+                                    }
+                                }
+                            }
+                        }
+                        Request::Metrics => {
+                            // Only the owning worker holds the client's connection.
+                            if owner == worker.index() {
+                                reply(client, &("df.metrics", server.metrics()), &mut connections, &poll);
+                            }
+                        }
+                        Request::Explain(plan) => {
+                            // Only the owning worker holds the client's connection.
+                            if owner == worker.index() {
+                                reply(client, &("df.explain", server.explain(&plan)), &mut connections, &poll);
+                            }
+                        }
+                        Request::Statistics => {
+                            // Every worker computes its statistics, s.t.
+                            // all of them agree on meta transactions.
+                            match server.statistics() {
+                                Err(error) => {
+                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                }
+                                Ok(statistics) => {
+                                    // Only the owning worker holds the client's connection.
+                                    if owner == worker.index() {
+                                        reply(client, &("df.statistics", statistics), &mut connections, &poll);
+                                    }
+                                }
+                            }
+                        }
+                        Request::AllocateEids(count) => {
+                            // Every worker allocates, s.t. all of them
+                            // agree on the next free id.
+                            match server.allocate_eids(count) {
+                                Err(error) => {
+                                    send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                                }
+                                Ok(eids) => {
+                                    // Only the owning worker holds the client's connection.
+                                    if owner == worker.index() {
+                                        reply(client, &("df.eids", eids), &mut connections, &poll);
+                                    }
+                                }
+                            }
                         }
                     }
                 }
 
+                if !acks.is_empty() {
+                    send_acks.send((vec![Token(client)], acks)).unwrap();
+                }
+
+                if shutdown {
+                    break;
+                }
+
                 if !config.manual_advance {
                     #[cfg(not(feature = "real-time"))]
                     let next = next_tx as u64;
@@ -689,8 +1924,12 @@ fn main() {
                     #[cfg(feature = "real-time")]
                     let next = Instant::now().duration_since(worker.timer());
 
-                    if let Err(error) = server.advance_domain(None, next) {
-                        send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                    // Batched transactions might have moved the internal
+                    // domain past the sequence number already.
+                    if next >= *server.context.internal.time() {
+                        if let Err(error) = server.advance_domain(None, next) {
+                            send_errors.send((vec![Token(client)], vec![(error, last_tx)])).unwrap();
+                        }
                     }
                 }
             }
@@ -699,13 +1938,96 @@ fn main() {
             // s.t. the sequencer continues issuing commands
             worker.step();
 
-            worker.step_while(|| server.is_any_outdated());
+            // Stepping stops early once a deadline has passed, s.t.
+            // the timed out dataflow can be torn down. Expired deadlines
+            // are merely waiting for their uninterest to be sequenced and
+            // must not hold up stepping in the meantime.
+            worker.step_while(|| {
+                server.is_any_outdated()
+                    && deadlines
+                        .iter()
+                        .filter(|deadline| !deadline.expired)
+                        .all(|deadline| Instant::now() < deadline.deadline)
+            });
+
+            let now = Instant::now();
+            for deadline in deadlines.iter_mut() {
+                let caught_up = !deadline.probe.less_than(&deadline.at);
+
+                if !deadline.expired && !caught_up && now >= deadline.deadline {
+                    deadline.expired = true;
+
+                    // Only the owner drops the interest, all other workers
+                    // follow once the uninterest has been sequenced.
+                    if deadline.owner == worker.index() {
+                        warn!("[WORKER {}] {:?} timed out on {}", worker.index(), deadline.token, deadline.name);
+
+                        let error = Error {
+                            category: ErrorKind::Timeout,
+                            message: format!("{} did not catch up within its deadline", deadline.name),
+                        };
+
+                        send_errors.send((vec![deadline.token], vec![(error, deadline.last_tx)])).unwrap();
+
+                        sequencer.push(Command {
+                            owner: worker.index(),
+                            client: deadline.token.into(),
+                            requests: vec![Request::Uninterest(deadline.name.clone())],
+                        });
+                    }
+                }
+            }
+
+            // Dataflows that caught up in time are no longer bounded.
+            deadlines.retain(|deadline| deadline.expired || deadline.probe.less_than(&deadline.at));
+
+            // Rules exceeding their iteration limit might only do so on
+            // some workers, so they are failed in sequence on all of them.
+            for name in server.take_exceeded() {
+                warn!("[WORKER {}] {} exceeded its iteration limit", worker.index(), name);
+
+                sequencer.push(Command {
+                    owner: worker.index(),
+                    client: SYSTEM.0,
+                    requests: vec![Request::FailRule(name)],
+                });
+            }
+
+            pending_snapshots.retain(|(token, name, at, last_tx)| {
+                match server.materialized_snapshot(name, at) {
+                    Err(error) => {
+                        send_errors.send((vec![*token], vec![(error, *last_tx)])).unwrap();
+                        false
+                    }
+                    Ok(None) => true,
+                    Ok(Some(results)) => {
+                        send_snapshots.send((*token, name.clone(), results)).unwrap();
+                        false
+                    }
+                }
+            });
+
+            let frontier = server.probe.with_frontier(|frontier| frontier.to_vec());
+            if frontier != last_frontier || !server.is_any_outdated() {
+                last_frontier = frontier;
+                last_progress = Instant::now();
+            }
         }
 
         info!("Shutting down");
 
+        // Drain commands that were already sequenced, such that all
+        // workers agree on the sequence up to the shutdown request.
+        while let Some(command) = sequencer.next() {
+            warn!("[WORKER {}] discarding {:?}", worker.index(), command);
+        }
+
         drop(sequencer);
 
+        // Step until all probed dataflows have completed, now that
+        // their inputs are closed.
+        worker.step_while(|| !server.probe.done());
+
         // @TODO de-register loggers s.t. logging dataflows can shut down.
         // worker
         //     .log_register()
@@ -716,3 +2038,4 @@ fn main() {
         //     .insert::<DifferentialEvent,_>("differential/arrange", move |_time, _data| { });
     }).expect("Timely computation did not exit cleanly");
 }
+
@@ -59,6 +59,8 @@ pub enum Binding {
     Constant(ConstantBinding),
     /// Two variables bound by a binary predicate.
     BinaryPredicate(BinaryPredicateBinding),
+    /// Variables bound by the tuples of a named relation.
+    Relation(RelationBinding),
 }
 
 impl Binding {
@@ -66,7 +68,7 @@ impl Binding {
     pub fn attribute(e: Var, name: &str, v: Var) -> Binding {
         Binding::Attribute(AttributeBinding {
             variables: (e, v),
-            source_attribute: name.to_string(),
+            source_attribute: Aid::from(name),
             default: None,
         })
     }
@@ -75,7 +77,7 @@ impl Binding {
     pub fn optional_attribute(e: Var, name: &str, v: Var, default: Value) -> Binding {
         Binding::Attribute(AttributeBinding {
             variables: (e, v),
-            source_attribute: name.to_string(),
+            source_attribute: Aid::from(name),
             default: Some(default),
         })
     }
@@ -99,6 +101,29 @@ impl Binding {
             binding: Box::new(binding),
         })
     }
+
+    /// Applies `f` to every constant value this binding refers to,
+    /// including attribute defaults.
+    pub fn map_constants<F: FnMut(&mut Value)>(&mut self, f: &mut F) {
+        match *self {
+            Binding::Attribute(ref mut binding) => {
+                if let Some(ref mut default) = binding.default {
+                    f(default);
+                }
+            }
+            Binding::Not(ref mut binding) => binding.binding.map_constants(f),
+            Binding::Constant(ref mut binding) => f(&mut binding.value),
+            Binding::BinaryPredicate(_) | Binding::Relation(_) => {}
+        }
+    }
+
+    /// Creates a RelationBinding.
+    pub fn relation(name: &str, variables: Vec<Var>) -> Binding {
+        Binding::Relation(RelationBinding {
+            variables,
+            source_name: name.to_string(),
+        })
+    }
 }
 
 impl AsBinding for Binding {
@@ -108,6 +133,7 @@ impl AsBinding for Binding {
             Binding::Not(ref binding) => binding.variables(),
             Binding::Constant(ref binding) => binding.variables(),
             Binding::BinaryPredicate(ref binding) => binding.variables(),
+            Binding::Relation(ref binding) => binding.variables(),
         }
     }
 
@@ -117,6 +143,7 @@ impl AsBinding for Binding {
             Binding::Not(ref binding) => binding.binds(variable),
             Binding::Constant(ref binding) => binding.binds(variable),
             Binding::BinaryPredicate(ref binding) => binding.binds(variable),
+            Binding::Relation(ref binding) => binding.binds(variable),
         }
     }
 
@@ -126,6 +153,7 @@ impl AsBinding for Binding {
             Binding::Not(ref binding) => binding.ready_to_extend(prefix),
             Binding::Constant(ref binding) => binding.ready_to_extend(prefix),
             Binding::BinaryPredicate(ref binding) => binding.ready_to_extend(prefix),
+            Binding::Relation(ref binding) => binding.ready_to_extend(prefix),
         }
     }
 
@@ -135,6 +163,7 @@ impl AsBinding for Binding {
             Binding::Not(ref binding) => binding.required_to_extend(prefix, target),
             Binding::Constant(ref binding) => binding.required_to_extend(prefix, target),
             Binding::BinaryPredicate(ref binding) => binding.required_to_extend(prefix, target),
+            Binding::Relation(ref binding) => binding.required_to_extend(prefix, target),
         }
     }
 }
@@ -378,3 +407,39 @@ impl fmt::Debug for BinaryPredicateBinding {
         )
     }
 }
+
+/// Describes variables whose possible values are given by the tuples
+/// of a named relation (e.g. the output of another rule).
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct RelationBinding {
+    /// The variables this binding talks about.
+    pub variables: Vec<Var>,
+    /// The name of the relation backing this binding.
+    pub source_name: String,
+}
+
+impl AsBinding for RelationBinding {
+    fn variables(&self) -> Vec<Var> {
+        self.variables.clone()
+    }
+
+    fn binds(&self, variable: Var) -> Option<usize> {
+        self.variables.binds(variable)
+    }
+
+    fn ready_to_extend(&self, _prefix: &AsBinding) -> Option<Var> {
+        // Relations are not (yet) arranged by arbitrary prefixes,
+        // and therefore never participate in prefix extension.
+        None
+    }
+
+    fn required_to_extend(&self, _prefix: &AsBinding, _target: Var) -> Option<Option<Var>> {
+        None
+    }
+}
+
+impl fmt::Debug for RelationBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{:?}", self.source_name, self.variables)
+    }
+}
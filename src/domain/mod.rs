@@ -1,24 +1,728 @@
 //! Logic for working with attributes under a shared timestamp
 //! semantics.
 
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::ops::Sub;
+use std::rc::Rc;
 
-use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::channels::pact::{Exchange, Pipeline};
 use timely::dataflow::operators::generic::operator::Operator;
-use timely::dataflow::operators::FrontierNotificator;
+use timely::dataflow::operators::{Broadcast, Capability};
 use timely::dataflow::{ProbeHandle, Scope, Stream};
 use timely::order::TotalOrder;
 use timely::progress::Timestamp;
 
+use timely_sort::Unsigned;
+
 use differential_dataflow::input::{Input, InputSession};
 use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::arrange::Arrange;
 use differential_dataflow::operators::Threshold;
-use differential_dataflow::trace::TraceReader;
-use differential_dataflow::AsCollection;
+use differential_dataflow::trace::{BatchReader, Cursor, TraceReader};
+use differential_dataflow::{AsCollection, Collection, Hashable};
+
+use crate::storage::{with_storage, StoredValHandle, StoredValSpine};
+use crate::{Aid, Eid, Error, ErrorKind, Time, TxData, Value};
+use crate::{
+    AttributeConfig, CollectionIndex, CompactionPolicy, InputSemantics, RelationConfig,
+    RelationHandle,
+};
+
+/// A trace handle that is only available after the operator that
+/// wants to read from it has been constructed. It shares the type of
+/// the attribute's own forward index.
+type SharedTrace<T> = Rc<RefCell<Option<StoredValHandle<Value, Value, T, isize>>>>;
+
+/// Enforces CardinalityOne semantics on a collection of (e,v)
+/// pairs. Whenever a new value is asserted for an eid, the value
+/// currently held for that eid (as reported by `current_trace`, the
+/// attribute's own forward index) is retracted. If multiple values
+/// are asserted for the same eid at the same time, the largest one
+/// wins, s.t. at most one (e,v) pair survives each timestamp.
+///
+/// Eids arriving on `retractions` have whatever value they currently
+/// hold retracted, unless a new value is asserted for them at the
+/// same time. Eids not holding a value are ignored.
+fn cardinality_one<S>(
+    tuples: &Collection<S, (Value, Value), isize>,
+    retractions: &Collection<S, Value, isize>,
+    current_trace: SharedTrace<S::Timestamp>,
+) -> Collection<S, (Value, Value), isize>
+where
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + TotalOrder,
+{
+    // Must match the exchange used when arranging the forward index,
+    // s.t. each worker finds its eids in its local trace shard.
+    let exchange = Exchange::new(
+        |((e, _v), _t, _diff): &((Value, Option<Value>), S::Timestamp, isize)| e.hashed().as_u64(),
+    );
+
+    let scope = tuples.scope();
+
+    // Retractions of whatever value is current don't name a value.
+    let updates = tuples
+        .map(|(e, v)| (e, Some(v)))
+        .concat(&retractions.map(|e| (e, None)));
+
+    updates
+        .inner
+        .unary_frontier(exchange, "CardinalityOne", move |_capability, info| {
+            let activator = scope.activator_for(&info.address[..]);
+
+            let mut trace = None;
+            let mut stash: HashMap<
+                S::Timestamp,
+                (
+                    Capability<S::Timestamp>,
+                    Vec<((Value, Option<Value>), isize)>,
+                ),
+            > = HashMap::new();
+            let mut buffer = Vec::new();
+
+            move |input, output| {
+                input.for_each(|cap, data| {
+                    data.swap(&mut buffer);
+                    for (tuple, time, diff) in buffer.drain(..) {
+                        stash
+                            .entry(time.clone())
+                            .or_insert_with(|| (cap.delayed(&time), Vec::new()))
+                            .1
+                            .push((tuple, diff));
+                    }
+                });
 
-use crate::{Aid, Error, Time, TxData, Value};
-use crate::{AttributeConfig, CollectionIndex, InputSemantics, RelationConfig, RelationHandle};
+                if trace.is_none() {
+                    trace = current_trace.borrow_mut().take();
+                }
+
+                if let Some(ref mut trace) = trace {
+                    // Updates at times not in advance of the trace's
+                    // upper bound are reflected in the trace.
+                    let mut upper = vec![<S::Timestamp as Lattice>::minimum()];
+                    trace.map_batches(|batch| upper = batch.upper().to_vec());
+
+                    // We can only decide on the earliest pending time,
+                    // because our own outputs at that time have to
+                    // make it into the trace before anything later can
+                    // be looked up correctly.
+                    let next = stash.keys().min().cloned();
+                    if let Some(time) = next {
+                        let complete = !input.frontier().less_equal(&time);
+                        let visible = upper.iter().all(|u| time.less_equal(u));
+
+                        if complete && visible {
+                            let (cap, updates) = stash.remove(&time).unwrap();
+
+                            let mut changes: BTreeMap<Value, BTreeMap<Option<Value>, isize>> =
+                                BTreeMap::new();
+                            for ((e, v), diff) in updates.into_iter() {
+                                *changes
+                                    .entry(e)
+                                    .or_insert_with(BTreeMap::new)
+                                    .entry(v)
+                                    .or_insert(0) += diff;
+                            }
+
+                            let mut session = output.session(&cap);
+                            let (mut cursor, storage) = trace.cursor();
+
+                            // Eids are visited in order, s.t. the
+                            // cursor only ever has to seek forward.
+                            for (e, values) in changes.into_iter() {
+                                let mut current = Vec::new();
+
+                                cursor.seek_key(&storage, &e);
+                                if cursor.get_key(&storage) == Some(&e) {
+                                    while let Some(v) = cursor.get_val(&storage) {
+                                        let mut count = 0;
+                                        cursor.map_times(&storage, |t, d| {
+                                            if t.less_equal(&time) {
+                                                count += d;
+                                            }
+                                        });
+                                        if count > 0 {
+                                            current.push(v.clone());
+                                        }
+                                        cursor.step_val(&storage);
+                                    }
+                                }
+
+                                let asserted = values
+                                    .iter()
+                                    .filter(|(_v, diff)| **diff > 0)
+                                    .filter_map(|(v, _diff)| v.as_ref())
+                                    .max();
+
+                                match asserted {
+                                    Some(next_v) => {
+                                        for v in current.iter().filter(|v| *v != next_v) {
+                                            session.give((
+                                                (e.clone(), v.clone()),
+                                                time.clone(),
+                                                -1,
+                                            ));
+                                        }
+                                        if !current.contains(next_v) {
+                                            session.give((
+                                                (e.clone(), next_v.clone()),
+                                                time.clone(),
+                                                1,
+                                            ));
+                                        }
+                                    }
+                                    None => {
+                                        // Only retractions, which only
+                                        // matter if they hit the
+                                        // current value.
+                                        let retract_current = values
+                                            .get(&None)
+                                            .map(|diff| *diff > 0)
+                                            .unwrap_or(false);
+
+                                        for v in current.iter() {
+                                            let hit = values
+                                                .get(&Some(v.clone()))
+                                                .map(|diff| *diff < 0)
+                                                .unwrap_or(false);
+
+                                            if retract_current || hit {
+                                                session.give((
+                                                    (e.clone(), v.clone()),
+                                                    time.clone(),
+                                                    -1,
+                                                ));
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    // Allow the trace to compact up to the earliest
+                    // time we might still have to look up.
+                    let frontier: Vec<S::Timestamp> = input
+                        .frontier()
+                        .frontier()
+                        .iter()
+                        .chain(stash.keys())
+                        .min()
+                        .cloned()
+                        .into_iter()
+                        .collect();
+
+                    trace.advance_by(&frontier);
+                }
+
+                if !stash.is_empty() {
+                    activator.activate();
+                } else if input.frontier().is_empty() {
+                    trace = None;
+                }
+            }
+        })
+        .as_collection()
+}
+
+/// Pending assertions to an attribute with a time-to-live. They are
+/// kept by the time they were made at, s.t. expired ones are found
+/// without looking at the others, and by tuple, s.t. retractions
+/// find the oldest matching assertion without scanning all of them.
+/// The time-ordered index may hold tuples whose assertions have been
+/// cancelled already, which are skipped on expiry.
+struct Expirations<T> {
+    by_time: BTreeMap<T, Vec<(Value, Value)>>,
+    by_tuple: HashMap<(Value, Value), VecDeque<(T, isize)>>,
+}
+
+impl<T: Ord + Clone> Expirations<T> {
+    fn new() -> Self {
+        Expirations {
+            by_time: BTreeMap::new(),
+            by_tuple: HashMap::new(),
+        }
+    }
+
+    /// Records an update made at the given time. Assertions are kept
+    /// until they expire, retractions cancel the oldest matching
+    /// assertions still pending. Times must not go backwards.
+    fn track(&mut self, time: &T, tuple: &(Value, Value), diff: isize) {
+        if diff > 0 {
+            let assertions = self
+                .by_tuple
+                .entry(tuple.clone())
+                .or_insert_with(VecDeque::new);
+
+            // Repeated assertions at the same time are merged.
+            if let Some((last, count)) = assertions.back_mut() {
+                if *last == *time {
+                    *count += diff;
+                    return;
+                }
+            }
+
+            assertions.push_back((time.clone(), diff));
+            self.by_time
+                .entry(time.clone())
+                .or_insert_with(Vec::new)
+                .push(tuple.clone());
+        } else if let Some(assertions) = self.by_tuple.get_mut(tuple) {
+            let mut remaining = -diff;
+
+            while remaining > 0 {
+                match assertions.front_mut() {
+                    None => break,
+                    Some((_time, count)) => {
+                        let cancelled = std::cmp::min(remaining, *count);
+                        *count -= cancelled;
+                        remaining -= cancelled;
+
+                        if *count == 0 {
+                            assertions.pop_front();
+                        }
+                    }
+                }
+            }
+
+            if assertions.is_empty() {
+                self.by_tuple.remove(tuple);
+            }
+        }
+    }
+
+    /// Forgets all pending assertions made by the given eid before
+    /// the given time, as they are retracted by other means than
+    /// expiry. Assertions made at that time survive, as they do in
+    /// `retract_held`.
+    fn cancel(&mut self, before: &T, e: &Value) {
+        self.by_tuple.retain(|(asserted, _v), assertions| {
+            if asserted == e {
+                assertions.retain(|(time, _count)| time >= before);
+            }
+
+            !assertions.is_empty()
+        });
+    }
+
+    /// Removes and returns all pending assertions made before the
+    /// given cutoff.
+    fn expire(&mut self, cutoff: &T) -> Vec<((Value, Value), isize)> {
+        let alive = self.by_time.split_off(cutoff);
+        let expired = std::mem::replace(&mut self.by_time, alive);
+
+        let mut updates = Vec::new();
+        for tuple in expired.into_iter().flat_map(|(_time, tuples)| tuples) {
+            if let Some(assertions) = self.by_tuple.get_mut(&tuple) {
+                while assertions
+                    .front()
+                    .map(|(time, _count)| time < cutoff)
+                    .unwrap_or(false)
+                {
+                    let (_time, count) = assertions.pop_front().unwrap();
+                    updates.push((tuple.clone(), count));
+                }
+
+                if assertions.is_empty() {
+                    self.by_tuple.remove(&tuple);
+                }
+            }
+        }
+
+        updates
+    }
+}
+
+/// Enforces Unique semantics on a collection of (e,v) pairs. An
+/// assertion is dropped if its value is currently held by a different
+/// eid (as reported by `current_trace`, an index v -> e of the
+/// attribute's own contents). If multiple eids assert the same
+/// previously unheld value at the same time, the smallest eid wins.
+///
+/// Most conflicts are rejected by `Domain::transact` already. Those
+/// left are assertions racing with others the index did not reflect
+/// yet, which are dropped here and logged, as there is no client to
+/// report them to anymore.
+fn unique<S>(
+    name: &str,
+    tuples: &Collection<S, (Value, Value), isize>,
+    current_trace: SharedTrace<S::Timestamp>,
+) -> Collection<S, (Value, Value), isize>
+where
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + TotalOrder,
+{
+    // Must match the exchange used when arranging `current_trace`,
+    // s.t. each worker finds its values in its local trace shard.
+    let exchange = Exchange::new(
+        |((_e, v), _t, _diff): &((Value, Value), S::Timestamp, isize)| v.hashed().as_u64(),
+    );
+
+    let scope = tuples.scope();
+    let name = name.to_string();
+
+    tuples
+        .inner
+        .unary_frontier(exchange, "Unique", move |_capability, info| {
+            let activator = scope.activator_for(&info.address[..]);
+
+            let mut trace = None;
+            let mut stash: HashMap<
+                S::Timestamp,
+                (Capability<S::Timestamp>, Vec<((Value, Value), isize)>),
+            > = HashMap::new();
+            let mut buffer = Vec::new();
+
+            move |input, output| {
+                input.for_each(|cap, data| {
+                    data.swap(&mut buffer);
+                    for (tuple, time, diff) in buffer.drain(..) {
+                        stash
+                            .entry(time.clone())
+                            .or_insert_with(|| (cap.delayed(&time), Vec::new()))
+                            .1
+                            .push((tuple, diff));
+                    }
+                });
+
+                if trace.is_none() {
+                    trace = current_trace.borrow_mut().take();
+                }
+
+                if let Some(ref mut trace) = trace {
+                    let mut upper = vec![<S::Timestamp as Lattice>::minimum()];
+                    trace.map_batches(|batch| upper = batch.upper().to_vec());
+
+                    // As for CardinalityOne, only the earliest pending
+                    // time can be decided on.
+                    let next = stash.keys().min().cloned();
+                    if let Some(time) = next {
+                        let complete = !input.frontier().less_equal(&time);
+                        let visible = upper.iter().all(|u| time.less_equal(u));
+
+                        if complete && visible {
+                            let (cap, updates) = stash.remove(&time).unwrap();
+
+                            let mut changes: BTreeMap<Value, BTreeMap<Value, isize>> =
+                                BTreeMap::new();
+                            for ((e, v), diff) in updates.into_iter() {
+                                *changes
+                                    .entry(v)
+                                    .or_insert_with(BTreeMap::new)
+                                    .entry(e)
+                                    .or_insert(0) += diff;
+                            }
+
+                            let mut session = output.session(&cap);
+                            let (mut cursor, storage) = trace.cursor();
+
+                            for (v, eids) in changes.into_iter() {
+                                let mut current = None;
+
+                                cursor.seek_key(&storage, &v);
+                                if cursor.get_key(&storage) == Some(&v) {
+                                    while let Some(e) = cursor.get_val(&storage) {
+                                        let mut count = 0;
+                                        cursor.map_times(&storage, |t, d| {
+                                            if t.less_equal(&time) {
+                                                count += d;
+                                            }
+                                        });
+                                        if count > 0 {
+                                            current = Some(e.clone());
+                                        }
+                                        cursor.step_val(&storage);
+                                    }
+                                }
+
+                                // Retractions of the current holder
+                                // free up the value.
+                                if let Some(ref e) = current {
+                                    if eids.get(e).map(|diff| *diff < 0).unwrap_or(false) {
+                                        session.give(((e.clone(), v.clone()), time.clone(), -1));
+                                        current = None;
+                                    }
+                                }
+
+                                let mut asserted = eids
+                                    .iter()
+                                    .filter(|(_e, diff)| **diff > 0)
+                                    .map(|(e, _diff)| e);
+
+                                // Eids are visited in order, s.t. the
+                                // smallest one wins any ties.
+                                if current.is_none() {
+                                    if let Some(e) = asserted.next() {
+                                        session.give(((e.clone(), v.clone()), time.clone(), 1));
+                                    }
+                                }
+
+                                for e in asserted.filter(|e| current.as_ref() != Some(*e)) {
+                                    error!(
+                                        "Dropped assertion of {:?} for {:?} on unique attribute {}, as the value is already held.",
+                                        v, e, name
+                                    );
+                                }
+                            }
+                        }
+                    }
+
+                    let frontier: Vec<S::Timestamp> = input
+                        .frontier()
+                        .frontier()
+                        .iter()
+                        .chain(stash.keys())
+                        .min()
+                        .cloned()
+                        .into_iter()
+                        .collect();
+
+                    trace.advance_by(&frontier);
+                }
+
+                if !stash.is_empty() {
+                    activator.activate();
+                } else if input.frontier().is_empty() {
+                    trace = None;
+                }
+            }
+        })
+        .as_collection()
+}
+
+/// Retracts every value held by the eids arriving on `retractions`,
+/// as reported by `held_trace`, an index e -> v of the tuples to
+/// retract from. Each value is retracted as often as it is held.
+/// Values asserted at the same time as the retraction are not yet
+/// held, and thus survive it.
+fn retract_held<S>(
+    retractions: &Collection<S, Value, isize>,
+    held_trace: SharedTrace<S::Timestamp>,
+) -> Collection<S, (Value, Value), isize>
+where
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + TotalOrder,
+{
+    // Must match the exchange used when arranging `held_trace`, s.t.
+    // each worker finds its eids in its local trace shard.
+    let exchange =
+        Exchange::new(|(e, _t, _diff): &(Value, S::Timestamp, isize)| e.hashed().as_u64());
+
+    let scope = retractions.scope();
+
+    retractions
+        .inner
+        .unary_frontier(exchange, "RetractHeld", move |_capability, info| {
+            let activator = scope.activator_for(&info.address[..]);
+
+            let mut trace = None;
+            let mut stash: HashMap<S::Timestamp, (Capability<S::Timestamp>, Vec<(Value, isize)>)> =
+                HashMap::new();
+            let mut buffer = Vec::new();
+
+            move |input, output| {
+                input.for_each(|cap, data| {
+                    data.swap(&mut buffer);
+                    for (e, time, diff) in buffer.drain(..) {
+                        stash
+                            .entry(time.clone())
+                            .or_insert_with(|| (cap.delayed(&time), Vec::new()))
+                            .1
+                            .push((e, diff));
+                    }
+                });
+
+                if trace.is_none() {
+                    trace = held_trace.borrow_mut().take();
+                }
+
+                if let Some(ref mut trace) = trace {
+                    let mut upper = vec![<S::Timestamp as Lattice>::minimum()];
+                    trace.map_batches(|batch| upper = batch.upper().to_vec());
+
+                    // As for CardinalityOne, only the earliest pending
+                    // time can be decided on.
+                    let next = stash.keys().min().cloned();
+                    if let Some(time) = next {
+                        let complete = !input.frontier().less_equal(&time);
+                        let visible = upper.iter().all(|u| time.less_equal(u));
+
+                        if complete && visible {
+                            let (cap, updates) = stash.remove(&time).unwrap();
+
+                            let mut eids: BTreeMap<Value, isize> = BTreeMap::new();
+                            for (e, diff) in updates.into_iter() {
+                                *eids.entry(e).or_insert(0) += diff;
+                            }
+
+                            let mut session = output.session(&cap);
+                            let (mut cursor, storage) = trace.cursor();
+
+                            for e in eids
+                                .into_iter()
+                                .filter(|(_e, diff)| *diff > 0)
+                                .map(|(e, _diff)| e)
+                            {
+                                cursor.seek_key(&storage, &e);
+                                if cursor.get_key(&storage) == Some(&e) {
+                                    while let Some(v) = cursor.get_val(&storage) {
+                                        let mut count = 0;
+                                        cursor.map_times(&storage, |t, d| {
+                                            if t.less_equal(&time) {
+                                                count += d;
+                                            }
+                                        });
+                                        if count > 0 {
+                                            session.give((
+                                                (e.clone(), v.clone()),
+                                                time.clone(),
+                                                -count,
+                                            ));
+                                        }
+                                        cursor.step_val(&storage);
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    let frontier: Vec<S::Timestamp> = input
+                        .frontier()
+                        .frontier()
+                        .iter()
+                        .chain(stash.keys())
+                        .min()
+                        .cloned()
+                        .into_iter()
+                        .collect();
+
+                    trace.advance_by(&frontier);
+                }
+
+                if !stash.is_empty() {
+                    activator.activate();
+                } else if input.frontier().is_empty() {
+                    trace = None;
+                }
+            }
+        })
+        .as_collection()
+}
+
+/// Indexes a collection of (e,v) tuples, after enforcing the given
+/// input semantics on it. Eids arriving on `retractions` have all
+/// their values retracted. Unique attributes are additionally indexed
+/// from values to the eids holding them, which is returned as well.
+fn index_with_semantics<S>(
+    name: &str,
+    tuples: &Collection<S, (Value, Value), isize>,
+    retractions: &Collection<S, Value, isize>,
+    input_semantics: &InputSemantics,
+) -> (
+    CollectionIndex<Value, Value, S::Timestamp>,
+    Option<StoredValHandle<Value, Value, S::Timestamp, isize>>,
+)
+where
+    S: Scope,
+    S::Timestamp: Timestamp + Lattice + TotalOrder,
+{
+    // CardinalityOne needs to look up the value currently held for an
+    // eid, which it does via the attribute's own forward index. That
+    // index only exists further down, so we hand its trace over once
+    // it has been created.
+    let current_trace = Rc::new(RefCell::new(None));
+
+    // The same goes for the values to retract from eids, which are
+    // looked up in the forward index as well, unless stated otherwise.
+    let held_trace = Rc::new(RefCell::new(None));
+
+    let tuples = match *input_semantics {
+        InputSemantics::Raw => tuples.concat(&retract_held(retractions, held_trace.clone())),
+        InputSemantics::CardinalityOne => {
+            cardinality_one(tuples, retractions, current_trace.clone())
+        }
+        InputSemantics::CardinalityMany => {
+            // Every assertion of a value has to be retracted, not
+            // just the one surviving deduplication, so values are
+            // looked up in an index of all assertions.
+            let asserted = tuples
+                .concat(&retract_held(retractions, held_trace.clone()))
+                .arrange_named(&format!("Asserted({})", name));
+
+            *held_trace.borrow_mut() = Some(asserted.trace.clone());
+
+            // Ensure that redundant (e,v) pairs don't cause
+            // misleading proposals during joining.
+            asserted
+                .as_collection(|e, v| (e.clone(), v.clone()))
+                .distinct()
+        }
+        InputSemantics::Unique => unique(
+            name,
+            &tuples.concat(&retract_held(retractions, held_trace.clone())),
+            current_trace.clone(),
+        ),
+    };
+
+    let forward = CollectionIndex::index(name, &tuples);
+
+    let holders = if *input_semantics == InputSemantics::Unique {
+        // Values are looked up by value, which the forward index
+        // can't do. This index is private to the attribute, because
+        // the reverse index is only created on demand. It is kept in
+        // full on every worker, s.t. whichever worker introduces an
+        // assertion can check it against all values held.
+        let holders = tuples
+            .map(|(e, v)| (v, e))
+            .inner
+            .broadcast()
+            .as_collection()
+            .arrange_core::<_, StoredValSpine<Value, Value, S::Timestamp, isize>>(
+                Pipeline,
+                &format!("Holders({})", name),
+            );
+
+        *current_trace.borrow_mut() = Some(holders.trace.clone());
+
+        Some(holders.trace)
+    } else {
+        *current_trace.borrow_mut() = Some(forward.propose_trace.clone());
+
+        None
+    };
+
+    if *input_semantics == InputSemantics::Raw || *input_semantics == InputSemantics::Unique {
+        *held_trace.borrow_mut() = Some(forward.propose_trace.clone());
+    }
+
+    (forward, holders)
+}
+
+/// Returns the eid holding the given value, according to an index
+/// from values to eids, as of all times indexed so far.
+fn holder<T>(trace: &mut StoredValHandle<Value, Value, T, isize>, v: &Value) -> Option<Value>
+where
+    T: Timestamp + Lattice + TotalOrder,
+{
+    let (mut cursor, storage) = trace.cursor();
+
+    cursor.seek_key(&storage, v);
+    if cursor.get_key(&storage) == Some(v) {
+        while let Some(e) = cursor.get_val(&storage) {
+            let mut count = 0;
+            cursor.map_times(&storage, |_t, d| count += d);
+
+            if count > 0 {
+                return Some(e.clone());
+            }
+
+            cursor.step_val(&storage);
+        }
+    }
+
+    None
+}
 
 /// A domain manages attributes (and their inputs) that share a
 /// timestamp semantics (e.g. come from the same logical source).
@@ -27,20 +731,34 @@ pub struct Domain<T: Timestamp + Lattice + TotalOrder> {
     now_at: T,
     /// Input handles to attributes in this domain.
     input_sessions: HashMap<String, InputSession<T, (Value, Value), isize>>,
+    /// Input handles for retracting the current values of
+    /// attributes, by eid.
+    retractions: HashMap<String, InputSession<T, Value, isize>>,
     /// Input handles to named sinks in this domain.
     pub sinks: HashMap<String, InputSession<T, Vec<Value>, isize>>,
     /// The probe keeping track of progress in this domain.
     probe: ProbeHandle<T>,
+    /// Number of datoms transacted into this domain by this worker.
+    transacted: usize,
+    /// Pending assertions to attributes with a time-to-live.
+    expirations: HashMap<Aid, Expirations<T>>,
     /// Configurations for attributes in this domain.
     pub attributes: HashMap<Aid, AttributeConfig>,
+    /// Alternative names for attributes in this domain, mapped to
+    /// the names of the attributes themselves.
+    pub aliases: HashMap<Aid, Aid>,
     /// Forward attribute indices eid -> v.
     pub forward: HashMap<Aid, CollectionIndex<Value, Value, T>>,
-    /// Reverse attribute indices v -> eid.
+    /// Indices v -> eid of unique attributes, used to reject values
+    /// held by other eids before they are transacted.
+    holders: HashMap<Aid, StoredValHandle<Value, Value, T, isize>>,
+    /// Reverse attribute indices v -> eid. These are created lazily,
+    /// see `reverse_index`.
     pub reverse: HashMap<Aid, CollectionIndex<Value, Value, T>>,
     /// Configuration for relations in this domain.
-    pub relations: HashMap<Aid, RelationConfig<T>>,
+    pub relations: HashMap<String, RelationConfig<T>>,
     /// Relation traces.
-    pub arrangements: HashMap<Aid, RelationHandle<T>>,
+    pub arrangements: HashMap<String, RelationHandle<T>>,
 }
 
 impl<T> Domain<T>
@@ -52,10 +770,15 @@ where
         Domain {
             now_at: start_at,
             input_sessions: HashMap::new(),
+            retractions: HashMap::new(),
             sinks: HashMap::new(),
             probe: ProbeHandle::new(),
+            transacted: 0,
+            expirations: HashMap::new(),
             attributes: HashMap::new(),
+            aliases: HashMap::new(),
             forward: HashMap::new(),
+            holders: HashMap::new(),
             reverse: HashMap::new(),
             relations: HashMap::new(),
             arrangements: HashMap::new(),
@@ -73,103 +796,49 @@ where
     ) -> Result<(), Error> {
         if self.forward.contains_key(name) {
             Err(Error {
-                category: "df.error.category/conflict",
+                category: ErrorKind::Conflict,
                 message: format!("An attribute of name {} already exists.", name),
             })
+        } else if self.aliases.contains_key(name) {
+            Err(Error {
+                category: ErrorKind::Conflict,
+                message: format!("An alias of name {} already exists.", name),
+            })
+        } else if config.valid_time
+            && config.input_semantics != InputSemantics::Raw
+            && config.input_semantics != InputSemantics::CardinalityMany
+        {
+            Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!(
+                    "Bitemporal attribute {} can't have {:?} semantics.",
+                    name, config.input_semantics
+                ),
+            })
         } else {
-            let (handle, mut tuples) = scope.new_collection::<(Value, Value), isize>();
-
-            tuples = match config.input_semantics {
-                InputSemantics::Raw => tuples,
-                InputSemantics::CardinalityOne => {
-                    let exchange =
-                        Exchange::new(|((e, _v), _t, _diff): &((Value, Value), T, isize)| {
-                            if let Value::Eid(eid) = e {
-                                *eid as u64
-                            } else {
-                                panic!("Expected an eid.");
-                            }
-                        });
-
-                    // @TODO replace this with a delta-query, looking
-                    // up eids in the validate trace and retracting
-                    // old values
-                    tuples
-                        .inner
-                        .unary_frontier(exchange, "CardinalityOne", |_, _| {
-                            let mut notificator = FrontierNotificator::new();
-
-                            let mut eids: HashMap<T, HashSet<Value>> = HashMap::new();
-                            let mut current: HashMap<Value, Value> = HashMap::new();
-                            let mut next: HashMap<Value, (T, Value)> = HashMap::new();
-
-                            let mut tuples = Vec::new();
+            let (handle, tuples) = scope.new_collection::<(Value, Value), isize>();
+            let (retractions_handle, retractions) = scope.new_collection::<Value, isize>();
 
-                            move |input, output| {
-                                while let Some((cap, data)) = input.next() {
-                                    data.swap(&mut tuples);
+            // All of the attribute's arrangements, including those
+            // private to its input semantics, live in its storage.
+            let (forward, holders) = with_storage(config.storage, || {
+                index_with_semantics(name, &tuples, &retractions, &config.input_semantics)
+            });
 
-                                    let mut interest = false;
-                                    for ((eid, v), t, _) in tuples.drain(..) {
-                                        let (last_t, _next_v) = next
-                                            .entry(eid.clone())
-                                            .or_insert((cap.time().clone(), v.clone()));
-
-                                        if last_t.less_equal(&t) {
-                                            next.insert(eid.clone(), (t.clone(), v.clone()));
-
-                                            eids.entry(t).or_insert_with(HashSet::new).insert(eid);
-
-                                            interest = true;
-                                        }
-                                    }
-
-                                    if interest {
-                                        notificator.notify_at(cap.retain());
-                                    }
-                                }
-
-                                notificator.for_each(&[input.frontier()], |cap, _| {
-                                    let mut session = output.session(&cap);
-
-                                    if let Some(mut eids) = eids.remove(cap.time()) {
-                                        for eid in eids.drain() {
-                                            if let Some(current_v) = current.remove(&eid) {
-                                                session.give((
-                                                    (eid.clone(), current_v),
-                                                    cap.time().clone(),
-                                                    -1,
-                                                ));
-                                            }
-                                            if let Some((_t, next_v)) = next.remove(&eid) {
-                                                session.give((
-                                                    (eid.clone(), next_v.clone()),
-                                                    cap.time().clone(),
-                                                    1,
-                                                ));
-                                                current.insert(eid, next_v);
-                                            }
-                                        }
-                                    }
-                                });
-                            }
-                        })
-                        .as_collection()
-                }
-                InputSemantics::CardinalityMany => {
-                    // Ensure that redundant (e,v) pairs don't cause
-                    // misleading proposals during joining.
-                    tuples.distinct()
-                }
-            };
+            if let Some(mut holders) = holders {
+                holders.distinguish_since(&[]);
+                self.holders.insert(Aid::from(name), holders);
+            }
 
-            self.attributes.insert(name.to_string(), config);
+            self.retractions
+                .insert(name.to_string(), retractions_handle);
 
-            let forward = CollectionIndex::index(name, &tuples);
-            let reverse = CollectionIndex::index(name, &tuples.map(|(e, v)| (v, e)));
+            if config.ttl.is_some() {
+                self.expirations.insert(Aid::from(name), Expirations::new());
+            }
 
-            self.forward.insert(name.to_string(), forward);
-            self.reverse.insert(name.to_string(), reverse);
+            self.attributes.insert(Aid::from(name), config);
+            self.forward.insert(Aid::from(name), forward);
 
             self.input_sessions.insert(name.to_string(), handle);
 
@@ -185,9 +854,14 @@ where
     ) -> Result<(), Error> {
         if self.forward.contains_key(name) {
             Err(Error {
-                category: "df.error.category/conflict",
+                category: ErrorKind::Conflict,
                 message: format!("An attribute of name {} already exists.", name),
             })
+        } else if self.aliases.contains_key(name) {
+            Err(Error {
+                category: ErrorKind::Conflict,
+                message: format!("An alias of name {} already exists.", name),
+            })
         } else {
             let tuples = datoms
                 .as_collection()
@@ -196,10 +870,8 @@ where
                 .distinct();
 
             let forward = CollectionIndex::index(&name, &tuples);
-            let reverse = CollectionIndex::index(&name, &tuples.map(|(e, v)| (v, e)));
 
-            self.forward.insert(name.to_string(), forward);
-            self.reverse.insert(name.to_string(), reverse);
+            self.forward.insert(Aid::from(name), forward);
 
             info!("Created source {}", name);
 
@@ -207,6 +879,173 @@ where
         }
     }
 
+    /// Replaces the compaction policy of an existing attribute. The
+    /// new policy takes effect on the next call to `advance_to`. As
+    /// compacted history can't be restored, a policy that would
+    /// compact less than the current one is rejected.
+    pub fn set_compaction(
+        &mut self,
+        name: &str,
+        compaction: CompactionPolicy<Time>,
+    ) -> Result<(), Error> {
+        match self.attributes.get_mut(name) {
+            None => Err(Error {
+                category: ErrorKind::NotFound,
+                message: format!("Attribute {} does not exist.", name),
+            }),
+            Some(config) => {
+                let compacted = config.compaction.frontier(&self.now_at);
+                let requested = compaction.frontier(&self.now_at);
+
+                let uncompacts = match (compacted, requested) {
+                    (None, _) => false,
+                    (Some(_), None) => true,
+                    (Some(compacted), Some(requested)) => requested.less_than(&compacted),
+                };
+
+                if uncompacts {
+                    Err(Error {
+                        category: ErrorKind::Conflict,
+                        message: format!(
+                            "Attribute {} is already compacted beyond what {:?} would retain.",
+                            name, compaction
+                        ),
+                    })
+                } else {
+                    config.compaction = compaction;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Changes the input semantics of an existing attribute. As
+    /// existing dataflows can't be altered, the attribute is
+    /// re-indexed in the dataflow of the given scope, from the
+    /// contents of its current forward index, followed by all inputs
+    /// transacted from here on.
+    ///
+    /// Historical data is re-derived as if the new semantics had been
+    /// in effect all along, to the extent that history is still
+    /// available at the attribute's compaction frontier:
+    ///
+    /// - `CardinalityOne`: wherever several values were held for an
+    ///   eid at the same time, only the largest one survives. The
+    ///   others are dropped from the new index without being
+    ///   reported as retractions, as the new index never held them.
+    /// - `CardinalityMany`: duplicate (e,v) pairs collapse into one.
+    /// - `Unique`: wherever several eids held a value at the same
+    ///   time, only the smallest one keeps it.
+    /// - `Raw`: nothing is enforced and multiplicities are kept as
+    ///   they were.
+    ///
+    /// The old input is closed, s.t. dataflows created before the
+    /// migration see the attribute as of the migration and nothing
+    /// after it. Interests have to be re-expressed in order to pick
+    /// up the new index. The reverse index is dropped and re-created
+    /// on next use.
+    pub fn migrate_attribute<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        input_semantics: InputSemantics,
+        scope: &mut S,
+    ) -> Result<(), Error> {
+        if !self.attributes.contains_key(name) {
+            return Err(Error {
+                category: ErrorKind::NotFound,
+                message: format!("Attribute {} does not exist.", name),
+            });
+        }
+
+        let old_handle = match self.input_sessions.remove(name) {
+            None => {
+                return Err(Error {
+                    category: ErrorKind::Conflict,
+                    message: format!("Input to attribute {} has already been closed.", name),
+                });
+            }
+            Some(handle) => handle,
+        };
+
+        let (history, _shutdown) = self
+            .forward
+            .get_mut(name)
+            .unwrap()
+            .propose_trace
+            .import_core(scope, &format!("Migrate({})", name));
+
+        let (mut handle, tuples) = scope.new_collection::<(Value, Value), isize>();
+        handle.advance_to(self.now_at.clone());
+
+        let (mut retractions_handle, retractions) = scope.new_collection::<Value, isize>();
+        retractions_handle.advance_to(self.now_at.clone());
+
+        let tuples = history
+            .as_collection(|e, v| (e.clone(), v.clone()))
+            .concat(&tuples);
+
+        let (forward, holders) =
+            index_with_semantics(name, &tuples, &retractions, &input_semantics);
+
+        match holders {
+            None => {
+                self.holders.remove(name);
+            }
+            Some(mut holders) => {
+                holders.distinguish_since(&[]);
+                self.holders.insert(Aid::from(name), holders);
+            }
+        }
+
+        // Any inputs still pending on the old handle are flushed into
+        // the old index, and thus make it into the history above.
+        old_handle.close();
+
+        if let Some(old_retractions) = self.retractions.remove(name) {
+            old_retractions.close();
+        }
+
+        self.retractions
+            .insert(name.to_string(), retractions_handle);
+
+        self.forward.insert(Aid::from(name), forward);
+        self.reverse.remove(name);
+        self.input_sessions.insert(name.to_string(), handle);
+        info!("Migrated {} to {:?}", name, input_semantics);
+
+        self.attributes.get_mut(name).unwrap().input_semantics = input_semantics;
+
+        Ok(())
+    }
+
+    /// Registers an alias for an existing attribute, s.t. queries
+    /// can refer to the attribute under either name. Aliases of
+    /// aliases refer to the attribute itself. Aliases only apply to
+    /// reading, transactions have to name the attribute itself.
+    pub fn create_alias(&mut self, alias: &str, target: &str) -> Result<(), Error> {
+        if self.forward.contains_key(alias) || self.aliases.contains_key(alias) {
+            return Err(Error {
+                category: ErrorKind::Conflict,
+                message: format!("An attribute or alias of name {} already exists.", alias),
+            });
+        }
+
+        let target = Aid::from(self.resolve(target));
+
+        if !self.forward.contains_key(&target) {
+            return Err(Error {
+                category: ErrorKind::NotFound,
+                message: format!("Attribute {} does not exist.", target),
+            });
+        }
+
+        info!("Aliased {} as {}", target, alias);
+
+        self.aliases.insert(Aid::from(alias), target);
+
+        Ok(())
+    }
+
     /// Inserts a new named relation.
     pub fn register_arrangement(
         &mut self,
@@ -222,19 +1061,136 @@ where
         self.arrangements.insert(name, trace);
     }
 
+    /// Removes a named relation, dropping its trace. Its
+    /// configuration is kept for when it is registered again.
+    pub fn unregister_arrangement(&mut self, name: &str) {
+        self.arrangements.remove(name);
+    }
+
     /// Transact data into one or more inputs.
-    pub fn transact(&mut self, tx_data: Vec<TxData>) -> Result<(), Error> {
-        // @TODO do this smarter, e.g. grouped by handle
-        for TxData(op, e, a, v) in tx_data {
-            match self.input_sessions.get_mut(&a) {
+    pub fn transact(&mut self, mut tx_data: Vec<TxData>) -> Result<(), Error> {
+        // Group updates by attribute, s.t. each input session has to
+        // be looked up only once. The sort is stable, preserving the
+        // order of updates on any given attribute.
+        tx_data.sort_by(|x, y| x.2.cmp(&y.2));
+
+        // Type errors and conflicting assertions on unique attributes
+        // reject the transaction as a whole, before any of it reaches
+        // the inputs. Values are checked against each other, as well
+        // as against those held already, as far as the attribute's
+        // dataflow has indexed them. Conflicts with data transacted
+        // at times not yet indexed are only detected (and dropped) by
+        // the dataflow. Under `Partitioning::Entity`, assertions of
+        // the same value for different eids are introduced by
+        // different workers, and thus not checked against each
+        // other here.
+        let retracted: HashSet<(&Aid, &Value, Eid)> = tx_data
+            .iter()
+            .filter(|TxData(op, _e, _a, _v)| *op < 0)
+            .map(|TxData(_op, e, a, v)| (a, v, *e))
+            .collect();
+
+        let mut asserted: HashMap<(&Aid, &Value), Eid> = HashMap::new();
+        for TxData(op, e, a, v) in tx_data.iter() {
+            let config = self.attributes.get(a);
+
+            if *op > 0
+                && config
+                    .map(|config| config.input_semantics == InputSemantics::Unique)
+                    .unwrap_or(false)
+            {
+                if let Some(other) = asserted.insert((a, v), *e) {
+                    if other != *e {
+                        return Err(Error {
+                            category: ErrorKind::Conflict,
+                            message: format!(
+                                "Value {:?} of unique attribute {} is asserted for both {} and {}.",
+                                v, a, other, e
+                            ),
+                        });
+                    }
+                }
+
+                let held = self
+                    .holders
+                    .get_mut(a)
+                    .and_then(|holders| holder(holders, v));
+
+                if let Some(Value::Eid(other)) = held {
+                    if other != *e && !retracted.contains(&(a, v, other)) {
+                        return Err(Error {
+                            category: ErrorKind::Conflict,
+                            message: format!(
+                                "Value {:?} of unique attribute {} is already held by {}.",
+                                v, a, other
+                            ),
+                        });
+                    }
+                }
+            }
+
+            // Values of bitemporal attributes are typed by the value
+            // they pair with a valid time.
+            let v = match config {
+                Some(config) if config.valid_time => match v.as_valid() {
+                    None => {
+                        return Err(Error {
+                            category: ErrorKind::Incorrect,
+                            message: format!(
+                                "Bitemporal attribute {} expects values with a valid time, but got {:?}.",
+                                a, v
+                            ),
+                        });
+                    }
+                    Some((v, _valid_from)) => v,
+                },
+                _ => v,
+            };
+
+            let value_type = config.and_then(|config| config.value_type);
+
+            if let Some(value_type) = value_type {
+                if v.value_type() != value_type {
+                    return Err(Error {
+                        category: ErrorKind::Incorrect,
+                        message: format!(
+                            "Attribute {} expects values of type {:?}, but got {:?}.",
+                            a, value_type, v
+                        ),
+                    });
+                }
+            }
+        }
+
+        self.transacted += tx_data.len();
+
+        let mut tx_data = tx_data.into_iter().peekable();
+        while let Some(TxData(op, e, a, v)) = tx_data.next() {
+            match self.input_sessions.get_mut(a.as_str()) {
                 None => {
                     return Err(Error {
-                        category: "df.error.category/not-found",
+                        category: ErrorKind::NotFound,
                         message: format!("Attribute {} does not exist.", a),
                     });
                 }
                 Some(handle) => {
-                    handle.update((Value::Eid(e), v), op);
+                    let mut pending = self.expirations.get_mut(&a);
+
+                    let tuple = (Value::Eid(e), v);
+                    if let Some(ref mut pending) = pending {
+                        pending.track(&self.now_at, &tuple, op);
+                    }
+                    handle.update(tuple, op);
+
+                    while tx_data.peek().map(|datom| datom.2 == a).unwrap_or(false) {
+                        let TxData(op, e, _, v) = tx_data.next().unwrap();
+
+                        let tuple = (Value::Eid(e), v);
+                        if let Some(ref mut pending) = pending {
+                            pending.track(&self.now_at, &tuple, op);
+                        }
+                        handle.update(tuple, op);
+                    }
                 }
             }
         }
@@ -242,11 +1198,93 @@ where
         Ok(())
     }
 
+    /// Retracts whatever values the given eids currently hold for
+    /// the given attributes, as of the current time. Eids not holding
+    /// a value at that time are left alone.
+    ///
+    /// Values asserted at the same time are not affected, i.e. a
+    /// CardinalityOne attribute keeps its new value. Attributes
+    /// holding multiple values per eid have all of them retracted,
+    /// as often as they have been asserted.
+    pub fn retract_current(&mut self, retractions: Vec<(Eid, Aid)>) -> Result<(), Error> {
+        // As for transactions, the whole batch is rejected before any
+        // of it reaches the inputs.
+        for (_e, a) in retractions.iter() {
+            if !self.attributes.contains_key(a) {
+                return Err(Error {
+                    category: ErrorKind::NotFound,
+                    message: format!("Attribute {} does not exist.", a),
+                });
+            } else if !self.retractions.contains_key(a.as_str()) {
+                return Err(Error {
+                    category: ErrorKind::Conflict,
+                    message: format!("Input to attribute {} has already been closed.", a),
+                });
+            }
+        }
+
+        self.transacted += retractions.len();
+
+        for (e, a) in retractions.into_iter() {
+            self.cancel_expirations(e, &a);
+
+            self.retractions
+                .get_mut(a.as_str())
+                .unwrap()
+                .update(Value::Eid(e), 1);
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the pending expiry of whatever values the given eid
+    /// currently holds for the given attribute, because they are
+    /// being retracted otherwise. Must happen on every worker, not
+    /// just the one introducing the retraction, as expiries are
+    /// tracked by the worker that introduced the assertion.
+    pub fn cancel_expirations(&mut self, e: Eid, a: &str) {
+        if let Some(pending) = self.expirations.get_mut(a) {
+            pending.cancel(&self.now_at, &Value::Eid(e));
+        }
+    }
+
+    /// Retracts all values the given eids currently hold, across all
+    /// attributes of this domain whose inputs are still open, as
+    /// with `retract_current`. Only the retractions this worker
+    /// would introduce datoms for, according to each attribute's
+    /// partitioning, are introduced here.
+    pub fn retract_entities(
+        &mut self,
+        eids: &[Eid],
+        owner: usize,
+        worker_index: usize,
+        peers: usize,
+    ) {
+        for (aid, handle) in self.retractions.iter_mut() {
+            let partitioning = &self.attributes[aid.as_str()].partitioning;
+
+            for e in eids.iter() {
+                if let Some(pending) = self.expirations.get_mut(aid.as_str()) {
+                    pending.cancel(&self.now_at, &Value::Eid(*e));
+                }
+
+                if partitioning.worker(*e, owner, peers) == worker_index {
+                    handle.update(Value::Eid(*e), 1);
+                    self.transacted += 1;
+                }
+            }
+        }
+    }
+
     /// Closes and drops an existing input.
     pub fn close_input(&mut self, name: String) -> Result<(), Error> {
+        if let Some(handle) = self.retractions.remove(&name) {
+            handle.close();
+        }
+
         match self.input_sessions.remove(&name) {
             None => Err(Error {
-                category: "df.error.category/not-found",
+                category: ErrorKind::NotFound,
                 message: format!("Input {} does not exist.", name),
             }),
             Some(handle) => {
@@ -256,13 +1294,29 @@ where
         }
     }
 
-    /// Advances the domain to `next`. Advances all traces
-    /// accordingly, depending on their configured slack.
+    /// Closes and drops all existing inputs, including those of
+    /// named sinks.
+    pub fn close_inputs(&mut self) -> Result<(), Error> {
+        let names: Vec<String> = self.input_sessions.keys().cloned().collect();
+        for name in names.into_iter() {
+            self.close_input(name)?;
+        }
+
+        for (_name, handle) in self.sinks.drain() {
+            handle.close();
+        }
+
+        Ok(())
+    }
+
+    /// Advances the domain to `next`. Retracts datoms that have
+    /// outlived their attribute's time-to-live and advances all
+    /// traces accordingly, depending on their configured slack.
     pub fn advance_to(&mut self, next: T) -> Result<(), Error> {
         if !self.now_at.less_equal(&next) {
             // We can't rewind time.
             Err(Error {
-                category: "df.error.category/conflict",
+                category: ErrorKind::Conflict,
                 message: format!(
                     "Domain is at {:?}, you attempted to rewind to {:?}.",
                     &self.now_at, &next
@@ -273,12 +1327,42 @@ where
 
             for handle in self.input_sessions.values_mut() {
                 handle.advance_to(next.clone());
+            }
+
+            for handle in self.retractions.values_mut() {
+                handle.advance_to(next.clone());
+            }
+
+            for (aid, pending) in self.expirations.iter_mut() {
+                let ttl: T = match self.attributes[aid].ttl {
+                    None => continue,
+                    Some(ref ttl) => ttl.clone().into(),
+                };
+
+                // Datoms asserted before the cutoff have expired.
+                if ttl.less_equal(&next) {
+                    let cutoff = next.clone() - ttl;
+                    let expired = pending.expire(&cutoff);
+
+                    if let Some(handle) = self.input_sessions.get_mut(aid.as_str()) {
+                        for (tuple, diff) in expired.into_iter() {
+                            handle.update(tuple, -diff);
+                        }
+                    }
+                }
+            }
+
+            for handle in self.input_sessions.values_mut() {
+                handle.flush();
+            }
+
+            for handle in self.retractions.values_mut() {
                 handle.flush();
             }
 
             for (aid, config) in self.attributes.iter() {
-                if let Some(ref trace_slack) = config.trace_slack {
-                    let frontier = &[next.clone() - trace_slack.clone().into()];
+                if let Some(frontier) = config.compaction.frontier(&next) {
+                    let frontier = &[frontier];
 
                     self.forward
                         .get_mut(aid)
@@ -288,27 +1372,23 @@ where
                         ))
                         .advance_by(frontier);
 
-                    self.reverse
-                        .get_mut(aid)
-                        .expect(&format!(
-                            "Configuration available for unknown attribute {}",
-                            aid
-                        ))
-                        .advance_by(frontier);
+                    if let Some(reverse) = self.reverse.get_mut(aid) {
+                        reverse.advance_by(frontier);
+                    }
+
+                    if let Some(holders) = self.holders.get_mut(aid) {
+                        holders.advance_by(frontier);
+                    }
                 }
             }
 
             for (name, config) in self.relations.iter() {
-                if let Some(ref trace_slack) = config.trace_slack {
-                    let frontier = &[next.clone() - trace_slack.clone()];
-
-                    self.arrangements
-                        .get_mut(name)
-                        .expect(&format!(
-                            "Configuration available for unknown relation {}",
-                            name
-                        ))
-                        .advance_by(frontier);
+                // Relations may be configured before they are
+                // registered, or after they have been released.
+                if let Some(trace) = self.arrangements.get_mut(name) {
+                    if let Some(frontier) = config.compaction.frontier(&next) {
+                        trace.advance_by(&[frontier]);
+                    }
                 }
             }
 
@@ -322,4 +1402,87 @@ where
     pub fn time(&self) -> &T {
         &self.now_at
     }
+
+    /// Reports the number of datoms transacted into this domain by
+    /// this worker.
+    pub fn transacted(&self) -> usize {
+        self.transacted
+    }
+
+    /// Reports the number of distinct entities and the number of
+    /// datoms held by each attribute in this domain, sorted by
+    /// attribute. Counts are read from the count traces of the
+    /// forward indices and only cover this worker's shard.
+    pub fn statistics(&mut self) -> Vec<(Aid, usize, isize)> {
+        let mut statistics: Vec<(Aid, usize, isize)> = self
+            .forward
+            .iter_mut()
+            .map(|(aid, index)| {
+                let (entities, datoms) = index.statistics();
+                (aid.clone(), entities, datoms)
+            })
+            .collect();
+
+        statistics.sort();
+        statistics
+    }
+}
+
+impl<T> Domain<T>
+where
+    T: Timestamp + Lattice + TotalOrder,
+{
+    /// Returns the name of the attribute the given name is an alias
+    /// for, or the name itself if it isn't an alias.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases
+            .get(name)
+            .map(|target| target.as_str())
+            .unwrap_or(name)
+    }
+
+    /// Returns the forward index of the named attribute or alias.
+    pub fn forward_index(&mut self, name: &str) -> Option<&mut CollectionIndex<Value, Value, T>> {
+        let name = self.resolve(name).to_string();
+        self.forward.get_mut(name.as_str())
+    }
+
+    /// Returns the reverse index of the named attribute or alias,
+    /// creating it from the forward index on first use. A newly
+    /// created reverse index lives in the dataflow of the given
+    /// scope, but keeps up with the attribute beyond that dataflow's
+    /// lifetime.
+    pub fn reverse_index<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        scope: &S,
+    ) -> Option<&mut CollectionIndex<Value, Value, T>> {
+        let name = self.resolve(name).to_string();
+        let name = name.as_str();
+
+        if !self.reverse.contains_key(name) {
+            let forward = self.forward.get_mut(name)?;
+
+            // The import is never shut down, as the reverse index
+            // might be used by other dataflows later on.
+            let (propose, _shutdown) = forward
+                .propose_trace
+                .import_core(scope, &format!("Proposals({})", name));
+
+            let storage = self
+                .attributes
+                .get(name)
+                .map(|config| config.storage)
+                .unwrap_or_default();
+
+            let tuples = propose.as_collection(|e, v| (e.clone(), v.clone()));
+            let reverse = with_storage(storage, || forward.reverse(&tuples));
+
+            info!("Created reverse index for {}", name);
+
+            self.reverse.insert(Aid::from(name), reverse);
+        }
+
+        self.reverse.get_mut(name)
+    }
 }
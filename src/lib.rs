@@ -18,46 +18,128 @@ extern crate num_rational;
 
 pub mod binding;
 pub mod domain;
+pub mod parser;
 pub mod plan;
 pub mod server;
 pub mod sinks;
 pub mod sources;
+pub mod storage;
 pub mod timestamp;
 
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::Hash;
+use std::rc::Rc;
 use std::time::Duration;
 
-use timely::dataflow::operators::CapabilitySet;
+use timely::dataflow::operators::{CapabilitySet, Filter};
 use timely::dataflow::scopes::child::{Child, Iterative};
 use timely::dataflow::*;
 use timely::order::{Product, TotalOrder};
 use timely::progress::timestamp::Refines;
 use timely::progress::Timestamp;
 
+use timely_sort::Unsigned;
+
+use differential_dataflow::difference::Semigroup;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::operators::arrange::{Arrange, Arranged, ShutdownButton, TraceAgent};
 use differential_dataflow::operators::iterate::Variable;
-#[cfg(not(feature = "set-semantics"))]
-use differential_dataflow::operators::Consolidate;
-#[cfg(feature = "set-semantics")]
-use differential_dataflow::operators::Threshold;
+use differential_dataflow::operators::{Consolidate, Threshold};
 use differential_dataflow::trace::implementations::ord::{OrdKeySpine, OrdValSpine};
 use differential_dataflow::trace::wrappers::enter::TraceEnter;
 use differential_dataflow::trace::wrappers::enter_at::TraceEnter as TraceEnterAt;
-use differential_dataflow::trace::TraceReader;
-use differential_dataflow::{Collection, Data};
+use differential_dataflow::trace::{Cursor, TraceReader};
+use differential_dataflow::{AsCollection, Collection, Data, ExchangeData, Hashable};
 
 pub use num_rational::Rational32;
 
+use num_rational::Ratio;
+
 pub use binding::{AsBinding, AttributeBinding, Binding};
 pub use plan::{Hector, ImplContext, Implementable, Plan};
 
+use storage::{Storage, StoredKeyHandle, StoredValHandle};
+
 /// A unique entity identifier.
 pub type Eid = u64;
 
-/// A unique attribute identifier.
-pub type Aid = String; // u32
+/// A unique attribute identifier. Kept apart from plain strings at
+/// the type level, s.t. attribute identifiers can't be mistaken for
+/// string values. Dereferences to `str`.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Aid(Box<str>);
+
+impl Aid {
+    /// Returns the identifier as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for Aid {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::borrow::Borrow<str> for Aid {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Aid {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Aid {
+    fn from(aid: &str) -> Aid {
+        Aid(aid.into())
+    }
+}
+
+impl From<String> for Aid {
+    fn from(aid: String) -> Aid {
+        Aid(aid.into_boxed_str())
+    }
+}
+
+impl From<Aid> for String {
+    fn from(aid: Aid) -> String {
+        aid.0.into_string()
+    }
+}
+
+impl PartialEq<str> for Aid {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Aid {
+    fn eq(&self, other: &&'a str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl std::fmt::Display for Aid {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl std::fmt::Debug for Aid {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&*self.0, f)
+    }
+}
 
 /// Possible data values.
 ///
@@ -81,6 +163,93 @@ pub enum Value {
     Instant(u64),
     /// A 16 byte unique identifier.
     Uuid([u8; 16]),
+    /// An interned attribute identifier, as stored by servers
+    /// interning attribute identifiers in value position.
+    Symbol(u32),
+    /// Multiple values grouped into one, e.g. all values of an
+    /// attribute for a single entity, kept in sorted order, or a
+    /// value paired with its valid time (see `Value::valid`).
+    List(Vec<Value>),
+}
+
+/// Types of data values, as declared for attributes.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ValueType {
+    /// Attribute identifiers
+    Aid,
+    /// Strings
+    String,
+    /// Booleans
+    Bool,
+    /// 64 bit signed integers
+    Number,
+    /// 32 bit rationals
+    Rational32,
+    /// Entity identifiers
+    Eid,
+    /// Instants
+    Instant,
+    /// Unique identifiers
+    Uuid,
+    /// Lists of values
+    List,
+}
+
+impl Value {
+    /// Compares two numeric values by magnitude, across numeric
+    /// types. Returns None if either value isn't numeric. The derived
+    /// ordering instead orders values of different types by their
+    /// variant.
+    pub fn numeric_cmp(&self, other: &Value) -> Option<Ordering> {
+        let as_ratio = |x: &Rational32| Ratio::new(i64::from(*x.numer()), i64::from(*x.denom()));
+
+        match (self, other) {
+            (Value::Number(x), Value::Number(y)) => Some(x.cmp(y)),
+            (Value::Rational32(x), Value::Rational32(y)) => Some(x.cmp(y)),
+            (Value::Number(x), Value::Rational32(y)) => {
+                Some(Ratio::from_integer(*x).cmp(&as_ratio(y)))
+            }
+            (Value::Rational32(x), Value::Number(y)) => {
+                Some(as_ratio(x).cmp(&Ratio::from_integer(*y)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the type of this value.
+    pub fn value_type(&self) -> ValueType {
+        match *self {
+            Value::Aid(_) => ValueType::Aid,
+            Value::String(_) => ValueType::String,
+            Value::Bool(_) => ValueType::Bool,
+            Value::Number(_) => ValueType::Number,
+            Value::Rational32(_) => ValueType::Rational32,
+            Value::Eid(_) => ValueType::Eid,
+            Value::Instant(_) => ValueType::Instant,
+            Value::Uuid(_) => ValueType::Uuid,
+            Value::Symbol(_) => ValueType::Aid,
+            Value::List(_) => ValueType::List,
+        }
+    }
+
+    /// Pairs a value of a bitemporal attribute with the instant (in
+    /// milliseconds since the epoch) from which it is valid.
+    pub fn valid(value: Value, valid_from: u64) -> Value {
+        Value::List(vec![value, Value::Instant(valid_from)])
+    }
+
+    /// Splits a value of a bitemporal attribute into the value
+    /// proper and the instant from which it is valid. Returns None
+    /// for values not constructed via `Value::valid`.
+    pub fn as_valid(&self) -> Option<(&Value, u64)> {
+        match *self {
+            Value::List(ref pair) => match pair[..] {
+                [ref value, Value::Instant(valid_from)] => Some((value, valid_from)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 }
 
 /// Possible timestamp types.
@@ -95,6 +264,17 @@ pub enum Time {
     Real(Duration),
 }
 
+impl Time {
+    /// Returns the smallest positive offset between times of the
+    /// same kind as this one.
+    pub fn tick(&self) -> Time {
+        match *self {
+            Time::TxId(_) => Time::TxId(1),
+            Time::Real(_) => Time::Real(Duration::from_nanos(1)),
+        }
+    }
+}
+
 impl std::convert::From<Time> for u64 {
     fn from(t: Time) -> u64 {
         if let Time::TxId(time) = t {
@@ -115,20 +295,102 @@ impl std::convert::From<Time> for Duration {
     }
 }
 
+impl std::convert::From<u64> for Time {
+    fn from(t: u64) -> Time {
+        Time::TxId(t)
+    }
+}
+
+impl std::convert::From<Duration> for Time {
+    fn from(t: Duration) -> Time {
+        Time::Real(t)
+    }
+}
+
+impl std::convert::From<Time> for Value {
+    /// Logical times become numbers, real times become instants.
+    fn from(t: Time) -> Value {
+        match t {
+            Time::TxId(time) => Value::Number(time as i64),
+            Time::Real(time) => {
+                Value::Instant(time.as_secs() * 1000 + u64::from(time.subsec_millis()))
+            }
+        }
+    }
+}
+
+/// Categories of client-facing errors. Displayed as the category
+/// strings that are sent to clients.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum ErrorKind {
+    /// A referenced attribute, rule, or entity doesn't exist.
+    NotFound,
+    /// The request conflicts with the current state of the server.
+    Conflict,
+    /// The request is malformed or otherwise invalid.
+    Incorrect,
+    /// The request asks for something that isn't supported.
+    Unsupported,
+    /// Something went wrong while serving a valid request.
+    Fault,
+    /// The client isn't allowed to make the request.
+    Forbidden,
+    /// The request wasn't served within its deadline.
+    Timeout,
+}
+
+impl ErrorKind {
+    /// Returns the category string clients receive for this kind.
+    pub fn category(&self) -> &'static str {
+        match *self {
+            ErrorKind::NotFound => "df.error.category/not-found",
+            ErrorKind::Conflict => "df.error.category/conflict",
+            ErrorKind::Incorrect => "df.error.category/incorrect",
+            ErrorKind::Unsupported => "df.error.category/unsupported",
+            ErrorKind::Fault => "df.error.category/fault",
+            ErrorKind::Forbidden => "df.error.category/forbidden",
+            ErrorKind::Timeout => "df.error.category/timeout",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.category())
+    }
+}
+
 /// A client-facing, non-exceptional error.
 #[derive(Debug)]
 pub struct Error {
     /// Error category.
-    pub category: &'static str,
+    pub category: ErrorKind,
     /// Free-frorm description.
     pub message: String,
 }
 
 /// Transaction data. Conceptually a pair (Datom, diff) but it's kept
 /// intentionally flat to be more directly compatible with Datomic.
+///
+/// Entity ids that are negative when read as signed integers (see
+/// `tempid`) are placeholders, resolved to fresh entity ids when
+/// transacted.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct TxData(pub isize, pub Eid, pub Aid, pub Value);
 
+/// Returns the placeholder entity id for the given negative number,
+/// to be used in place of not-yet-assigned entities within a single
+/// transaction.
+pub fn tempid(n: i64) -> Eid {
+    assert!(n < 0, "Temporary ids must be negative.");
+    n as Eid
+}
+
+/// Returns true iff the given entity id is a placeholder.
+pub fn is_tempid(e: Eid) -> bool {
+    (e as i64) < 0
+}
+
 /// A (tuple, time, diff) triple, as sent back to clients.
 pub type ResultDiff<T> = (Vec<Value>, T, isize);
 
@@ -160,6 +422,13 @@ impl<T> Shutdownable for ShutdownButton<T> {
     }
 }
 
+// Shared handles are pressed once the last of their holders lets go
+// of them, i.e. when they are dropped.
+impl Shutdownable for Rc<ShutdownHandle> {
+    #[inline(always)]
+    fn press(&mut self) {}
+}
+
 /// A wrapper around a vector of ShutdownButton's. Ensures they will
 /// be pressed on dropping the handle.
 pub struct ShutdownHandle {
@@ -168,9 +437,7 @@ pub struct ShutdownHandle {
 
 impl Drop for ShutdownHandle {
     fn drop(&mut self) {
-        for mut button in self.shutdown_buttons.drain(..) {
-            button.press();
-        }
+        self.press_all();
     }
 }
 
@@ -196,6 +463,27 @@ impl ShutdownHandle {
         self.shutdown_buttons.push(Box::new(button));
     }
 
+    /// Keeps a shared handle alive for as long as this one. Its
+    /// buttons are pressed once all handles sharing it have been
+    /// shut down or dropped.
+    pub fn add_shared(&mut self, shared: Rc<ShutdownHandle>) {
+        self.shutdown_buttons.push(Box::new(shared));
+    }
+
+    /// Presses all buttons right away, rather than whenever the
+    /// handle happens to be dropped.
+    pub fn shutdown(mut self) {
+        self.press_all();
+    }
+
+    /// Presses and removes all buttons, s.t. none of them is pressed
+    /// twice.
+    fn press_all(&mut self) {
+        for mut button in self.shutdown_buttons.drain(..) {
+            button.press();
+        }
+    }
+
     /// Combines the buttons of another handle into self.
     pub fn merge_with(&mut self, mut other: Self) {
         self.shutdown_buttons.append(&mut other.shutdown_buttons);
@@ -225,19 +513,124 @@ pub enum InputSemantics {
     /// Multiple different values for any given eid are allowed, but
     /// (e,v) pairs are enforced to be distinct.
     CardinalityMany,
+    /// Each value may be held by at most one eid at any given
+    /// timestamp. Transactions asserting a value already held by
+    /// another eid are rejected. Only assertions racing with ones not
+    /// yet indexed are dropped silently instead, see `Domain::transact`.
+    Unique,
     // /// @TODO
     // CAS,
 }
 
+/// Placement of newly transacted datoms across workers.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum Partitioning {
+    /// Datoms are introduced by the worker that received the
+    /// transaction.
+    Owner,
+    /// Datoms are introduced by the worker their eid hashes to. This
+    /// matches the exchange used when indexing attributes, s.t.
+    /// updates arrive at their index without being reshuffled.
+    Entity,
+}
+
+impl Default for Partitioning {
+    fn default() -> Self {
+        Partitioning::Owner
+    }
+}
+
+impl Partitioning {
+    /// Returns the index of the worker that should introduce a datom
+    /// on the given eid.
+    pub fn worker(&self, e: Eid, owner: usize, peers: usize) -> usize {
+        match *self {
+            Partitioning::Owner => owner,
+            Partitioning::Entity => (Value::Eid(e).hashed().as_u64() % (peers as u64)) as usize,
+        }
+    }
+}
+
+/// Policies for compacting traces as the computation frontier
+/// advances, trading retained history for memory.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum CompactionPolicy<T> {
+    /// Never compact, retaining the full history.
+    None,
+    /// Compact up to a fixed offset behind the computation frontier.
+    Slack(T),
+    /// Retain all history since the specified time, compacting
+    /// everything before it.
+    KeepSince(T),
+}
+
+impl<T: Clone> CompactionPolicy<T> {
+    /// Returns the time up to which a trace may be compacted, once
+    /// the computation frontier has reached `now`, or None if the
+    /// trace should not be compacted at all.
+    pub fn frontier<U>(&self, now: &U) -> Option<U>
+    where
+        U: Timestamp + TotalOrder + std::ops::Sub<Output = U> + From<T>,
+    {
+        match *self {
+            CompactionPolicy::None => None,
+            CompactionPolicy::Slack(ref slack) => Some(now.clone() - slack.clone().into()),
+            CompactionPolicy::KeepSince(ref since) => {
+                let since: U = since.clone().into();
+                // We must never compact beyond the computation frontier.
+                if since.less_equal(now) {
+                    Some(since)
+                } else {
+                    Some(now.clone())
+                }
+            }
+        }
+    }
+}
+
 /// Per-attribute semantics.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct AttributeConfig {
     /// Modifiers to apply on attribute inputs, such as keeping only
     /// the most recent value per eid, or compare-and-swap.
     pub input_semantics: InputSemantics,
-    /// How close indexed traces should follow the computation
-    /// frontier.
-    pub trace_slack: Option<Time>,
+    /// How indexed traces should be compacted as the computation
+    /// frontier advances.
+    pub compaction: CompactionPolicy<Time>,
+    /// Which worker introduces transacted datoms.
+    #[serde(default)]
+    pub partitioning: Partitioning,
+    /// The type all values of this attribute must have. Transactions
+    /// containing values of any other type are rejected. Unchecked if
+    /// not set.
+    #[serde(default)]
+    pub value_type: Option<ValueType>,
+    /// How long asserted datoms live before they are retracted
+    /// automatically, i.e. datoms asserted at `t` are retracted once
+    /// the domain advances beyond `t + ttl`. Unlike compaction, this
+    /// changes the logical contents of the attribute. Datoms live
+    /// forever if not set.
+    ///
+    /// Under `CardinalityOne`, expiry only has an effect on the value
+    /// an eid currently holds, as superseded values have already been
+    /// retracted. Re-asserting a value before it has expired does not
+    /// extend its lifetime.
+    #[serde(default)]
+    pub ttl: Option<Time>,
+    /// Whether values are bitemporal, i.e. additionally carry the
+    /// instant from which they are valid in the world (see
+    /// `Value::valid`), independent of the system time at which
+    /// they are transacted. Valid times are read via
+    /// `Plan::ValidTime`. Only available under `Raw` and
+    /// `CardinalityMany` semantics, as the others would supersede
+    /// past versions of a value by later ones.
+    #[serde(default)]
+    pub valid_time: bool,
+    /// Where the batches of the attribute's indices are kept. Indices
+    /// of large attributes can be spilled to disk, at the expense of
+    /// reading them back in whenever they are used.
+    #[serde(default)]
+    pub storage: Storage,
 }
 
 impl AttributeConfig {
@@ -250,7 +643,12 @@ impl AttributeConfig {
             // @TODO make this 0? would have to check that no
             // dataflows are stalled if registered after inputs are
             // already available
-            trace_slack: Some(Time::TxId(1)),
+            compaction: CompactionPolicy::Slack(Time::TxId(1)),
+            partitioning: Partitioning::Owner,
+            value_type: None,
+            ttl: None,
+            valid_time: false,
+            storage: Storage::Memory,
         }
     }
 
@@ -261,7 +659,12 @@ impl AttributeConfig {
         AttributeConfig {
             input_semantics,
             // @TODO make this 0?
-            trace_slack: Some(Time::Real(Duration::from_secs(1))),
+            compaction: CompactionPolicy::Slack(Time::Real(Duration::from_secs(1))),
+            partitioning: Partitioning::Owner,
+            value_type: None,
+            ttl: None,
+            valid_time: false,
+            storage: Storage::Memory,
         }
     }
 
@@ -270,45 +673,112 @@ impl AttributeConfig {
     pub fn uncompacted(input_semantics: InputSemantics) -> Self {
         AttributeConfig {
             input_semantics,
-            trace_slack: None,
+            compaction: CompactionPolicy::None,
+            partitioning: Partitioning::Owner,
+            value_type: None,
+            ttl: None,
+            valid_time: false,
+            storage: Storage::Memory,
         }
     }
 }
 
+/// Whether a rule's results form a set or a multiset.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum RuleSemantics {
+    /// Each tuple is contained at most once, duplicates are removed
+    /// via `distinct`.
+    Set,
+    /// Tuples may be contained multiple times, their multiplicities
+    /// are merely consolidated.
+    Multiset,
+}
+
+impl Default for RuleSemantics {
+    #[cfg(feature = "set-semantics")]
+    fn default() -> Self {
+        RuleSemantics::Set
+    }
+
+    #[cfg(not(feature = "set-semantics"))]
+    fn default() -> Self {
+        RuleSemantics::Multiset
+    }
+}
+
+/// How a rule is implemented by the dataflows depending on it.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum RuleKind {
+    /// The rule is synthesized anew by every dataflow depending on
+    /// it, wrapped in a variable s.t. it may be defined recursively.
+    Recursive,
+    /// The rule is implemented once and its global arrangement
+    /// re-used by all dataflows depending on it. Cached rules must
+    /// not be part of a dependency cycle.
+    Cached,
+}
+
+impl Default for RuleKind {
+    fn default() -> Self {
+        RuleKind::Recursive
+    }
+}
+
 /// Per-relation semantics.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub struct RelationConfig<T>
 where
     T: Timestamp + Lattice + TotalOrder,
 {
-    /// How close the arranged trace should follow the computation
-    /// frontier.
-    pub trace_slack: Option<T>,
+    /// How the arranged trace should be compacted as the computation
+    /// frontier advances.
+    pub compaction: CompactionPolicy<T>,
+    /// Maximum number of iterations the relation's rule may take to
+    /// converge, if it is recursive. Rules exceeding their limit are
+    /// failed, see `Iterations`.
+    pub iteration_limit: Option<u64>,
+}
+
+/// Iterations taken by recursive rules, as reported by their
+/// dataflows on a single worker.
+#[derive(Clone, Debug, Default)]
+pub struct Iterations {
+    /// The most iterations each rule has taken to converge so far.
+    pub reached: HashMap<String, u64>,
+    /// Rules that exceeded their iteration limit since they were last
+    /// taken from here. Their dataflows stop iterating, but their
+    /// results are incomplete and must be torn down.
+    pub exceeded: HashSet<String>,
 }
 
 /// Various indices over a collection of (K, V) pairs, required to
 /// participate in delta-join pipelines.
-pub struct CollectionIndex<K, V, T>
+pub struct CollectionIndex<K, V, T, R = isize>
 where
-    K: Data,
-    V: Data,
-    T: Lattice + Data,
+    K: ExchangeData,
+    V: ExchangeData,
+    T: Lattice + ExchangeData,
+    R: ExchangeData + Semigroup,
 {
     /// A name uniquely identifying this index.
     pub name: String,
     /// A trace of type (K, ()), used to count extensions for each prefix.
-    count_trace: TraceKeyHandle<K, T, isize>,
+    count_trace: StoredKeyHandle<K, T, R>,
     /// A trace of type (K, V), used to propose extensions for each prefix.
-    propose_trace: TraceValHandle<K, V, T, isize>,
+    propose_trace: StoredValHandle<K, V, T, R>,
     /// A trace of type ((K, V), ()), used to validate proposed extensions.
-    validate_trace: TraceKeyHandle<(K, V), T, isize>,
+    validate_trace: StoredKeyHandle<(K, V), T, R>,
+    /// Maps (K, V) pairs onto keys of the validation trace, which
+    /// might be shared with an index in the opposite direction.
+    validate_key: Rc<dyn Fn(&K, &V) -> (K, V)>,
 }
 
-impl<K, V, T> Clone for CollectionIndex<K, V, T>
+impl<K, V, T, R> Clone for CollectionIndex<K, V, T, R>
 where
-    K: Data + Hash,
-    V: Data + Hash,
-    T: Lattice + Data + Timestamp,
+    K: ExchangeData + Hash,
+    V: ExchangeData + Hash,
+    T: Lattice + ExchangeData + Timestamp,
+    R: ExchangeData + Semigroup,
 {
     fn clone(&self) -> Self {
         CollectionIndex {
@@ -316,20 +786,22 @@ where
             count_trace: self.count_trace.clone(),
             propose_trace: self.propose_trace.clone(),
             validate_trace: self.validate_trace.clone(),
+            validate_key: self.validate_key.clone(),
         }
     }
 }
 
-impl<K, V, T> CollectionIndex<K, V, T>
+impl<K, V, T, R> CollectionIndex<K, V, T, R>
 where
-    K: Data + Hash,
-    V: Data + Hash,
-    T: Lattice + Data + Timestamp,
+    K: ExchangeData + Hash,
+    V: ExchangeData + Hash,
+    T: Lattice + ExchangeData + Timestamp,
+    R: ExchangeData + Semigroup,
 {
     /// Creates a named CollectionIndex from a (K, V) collection.
     pub fn index<G: Scope<Timestamp = T>>(
         name: &str,
-        collection: &Collection<G, (K, V), isize>,
+        collection: &Collection<G, (K, V), R>,
     ) -> Self {
         let mut count_trace = collection
             .map(|(k, _v)| (k, ()))
@@ -352,6 +824,7 @@ where
             count_trace,
             propose_trace,
             validate_trace,
+            validate_key: Rc::new(|k: &K, v: &V| (k.clone(), v.clone())),
         }
     }
 
@@ -364,9 +837,10 @@ where
             G,
             K,
             V,
-            TraceKeyHandle<K, T, isize>,
-            TraceValHandle<K, V, T, isize>,
-            TraceKeyHandle<(K, V), T, isize>,
+            StoredKeyHandle<K, T, R>,
+            StoredValHandle<K, V, T, R>,
+            StoredKeyHandle<(K, V), T, R>,
+            R,
         >,
         ShutdownHandle,
     ) {
@@ -384,6 +858,7 @@ where
             count,
             propose,
             validate,
+            validate_key: self.validate_key.clone(),
         };
 
         let mut shutdown_handle = ShutdownHandle::empty();
@@ -402,51 +877,129 @@ where
     }
 }
 
+impl<K, V, T> CollectionIndex<K, V, T, isize>
+where
+    K: ExchangeData + Hash,
+    V: ExchangeData + Hash,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    /// Counts the distinct keys and the tuples in this index, as
+    /// held by its count trace. Only the shard maintained by the
+    /// current worker is taken into account.
+    pub fn statistics(&mut self) -> (usize, isize) {
+        let (mut cursor, storage) = self.count_trace.cursor();
+        let (mut keys, mut tuples) = (0, 0);
+
+        while cursor.key_valid(&storage) {
+            let mut count = 0;
+            cursor.map_times(&storage, |_time, diff| count += diff);
+
+            if count > 0 {
+                keys += 1;
+                tuples += count;
+            }
+
+            cursor.step_key(&storage);
+        }
+
+        (keys, tuples)
+    }
+
+    /// Estimates the number of tuples in this index from its count
+    /// trace. Only the shard maintained by the current worker is
+    /// taken into account.
+    pub fn cardinality(&mut self) -> isize {
+        self.statistics().1
+    }
+}
+
+impl<K, T, R> CollectionIndex<K, K, T, R>
+where
+    K: ExchangeData + Hash,
+    T: Lattice + ExchangeData + Timestamp,
+    R: ExchangeData + Semigroup,
+{
+    /// Creates the reverse of this index, from the (K, K) collection
+    /// it was created from. A (k, v) pair is valid in one direction
+    /// iff (v, k) is valid in the other, so both share a single
+    /// validation trace, which the reverse index probes with
+    /// transposed keys.
+    pub fn reverse<G: Scope<Timestamp = T>>(&self, collection: &Collection<G, (K, K), R>) -> Self {
+        let name = &self.name;
+        let transposed = collection.map(|(k, v)| (v, k));
+
+        let mut count_trace = transposed
+            .map(|(k, _v)| (k, ()))
+            .arrange_named(&format!("Counts({})", name))
+            .trace;
+        let mut propose_trace = transposed
+            .arrange_named(&format!("Proposals({})", name))
+            .trace;
+
+        count_trace.distinguish_since(&[]);
+        propose_trace.distinguish_since(&[]);
+
+        CollectionIndex {
+            name: name.to_string(),
+            count_trace,
+            propose_trace,
+            validate_trace: self.validate_trace.clone(),
+            validate_key: Rc::new(|k: &K, v: &K| (v.clone(), k.clone())),
+        }
+    }
+}
+
 /// CollectionIndex that was imported into a scope.
-pub struct LiveIndex<G, K, V, TrCount, TrPropose, TrValidate>
+pub struct LiveIndex<G, K, V, TrCount, TrPropose, TrValidate, R = isize>
 where
     G: Scope,
     G::Timestamp: Lattice + Data,
     K: Data,
     V: Data,
-    TrCount: TraceReader<K, (), G::Timestamp, isize> + Clone,
-    TrPropose: TraceReader<K, V, G::Timestamp, isize> + Clone,
-    TrValidate: TraceReader<(K, V), (), G::Timestamp, isize> + Clone,
+    TrCount: TraceReader<K, (), G::Timestamp, R> + Clone,
+    TrPropose: TraceReader<K, V, G::Timestamp, R> + Clone,
+    TrValidate: TraceReader<(K, V), (), G::Timestamp, R> + Clone,
+    R: Semigroup,
 {
-    count: Arranged<G, K, (), isize, TrCount>,
-    propose: Arranged<G, K, V, isize, TrPropose>,
-    validate: Arranged<G, (K, V), (), isize, TrValidate>,
+    count: Arranged<G, K, (), R, TrCount>,
+    propose: Arranged<G, K, V, R, TrPropose>,
+    validate: Arranged<G, (K, V), (), R, TrValidate>,
+    validate_key: Rc<dyn Fn(&K, &V) -> (K, V)>,
 }
 
-impl<G, K, V, TrCount, TrPropose, TrValidate> Clone
-    for LiveIndex<G, K, V, TrCount, TrPropose, TrValidate>
+impl<G, K, V, TrCount, TrPropose, TrValidate, R> Clone
+    for LiveIndex<G, K, V, TrCount, TrPropose, TrValidate, R>
 where
     G: Scope,
     G::Timestamp: Lattice + Data,
     K: Data,
     V: Data,
-    TrCount: TraceReader<K, (), G::Timestamp, isize> + Clone,
-    TrPropose: TraceReader<K, V, G::Timestamp, isize> + Clone,
-    TrValidate: TraceReader<(K, V), (), G::Timestamp, isize> + Clone,
+    TrCount: TraceReader<K, (), G::Timestamp, R> + Clone,
+    TrPropose: TraceReader<K, V, G::Timestamp, R> + Clone,
+    TrValidate: TraceReader<(K, V), (), G::Timestamp, R> + Clone,
+    R: Semigroup,
 {
     fn clone(&self) -> Self {
         LiveIndex {
             count: self.count.clone(),
             propose: self.propose.clone(),
             validate: self.validate.clone(),
+            validate_key: self.validate_key.clone(),
         }
     }
 }
 
-impl<G, K, V, TrCount, TrPropose, TrValidate> LiveIndex<G, K, V, TrCount, TrPropose, TrValidate>
+impl<G, K, V, TrCount, TrPropose, TrValidate, R>
+    LiveIndex<G, K, V, TrCount, TrPropose, TrValidate, R>
 where
     G: Scope,
     G::Timestamp: Lattice + Data,
     K: Data,
     V: Data,
-    TrCount: TraceReader<K, (), G::Timestamp, isize> + Clone,
-    TrPropose: TraceReader<K, V, G::Timestamp, isize> + Clone,
-    TrValidate: TraceReader<(K, V), (), G::Timestamp, isize> + Clone,
+    TrCount: TraceReader<K, (), G::Timestamp, R> + Clone,
+    TrPropose: TraceReader<K, V, G::Timestamp, R> + Clone,
+    TrValidate: TraceReader<(K, V), (), G::Timestamp, R> + Clone,
+    R: Semigroup,
 {
     /// Brings the index's traces into the specified scope.
     pub fn enter<'a, TInner>(
@@ -456,9 +1009,10 @@ where
         Child<'a, G, TInner>,
         K,
         V,
-        TraceEnter<K, (), G::Timestamp, isize, TrCount, TInner>,
-        TraceEnter<K, V, G::Timestamp, isize, TrPropose, TInner>,
-        TraceEnter<(K, V), (), G::Timestamp, isize, TrValidate, TInner>,
+        TraceEnter<K, (), G::Timestamp, R, TrCount, TInner>,
+        TraceEnter<K, V, G::Timestamp, R, TrPropose, TInner>,
+        TraceEnter<(K, V), (), G::Timestamp, R, TrValidate, TInner>,
+        R,
     >
     where
         TrCount::Batch: Clone,
@@ -473,6 +1027,7 @@ where
             count: self.count.enter(child),
             propose: self.propose.enter(child),
             validate: self.validate.enter(child),
+            validate_key: self.validate_key.clone(),
         }
     }
 
@@ -487,9 +1042,10 @@ where
         Child<'a, G, TInner>,
         K,
         V,
-        TraceEnterAt<K, (), G::Timestamp, isize, TrCount, TInner, FCount>,
-        TraceEnterAt<K, V, G::Timestamp, isize, TrPropose, TInner, FPropose>,
-        TraceEnterAt<(K, V), (), G::Timestamp, isize, TrValidate, TInner, FValidate>,
+        TraceEnterAt<K, (), G::Timestamp, R, TrCount, TInner, FCount>,
+        TraceEnterAt<K, V, G::Timestamp, R, TrPropose, TInner, FPropose>,
+        TraceEnterAt<(K, V), (), G::Timestamp, R, TrValidate, TInner, FValidate>,
+        R,
     >
     where
         TrCount::Batch: Clone,
@@ -507,6 +1063,7 @@ where
             count: self.count.enter_at(child, fcount),
             propose: self.propose.enter_at(child, fpropose),
             validate: self.validate.enter_at(child, fvalidate),
+            validate_key: self.validate_key.clone(),
         }
     }
 }
@@ -519,6 +1076,9 @@ type Var = u32;
 pub struct Rule {
     /// The name identifying the relation.
     pub name: String,
+    /// Whether the relation is synthesized recursively or re-used.
+    #[serde(default)]
+    pub kind: RuleKind,
     /// The plan describing contents of the relation.
     pub plan: Plan,
 }
@@ -775,6 +1335,31 @@ pub fn q(target_variables: Vec<Var>, bindings: Vec<Binding>) -> Plan {
 /// Returns a deduplicates list of all rules used in the definition of
 /// the specified names. Includes the specified names.
 pub fn collect_dependencies<T, I>(context: &I, names: &[&str]) -> Result<Vec<Rule>, Error>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+{
+    collect_rules(context, names, true)
+}
+
+/// Returns a deduplicated list of the rules that have to be
+/// synthesized along with the specified names, i.e. the specified
+/// names and all recursive rules they depend on. Rules are not
+/// collected beyond cached or already implemented rules, which are
+/// imported from their global arrangements instead.
+pub fn collect_local_dependencies<T, I>(context: &I, names: &[&str]) -> Result<Vec<Rule>, Error>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+{
+    collect_rules(context, names, false)
+}
+
+fn collect_rules<T, I>(
+    context: &I,
+    names: &[&str],
+    include_cached: bool,
+) -> Result<Vec<Rule>, Error>
 where
     T: Timestamp + Lattice + TotalOrder,
     I: ImplContext<T>,
@@ -787,7 +1372,7 @@ where
         match context.rule(name) {
             None => {
                 return Err(Error {
-                    category: "df.error.category/not-found",
+                    category: ErrorKind::NotFound,
                     message: format!("Unknown rule {}.", name),
                 });
             }
@@ -805,13 +1390,19 @@ where
                 match context.rule(dep_name) {
                     None => {
                         return Err(Error {
-                            category: "df.error.category/not-found",
+                            category: ErrorKind::NotFound,
                             message: format!("Unknown rule {}", dep_name),
                         });
                     }
                     Some(rule) => {
                         seen.insert(dep_name.to_string());
-                        queue.push_back(rule.clone());
+
+                        if include_cached
+                            || (context.is_underconstrained(dep_name)
+                                && !context.is_implemented(dep_name))
+                        {
+                            queue.push_back(rule.clone());
+                        }
                     }
                 }
             }
@@ -821,7 +1412,7 @@ where
         for aid in dependencies.attributes.iter() {
             if !context.has_attribute(aid) {
                 return Err(Error {
-                    category: "df.error.category/not-found",
+                    category: ErrorKind::NotFound,
                     message: format!("Rule depends on unknown attribute {}", aid),
                 });
             }
@@ -833,6 +1424,193 @@ where
     Ok(rules)
 }
 
+/// Checks the dependency graph between the given rules for cycles. A
+/// cycle is only allowed if all rules participating in it are
+/// implemented recursively, i.e. are underconstrained. Otherwise an
+/// error naming the rules in the cycle is returned.
+pub fn check_cycles<T, I>(context: &I, rules: &[Rule]) -> Result<(), Error>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+{
+    let names: HashMap<&str, usize> = rules
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| (rule.name.as_str(), index))
+        .collect();
+
+    let edges: Vec<Vec<usize>> = rules
+        .iter()
+        .map(|rule| {
+            let mut targets: Vec<usize> = rule
+                .plan
+                .dependencies()
+                .names
+                .iter()
+                .filter_map(|name| names.get(name.as_str()).cloned())
+                .collect();
+            targets.sort();
+            targets
+        })
+        .collect();
+
+    for component in strongly_connected(&edges).into_iter() {
+        let cyclic = component.len() > 1 || edges[component[0]].contains(&component[0]);
+
+        if cyclic {
+            let mut members: Vec<&str> = component
+                .iter()
+                .map(|index| rules[*index].name.as_str())
+                .collect();
+            members.sort();
+
+            if members
+                .iter()
+                .any(|name| !context.is_underconstrained(name))
+            {
+                let message = if members.len() == 1 {
+                    format!(
+                        "Rule {} depends on itself, but is not recursive.",
+                        members[0]
+                    )
+                } else {
+                    format!(
+                        "Rules {} depend on each other cyclically, but are not all recursive.",
+                        members.join(", ")
+                    )
+                };
+
+                return Err(Error {
+                    category: ErrorKind::Unsupported,
+                    message,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Tarjan's algorithm, returning the strongly connected components of
+/// a graph given as adjacency lists.
+fn strongly_connected(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        edges: &'a [Vec<usize>],
+        next_index: usize,
+        index: Vec<Option<usize>>,
+        lowlink: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(state: &mut State, node: usize) {
+        state.index[node] = Some(state.next_index);
+        state.lowlink[node] = state.next_index;
+        state.next_index += 1;
+        state.stack.push(node);
+        state.on_stack[node] = true;
+
+        for &target in state.edges[node].iter() {
+            match state.index[target] {
+                None => {
+                    visit(state, target);
+                    state.lowlink[node] = state.lowlink[node].min(state.lowlink[target]);
+                }
+                Some(index) if state.on_stack[target] => {
+                    state.lowlink[node] = state.lowlink[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(state.lowlink[node]) == state.index[node] {
+            let mut component = Vec::new();
+            loop {
+                let member = state.stack.pop().unwrap();
+                state.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        edges,
+        next_index: 0,
+        index: vec![None; edges.len()],
+        lowlink: vec![0; edges.len()],
+        stack: Vec::new(),
+        on_stack: vec![false; edges.len()],
+        components: Vec::new(),
+    };
+
+    for node in 0..edges.len() {
+        if state.index[node].is_none() {
+            visit(&mut state, node);
+        }
+    }
+
+    state.components
+}
+
+/// Reports the iterations a recursive rule takes to the given
+/// tracker, and drops all of its updates from the specified iteration
+/// onwards, s.t. a rule that fails to converge stops iterating. Rules
+/// exceeding their limit are marked as such, for their results to be
+/// torn down.
+fn limit_iterations<'a, G>(
+    name: &str,
+    tuples: &Collection<Iterative<'a, G, u64>, Vec<Value>, isize>,
+    limit: Option<u64>,
+    iterations: Option<Rc<RefCell<Iterations>>>,
+) -> Collection<Iterative<'a, G, u64>, Vec<Value>, isize>
+where
+    G: Scope,
+    G::Timestamp: Lattice,
+{
+    let name = name.to_string();
+    let reached = Cell::new(0);
+    let exceeded = Cell::new(false);
+
+    tuples
+        .inner
+        .filter(move |(_tuple, time, _diff)| {
+            if time.inner > reached.get() {
+                reached.set(time.inner);
+                debug!("Rule {} reached iteration {}", name, time.inner);
+
+                if let Some(ref iterations) = iterations {
+                    let mut iterations = iterations.borrow_mut();
+                    let most = iterations.reached.entry(name.clone()).or_insert(0);
+                    *most = std::cmp::max(*most, time.inner);
+                }
+            }
+
+            match limit {
+                Some(limit) if time.inner >= limit => {
+                    if !exceeded.replace(true) {
+                        error!(
+                            "Rule {} did not converge within {} iterations.",
+                            name, limit
+                        );
+
+                        if let Some(ref iterations) = iterations {
+                            iterations.borrow_mut().exceeded.insert(name.clone());
+                        }
+                    }
+
+                    false
+                }
+                _ => true,
+            }
+        })
+        .as_collection()
+}
+
 /// Takes a query plan and turns it into a differential dataflow.
 pub fn implement<T, I, S>(
     name: &str,
@@ -852,7 +1630,7 @@ where
 {
     scope.iterative::<u64, _, _>(|nested| {
         let publish = vec![name];
-        let mut rules = collect_dependencies(&*context, &publish[..])?;
+        let mut rules = collect_local_dependencies(&*context, &publish[..])?;
 
         let mut local_arrangements = VariableMap::new();
         let mut result_map = HashMap::new();
@@ -860,29 +1638,31 @@ where
         // Step 0: Canonicalize, check uniqueness of bindings.
         if rules.is_empty() {
             return Err(Error {
-                category: "df.error.category/not-found",
+                category: ErrorKind::NotFound,
                 message: format!("Couldn't find any rules for name {}.", name),
             });
         }
 
         rules.sort_by(|x, y| x.name.cmp(&y.name));
-        for index in 1..rules.len() - 1 {
+        for index in 1..rules.len() {
             if rules[index].name == rules[index - 1].name {
                 return Err(Error {
-                    category: "df.error.category/conflict",
+                    category: ErrorKind::Conflict,
                     message: format!("Duplicate rule definitions for rule {}", rules[index].name),
                 });
             }
         }
 
-        // Step 1: Create new recursive variables for each rule.
+        check_cycles(&*context, &rules)?;
+
+        // Step 1: Create new recursive variables for each rule. Cached
+        // and already implemented dependencies are not among them,
+        // they are imported from their global arrangements instead.
         for rule in rules.iter() {
-            if context.is_underconstrained(&rule.name) {
-                local_arrangements.insert(
-                    rule.name.clone(),
-                    Variable::new(nested, Product::new(Default::default(), 1)),
-                );
-            }
+            local_arrangements.insert(
+                rule.name.clone(),
+                Variable::new(nested, Product::new(Default::default(), 1)),
+            );
         }
 
         // Step 2: Create public arrangements for published relations.
@@ -891,7 +1671,7 @@ where
                 result_map.insert(name.to_string(), relation.leave());
             } else {
                 return Err(Error {
-                    category: "df.error.category/not-found",
+                    category: ErrorKind::NotFound,
                     message: format!("Attempted to publish undefined name {}.", name),
                 });
             }
@@ -902,7 +1682,7 @@ where
         let mut shutdown_handle = ShutdownHandle::empty();
         for rule in rules.iter() {
             info!("planning {:?}", rule.name);
-            let (relation, shutdown) = rule.plan.implement(nested, &local_arrangements, context);
+            let (relation, shutdown) = rule.plan.implement(nested, &local_arrangements, context)?;
 
             executions.push(relation);
             shutdown_handle.merge_with(shutdown);
@@ -913,7 +1693,7 @@ where
             match local_arrangements.remove(&rule.name) {
                 None => {
                     return Err(Error {
-                        category: "df.error.category/not-found",
+                        category: ErrorKind::NotFound,
                         message: format!(
                             "Rule {} should be in local arrangements, but isn't.",
                             &rule.name
@@ -921,11 +1701,18 @@ where
                     });
                 }
                 Some(variable) => {
-                    #[cfg(feature = "set-semantics")]
-                    variable.set(&execution.tuples().distinct());
-
-                    #[cfg(not(feature = "set-semantics"))]
-                    variable.set(&execution.tuples().consolidate());
+                    let limit = context.iteration_limit(&rule.name);
+                    let tuples = match (limit, context.iterations()) {
+                        (None, None) => execution.tuples(),
+                        (limit, iterations) => {
+                            limit_iterations(&rule.name, &execution.tuples(), limit, iterations)
+                        }
+                    };
+
+                    match context.rule_semantics(&rule.name) {
+                        RuleSemantics::Set => variable.set(&tuples.distinct()),
+                        RuleSemantics::Multiset => variable.set(&tuples.consolidate()),
+                    }
                 }
             }
         }
@@ -953,7 +1740,7 @@ where
 {
     scope.iterative::<u64, _, _>(move |nested| {
         let publish = vec![name];
-        let mut rules = collect_dependencies(&*context, &publish[..])?;
+        let mut rules = collect_local_dependencies(&*context, &publish[..])?;
 
         let mut local_arrangements = VariableMap::new();
         let mut result_map = HashMap::new();
@@ -961,37 +1748,32 @@ where
         // Step 0: Canonicalize, check uniqueness of bindings.
         if rules.is_empty() {
             return Err(Error {
-                category: "df.error.category/not-found",
+                category: ErrorKind::NotFound,
                 message: format!("Couldn't find any rules for name {}.", name),
             });
         }
 
         rules.sort_by(|x, y| x.name.cmp(&y.name));
-        for index in 1..rules.len() - 1 {
+        for index in 1..rules.len() {
             if rules[index].name == rules[index - 1].name {
                 return Err(Error {
-                    category: "df.error.category/conflict",
+                    category: ErrorKind::Conflict,
                     message: format!("Duplicate rule definitions for rule {}", rules[index].name),
                 });
             }
         }
 
-        // @TODO at this point we need to know about...
-        // @TODO ... which rules require recursion (and thus need wrapping in a Variable)
-        // @TODO ... which rules are supposed to be re-used
-        // @TODO ... which rules are supposed to be re-synthesized
-        //
-        // but based entirely on control data written to the server by something external
-        // (for the old implement it could just be a decision based on whether the rule has a namespace)
-
-        // Step 1: Create new recursive variables for each rule.
-        for name in publish.iter() {
-            if context.is_underconstrained(name) {
-                local_arrangements.insert(
-                    name.to_string(),
-                    Variable::new(nested, Product::new(Default::default(), 1)),
-                );
-            }
+        check_cycles(&*context, &rules)?;
+
+        // Step 1: Create new recursive variables for each rule. Which
+        // rules are re-synthesized and which are re-used is decided
+        // by their kind, cached and already implemented dependencies
+        // are imported from their global arrangements instead.
+        for rule in rules.iter() {
+            local_arrangements.insert(
+                rule.name.clone(),
+                Variable::new(nested, Product::new(Default::default(), 1)),
+            );
         }
 
         // Step 2: Create public arrangements for published relations.
@@ -1000,7 +1782,7 @@ where
                 result_map.insert(name.to_string(), relation.leave());
             } else {
                 return Err(Error {
-                    category: "df.error.category/not-found",
+                    category: ErrorKind::NotFound,
                     message: format!("Attempted to publish undefined name {}.", name),
                 });
             }
@@ -1012,9 +1794,14 @@ where
         for rule in rules.iter() {
             info!("neu_planning {:?}", rule.name);
 
-            let plan = q(rule.plan.variables(), rule.plan.into_bindings());
-
-            let (relation, shutdown) = plan.implement(nested, &local_arrangements, context);
+            // Negated conjunctions can't be expressed as bindings, so
+            // such rules are implemented as they are.
+            let (relation, shutdown) = if rule.plan.negates_conjunction() {
+                rule.plan.implement(nested, &local_arrangements, context)?
+            } else {
+                let plan = q(rule.plan.variables(), rule.plan.into_bindings());
+                plan.implement(nested, &local_arrangements, context)?
+            };
 
             executions.push(relation);
             shutdown_handle.merge_with(shutdown);
@@ -1025,7 +1812,7 @@ where
             match local_arrangements.remove(&rule.name) {
                 None => {
                     return Err(Error {
-                        category: "df.error.category/not-found",
+                        category: ErrorKind::NotFound,
                         message: format!(
                             "Rule {} should be in local arrangements, but isn't.",
                             &rule.name
@@ -1033,11 +1820,18 @@ where
                     });
                 }
                 Some(variable) => {
-                    #[cfg(feature = "set-semantics")]
-                    variable.set(&execution.tuples().distinct());
-
-                    #[cfg(not(feature = "set-semantics"))]
-                    variable.set(&execution.tuples().consolidate());
+                    let limit = context.iteration_limit(&rule.name);
+                    let tuples = match (limit, context.iterations()) {
+                        (None, None) => execution.tuples(),
+                        (limit, iterations) => {
+                            limit_iterations(&rule.name, &execution.tuples(), limit, iterations)
+                        }
+                    };
+
+                    match context.rule_semantics(&rule.name) {
+                        RuleSemantics::Set => variable.set(&tuples.distinct()),
+                        RuleSemantics::Multiset => variable.set(&tuples.consolidate()),
+                    }
                 }
             }
         }
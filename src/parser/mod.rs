@@ -0,0 +1,349 @@
+//! Parser for a small, SPARQL-like triple-pattern query language.
+//!
+//! Queries consist of a header naming the target variables, followed
+//! by one triple pattern per clause:
+//!
+//! ```text
+//! select ?e ?name
+//! where {
+//!   ?e :name ?name .
+//!   ?e :age 30 .
+//! }
+//! ```
+//!
+//! Variables are prefixed with `?`. The `where` keyword, the braces
+//! and the trailing dots are optional. Entities may be given as
+//! integers, values as integers, `true`/`false`, double-quoted
+//! strings, or bare attribute identifiers. Constants are bound to
+//! fresh variables via constant bindings, thus producing the same
+//! Hector plan as the `q` helper.
+
+use std::collections::HashMap;
+
+use crate::binding::{AsBinding, Binding};
+use crate::{q, Aid, Error, ErrorKind, Plan, Value, Var};
+
+/// A parsed triple-pattern query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    /// Target variables, in the order they were listed.
+    pub variables: Vec<Var>,
+    /// Bindings constraining the variables.
+    pub bindings: Vec<Binding>,
+    /// Names of all variables, indexed by variable.
+    pub symbols: Vec<String>,
+}
+
+impl Query {
+    /// Returns the Hector plan for this query.
+    pub fn into_plan(self) -> Plan {
+        q(self.variables, self.bindings)
+    }
+}
+
+/// Parses a textual query into target variables and bindings.
+pub fn parse(input: &str) -> Result<Query, Error> {
+    Parser::new(tokenize(input)?).query()
+}
+
+/// Parses a textual query straight into a Hector plan.
+pub fn parse_plan(input: &str) -> Result<Plan, Error> {
+    parse(input).map(Query::into_plan)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Variable(String),
+    Word(String),
+    String(String),
+    Number(i64),
+    Open,
+    Close,
+    Dot,
+}
+
+fn incorrect(line: usize, message: &str) -> Error {
+    Error {
+        category: ErrorKind::Incorrect,
+        message: format!("Line {}: {}", line, message),
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let mut tokens = Vec::new();
+
+    for (idx, line) in input.lines().enumerate() {
+        let line_number = idx + 1;
+        let mut chars = line.chars().peekable();
+
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == '#' {
+                break;
+            } else if c == '{' || c == '}' {
+                chars.next();
+                let token = if c == '{' { Token::Open } else { Token::Close };
+                tokens.push((token, line_number));
+            } else if c == '"' {
+                chars.next();
+                let mut string = String::new();
+                let mut terminated = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => {
+                            terminated = true;
+                            break;
+                        }
+                        '\\' => match chars.next() {
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some(other) => string.push(other),
+                            None => break,
+                        },
+                        other => string.push(other),
+                    }
+                }
+
+                if !terminated {
+                    return Err(incorrect(line_number, "unterminated string literal"));
+                }
+
+                tokens.push((Token::String(string), line_number));
+            } else {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                // A trailing dot terminates the pattern, unless it is
+                // all there is.
+                let dotted = word.len() > 1 && word.ends_with('.');
+                if dotted {
+                    word.pop();
+                }
+
+                tokens.push((classify(word, line_number)?, line_number));
+
+                if dotted {
+                    tokens.push((Token::Dot, line_number));
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn classify(word: String, line: usize) -> Result<Token, Error> {
+    if word == "." {
+        Ok(Token::Dot)
+    } else if word.starts_with('?') {
+        if word.len() == 1 {
+            Err(incorrect(line, "variables must be named, e.g. ?e"))
+        } else {
+            Ok(Token::Variable(word[1..].to_string()))
+        }
+    } else if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        word.parse::<i64>()
+            .map(Token::Number)
+            .map_err(|_| incorrect(line, &format!("{} is not a valid integer", word)))
+    } else {
+        Ok(Token::Word(word))
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    symbols: HashMap<String, Var>,
+    names: Vec<String>,
+}
+
+impl Parser {
+    fn new(tokens: Vec<(Token, usize)>) -> Self {
+        Parser {
+            tokens,
+            position: 0,
+            symbols: HashMap::new(),
+            names: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        match self.tokens.get(self.position) {
+            None => self.tokens.last().map(|(_, line)| *line).unwrap_or(1),
+            Some((_, line)) => *line,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn variable(&mut self, name: &str) -> Var {
+        let names = &mut self.names;
+        *self.symbols.entry(name.to_string()).or_insert_with(|| {
+            names.push(name.to_string());
+            (names.len() - 1) as Var
+        })
+    }
+
+    /// Allocates a variable for a constant, which can not be
+    /// referred to by name.
+    fn fresh(&mut self) -> Var {
+        self.names.push(format!("_{}", self.names.len()));
+        (self.names.len() - 1) as Var
+    }
+
+    fn query(mut self) -> Result<Query, Error> {
+        if !self.keyword("select") && !self.keyword("find") {
+            return Err(incorrect(
+                self.line(),
+                "queries must start with select or find",
+            ));
+        }
+
+        let mut variables = Vec::new();
+        while let Some(Token::Variable(name)) = self.peek().cloned() {
+            self.position += 1;
+            variables.push(self.variable(&name));
+        }
+
+        if variables.is_empty() {
+            return Err(incorrect(
+                self.line(),
+                "expected at least one target variable",
+            ));
+        }
+
+        self.keyword("where");
+
+        let braced = self.peek() == Some(&Token::Open);
+        if braced {
+            self.position += 1;
+        }
+
+        let mut bindings = Vec::new();
+        loop {
+            match self.peek() {
+                None if braced => return Err(incorrect(self.line(), "expected }")),
+                None => break,
+                Some(Token::Close) if braced => {
+                    self.position += 1;
+                    if self.peek().is_some() {
+                        return Err(incorrect(self.line(), "unexpected input after }"));
+                    }
+                    break;
+                }
+                Some(Token::Dot) => {
+                    self.position += 1;
+                }
+                Some(_) => self.pattern(&mut bindings)?,
+            }
+        }
+
+        if bindings.is_empty() {
+            return Err(incorrect(self.line(), "expected at least one pattern"));
+        }
+
+        for variable in variables.iter() {
+            let bound = bindings
+                .iter()
+                .any(|binding| binding.variables().contains(variable));
+
+            if !bound {
+                let name = &self.names[*variable as usize];
+                return Err(incorrect(
+                    self.line(),
+                    &format!("?{} is not bound by any pattern", name),
+                ));
+            }
+        }
+
+        Ok(Query {
+            variables,
+            bindings,
+            symbols: self.names,
+        })
+    }
+
+    fn pattern(&mut self, bindings: &mut Vec<Binding>) -> Result<(), Error> {
+        let line = self.line();
+
+        let e = match self.next() {
+            Some(Token::Variable(name)) => self.variable(&name),
+            Some(Token::Number(eid)) if eid >= 0 => {
+                let e = self.fresh();
+                bindings.push(Binding::constant(e, Value::Eid(eid as u64)));
+                e
+            }
+            other => return Err(unexpected(line, "an entity", other)),
+        };
+
+        let a: Aid = match self.next() {
+            Some(Token::Word(name)) => Aid::from(name),
+            other => return Err(unexpected(line, "an attribute", other)),
+        };
+
+        let v = match self.next() {
+            Some(Token::Variable(name)) => self.variable(&name),
+            Some(token) => {
+                let value = match token {
+                    Token::Word(ref word) if word == "true" => Value::Bool(true),
+                    Token::Word(ref word) if word == "false" => Value::Bool(false),
+                    Token::Word(word) => Value::Aid(Aid::from(word)),
+                    Token::String(string) => Value::String(string),
+                    Token::Number(number) => Value::Number(number),
+                    other => return Err(unexpected(line, "a value", Some(other))),
+                };
+
+                let v = self.fresh();
+                bindings.push(Binding::constant(v, value));
+                v
+            }
+            None => return Err(unexpected(line, "a value", None)),
+        };
+
+        bindings.push(Binding::attribute(e, &a, v));
+
+        Ok(())
+    }
+}
+
+fn unexpected(line: usize, expected: &str, found: Option<Token>) -> Error {
+    let found = match found {
+        None => "end of input".to_string(),
+        Some(Token::Variable(name)) => format!("?{}", name),
+        Some(Token::Word(word)) => word,
+        Some(Token::String(string)) => format!("{:?}", string),
+        Some(Token::Number(number)) => number.to_string(),
+        Some(Token::Open) => "{".to_string(),
+        Some(Token::Close) => "}".to_string(),
+        Some(Token::Dot) => ".".to_string(),
+    };
+
+    incorrect(line, &format!("expected {}, found {}", expected, found))
+}
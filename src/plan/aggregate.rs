@@ -1,5 +1,6 @@
 //! Aggregate expression plan.
 
+use timely::dataflow::operators::ToStream;
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
 use timely::order::TotalOrder;
@@ -9,10 +10,11 @@ use differential_dataflow::difference::DiffPair;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::operators::Join as JoinMap;
 use differential_dataflow::operators::{Count, Reduce, Threshold};
+use differential_dataflow::AsCollection;
 
 use crate::binding::{AsBinding, Binding};
 use crate::plan::{Dependencies, ImplContext, Implementable};
-use crate::{CollectionRelation, Relation, ShutdownHandle, Value, Var, VariableMap};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, Value, Var, VariableMap};
 
 use num_rational::{Ratio, Rational32};
 
@@ -33,6 +35,8 @@ pub enum AggregationFn {
     AVG,
     /// Variance
     VARIANCE,
+    /// All values, as a single sorted list
+    LIST,
     // /// Standard deviation
     // STDDEV,
 }
@@ -40,6 +44,9 @@ pub enum AggregationFn {
 /// [WIP] A plan stage applying the specified aggregation functions to
 /// bindings for the specified variables. Given multiple aggregations
 /// we iterate and n-1 joins are applied to the results.
+///
+/// Without any key variables, the aggregations are applied to the
+/// relation as a whole, producing at most a single tuple.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Aggregate<P: Implementable> {
     /// TODO
@@ -54,6 +61,10 @@ pub struct Aggregate<P: Implementable> {
     pub aggregation_variables: Vec<Var>,
     /// With variables
     pub with_variables: Vec<Var>,
+    /// Whether an ungrouped COUNT over an empty relation should
+    /// produce a count of zero, rather than no tuple at all.
+    #[serde(default)]
+    pub count_empty: bool,
 }
 
 impl<P: Implementable> Implementable for Aggregate<P> {
@@ -70,13 +81,14 @@ impl<P: Implementable> Implementable for Aggregate<P> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        let (relation, shutdown_handle) = self.plan.implement(nested, local_arrangements, context);
+        let (relation, shutdown_handle) =
+            self.plan.implement(nested, local_arrangements, context)?;
 
         // We split the incoming tuples into their (key, value) parts.
         let tuples = relation.tuples_by_variables(&self.key_variables);
@@ -164,12 +176,40 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                         .map(move |(key, med)| (key, vec![med]));
                     collections.push(tuples);
                 }
+                AggregationFn::LIST => {
+                    let tuples = tuples
+                        .map(prepare_unary)
+                        .reduce(|_key, vals, output| {
+                            // Values arrive in sorted order.
+                            let list = vals.iter().map(|(val, _count)| val[0].clone()).collect();
+                            output.push((Value::List(list), 1));
+                        })
+                        .map(move |(key, list)| (key, vec![list]));
+                    collections.push(tuples);
+                }
                 AggregationFn::COUNT => {
                     let tuples = tuples
                         .map(prepare_unary)
                         .reduce(|_key, input, output| output.push((input.len(), 1)))
                         .map(move |(key, count)| (key, vec![Value::Number(count as i64)]));
-                    collections.push(tuples);
+
+                    if self.count_empty && self.key_variables.is_empty() {
+                        // There is at most one count, so subtracting
+                        // it from a constant zero leaves the zero in
+                        // place exactly when the input is empty.
+                        let zero = Some((vec![], vec![Value::Number(0)]))
+                            .into_iter()
+                            .map(|x| (x, Default::default(), 1))
+                            .to_stream(nested)
+                            .as_collection();
+
+                        let empty =
+                            zero.concat(&tuples.map(|_| (vec![], vec![Value::Number(0)])).negate());
+
+                        collections.push(tuples.concat(&empty));
+                    } else {
+                        collections.push(tuples);
+                    }
                 }
                 AggregationFn::SUM => {
                     let tuples = tuples
@@ -277,6 +317,6 @@ impl<P: Implementable> Implementable for Aggregate<P> {
             }
         };
 
-        (aggregated, shutdown_handle)
+        Ok((aggregated, shutdown_handle))
     }
 }
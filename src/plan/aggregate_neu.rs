@@ -1,5 +1,6 @@
 //! Aggregate expression plan.
 
+use timely::dataflow::operators::ToStream;
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
 use timely::order::TotalOrder;
@@ -9,10 +10,11 @@ use differential_dataflow::difference::DiffPair;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::operators::Join as JoinMap;
 use differential_dataflow::operators::{Count, Reduce};
+use differential_dataflow::AsCollection;
 
 use crate::binding::{AsBinding, Binding};
 use crate::plan::{Dependencies, ImplContext, Implementable};
-use crate::{CollectionRelation, Relation, ShutdownHandle, Value, Var, VariableMap};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, Value, Var, VariableMap};
 
 use num_rational::{Ratio, Rational32};
 
@@ -33,6 +35,8 @@ pub enum AggregationFn {
     AVG,
     /// Variance
     VARIANCE,
+    /// All values, as a single sorted list
+    LIST,
     // /// Standard deviation
     // STDDEV,
 }
@@ -40,6 +44,9 @@ pub enum AggregationFn {
 /// [WIP] A plan stage applying the specified aggregation functions to
 /// bindings for the specified variables. Given multiple aggregations
 /// we iterate and n-1 joins are applied to the results.
+///
+/// Without any key variables, the aggregations are applied to the
+/// relation as a whole, producing at most a single tuple.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Aggregate<P: Implementable> {
     /// TODO
@@ -54,6 +61,10 @@ pub struct Aggregate<P: Implementable> {
     pub aggregation_variables: Vec<Var>,
     /// With variables
     pub with_variables: Vec<Var>,
+    /// Whether an ungrouped COUNT over an empty relation should
+    /// produce a count of zero, rather than no tuple at all.
+    #[serde(default)]
+    pub count_empty: bool,
 }
 
 impl<P: Implementable> Implementable for Aggregate<P> {
@@ -70,13 +81,14 @@ impl<P: Implementable> Implementable for Aggregate<P> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        let (relation, shutdown_handle) = self.plan.implement(nested, local_arrangements, context);
+        let (relation, shutdown_handle) =
+            self.plan.implement(nested, local_arrangements, context)?;
 
         // We split the incoming tuples into their (key, value) parts.
         let tuples = relation.tuples_by_variables(&self.key_variables);
@@ -156,6 +168,20 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                     });
                     collections.push(tuples);
                 }
+                AggregationFn::LIST => {
+                    let tuples = tuples.map(prepare_unary).reduce(|_key, vals, output| {
+                        // Values arrive in sorted order.
+                        let mut list = Vec::new();
+                        for (val, count) in vals.iter() {
+                            for _ in 0..*count {
+                                list.push(val[0].clone());
+                            }
+                        }
+
+                        output.push((vec![Value::List(list)], 1));
+                    });
+                    collections.push(tuples);
+                }
                 AggregationFn::COUNT => {
                     let tuples = tuples.map(prepare_unary).reduce(|_key, input, output| {
                         let mut total_count = 0;
@@ -165,7 +191,24 @@ impl<P: Implementable> Implementable for Aggregate<P> {
 
                         output.push((vec![Value::Number(total_count as i64)], 1))
                     });
-                    collections.push(tuples);
+
+                    if self.count_empty && self.key_variables.is_empty() {
+                        // There is at most one count, so subtracting
+                        // it from a constant zero leaves the zero in
+                        // place exactly when the input is empty.
+                        let zero = Some((vec![], vec![Value::Number(0)]))
+                            .into_iter()
+                            .map(|x| (x, Default::default(), 1))
+                            .to_stream(nested)
+                            .as_collection();
+
+                        let empty =
+                            zero.concat(&tuples.map(|_| (vec![], vec![Value::Number(0)])).negate());
+
+                        collections.push(tuples.concat(&empty));
+                    } else {
+                        collections.push(tuples);
+                    }
                 }
                 AggregationFn::SUM => {
                     let tuples = tuples
@@ -248,7 +291,7 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                 }),
             };
 
-            (relation, shutdown_handle)
+            Ok((relation, shutdown_handle))
         } else {
             // @TODO replace this with a join application
             let left = collections.remove(0);
@@ -271,7 +314,7 @@ impl<P: Implementable> Implementable for Aggregate<P> {
                 }),
             };
 
-            (relation, shutdown_handle)
+            Ok((relation, shutdown_handle))
         }
     }
 }
@@ -10,7 +10,7 @@ use differential_dataflow::operators::{Join, Threshold};
 
 use crate::binding::{AsBinding, Binding};
 use crate::plan::{Dependencies, ImplContext, Implementable};
-use crate::{CollectionRelation, Relation, ShutdownHandle, Var, VariableMap};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, Var, VariableMap};
 
 /// A plan stage anti-joining both its sources on the specified
 /// variables. Throws if the sources are not union-compatible, i.e. bind
@@ -50,18 +50,18 @@ impl<P1: Implementable, P2: Implementable> Implementable for Antijoin<P1, P2> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        let (left, shutdown_left) = self
-            .left_plan
-            .implement(nested, local_arrangements, context);
+        let (left, shutdown_left) =
+            self.left_plan
+                .implement(nested, local_arrangements, context)?;
         let (right, shutdown_right) =
             self.right_plan
-                .implement(nested, local_arrangements, context);
+                .implement(nested, local_arrangements, context)?;
 
         let variables = self
             .variables
@@ -82,6 +82,6 @@ impl<P1: Implementable, P2: Implementable> Implementable for Antijoin<P1, P2> {
 
         let shutdown_handle = ShutdownHandle::merge(shutdown_left, shutdown_right);
 
-        (CollectionRelation { variables, tuples }, shutdown_handle)
+        Ok((CollectionRelation { variables, tuples }, shutdown_handle))
     }
 }
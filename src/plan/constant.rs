@@ -0,0 +1,87 @@
+//! Constant relation expression plan.
+
+use timely::dataflow::operators::ToStream;
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::TotalOrder;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::AsCollection;
+
+use crate::plan::{Dependencies, ImplContext, Implementable};
+use crate::{CollectionRelation, Error, ErrorKind, ShutdownHandle, Value, Var, VariableMap};
+
+/// A plan stage producing a fixed set of literal tuples, e.g. a small
+/// lookup table to join against, without storing it as attributes.
+/// All tuples are fed in at the beginning of time and never change.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Constant {
+    /// Variables bound by the tuples, in order.
+    pub variables: Vec<Var>,
+    /// Literal tuples, each holding one value per variable.
+    pub tuples: Vec<Vec<Value>>,
+}
+
+impl Constant {
+    /// Checks that every tuple holds exactly one value per variable.
+    pub fn validate(&self) -> Result<(), Error> {
+        match self
+            .tuples
+            .iter()
+            .find(|tuple| tuple.len() != self.variables.len())
+        {
+            None => Ok(()),
+            Some(tuple) => Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!(
+                    "Constant binds {} variables, but tuple {:?} holds {} values.",
+                    self.variables.len(),
+                    tuple,
+                    tuple.len()
+                ),
+            }),
+        }
+    }
+}
+
+impl Implementable for Constant {
+    fn dependencies(&self) -> Dependencies {
+        Dependencies::none()
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        _local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        _context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        self.validate()?;
+
+        // Every worker builds the same dataflow, so only the first
+        // one may feed the tuples in.
+        let tuples = if nested.index() == 0 {
+            self.tuples.clone()
+        } else {
+            Vec::new()
+        };
+
+        let tuples = tuples
+            .into_iter()
+            .map(|tuple| (tuple, Default::default(), 1))
+            .to_stream(nested)
+            .as_collection();
+
+        let relation = CollectionRelation {
+            variables: self.variables.clone(),
+            tuples,
+        };
+
+        Ok((relation, ShutdownHandle::empty()))
+    }
+}
@@ -0,0 +1,305 @@
+//! Explanations of how plans would be implemented.
+
+use std::fmt;
+
+use timely::order::TotalOrder;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::binding::Binding;
+use crate::plan::{ImplContext, Plan, PullLevel};
+use crate::Aid;
+
+/// A description of the operator a plan stage would be implemented
+/// as, without implementing it. Notes point out which indices are
+/// imported, where arrangements are built from scratch, and where
+/// existing arrangements can't be re-used as they are.
+#[derive(Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct Explain {
+    /// Name of the operator.
+    pub operator: String,
+    /// Notes on the arrangements used by this operator.
+    pub notes: Vec<String>,
+    /// Explanations of the operator's inputs.
+    pub inputs: Vec<Explain>,
+}
+
+impl Explain {
+    fn new(operator: &str, notes: Vec<String>, inputs: Vec<Explain>) -> Self {
+        Explain {
+            operator: operator.to_string(),
+            notes,
+            inputs,
+        }
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        writeln!(f, "{:indent$}{}", "", self.operator, indent = depth * 2)?;
+
+        for note in self.notes.iter() {
+            writeln!(f, "{:indent$}- {}", "", note, indent = depth * 2 + 2)?;
+        }
+
+        for input in self.inputs.iter() {
+            input.write_indented(f, depth + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Explain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// Notes on importing the index of an attribute, in the given
+/// direction.
+fn import_note<T, I>(context: &I, aid: &Aid, index: &str) -> String
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+{
+    if context.has_attribute(aid) {
+        format!("imports {} index of {}", index, aid)
+    } else {
+        format!("attribute {} does not exist", aid)
+    }
+}
+
+fn explain_hector<T, I>(bindings: &[Binding], context: &I) -> Vec<String>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+{
+    let attributes: Vec<&Aid> = bindings
+        .iter()
+        .filter_map(|binding| match binding {
+            Binding::Attribute(binding) => Some(&binding.source_attribute),
+            _ => None,
+        })
+        .collect();
+
+    // Binary relations with a global arrangement are indexed, s.t.
+    // they participate in delta queries just like attributes.
+    let (indexed, relations): (Vec<_>, Vec<_>) = bindings
+        .iter()
+        .filter_map(|binding| match binding {
+            Binding::Relation(binding) => Some(binding),
+            _ => None,
+        })
+        .partition(|binding| {
+            let name = &binding.source_name;
+
+            binding.variables.len() == 2
+                && context.rule(name).is_some()
+                && (!context.is_underconstrained(name) || context.is_implemented(name))
+        });
+
+    let mut notes = Vec::new();
+
+    for binding in indexed {
+        notes.push(format!(
+            "indexes global arrangement of {} like an attribute",
+            binding.source_name
+        ));
+    }
+
+    if !relations.is_empty() {
+        for binding in relations {
+            notes.push(format!(
+                "joins relation {} against the worst-case optimal part",
+                binding.source_name
+            ));
+        }
+    } else if bindings.len() == 1 {
+        for aid in attributes {
+            notes.push(import_note::<T, I>(context, aid, "forward validate"));
+        }
+    } else {
+        for aid in attributes {
+            notes.push(format!("builds delta query driven by {}", aid));
+        }
+
+        for binding in bindings.iter() {
+            if let Binding::Attribute(binding) = binding {
+                notes.push(format!(
+                    "builds extenders over indices of {}",
+                    binding.source_attribute
+                ));
+            }
+        }
+    }
+
+    notes.dedup();
+    notes
+}
+
+fn explain_pull_level<T, I>(path: &PullLevel<Plan>, context: &I) -> Explain
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+{
+    let mut notes: Vec<String> = path
+        .pull_attributes
+        .iter()
+        .chain(path.aliased_attributes.iter().map(|(a, _alias)| a))
+        .map(|a| import_note::<T, I>(context, a, "forward propose"))
+        .collect();
+
+    if path.wildcard {
+        notes.push("imports forward propose indices of all attributes".to_string());
+    }
+
+    Explain::new("PullLevel", notes, vec![path.plan.explain::<T, I>(context)])
+}
+
+impl Plan {
+    /// Returns a description of how this plan would be implemented
+    /// in the given context. Named relations are not explained
+    /// recursively, as they are implemented separately.
+    pub fn explain<T, I>(&self, context: &I) -> Explain
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+    {
+        let explain = |plan: &Plan| plan.explain::<T, I>(context);
+
+        match *self {
+            Plan::Project(ref projection) => {
+                Explain::new("Project", vec![], vec![explain(&projection.plan)])
+            }
+            Plan::ProjectDistinct(ref projection) => Explain::new(
+                "ProjectDistinct",
+                vec!["arranges projected tuples to remove duplicates".to_string()],
+                vec![explain(&projection.plan)],
+            ),
+            Plan::Aggregate(ref aggregate) => Explain::new(
+                "Aggregate",
+                vec!["arranges input by key variables".to_string()],
+                vec![explain(&aggregate.plan)],
+            ),
+            Plan::Window(ref window) => Explain::new(
+                "Window",
+                vec!["arranges input by key variables and window".to_string()],
+                vec![explain(&window.plan)],
+            ),
+            Plan::Union(ref union) if union.all => {
+                Explain::new("Union", vec![], union.plans.iter().map(explain).collect())
+            }
+            Plan::Union(ref union) => Explain::new(
+                "Union",
+                vec!["arranges concatenated inputs to remove duplicates".to_string()],
+                union.plans.iter().map(explain).collect(),
+            ),
+            Plan::Join(ref join) => Explain::new(
+                "Join",
+                vec!["arranges both inputs by join variables".to_string()],
+                vec![explain(&join.left_plan), explain(&join.right_plan)],
+            ),
+            Plan::ThetaJoin(ref join) => Explain::new(
+                "ThetaJoin",
+                vec!["arranges both inputs by join variables".to_string()],
+                vec![explain(&join.left_plan), explain(&join.right_plan)],
+            ),
+            Plan::Hector(ref hector) => Explain::new(
+                "Hector",
+                explain_hector::<T, I>(&hector.bindings, context),
+                vec![],
+            ),
+            Plan::Antijoin(ref antijoin) => Explain::new(
+                "Antijoin",
+                vec!["arranges both inputs by antijoin variables".to_string()],
+                vec![explain(&antijoin.left_plan), explain(&antijoin.right_plan)],
+            ),
+            Plan::NotExists(ref not_exists) => Explain::new(
+                "NotExists",
+                vec!["arranges both inputs by shared variables".to_string()],
+                vec![
+                    explain(&not_exists.left_plan),
+                    explain(&not_exists.right_plan),
+                ],
+            ),
+            Plan::Negate(ref plan) => Explain::new("Negate", vec![], vec![explain(plan)]),
+            Plan::Filter(ref filter) => Explain::new("Filter", vec![], vec![explain(&filter.plan)]),
+            Plan::Transform(ref transform) => {
+                Explain::new("Transform", vec![], vec![explain(&transform.plan)])
+            }
+            Plan::Rename(ref rename) => Explain::new("Rename", vec![], vec![explain(&rename.plan)]),
+            Plan::Sample(ref sample) => Explain::new(
+                "Sample",
+                vec!["arranges ranked tuples twice to keep the lowest ranked".to_string()],
+                vec![explain(&sample.plan)],
+            ),
+            Plan::Constant(ref constant) => Explain::new(
+                "Constant",
+                vec![format!(
+                    "feeds in {} literal tuples at the beginning of time",
+                    constant.tuples.len()
+                )],
+                vec![],
+            ),
+            Plan::MatchA(_, ref a, _) => Explain::new(
+                "MatchA",
+                vec![import_note::<T, I>(context, a, "forward validate")],
+                vec![],
+            ),
+            Plan::MatchEA(_, ref a, _) => Explain::new(
+                "MatchEA",
+                vec![import_note::<T, I>(context, a, "forward propose")],
+                vec![],
+            ),
+            Plan::MatchAV(_, ref a, _) => Explain::new(
+                "MatchAV",
+                vec![import_note::<T, I>(context, a, "reverse propose")],
+                vec![],
+            ),
+            Plan::ValidTime(ref valid_time) => {
+                let mut notes = vec![import_note::<T, I>(
+                    context,
+                    &valid_time.attribute,
+                    "forward validate",
+                )];
+
+                if let Some(valid_at) = valid_time.valid_at {
+                    notes.push(format!(
+                        "arranges versions by entity to keep those valid at {}",
+                        valid_at
+                    ));
+                }
+
+                Explain::new("ValidTime", notes, vec![])
+            }
+            Plan::NameExpr(_, ref name) => {
+                let note = if context.rule(name).is_none() {
+                    format!("relation {} does not exist", name)
+                } else if context.is_underconstrained(name) && !context.is_implemented(name) {
+                    format!("re-uses local relation {}, but not its arrangement", name)
+                } else {
+                    format!(
+                        "imports global arrangement of {} and re-arranges its tuples",
+                        name
+                    )
+                };
+
+                Explain::new("NameExpr", vec![note], vec![])
+            }
+            Plan::Pull(ref pull) => Explain::new(
+                "Pull",
+                vec![],
+                pull.paths
+                    .iter()
+                    .map(|path| explain_pull_level::<T, I>(path, context))
+                    .collect(),
+            ),
+            Plan::PullLevel(ref path) => explain_pull_level::<T, I>(path, context),
+            Plan::GraphQl(_) => Explain::new(
+                "GraphQl",
+                vec!["compiled to pull levels on implementation".to_string()],
+                vec![],
+            ),
+        }
+    }
+}
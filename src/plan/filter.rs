@@ -1,5 +1,7 @@
 //! Predicate expression plan.
 
+use std::cmp::Ordering;
+
 use timely::dataflow::scopes::child::Iterative;
 use timely::dataflow::Scope;
 use timely::order::TotalOrder;
@@ -11,36 +13,52 @@ pub use crate::binding::{
     AsBinding, BinaryPredicate as Predicate, BinaryPredicateBinding, Binding,
 };
 use crate::plan::{Dependencies, ImplContext, Implementable};
-use crate::{CollectionRelation, Relation, ShutdownHandle, Value, Var, VariableMap};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, Value, Var, VariableMap};
 
+/// Numeric values are compared by magnitude, regardless of their
+/// type. All others fall back to the derived ordering.
+#[inline(always)]
+pub(crate) fn compare(a: &Value, b: &Value) -> Ordering {
+    a.numeric_cmp(b).unwrap_or_else(|| a.cmp(b))
+}
+/// Values are comparable if they are both numeric or of the same
+/// type.
+#[inline(always)]
+pub(crate) fn comparable(a: &Value, b: &Value) -> bool {
+    a.numeric_cmp(b).is_some() || a.value_type() == b.value_type()
+}
 #[inline(always)]
 fn lt(a: &Value, b: &Value) -> bool {
-    a < b
+    compare(a, b) == Ordering::Less
 }
 #[inline(always)]
 fn lte(a: &Value, b: &Value) -> bool {
-    a <= b
+    compare(a, b) != Ordering::Greater
 }
 #[inline(always)]
 fn gt(a: &Value, b: &Value) -> bool {
-    a > b
+    compare(a, b) == Ordering::Greater
 }
 #[inline(always)]
 fn gte(a: &Value, b: &Value) -> bool {
-    a >= b
+    compare(a, b) != Ordering::Less
 }
 #[inline(always)]
 fn eq(a: &Value, b: &Value) -> bool {
-    a == b
+    compare(a, b) == Ordering::Equal
 }
 #[inline(always)]
 fn neq(a: &Value, b: &Value) -> bool {
-    a != b
+    compare(a, b) != Ordering::Equal
 }
 
 /// A plan stage filtering source tuples by the specified
 /// predicate. Frontends are responsible for ensuring that the source
 /// binds the argument variables.
+///
+/// Without any constants, the predicate is applied between the
+/// values bound to the two variables. Tuples binding incomparable
+/// values to them are dropped.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Filter<P: Implementable> {
     /// TODO
@@ -76,13 +94,14 @@ impl<P: Implementable> Implementable for Filter<P> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        let (relation, shutdown_handle) = self.plan.implement(nested, local_arrangements, context);
+        let (relation, shutdown_handle) =
+            self.plan.implement(nested, local_arrangements, context)?;
 
         let key_offsets: Vec<usize> = self
             .variables
@@ -117,11 +136,12 @@ impl<P: Implementable> Implementable for Filter<P> {
             CollectionRelation {
                 variables: relation.variables().to_vec(),
                 tuples: relation.tuples().filter(move |tuple| {
-                    binary_predicate(&tuple[key_offsets[0]], &tuple[key_offsets[1]])
+                    let (x, y) = (&tuple[key_offsets[0]], &tuple[key_offsets[1]]);
+                    comparable(x, y) && binary_predicate(x, y)
                 }),
             }
         };
 
-        (filtered, shutdown_handle)
+        Ok((filtered, shutdown_handle))
     }
 }
@@ -16,16 +16,19 @@ use timely::PartialOrder;
 use timely_sort::Unsigned;
 
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::operators::Threshold;
+use differential_dataflow::operators::{Join, JoinCore, Threshold};
 use differential_dataflow::trace::{BatchReader, Cursor, TraceReader};
 use differential_dataflow::{AsCollection, Collection, Data, Hashable};
 
 use crate::binding::{AsBinding, BinaryPredicate, Binding};
 use crate::binding::{BinaryPredicateBinding, ConstantBinding};
-use crate::plan::{Dependencies, ImplContext, Implementable};
+use crate::plan::{Dependencies, ImplContext, Implementable, Plan};
 use crate::timestamp::altneu::AltNeu;
 use crate::{Aid, Value, Var};
-use crate::{CollectionRelation, LiveIndex, ShutdownHandle, VariableMap};
+use crate::{
+    CollectionIndex, CollectionRelation, Error, ErrorKind, LiveIndex, Relation, ShutdownHandle,
+    VariableMap,
+};
 
 type Extender<'a, S, P, V> = Box<(dyn PrefixExtender<S, Prefix = P, Extension = V> + 'a)>;
 
@@ -109,6 +112,62 @@ where
     }
 }
 
+/// Indices over a binary relation, s.t. it can participate in delta
+/// pipelines just like an attribute.
+struct RelationIndex<T>
+where
+    T: Timestamp + Lattice + TotalOrder,
+{
+    forward: CollectionIndex<Value, Value, T>,
+    reverse: CollectionIndex<Value, Value, T>,
+}
+
+/// Relation indices built for a single query, by the names of the
+/// attribute bindings onto them.
+type RelationIndices<T> = HashMap<Aid, RelationIndex<T>>;
+
+/// Returns the name under which the indices over the named relation
+/// are bound.
+fn relation_index_name(name: &str) -> Aid {
+    Aid::from(format!("df.relation/{}", name))
+}
+
+/// Looks up the forward index of an attribute, or of a relation
+/// indexed for the current query.
+fn forward_index<'c, T, I>(
+    context: &'c mut I,
+    indices: &'c mut RelationIndices<T>,
+    name: &str,
+) -> Option<&'c mut CollectionIndex<Value, Value, T>>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+{
+    match indices.get_mut(name) {
+        Some(index) => Some(&mut index.forward),
+        None => context.forward_index(name),
+    }
+}
+
+/// Looks up the reverse index of an attribute, or of a relation
+/// indexed for the current query.
+fn reverse_index<'c, T, I, S>(
+    context: &'c mut I,
+    indices: &'c mut RelationIndices<T>,
+    name: &str,
+    scope: &S,
+) -> Option<&'c mut CollectionIndex<Value, Value, T>>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+    S: Scope<Timestamp = T>,
+{
+    match indices.get_mut(name) {
+        Some(index) => Some(&mut index.reverse),
+        None => context.reverse_index(name, scope),
+    }
+}
+
 //
 // OPERATOR
 //
@@ -157,6 +216,144 @@ where
     }
 }
 
+/// Evaluates a binary predicate on the values of its two variables,
+/// comparing the second against the first, as the corresponding
+/// extenders do.
+fn holds(predicate: &BinaryPredicate, x: &Value, y: &Value) -> bool {
+    match *predicate {
+        BinaryPredicate::LT => y < x,
+        BinaryPredicate::LTE => y <= x,
+        BinaryPredicate::GT => y > x,
+        BinaryPredicate::GTE => y >= x,
+        BinaryPredicate::EQ => y == x,
+        BinaryPredicate::NEQ => y != x,
+    }
+}
+
+/// Restricts the tuples of a relation to those satisfying the given
+/// constant, predicate, or negated binding. All variables of the
+/// binding must be bound by the relation.
+fn constrain<'b, T, I, S>(
+    relation: CollectionRelation<'b, S>,
+    binding: Binding,
+    nested: &mut Iterative<'b, S, u64>,
+    local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+    context: &mut I,
+) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+    S: Scope<Timestamp = T>,
+{
+    let variables = relation.variables();
+
+    if let Some(x) = binding
+        .variables()
+        .into_iter()
+        .find(|x| variables.binds(*x).is_none())
+    {
+        return Err(Error {
+            category: ErrorKind::Incorrect,
+            message: format!(
+                "Variable {} is constrained, but not bound by any binding.",
+                x
+            ),
+        });
+    }
+
+    let (negated, binding) = match binding {
+        Binding::Not(antijoin) => (true, *antijoin.binding),
+        binding => (false, binding),
+    };
+
+    let (tuples, shutdown_handle) = match (negated, binding) {
+        (_, Binding::Constant(constant)) => {
+            let offset = variables.binds(constant.variable).unwrap();
+            let value = constant.value;
+
+            let tuples = relation
+                .tuples()
+                .filter(move |tuple| (tuple[offset] == value) != negated);
+
+            (tuples, ShutdownHandle::empty())
+        }
+        (_, Binding::BinaryPredicate(binding)) => {
+            let x = variables.binds(binding.variables.0).unwrap();
+            let y = variables.binds(binding.variables.1).unwrap();
+            let predicate = binding.predicate;
+
+            let tuples = relation
+                .tuples()
+                .filter(move |tuple| holds(&predicate, &tuple[x], &tuple[y]) != negated);
+
+            (tuples, ShutdownHandle::empty())
+        }
+        (true, Binding::Attribute(binding)) => {
+            let source = Plan::MatchA(
+                binding.variables.0,
+                binding.source_attribute,
+                binding.variables.1,
+            );
+
+            exclude(relation, source, nested, local_arrangements, context)?
+        }
+        (true, Binding::Relation(binding)) => {
+            let source = Plan::NameExpr(binding.variables, binding.source_name);
+
+            exclude(relation, source, nested, local_arrangements, context)?
+        }
+        (_, other) => {
+            return Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!("Can't constrain a relation by {:?}.", other),
+            });
+        }
+    };
+
+    Ok((CollectionRelation { variables, tuples }, shutdown_handle))
+}
+
+/// Removes all tuples of a relation that agree with some tuple of
+/// the given source on all of the source's variables.
+fn exclude<'b, T, I, S>(
+    relation: CollectionRelation<'b, S>,
+    source: Plan,
+    nested: &mut Iterative<'b, S, u64>,
+    local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+    context: &mut I,
+) -> Result<
+    (
+        Collection<Iterative<'b, S, u64>, Vec<Value>, isize>,
+        ShutdownHandle,
+    ),
+    Error,
+>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+    S: Scope<Timestamp = T>,
+{
+    let (right, shutdown_handle) = source.implement(nested, local_arrangements, context)?;
+
+    let variables = relation.variables();
+    let right_variables = right.variables();
+    let offsets: Vec<usize> = right_variables
+        .iter()
+        .map(|x| variables.binds(*x).unwrap())
+        .collect();
+
+    let tuples = relation
+        .tuples()
+        .map(move |tuple| {
+            let key: Vec<Value> = offsets.iter().map(|idx| tuple[*idx].clone()).collect();
+            (key, tuple)
+        })
+        .antijoin(&right.projected(&right_variables).distinct())
+        .map(|(_key, tuple)| tuple);
+
+    Ok((tuples, shutdown_handle))
+}
+
 /// Bindings can be in conflict with the source binding of a given
 /// delta pipeline. We need to identify them and handle them as
 /// special cases, because we always have to start from prefixes of
@@ -195,6 +392,27 @@ pub fn source_conflicts(source_index: usize, bindings: &[Binding]) -> Vec<&Bindi
 ///
 /// (adapted from github.com/frankmcsherry/dataflow-join/src/motif.rs)
 pub fn plan_order(source_index: usize, bindings: &[Binding]) -> (Vec<Var>, Vec<Binding>) {
+    plan_order_by_cost(source_index, bindings, &HashMap::new())
+}
+
+/// Like `plan_order`, but whenever several bindings are ready to
+/// extend the prefix, those over attributes with smaller estimated
+/// cardinalities go first. Bindings without an estimate are
+/// considered cheapest, s.t. constants and predicates are applied as
+/// early as possible.
+pub fn plan_order_by_cost(
+    source_index: usize,
+    bindings: &[Binding],
+    cardinalities: &HashMap<Aid, isize>,
+) -> (Vec<Var>, Vec<Binding>) {
+    let cost = |binding: &Binding| match binding {
+        Binding::Attribute(binding) => cardinalities
+            .get(&binding.source_attribute)
+            .cloned()
+            .unwrap_or(0),
+        _ => 0,
+    };
+
     let mut variables = bindings
         .iter()
         .flat_map(AsBinding::variables)
@@ -207,7 +425,7 @@ pub fn plan_order(source_index: usize, bindings: &[Binding]) -> (Vec<Var>, Vec<B
     // bound by the prefix. These constraints are captured via the
     // `AsBinding::ready_to_extend` method. The order may otherwise be
     // arbitrary, for example selecting the most constrained attribute
-    // first. Presently, we just pick attributes arbitrarily.
+    // first. Absent any estimates, we just pick attributes arbitrarily.
 
     let mut prefix: Vec<Var> = Vec::with_capacity(variables.len());
     match bindings[source_index] {
@@ -248,7 +466,7 @@ pub fn plan_order(source_index: usize, bindings: &[Binding]) -> (Vec<Var>, Vec<B
 
         let mut waiting_candidates = Vec::new();
 
-        candidates.sort();
+        candidates.sort_by(|x, y| cost(x).cmp(&cost(y)).then_with(|| x.cmp(y)));
         candidates.dedup();
 
         for candidate in candidates.drain(..) {
@@ -316,53 +534,331 @@ impl IndexNode<Value> for Vec<Value> {
     }
 }
 
-impl Implementable for Hector {
-    fn dependencies(&self) -> Dependencies {
-        let attributes = self
+impl Hector {
+    /// Binary relations with a global arrangement can extend prefixes
+    /// and source delta pipelines just like attributes. Builds
+    /// forward and reverse indices from the arrangement of each such
+    /// relation and returns the bindings with relation bindings onto
+    /// them replaced by attribute bindings onto the indices.
+    fn index_relations<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        context: &mut I,
+        indices: &mut RelationIndices<T>,
+        shutdown_handle: &mut ShutdownHandle,
+    ) -> Vec<Binding>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let mut bindings = Vec::with_capacity(self.bindings.len());
+
+        for binding in self.bindings.iter() {
+            let relation = match binding {
+                Binding::Relation(relation) if relation.variables.len() == 2 => relation,
+                _ => {
+                    bindings.push(binding.clone());
+                    continue;
+                }
+            };
+
+            let name = &relation.source_name;
+
+            // Rules synthesized along with this query are only
+            // available as local variables.
+            if context.is_underconstrained(name) && !context.is_implemented(name) {
+                bindings.push(binding.clone());
+                continue;
+            }
+
+            let aid = relation_index_name(name);
+
+            if !indices.contains_key(&aid) {
+                match context.global_arrangement(name) {
+                    None => {
+                        bindings.push(binding.clone());
+                        continue;
+                    }
+                    Some(trace) => {
+                        let (arranged, shutdown_button) = trace.import_core(&nested.parent, name);
+                        shutdown_handle.add_button(shutdown_button);
+
+                        let pairs =
+                            arranged.as_collection(|tuple, _| (tuple[0].clone(), tuple[1].clone()));
+
+                        let forward = CollectionIndex::index(&aid, &pairs);
+                        let reverse = forward.reverse(&pairs);
+
+                        indices.insert(aid.clone(), RelationIndex { forward, reverse });
+                    }
+                }
+            }
+
+            bindings.push(Binding::attribute(
+                relation.variables[0],
+                &aid,
+                relation.variables[1],
+            ));
+        }
+
+        bindings
+    }
+
+    /// Named relations that can't be indexed can't act as sources of
+    /// delta pipelines, so bindings onto them are joined against the
+    /// result of the worst-case optimal part of the query instead.
+    /// Bindings constraining variables no attribute binds, as well as
+    /// negated relations, are applied to the joined result.
+    fn implement_relations<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+        indices: &mut RelationIndices<T>,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let (relations, others): (Vec<Binding>, Vec<Binding>) = self
             .bindings
             .iter()
-            .flat_map(|binding| {
-                if let Binding::Attribute(binding) = binding {
-                    Some(binding.source_attribute.clone())
-                } else {
-                    None
-                }
+            .cloned()
+            .partition(|binding| match binding {
+                Binding::Relation(_) => true,
+                _ => false,
+            });
+
+        let attribute_variables: HashSet<Var> = others
+            .iter()
+            .flat_map(|binding| match binding {
+                Binding::Attribute(binding) => binding.variables(),
+                _ => Vec::new(),
             })
-            .collect::<HashSet<Aid>>();
+            .collect();
+
+        let bound_by_attributes = |binding: &Binding| {
+            binding
+                .variables()
+                .iter()
+                .all(|x| attribute_variables.contains(x))
+        };
+
+        let (others, constraints): (Vec<Binding>, Vec<Binding>) =
+            others.into_iter().partition(|binding| match binding {
+                Binding::Attribute(_) => true,
+                Binding::Not(antijoin) => match *antijoin.binding {
+                    Binding::Relation(_) => false,
+                    _ => bound_by_attributes(binding),
+                },
+                _ => bound_by_attributes(binding),
+            });
+
+        let mut shutdown_handle = ShutdownHandle::empty();
+        let mut joined: Option<CollectionRelation<'b, S>> = None;
+
+        if !others.is_empty() {
+            let mut variables: Vec<Var> = others.iter().flat_map(AsBinding::variables).collect();
+            variables.sort();
+            variables.dedup();
+
+            let hector = Hector {
+                variables,
+                bindings: others,
+            };
 
-        Dependencies {
-            names: HashSet::new(),
-            attributes,
+            let (relation, shutdown) =
+                hector.implement_indexed(nested, local_arrangements, context, indices)?;
+            shutdown_handle.merge_with(shutdown);
+
+            joined = Some(relation);
+        }
+
+        for binding in relations.into_iter() {
+            if let Binding::Relation(binding) = binding {
+                let source = Plan::NameExpr(binding.variables, binding.source_name);
+                let (right, shutdown) = source.implement(nested, local_arrangements, context)?;
+                shutdown_handle.merge_with(shutdown);
+
+                joined = Some(match joined {
+                    None => right,
+                    Some(left) => {
+                        let left_variables = left.variables();
+                        let right_variables = right.variables();
+
+                        let shared: Vec<Var> = left_variables
+                            .iter()
+                            .cloned()
+                            .filter(|x| right_variables.contains(x))
+                            .collect();
+
+                        let variables = shared
+                            .iter()
+                            .cloned()
+                            .chain(left_variables.into_iter().filter(|x| !shared.contains(x)))
+                            .chain(right_variables.into_iter().filter(|x| !shared.contains(x)))
+                            .collect();
+
+                        let tuples = left.arrange_by_variables(&shared).join_core(
+                            &right.arrange_by_variables(&shared),
+                            |key, v1, v2| {
+                                Some(
+                                    key.iter()
+                                        .cloned()
+                                        .chain(v1.iter().cloned())
+                                        .chain(v2.iter().cloned())
+                                        .collect(),
+                                )
+                            },
+                        );
+
+                        CollectionRelation { variables, tuples }
+                    }
+                });
+            }
+        }
+
+        for binding in constraints.into_iter() {
+            joined = match joined {
+                None => None,
+                Some(relation) => {
+                    let (relation, shutdown) =
+                        constrain(relation, binding, nested, local_arrangements, context)?;
+                    shutdown_handle.merge_with(shutdown);
+
+                    Some(relation)
+                }
+            };
+        }
+
+        match joined {
+            None => Err(Error {
+                category: ErrorKind::Incorrect,
+                message: "No bindings passed.".to_string(),
+            }),
+            Some(relation) => {
+                let relation = CollectionRelation {
+                    variables: self.variables.clone(),
+                    tuples: relation.projected(&self.variables).distinct(),
+                };
+
+                Ok((relation, shutdown_handle))
+            }
         }
     }
 
-    fn into_bindings(&self) -> Vec<Binding> {
-        self.bindings.clone()
+    /// Estimates the cardinalities of the attributes bound by this
+    /// query from their count traces, if the optimizer is enabled.
+    /// Each worker only sees its own shard of an index, so workers
+    /// could disagree on the resulting plan. Estimates are therefore
+    /// only used in single-worker dataflows.
+    fn estimate_cardinalities<T, I>(
+        &self,
+        peers: usize,
+        context: &mut I,
+        indices: &mut RelationIndices<T>,
+    ) -> HashMap<Aid, isize>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+    {
+        let mut cardinalities = HashMap::new();
+
+        if !context.enable_optimizer() || peers > 1 {
+            return cardinalities;
+        }
+
+        for binding in self.bindings.iter() {
+            if let Binding::Attribute(binding) = binding {
+                let aid = &binding.source_attribute;
+
+                if !cardinalities.contains_key(aid) {
+                    if let Some(index) = forward_index(context, indices, aid) {
+                        cardinalities.insert(aid.clone(), index.cardinality());
+                    }
+                }
+            }
+        }
+
+        debug!("Estimated cardinalities {:?}", cardinalities);
+
+        cardinalities
     }
 
-    fn implement<'b, T, I, S>(
+    /// Implements the query, looking up attribute indices among the
+    /// given relation indices first.
+    fn implement_indexed<'b, T, I, S>(
         &self,
         nested: &mut Iterative<'b, S, u64>,
-        _local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+        indices: &mut RelationIndices<T>,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        if self.bindings.is_empty() {
-            panic!("No bindings passed.");
+        // Delta pipelines are constructed deep inside nested
+        // operators, so we make sure all attributes are available
+        // upfront.
+        for binding in self.bindings.iter() {
+            let attribute = match binding {
+                Binding::Attribute(binding) => Some(&binding.source_attribute),
+                Binding::Not(antijoin) => match *antijoin.binding {
+                    Binding::Attribute(ref binding) => Some(&binding.source_attribute),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            if let Some(aid) = attribute {
+                if !indices.contains_key(aid) && !context.has_attribute(aid) {
+                    return Err(Error {
+                        category: ErrorKind::NotFound,
+                        message: format!("Attribute {} does not exist.", aid),
+                    });
+                }
+            }
+        }
+
+        let has_relations = self.bindings.iter().any(|binding| match binding {
+            Binding::Relation(_) => true,
+            Binding::Not(antijoin) => match *antijoin.binding {
+                Binding::Relation(_) => true,
+                _ => false,
+            },
+            _ => false,
+        });
+
+        if has_relations {
+            self.implement_relations(nested, local_arrangements, context, indices)
+        } else if self.bindings.is_empty() {
+            Err(Error {
+                category: ErrorKind::Incorrect,
+                message: "No bindings passed.".to_string(),
+            })
         } else if self.variables.is_empty() {
-            panic!("No variables requested.");
+            Err(Error {
+                category: ErrorKind::Incorrect,
+                message: "No variables requested.".to_string(),
+            })
         } else if self.bindings.len() == 1 {
             // With only a single binding given, we don't want to do
             // anything fancy (provided the binding is sourceable).
 
             match self.bindings.first().unwrap() {
                 Binding::Attribute(binding) => {
-                    match context.forward_index(&binding.source_attribute) {
-                        None => panic!("Unknown attribute {}", &binding.source_attribute),
+                    match forward_index(context, indices, &binding.source_attribute) {
+                        None => Err(Error {
+                            category: ErrorKind::NotFound,
+                            message: format!(
+                                "Attribute {} does not exist.",
+                                &binding.source_attribute
+                            ),
+                        }),
                         Some(index) => {
                             let frontier: Vec<T> = index.validate_trace.advance_frontier().to_vec();
                             let (validate, shutdown_validate) = index
@@ -393,15 +889,18 @@ impl Implementable for Hector {
                                 tuples,
                             };
 
-                            (relation, ShutdownHandle::from_button(shutdown_validate))
+                            Ok((relation, ShutdownHandle::from_button(shutdown_validate)))
                         }
                     }
                 }
-                _ => {
-                    panic!("Passed a single, non-sourceable binding.");
-                }
+                _ => Err(Error {
+                    category: ErrorKind::Incorrect,
+                    message: "Passed a single, non-sourceable binding.".to_string(),
+                }),
             }
         } else {
+            let cardinalities = self.estimate_cardinalities(nested.peers(), context, indices);
+
             // In order to avoid delta pipelines looking at each
             // other's data in naughty ways, we need to run them all
             // inside a scope with lexicographic times.
@@ -427,11 +926,12 @@ impl Implementable for Hector {
 
                             // We need to determine an order on the attributes
                             // that ensures that each is bound by preceeding
-                            // attributes. For now, we will take the requested order.
+                            // attributes, preferring small attributes if we
+                            // have estimates on them.
 
                             // @TODO use binding order returned here?
                             // might be problematic to ensure ordering is maintained?
-                            let (variables, _) = plan_order(idx, &self.bindings);
+                            let (variables, _) = plan_order_by_cost(idx, &self.bindings, &cardinalities);
 
                             let mut prefix = Vec::with_capacity(variables.len());
 
@@ -451,7 +951,7 @@ impl Implementable for Hector {
                                 .entry(delta_binding.source_attribute.to_string())
                                 .or_insert_with(|| {
                                     let (arranged, shutdown) =
-                                        context.forward_index(&delta_binding.source_attribute)
+                                        forward_index(context, indices, &delta_binding.source_attribute)
                                         .expect("forward_index doesn't exist")
                                         .import(&scope.parent.parent);
 
@@ -594,6 +1094,11 @@ impl Implementable for Hector {
                                                 Binding::BinaryPredicate(other) => {
                                                     extenders.append(&mut other.into_extender(&prefix));
                                                 }
+                                                Binding::Relation(other) => {
+                                                    // Queries binding relations are implemented
+                                                    // via `implement_relations` instead.
+                                                    unreachable!("Relation {:?} can't extend prefixes.", other);
+                                                }
                                                 Binding::Attribute(other) => {
                                                     match direction(&prefix, other.variables) {
                                                         Err(msg) => panic!(msg),
@@ -602,7 +1107,7 @@ impl Implementable for Hector {
                                                                 let index = forward_cache.entry(other.source_attribute.to_string())
                                                                     .or_insert_with(|| {
                                                                         let (arranged, shutdown) =
-                                                                            context.forward_index(&other.source_attribute)
+                                                                            forward_index(context, indices, &other.source_attribute)
                                                                             .expect("forward index doesn't exist")
                                                                             .import(&scope.parent.parent);
 
@@ -643,7 +1148,7 @@ impl Implementable for Hector {
                                                                 let index = reverse_cache.entry(other.source_attribute.to_string())
                                                                     .or_insert_with(|| {
                                                                         let (arranged, shutdown) =
-                                                                            context.reverse_index(&other.source_attribute).unwrap()
+                                                                            reverse_index(context, indices, &other.source_attribute, &scope.parent.parent).unwrap()
                                                                             .import(&scope.parent.parent);
 
                                                                         shutdown_handle.merge_with(shutdown);
@@ -726,11 +1231,68 @@ impl Implementable for Hector {
                 tuples: joined.distinct(),
             };
 
-            (relation, shutdown_handle)
+            Ok((relation, shutdown_handle))
         }
     }
 }
 
+impl Implementable for Hector {
+    fn dependencies(&self) -> Dependencies {
+        let mut names = HashSet::new();
+        let mut attributes = HashSet::new();
+
+        for binding in self.bindings.iter() {
+            match binding {
+                Binding::Attribute(binding) => {
+                    attributes.insert(binding.source_attribute.clone());
+                }
+                Binding::Relation(binding) => {
+                    names.insert(binding.source_name.clone());
+                }
+                _ => {}
+            }
+        }
+
+        Dependencies { names, attributes }
+    }
+
+    fn into_bindings(&self) -> Vec<Binding> {
+        self.bindings.clone()
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let mut indices = HashMap::new();
+        let mut shutdown_handle = ShutdownHandle::empty();
+
+        let bindings = self.index_relations(nested, context, &mut indices, &mut shutdown_handle);
+
+        let (relation, shutdown) = if indices.is_empty() {
+            self.implement_indexed(nested, local_arrangements, context, &mut indices)?
+        } else {
+            let hector = Hector {
+                variables: self.variables.clone(),
+                bindings,
+            };
+
+            hector.implement_indexed(nested, local_arrangements, context, &mut indices)?
+        };
+
+        shutdown_handle.merge_with(shutdown);
+
+        Ok((relation, shutdown_handle))
+    }
+}
+
 //
 // GENERIC IMPLEMENTATION
 //
@@ -1197,11 +1759,16 @@ where
         let logic1 = self.key_selector.clone();
         let logic2 = self.key_selector.clone();
 
+        // The validation trace might be shared with an index in the
+        // opposite direction, in which case keys are transposed.
+        let key1 = self.indices.validate_key.clone();
+        let key2 = self.indices.validate_key.clone();
+
         let mut buffer1 = Vec::new();
         let mut buffer2 = Vec::new();
 
         let exchange = Exchange::new(move |update: &((P, V), S::Timestamp, isize)| {
-            (logic1(&(update.0).0).clone(), ((update.0).1).clone())
+            key1(&logic1(&(update.0).0), &(update.0).1)
                 .hashed()
                 .as_u64()
         });
@@ -1245,8 +1812,8 @@ where
 
                                     // sort requests for in-order cursor traversal. could consolidate?
                                     prefixes.sort_by(|x, y| {
-                                        (logic2(&(x.0).0), &((x.0).1))
-                                            .cmp(&(logic2(&(y.0).0), &((y.0).1)))
+                                        key2(&logic2(&(x.0).0), &(x.0).1)
+                                            .cmp(&key2(&logic2(&(y.0).0), &(y.0).1))
                                     });
 
                                     let (mut cursor, storage) = trace.cursor();
@@ -1255,7 +1822,7 @@ where
                                         prefixes.iter_mut()
                                     {
                                         if !input2.frontier.less_equal(time) {
-                                            let key = (logic2(&prefix.0), (prefix.1).clone());
+                                            let key = key2(&logic2(&prefix.0), &prefix.1);
                                             cursor.seek_key(&storage, &key);
                                             if cursor.get_key(&storage) == Some(&key) {
                                                 let mut count = 0;
@@ -11,7 +11,7 @@ use differential_dataflow::operators::JoinCore;
 use crate::binding::{AsBinding, Binding};
 use crate::plan::{next_id, Dependencies, ImplContext, Implementable};
 use crate::{Aid, Eid, Value, Var};
-use crate::{CollectionRelation, Relation, ShutdownHandle, VariableMap};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, VariableMap};
 
 /// A plan stage joining two source relations on the specified
 /// variables. Throws if any of the join variables isn't bound by both
@@ -53,12 +53,12 @@ impl<P1: Implementable, P2: Implementable> Implementable for Join<P1, P2> {
 
         let mut left_eids: Vec<(Eid, Aid, Value)> = left_data
             .iter()
-            .map(|(e, _, _)| (eid, "df.join/binding".to_string(), Value::Eid(*e)))
+            .map(|(e, _, _)| (eid, Aid::from("df.join/binding"), Value::Eid(*e)))
             .collect();
 
         let mut right_eids: Vec<(Eid, Aid, Value)> = right_data
             .iter()
-            .map(|(e, _, _)| (eid, "df.join/binding".to_string(), Value::Eid(*e)))
+            .map(|(e, _, _)| (eid, Aid::from("df.join/binding"), Value::Eid(*e)))
             .collect();
 
         let mut data = Vec::with_capacity(
@@ -77,18 +77,18 @@ impl<P1: Implementable, P2: Implementable> Implementable for Join<P1, P2> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        let (left, shutdown_left) = self
-            .left_plan
-            .implement(nested, local_arrangements, context);
+        let (left, shutdown_left) =
+            self.left_plan
+                .implement(nested, local_arrangements, context)?;
         let (right, shutdown_right) =
             self.right_plan
-                .implement(nested, local_arrangements, context);
+                .implement(nested, local_arrangements, context)?;
 
         let variables = self
             .variables
@@ -122,6 +122,6 @@ impl<P1: Implementable, P2: Implementable> Implementable for Join<P1, P2> {
 
         let shutdown_handle = ShutdownHandle::merge(shutdown_left, shutdown_right);
 
-        (CollectionRelation { variables, tuples }, shutdown_handle)
+        Ok((CollectionRelation { variables, tuples }, shutdown_handle))
     }
 }
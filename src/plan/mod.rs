@@ -1,7 +1,9 @@
 //! Types and traits for implementing query plans.
 
+use std::cell::RefCell;
 use std::collections::HashSet;
 use std::ops::Deref;
+use std::rc::Rc;
 use std::sync::atomic::{self, AtomicUsize};
 
 use timely::dataflow::scopes::child::Iterative;
@@ -10,13 +12,15 @@ use timely::order::{Product, TotalOrder};
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Threshold;
 use differential_dataflow::trace::TraceReader;
 
 use crate::binding::{AsBinding, Binding};
 use crate::Rule;
-use crate::{Aid, Eid, Value, Var};
+use crate::{Aid, Eid, Error, ErrorKind, Value, Var};
 use crate::{
-    CollectionIndex, CollectionRelation, Relation, RelationHandle, ShutdownHandle, VariableMap,
+    CollectionIndex, CollectionRelation, Iterations, Relation, RelationHandle, RuleSemantics,
+    ShutdownHandle, VariableMap,
 };
 
 #[cfg(feature = "set-semantics")]
@@ -24,26 +28,45 @@ pub mod aggregate;
 #[cfg(not(feature = "set-semantics"))]
 pub mod aggregate_neu;
 pub mod antijoin;
+pub mod constant;
+pub mod explain;
 pub mod filter;
 pub mod hector;
 pub mod join;
+pub mod not_exists;
 pub mod project;
 pub mod pull;
+pub mod rename;
+pub mod sample;
+pub mod theta;
 pub mod transform;
 pub mod union;
+pub mod valid_time;
+pub mod window;
 
 #[cfg(feature = "set-semantics")]
 pub use self::aggregate::{Aggregate, AggregationFn};
 #[cfg(not(feature = "set-semantics"))]
 pub use self::aggregate_neu::{Aggregate, AggregationFn};
 pub use self::antijoin::Antijoin;
+pub use self::constant::Constant;
+pub use self::explain::Explain;
 pub use self::filter::{Filter, Predicate};
 pub use self::hector::Hector;
 pub use self::join::Join;
+pub use self::not_exists::NotExists;
 pub use self::project::Project;
-pub use self::pull::{Pull, PullLevel};
+pub use self::pull::{
+    graphql_to_json, order_nested, paths_to_nested, squash_nested, GraphQl, NestedPull, Pull,
+    PullLevel,
+};
+pub use self::rename::Rename;
+pub use self::sample::Sample;
+pub use self::theta::ThetaJoin;
 pub use self::transform::{Function, Transform};
 pub use self::union::Union;
+pub use self::valid_time::ValidTime;
+pub use self::window::Window;
 
 static ID: AtomicUsize = atomic::ATOMIC_USIZE_INIT;
 static SYM: AtomicUsize = atomic::ATOMIC_USIZE_INIT;
@@ -74,6 +97,9 @@ where
     /// Checks whether an attribute of that name exists.
     fn has_attribute(&self, name: &str) -> bool;
 
+    /// Returns the names of all attributes known at this point.
+    fn attributes(&self) -> Vec<Aid>;
+
     /// Returns a mutable reference to an attribute (a base relation)
     /// arranged from eid -> value, if one is registered under the
     /// given name.
@@ -81,14 +107,48 @@ where
 
     /// Returns a mutable reference to an attribute (a base relation)
     /// arranged from value -> eid, if one is registered under the
-    /// given name.
-    fn reverse_index(&mut self, name: &str) -> Option<&mut CollectionIndex<Value, Value, T>>;
+    /// given name. The index is created within the given scope on
+    /// first use.
+    fn reverse_index<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        scope: &S,
+    ) -> Option<&mut CollectionIndex<Value, Value, T>>;
 
     /// Returns the current opinion as to whether this rule is
     /// underconstrained. Underconstrained rules cannot be safely
     /// materialized and re-used on their own (i.e. without more
     /// specific constraints).
     fn is_underconstrained(&self, name: &str) -> bool;
+
+    /// Returns true iff the rule of the given name has been
+    /// implemented already and its global arrangement can be re-used,
+    /// rather than synthesizing the rule again.
+    fn is_implemented(&self, _name: &str) -> bool {
+        false
+    }
+
+    /// Returns the maximum number of iterations the recursive rule of
+    /// the given name may take, if it is limited.
+    fn iteration_limit(&self, name: &str) -> Option<u64>;
+
+    /// Returns the tracker recursive rules report the iterations they
+    /// take to, if they are tracked at all.
+    fn iterations(&self) -> Option<Rc<RefCell<Iterations>>> {
+        None
+    }
+
+    /// Returns whether the rule of the given name produces a set or a
+    /// multiset of tuples.
+    fn rule_semantics(&self, _name: &str) -> RuleSemantics {
+        RuleSemantics::default()
+    }
+
+    /// Returns true iff plans may be optimized based on statistics
+    /// about the data, e.g. by reordering joins.
+    fn enable_optimizer(&self) -> bool {
+        false
+    }
 }
 
 /// Description of everything a plan needs prior to synthesis.
@@ -122,7 +182,7 @@ impl Dependencies {
     /// A description representing a dependency on a single attribute.
     pub fn attribute(aid: &str) -> Dependencies {
         let mut attributes = HashSet::new();
-        attributes.insert(aid.to_string());
+        attributes.insert(Aid::from(aid));
 
         Dependencies {
             names: HashSet::new(),
@@ -158,13 +218,14 @@ pub trait Implementable {
         Vec::new()
     }
 
-    /// Implements the type as a simple relation.
+    /// Implements the type as a simple relation. Fails if the plan
+    /// refers to attributes or relations that are not available.
     fn implement<'b, T, I, S>(
         &self,
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
@@ -176,34 +237,53 @@ pub trait Implementable {
 pub enum Plan {
     /// Projection
     Project(Project<Plan>),
+    /// Projection, removing any duplicate tuples
+    ProjectDistinct(Project<Plan>),
     /// Aggregation
     Aggregate(Aggregate<Plan>),
+    /// Aggregation over windows of time
+    Window(Window<Plan>),
     /// Union
     Union(Union<Plan>),
     /// Equijoin
     Join(Join<Plan, Plan>),
+    /// Equijoin followed by an inequality predicate
+    ThetaJoin(ThetaJoin<Plan, Plan>),
     /// WCO
     Hector(Hector),
     /// Antijoin
     Antijoin(Antijoin<Plan, Plan>),
+    /// Tuples without a counterpart on their shared variables
+    NotExists(NotExists<Plan, Plan>),
     /// Negation
     Negate(Box<Plan>),
     /// Filters bindings by one of the built-in predicates
     Filter(Filter<Plan>),
     /// Transforms a binding by a function expression
     Transform(Transform<Plan>),
+    /// Relabels the variables bound by a plan
+    Rename(Rename<Plan>),
+    /// Bounded sample of the tuples of a plan
+    Sample(Sample<Plan>),
+    /// Literal tuples
+    Constant(Constant),
     /// Data pattern of the form [?e a ?v]
     MatchA(Var, Aid, Var),
     /// Data pattern of the form [e a ?v]
     MatchEA(Eid, Aid, Var),
     /// Data pattern of the form [?e a v]
     MatchAV(Var, Aid, Value),
+    /// Data pattern of the form [?e a ?v ?valid-from] on a
+    /// bitemporal attribute
+    ValidTime(ValidTime),
     /// Sources data from another relation.
     NameExpr(Vec<Var>, String),
     /// Pull expression
     Pull(Pull<Plan>),
     /// Single-level pull expression
     PullLevel(PullLevel<Plan>),
+    /// GraphQL query
+    GraphQl(GraphQl),
 }
 
 impl Plan {
@@ -211,43 +291,362 @@ impl Plan {
     pub fn variables(&self) -> Vec<Var> {
         match *self {
             Plan::Project(ref projection) => projection.variables.clone(),
+            Plan::ProjectDistinct(ref projection) => projection.variables.clone(),
             Plan::Aggregate(ref aggregate) => aggregate.variables.clone(),
+            Plan::Window(ref window) => window.variables.clone(),
             Plan::Union(ref union) => union.variables.clone(),
             Plan::Join(ref join) => join.variables.clone(),
+            Plan::ThetaJoin(ref join) => join.variables.clone(),
             Plan::Hector(ref hector) => hector.variables.clone(),
             Plan::Antijoin(ref antijoin) => antijoin.variables.clone(),
+            Plan::NotExists(ref not_exists) => not_exists.left_plan.variables(),
             Plan::Negate(ref plan) => plan.variables(),
             Plan::Filter(ref filter) => filter.variables.clone(),
             Plan::Transform(ref transform) => transform.variables.clone(),
+            Plan::Rename(ref rename) => rename
+                .plan
+                .variables()
+                .into_iter()
+                .map(|variable| *rename.mapping.get(&variable).unwrap_or(&variable))
+                .collect(),
+            Plan::Sample(ref sample) => sample.plan.variables(),
+            Plan::Constant(ref constant) => constant.variables.clone(),
             Plan::MatchA(e, _, v) => vec![e, v],
             Plan::MatchEA(_, _, v) => vec![v],
             Plan::MatchAV(e, _, _) => vec![e],
+            Plan::ValidTime(ref valid_time) => {
+                let (e, v, t) = valid_time.variables;
+                vec![e, v, t]
+            }
             Plan::NameExpr(ref variables, ref _name) => variables.clone(),
             Plan::Pull(ref pull) => pull.variables.clone(),
             Plan::PullLevel(ref path) => path.variables.clone(),
+            Plan::GraphQl(_) => vec![],
+        }
+    }
+
+    /// Returns the variables bound by the relation this plan
+    /// implements, in order, or None if they can't be determined
+    /// without implementing it.
+    fn output_variables(&self) -> Option<Vec<Var>> {
+        let joined = |variables: &[Var], left: &Plan, right: &Plan| {
+            let left = left.output_variables()?;
+            let right = right.output_variables()?;
+
+            Some(
+                variables
+                    .iter()
+                    .cloned()
+                    .chain(left.into_iter().filter(|x| !variables.contains(x)))
+                    .chain(right.into_iter().filter(|x| !variables.contains(x)))
+                    .collect(),
+            )
+        };
+
+        match *self {
+            Plan::Project(ref projection) => Some(projection.variables.clone()),
+            Plan::ProjectDistinct(ref projection) => Some(projection.variables.clone()),
+            Plan::Aggregate(ref aggregate) => Some(aggregate.variables.clone()),
+            Plan::Window(ref window) => Some(window.variables.clone()),
+            Plan::Union(ref union) => Some(union.variables.clone()),
+            Plan::Join(ref join) => joined(&join.variables, &join.left_plan, &join.right_plan),
+            Plan::ThetaJoin(ref join) => joined(&join.variables, &join.left_plan, &join.right_plan),
+            Plan::Hector(ref hector) => Some(hector.variables.clone()),
+            Plan::Antijoin(ref antijoin) => {
+                let left = antijoin.left_plan.output_variables()?;
+                Some(
+                    antijoin
+                        .variables
+                        .iter()
+                        .cloned()
+                        .chain(left.into_iter().filter(|x| !antijoin.variables.contains(x)))
+                        .collect(),
+                )
+            }
+            Plan::NotExists(ref not_exists) => not_exists.left_plan.output_variables(),
+            Plan::Negate(ref plan) => plan.output_variables(),
+            Plan::Filter(ref filter) => filter.plan.output_variables(),
+            Plan::Transform(ref transform) => {
+                let mut variables = transform.plan.output_variables()?;
+                variables.push(transform.result_variable);
+                Some(variables)
+            }
+            Plan::Rename(ref rename) => Some(
+                rename
+                    .plan
+                    .output_variables()?
+                    .into_iter()
+                    .map(|variable| *rename.mapping.get(&variable).unwrap_or(&variable))
+                    .collect(),
+            ),
+            Plan::Sample(ref sample) => sample.plan.output_variables(),
+            Plan::Constant(ref constant) => Some(constant.variables.clone()),
+            Plan::MatchA(e, _, v) => Some(vec![e, v]),
+            Plan::MatchEA(_, _, v) => Some(vec![v]),
+            Plan::MatchAV(e, _, _) => Some(vec![e]),
+            Plan::ValidTime(ref valid_time) => {
+                let (e, v, t) = valid_time.variables;
+                Some(vec![e, v, t])
+            }
+            Plan::NameExpr(ref variables, ref _name) => Some(variables.clone()),
+            Plan::Pull(_) | Plan::PullLevel(_) | Plan::GraphQl(_) => None,
+        }
+    }
+
+    /// Applies `f` to every constant value this plan refers to,
+    /// recursively.
+    pub fn map_constants<F: FnMut(&mut Value)>(&mut self, f: &mut F) {
+        match *self {
+            Plan::Project(ref mut projection) | Plan::ProjectDistinct(ref mut projection) => {
+                projection.plan.map_constants(f)
+            }
+            Plan::Aggregate(ref mut aggregate) => aggregate.plan.map_constants(f),
+            Plan::Window(ref mut window) => window.plan.map_constants(f),
+            Plan::Union(ref mut union) => {
+                for plan in union.plans.iter_mut() {
+                    plan.map_constants(f);
+                }
+            }
+            Plan::Join(ref mut join) => {
+                join.left_plan.map_constants(f);
+                join.right_plan.map_constants(f);
+            }
+            Plan::ThetaJoin(ref mut join) => {
+                join.left_plan.map_constants(f);
+                join.right_plan.map_constants(f);
+            }
+            Plan::Hector(ref mut hector) => {
+                for binding in hector.bindings.iter_mut() {
+                    binding.map_constants(f);
+                }
+            }
+            Plan::Antijoin(ref mut antijoin) => {
+                antijoin.left_plan.map_constants(f);
+                antijoin.right_plan.map_constants(f);
+            }
+            Plan::NotExists(ref mut not_exists) => {
+                not_exists.left_plan.map_constants(f);
+                not_exists.right_plan.map_constants(f);
+            }
+            Plan::Negate(ref mut plan) => plan.map_constants(f),
+            Plan::Filter(ref mut filter) => {
+                for constant in filter.constants.iter_mut().flatten() {
+                    f(constant);
+                }
+                filter.plan.map_constants(f);
+            }
+            Plan::Transform(ref mut transform) => {
+                for constant in transform.constants.iter_mut().flatten() {
+                    f(constant);
+                }
+                transform.plan.map_constants(f);
+            }
+            Plan::Rename(ref mut rename) => rename.plan.map_constants(f),
+            Plan::Sample(ref mut sample) => sample.plan.map_constants(f),
+            Plan::Constant(ref mut constant) => {
+                for value in constant.tuples.iter_mut().flatten() {
+                    f(value);
+                }
+            }
+            Plan::MatchAV(_, _, ref mut v) => f(v),
+            Plan::Pull(ref mut pull) => {
+                for path in pull.paths.iter_mut() {
+                    path.plan.map_constants(f);
+                }
+            }
+            Plan::PullLevel(ref mut path) => path.plan.map_constants(f),
+            Plan::MatchA(..)
+            | Plan::MatchEA(..)
+            | Plan::ValidTime(_)
+            | Plan::NameExpr(..)
+            | Plan::GraphQl(_) => {}
+        }
+    }
+
+    /// Returns true iff this plan, or any plan it is made of, negates
+    /// a conjunction of more than one binding. Such plans can't be
+    /// turned into bindings (see `NotExists`) and have to be
+    /// implemented as they are.
+    pub fn negates_conjunction(&self) -> bool {
+        match *self {
+            Plan::Project(ref projection) | Plan::ProjectDistinct(ref projection) => {
+                projection.plan.negates_conjunction()
+            }
+            Plan::Aggregate(ref aggregate) => aggregate.plan.negates_conjunction(),
+            Plan::Window(ref window) => window.plan.negates_conjunction(),
+            Plan::Union(ref union) => union.plans.iter().any(|plan| plan.negates_conjunction()),
+            Plan::Join(ref join) => {
+                join.left_plan.negates_conjunction() || join.right_plan.negates_conjunction()
+            }
+            Plan::ThetaJoin(ref join) => {
+                join.left_plan.negates_conjunction() || join.right_plan.negates_conjunction()
+            }
+            Plan::NotExists(ref not_exists) => {
+                not_exists.left_plan.negates_conjunction()
+                    || not_exists.right_plan.negates_conjunction()
+                    || not_exists.right_plan.into_bindings().len() != 1
+            }
+            Plan::Negate(ref plan) => plan.negates_conjunction(),
+            Plan::Transform(ref transform) => transform.plan.negates_conjunction(),
+            Plan::Rename(ref rename) => rename.plan.negates_conjunction(),
+            Plan::Sample(ref sample) => sample.plan.negates_conjunction(),
+            _ => false,
+        }
+    }
+
+    /// Checks that all variables a plan stage refers to are bound by
+    /// its inputs, recursively. This catches malformed plans before
+    /// any dataflow is built for them.
+    pub fn validate(&self) -> Result<(), Error> {
+        match *self {
+            Plan::Project(ref projection) | Plan::ProjectDistinct(ref projection) => {
+                projection.plan.validate()?;
+                require("Project", &projection.variables, &projection.plan)
+            }
+            Plan::Aggregate(ref aggregate) => {
+                aggregate.plan.validate()?;
+                require("Aggregate", &aggregate.key_variables, &aggregate.plan)?;
+                require(
+                    "Aggregate",
+                    &aggregate.aggregation_variables,
+                    &aggregate.plan,
+                )
+            }
+            Plan::Window(ref window) => {
+                window.plan.validate()?;
+                require("Window", &window.key_variables, &window.plan)?;
+                require("Window", &window.with_variables, &window.plan)?;
+                require(
+                    "Window",
+                    &[window.time_variable, window.aggregation_variable],
+                    &window.plan,
+                )
+            }
+            Plan::Union(ref union) => {
+                for plan in union.plans.iter() {
+                    plan.validate()?;
+                    require("Union", &union.variables, plan)?;
+                }
+                Ok(())
+            }
+            Plan::Join(ref join) => {
+                join.left_plan.validate()?;
+                join.right_plan.validate()?;
+                require("Join", &join.variables, &join.left_plan)?;
+                require("Join", &join.variables, &join.right_plan)
+            }
+            Plan::ThetaJoin(ref join) => {
+                join.left_plan.validate()?;
+                join.right_plan.validate()?;
+                require("ThetaJoin", &join.variables, &join.left_plan)?;
+                require("ThetaJoin", &join.variables, &join.right_plan)?;
+                require("ThetaJoin", &[join.predicate_variables.0], &join.left_plan)?;
+                require("ThetaJoin", &[join.predicate_variables.1], &join.right_plan)
+            }
+            Plan::Hector(ref hector) => {
+                let bound: HashSet<Var> = hector
+                    .bindings
+                    .iter()
+                    .flat_map(|binding| binding.variables())
+                    .collect();
+
+                match hector.variables.iter().find(|x| !bound.contains(x)) {
+                    None => Ok(()),
+                    Some(variable) => Err(Error {
+                        category: ErrorKind::Incorrect,
+                        message: format!(
+                            "Hector requires variable {}, which is not bound by any binding.",
+                            variable
+                        ),
+                    }),
+                }
+            }
+            Plan::Antijoin(ref antijoin) => {
+                antijoin.left_plan.validate()?;
+                antijoin.right_plan.validate()?;
+                require("Antijoin", &antijoin.variables, &antijoin.left_plan)?;
+                require("Antijoin", &antijoin.variables, &antijoin.right_plan)
+            }
+            Plan::NotExists(ref not_exists) => {
+                not_exists.left_plan.validate()?;
+                not_exists.right_plan.validate()
+            }
+            Plan::Negate(ref plan) => plan.validate(),
+            Plan::Filter(ref filter) => {
+                filter.plan.validate()?;
+                require("Filter", &filter.variables, &filter.plan)
+            }
+            Plan::Transform(ref transform) => {
+                transform.plan.validate()?;
+                require("Transform", &transform.variables, &transform.plan)
+            }
+            Plan::Rename(ref rename) => rename.plan.validate(),
+            Plan::Sample(ref sample) => sample.plan.validate(),
+            Plan::Constant(ref constant) => constant.validate(),
+            Plan::Pull(ref pull) => {
+                for path in pull.paths.iter() {
+                    path.plan.validate()?;
+                }
+                Ok(())
+            }
+            Plan::PullLevel(ref path) => path.plan.validate(),
+            Plan::MatchA(..)
+            | Plan::MatchEA(..)
+            | Plan::MatchAV(..)
+            | Plan::ValidTime(_)
+            | Plan::NameExpr(..)
+            | Plan::GraphQl(_) => Ok(()),
         }
     }
 }
 
+/// Ensures that the input to a plan stage binds all of the specified
+/// variables. Inputs whose variables can't be determined up front are
+/// accepted.
+fn require(stage: &str, variables: &[Var], input: &Plan) -> Result<(), Error> {
+    if let Some(bound) = input.output_variables() {
+        if let Some(variable) = variables.iter().find(|x| !bound.contains(x)) {
+            return Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!(
+                    "{} requires variable {}, which is not bound by its input.",
+                    stage, variable
+                ),
+            });
+        }
+    }
+
+    Ok(())
+}
+
 impl Implementable for Plan {
     fn dependencies(&self) -> Dependencies {
         // @TODO provide a general fold for plans
         match *self {
             Plan::Project(ref projection) => projection.dependencies(),
+            Plan::ProjectDistinct(ref projection) => projection.dependencies(),
             Plan::Aggregate(ref aggregate) => aggregate.dependencies(),
+            Plan::Window(ref window) => window.dependencies(),
             Plan::Union(ref union) => union.dependencies(),
             Plan::Join(ref join) => join.dependencies(),
+            Plan::ThetaJoin(ref join) => join.dependencies(),
             Plan::Hector(ref hector) => hector.dependencies(),
             Plan::Antijoin(ref antijoin) => antijoin.dependencies(),
+            Plan::NotExists(ref not_exists) => not_exists.dependencies(),
             Plan::Negate(ref plan) => plan.dependencies(),
             Plan::Filter(ref filter) => filter.dependencies(),
             Plan::Transform(ref transform) => transform.dependencies(),
+            Plan::Rename(ref rename) => rename.dependencies(),
+            Plan::Sample(ref sample) => sample.dependencies(),
+            Plan::Constant(ref constant) => constant.dependencies(),
             Plan::MatchA(_, ref a, _) => Dependencies::attribute(a),
             Plan::MatchEA(_, ref a, _) => Dependencies::attribute(a),
             Plan::MatchAV(_, ref a, _) => Dependencies::attribute(a),
+            Plan::ValidTime(ref valid_time) => valid_time.dependencies(),
             Plan::NameExpr(_, ref name) => Dependencies::name(name),
             Plan::Pull(ref pull) => pull.dependencies(),
             Plan::PullLevel(ref path) => path.dependencies(),
+            Plan::GraphQl(ref q) => q.dependencies(),
         }
     }
 
@@ -255,14 +654,21 @@ impl Implementable for Plan {
         // @TODO provide a general fold for plans
         match *self {
             Plan::Project(ref projection) => projection.into_bindings(),
+            Plan::ProjectDistinct(ref projection) => projection.into_bindings(),
             Plan::Aggregate(ref aggregate) => aggregate.into_bindings(),
+            Plan::Window(ref window) => window.into_bindings(),
             Plan::Union(ref union) => union.into_bindings(),
             Plan::Join(ref join) => join.into_bindings(),
+            Plan::ThetaJoin(ref join) => join.into_bindings(),
             Plan::Hector(ref hector) => hector.into_bindings(),
             Plan::Antijoin(ref antijoin) => antijoin.into_bindings(),
+            Plan::NotExists(ref not_exists) => not_exists.into_bindings(),
             Plan::Negate(ref plan) => plan.into_bindings(),
             Plan::Filter(ref filter) => filter.into_bindings(),
             Plan::Transform(ref transform) => transform.into_bindings(),
+            Plan::Rename(ref rename) => rename.into_bindings(),
+            Plan::Sample(ref sample) => sample.into_bindings(),
+            Plan::Constant(ref constant) => constant.into_bindings(),
             Plan::MatchA(e, ref a, v) => vec![Binding::attribute(e, a, v)],
             Plan::MatchEA(match_e, ref a, v) => {
                 let e = gensym();
@@ -278,9 +684,11 @@ impl Implementable for Plan {
                     Binding::constant(v, match_v.clone()),
                 ]
             }
-            Plan::NameExpr(_, ref _name) => unimplemented!(), // @TODO hmm...
+            Plan::ValidTime(ref valid_time) => valid_time.into_bindings(),
+            Plan::NameExpr(ref syms, ref name) => vec![Binding::relation(name, syms.clone())],
             Plan::Pull(ref pull) => pull.into_bindings(),
             Plan::PullLevel(ref path) => path.into_bindings(),
+            Plan::GraphQl(ref q) => q.into_bindings(),
         }
     }
 
@@ -288,38 +696,37 @@ impl Implementable for Plan {
         // @TODO provide a general fold for plans
         match *self {
             Plan::Project(ref projection) => projection.datafy(),
+            Plan::ProjectDistinct(ref projection) => projection.datafy(),
             Plan::Aggregate(ref aggregate) => aggregate.datafy(),
+            Plan::Window(ref window) => window.datafy(),
             Plan::Union(ref union) => union.datafy(),
             Plan::Join(ref join) => join.datafy(),
+            Plan::ThetaJoin(ref join) => join.datafy(),
             Plan::Hector(ref hector) => hector.datafy(),
             Plan::Antijoin(ref antijoin) => antijoin.datafy(),
+            Plan::NotExists(ref not_exists) => not_exists.datafy(),
             Plan::Negate(ref plan) => plan.datafy(),
             Plan::Filter(ref filter) => filter.datafy(),
             Plan::Transform(ref transform) => transform.datafy(),
-            Plan::MatchA(_e, ref a, _v) => vec![(
-                next_id(),
-                "df.pattern/a".to_string(),
-                Value::Aid(a.to_string()),
-            )],
+            Plan::Rename(ref rename) => rename.datafy(),
+            Plan::Sample(ref sample) => sample.datafy(),
+            Plan::Constant(ref constant) => constant.datafy(),
+            Plan::MatchA(_e, ref a, _v) => {
+                vec![(next_id(), Aid::from("df.pattern/a"), Value::Aid(a.clone()))]
+            }
             Plan::MatchEA(e, ref a, _) => vec![
-                (next_id(), "df.pattern/e".to_string(), Value::Eid(e)),
-                (
-                    next_id(),
-                    "df.pattern/a".to_string(),
-                    Value::Aid(a.to_string()),
-                ),
+                (next_id(), Aid::from("df.pattern/e"), Value::Eid(e)),
+                (next_id(), Aid::from("df.pattern/a"), Value::Aid(a.clone())),
             ],
             Plan::MatchAV(_, ref a, ref v) => vec![
-                (
-                    next_id(),
-                    "df.pattern/a".to_string(),
-                    Value::Aid(a.to_string()),
-                ),
-                (next_id(), "df.pattern/v".to_string(), v.clone()),
+                (next_id(), Aid::from("df.pattern/a"), Value::Aid(a.clone())),
+                (next_id(), Aid::from("df.pattern/v"), v.clone()),
             ],
+            Plan::ValidTime(ref valid_time) => valid_time.datafy(),
             Plan::NameExpr(_, ref _name) => Vec::new(),
             Plan::Pull(ref pull) => pull.datafy(),
             Plan::PullLevel(ref path) => path.datafy(),
+            Plan::GraphQl(ref q) => q.datafy(),
         }
     }
 
@@ -328,7 +735,7 @@ impl Implementable for Plan {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
@@ -338,29 +745,52 @@ impl Implementable for Plan {
             Plan::Project(ref projection) => {
                 projection.implement(nested, local_arrangements, context)
             }
+            Plan::ProjectDistinct(ref projection) => {
+                let (relation, shutdown) =
+                    projection.implement(nested, local_arrangements, context)?;
+                let distinct = CollectionRelation {
+                    variables: relation.variables(),
+                    tuples: relation.tuples().distinct(),
+                };
+
+                Ok((distinct, shutdown))
+            }
             Plan::Aggregate(ref aggregate) => {
                 aggregate.implement(nested, local_arrangements, context)
             }
+            Plan::Window(ref window) => window.implement(nested, local_arrangements, context),
             Plan::Union(ref union) => union.implement(nested, local_arrangements, context),
             Plan::Join(ref join) => join.implement(nested, local_arrangements, context),
+            Plan::ThetaJoin(ref join) => join.implement(nested, local_arrangements, context),
             Plan::Hector(ref hector) => hector.implement(nested, local_arrangements, context),
             Plan::Antijoin(ref antijoin) => antijoin.implement(nested, local_arrangements, context),
+            Plan::NotExists(ref not_exists) => {
+                not_exists.implement(nested, local_arrangements, context)
+            }
             Plan::Negate(ref plan) => {
-                let (relation, shutdown) = plan.implement(nested, local_arrangements, context);
+                let (relation, shutdown) = plan.implement(nested, local_arrangements, context)?;
                 let negated = CollectionRelation {
                     variables: relation.variables(),
                     tuples: relation.tuples().negate(),
                 };
 
-                (negated, shutdown)
+                Ok((negated, shutdown))
             }
             Plan::Filter(ref filter) => filter.implement(nested, local_arrangements, context),
             Plan::Transform(ref transform) => {
                 transform.implement(nested, local_arrangements, context)
             }
+            Plan::Rename(ref rename) => rename.implement(nested, local_arrangements, context),
+            Plan::Sample(ref sample) => sample.implement(nested, local_arrangements, context),
+            Plan::Constant(ref constant) => constant.implement(nested, local_arrangements, context),
             Plan::MatchA(sym1, ref a, sym2) => {
                 let (tuples, shutdown_validate) = match context.forward_index(a) {
-                    None => panic!("attribute {:?} does not exist", a),
+                    None => {
+                        return Err(Error {
+                            category: ErrorKind::NotFound,
+                            message: format!("Attribute {} does not exist.", a),
+                        });
+                    }
                     Some(index) => {
                         let frontier: Vec<T> = index.validate_trace.advance_frontier().to_vec();
                         let (validate, shutdown_validate) =
@@ -383,11 +813,16 @@ impl Implementable for Plan {
                     tuples,
                 };
 
-                (relation, ShutdownHandle::from_button(shutdown_validate))
+                Ok((relation, ShutdownHandle::from_button(shutdown_validate)))
             }
             Plan::MatchEA(match_e, ref a, sym1) => {
                 let (tuples, shutdown_propose) = match context.forward_index(a) {
-                    None => panic!("attribute {:?} does not exist", a),
+                    None => {
+                        return Err(Error {
+                            category: ErrorKind::NotFound,
+                            message: format!("Attribute {} does not exist.", a),
+                        });
+                    }
                     Some(index) => {
                         let frontier: Vec<T> = index.propose_trace.advance_frontier().to_vec();
                         let (propose, shutdown_propose) =
@@ -411,11 +846,16 @@ impl Implementable for Plan {
                     tuples,
                 };
 
-                (relation, ShutdownHandle::from_button(shutdown_propose))
+                Ok((relation, ShutdownHandle::from_button(shutdown_propose)))
             }
             Plan::MatchAV(sym1, ref a, ref match_v) => {
-                let (tuples, shutdown_propose) = match context.reverse_index(a) {
-                    None => panic!("attribute {:?} does not exist", a),
+                let (tuples, shutdown_propose) = match context.reverse_index(a, &nested.parent) {
+                    None => {
+                        return Err(Error {
+                            category: ErrorKind::NotFound,
+                            message: format!("Attribute {} does not exist.", a),
+                        });
+                    }
                     Some(index) => {
                         let match_v = match_v.clone();
                         let frontier: Vec<T> = index.propose_trace.advance_frontier().to_vec();
@@ -440,30 +880,39 @@ impl Implementable for Plan {
                     tuples,
                 };
 
-                (relation, ShutdownHandle::from_button(shutdown_propose))
+                Ok((relation, ShutdownHandle::from_button(shutdown_propose)))
+            }
+            Plan::ValidTime(ref valid_time) => {
+                valid_time.implement(nested, local_arrangements, context)
             }
             Plan::NameExpr(ref syms, ref name) => {
-                if context.is_underconstrained(name) {
+                if context.is_underconstrained(name) && !context.is_implemented(name) {
                     match local_arrangements.get(name) {
-                        None => panic!("{:?} not in relation map", name),
+                        None => Err(Error {
+                            category: ErrorKind::NotFound,
+                            message: format!("Relation {} is not available locally.", name),
+                        }),
                         Some(named) => {
                             let relation = CollectionRelation {
                                 variables: syms.clone(),
                                 tuples: named.deref().clone(), // @TODO re-use variable directly?
                             };
 
-                            (relation, ShutdownHandle::empty())
+                            Ok((relation, ShutdownHandle::empty()))
                         }
                     }
                 } else {
                     // If a rule is not underconstrained, we can
-                    // safely re-use it. @TODO it's debatable whether
-                    // we should then immediately assume that it is
-                    // available as a global arrangement, but we'll do
-                    // so for now.
+                    // safely re-use it. Cached rules are implemented
+                    // ahead of everything depending on them, so we
+                    // expect a global arrangement to be available,
+                    // just as for rules implemented before.
 
                     match context.global_arrangement(name) {
-                        None => panic!("{:?} not in query map", name),
+                        None => Err(Error {
+                            category: ErrorKind::NotFound,
+                            message: format!("Relation {} is not available globally.", name),
+                        }),
                         Some(named) => {
                             let frontier: Vec<T> = named.advance_frontier().to_vec();
                             let (arranged, shutdown_button) =
@@ -481,13 +930,14 @@ impl Implementable for Plan {
                                     .as_collection(|tuple, _| tuple.clone()),
                             };
 
-                            (relation, ShutdownHandle::from_button(shutdown_button))
+                            Ok((relation, ShutdownHandle::from_button(shutdown_button)))
                         }
                     }
                 }
             }
             Plan::Pull(ref pull) => pull.implement(nested, local_arrangements, context),
             Plan::PullLevel(ref path) => path.implement(nested, local_arrangements, context),
+            Plan::GraphQl(ref q) => q.implement(nested, local_arrangements, context),
         }
     }
 }
@@ -0,0 +1,96 @@
+//! Correlated non-existence expression plan.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::TotalOrder;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::{Join, Threshold};
+
+use crate::binding::{AsBinding, Binding};
+use crate::plan::{Dependencies, ImplContext, Implementable};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, Var, VariableMap};
+
+/// A plan stage keeping only those tuples of its left input, for
+/// which no tuple of its right input agrees on all variables shared
+/// between the two. Unlike with a raw `Negate`, the result never
+/// carries negative multiplicities, and unlike with `Antijoin`, the
+/// inputs need not be union-compatible. Variables bound only by the
+/// right input are discarded.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct NotExists<P1: Implementable, P2: Implementable> {
+    /// Plan for the tuples to keep.
+    pub left_plan: Box<P1>,
+    /// Plan for the tuples that must not exist.
+    pub right_plan: Box<P2>,
+}
+
+impl<P1: Implementable, P2: Implementable> Implementable for NotExists<P1, P2> {
+    fn dependencies(&self) -> Dependencies {
+        Dependencies::merge(
+            self.left_plan.dependencies(),
+            self.right_plan.dependencies(),
+        )
+    }
+
+    fn into_bindings(&self) -> Vec<Binding> {
+        let mut bindings = self.left_plan.into_bindings();
+        let mut right_bindings = self.right_plan.into_bindings();
+
+        // A conjunction of negated bindings is not the negation of
+        // a conjunction, so only single bindings translate directly.
+        // Other plans must be implemented as they are, which callers
+        // can check via `Plan::negates_conjunction`.
+        if right_bindings.len() != 1 {
+            panic!("NotExists over more than one binding can't be implemented via Hector.");
+        }
+
+        bindings.push(Binding::not(right_bindings.remove(0)));
+
+        bindings
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let (left, shutdown_left) =
+            self.left_plan
+                .implement(nested, local_arrangements, context)?;
+        let (right, shutdown_right) =
+            self.right_plan
+                .implement(nested, local_arrangements, context)?;
+
+        let variables = left.variables();
+        let right_variables = right.variables();
+
+        let correlated: Vec<Var> = variables
+            .iter()
+            .cloned()
+            .filter(|x| right_variables.contains(x))
+            .collect();
+
+        let offsets: Vec<usize> = correlated.iter().map(|x| left.binds(*x).unwrap()).collect();
+
+        let tuples = left
+            .tuples()
+            .map(move |tuple| {
+                let key: Vec<_> = offsets.iter().map(|idx| tuple[*idx].clone()).collect();
+                (key, tuple)
+            })
+            .antijoin(&right.projected(&correlated).distinct())
+            .map(|(_key, tuple)| tuple);
+
+        let shutdown_handle = ShutdownHandle::merge(shutdown_left, shutdown_right);
+
+        Ok((CollectionRelation { variables, tuples }, shutdown_handle))
+    }
+}
@@ -10,7 +10,7 @@ use differential_dataflow::lattice::Lattice;
 use crate::binding::Binding;
 use crate::plan::{next_id, Dependencies, ImplContext, Implementable};
 use crate::{Aid, Eid, Value, Var};
-use crate::{CollectionRelation, Relation, ShutdownHandle, VariableMap};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, VariableMap};
 
 /// A plan stage projecting its source to only the specified sequence
 /// of variables. Throws on unbound variables. Frontends are responsible
@@ -41,7 +41,7 @@ impl<P: Implementable> Implementable for Project<P> {
         } else {
             let child_eid = data[0].0;
 
-            data.push((eid, "df.project/binding".to_string(), Value::Eid(child_eid)));
+            data.push((eid, Aid::from("df.project/binding"), Value::Eid(child_eid)));
 
             data
         }
@@ -52,19 +52,20 @@ impl<P: Implementable> Implementable for Project<P> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        let (relation, shutdown_handle) = self.plan.implement(nested, local_arrangements, context);
+        let (relation, shutdown_handle) =
+            self.plan.implement(nested, local_arrangements, context)?;
 
         let projected = CollectionRelation {
             variables: self.variables.to_vec(),
             tuples: relation.projected(&self.variables),
         };
 
-        (projected, shutdown_handle)
+        Ok((projected, shutdown_handle))
     }
 }
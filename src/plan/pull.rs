@@ -1,16 +1,31 @@
-//! Pull expression plan, but without nesting.
+//! Pull expression plan, supporting nested pulls of bounded depth.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
 
 use timely::dataflow::operators::Concatenate;
 use timely::dataflow::scopes::child::Iterative;
-use timely::dataflow::Scope;
+use timely::dataflow::{Scope, Stream};
 use timely::order::{Product, TotalOrder};
 use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
-use differential_dataflow::AsCollection;
+use differential_dataflow::operators::arrange::{Arrange, Arranged, TraceAgent};
+use differential_dataflow::operators::{Join, JoinCore, Reduce, Threshold};
+use differential_dataflow::trace::implementations::ord::OrdValSpine;
+use differential_dataflow::trace::TraceReader;
+use differential_dataflow::{AsCollection, Collection};
+
+use graphql_parser::parse_query;
+use graphql_parser::query::{
+    Definition, Document, Field, FragmentDefinition, OperationDefinition, Selection, SelectionSet,
+};
 
-use crate::plan::{Dependencies, ImplContext, Implementable};
-use crate::{Aid, CollectionRelation, Relation, ShutdownHandle, Value, Var, VariableMap};
+use crate::plan::{Dependencies, ImplContext, Implementable, Join, Plan, Project};
+use crate::{
+    Aid, CollectionRelation, Eid, Error, ErrorKind, Relation, ResultDiff, ShutdownHandle, Value,
+};
+use crate::{Var, VariableMap};
 
 /// A plan stage for extracting all matching [e a v] tuples for a
 /// given set of attributes and an input relation specifying entities.
@@ -22,9 +37,80 @@ pub struct PullLevel<P: Implementable> {
     pub plan: Box<P>,
     /// Attributes to pull for the input entities.
     pub pull_attributes: Vec<Aid>,
+    /// Attributes to pull for the input entities, reported under a
+    /// different name, as (attribute, alias) pairs.
+    #[serde(default)]
+    pub aliased_attributes: Vec<(Aid, Aid)>,
+    /// Pull all attributes known at implementation time, in
+    /// addition to `pull_attributes`, i.e. `[*]`.
+    #[serde(default)]
+    pub wildcard: bool,
     /// Attribute names to distinguish plans of the same
     /// length. Useful to feed into a nested hash-map directly.
     pub path_attributes: Vec<Aid>,
+    /// Attributes to pull in reverse, i.e. for all entities
+    /// referring to the input entities via that attribute.
+    #[serde(default)]
+    pub reverse_attributes: Vec<Aid>,
+    /// Pulls on entities referenced by the input entities.
+    #[serde(default)]
+    pub nested: Vec<NestedPull>,
+    /// Maximum number of values to pull per input entity and
+    /// attribute on this level, e.g. to only show the first few
+    /// comments of a post. Doesn't apply to nested pulls.
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Attribute of the pulled values by which to order them before
+    /// applying the limit. Values are ordered by themselves
+    /// otherwise. Values lacking the attribute come last.
+    #[serde(default)]
+    pub sort_attribute: Option<Aid>,
+    /// Order values from greatest to least before applying the
+    /// limit, e.g. to show the most recent ones.
+    #[serde(default)]
+    pub sort_descending: bool,
+    /// Attribute by which to order the entities of this level among
+    /// their siblings when serializing results, see
+    /// `order_nested`. The attribute must be pulled on this level.
+    #[serde(default)]
+    pub order_by: Option<Aid>,
+    /// Order entities from greatest to least when serializing.
+    #[serde(default)]
+    pub order_descending: bool,
+    /// Collapse all values pulled per input entity and attribute on
+    /// this level into a single, sorted `Value::List`, rather than
+    /// producing one tuple per value. Applies after the limit and
+    /// doesn't apply to nested pulls.
+    #[serde(default)]
+    pub collect_values: bool,
+}
+
+/// A pull nested within another pull level, following a reference
+/// attribute from the entities of the enclosing level. So
+/// `{:parent/child [:name {:parent/child [:name]}]}` would be
+/// represented as a nested pull on `:parent/child`, pulling `:name`,
+/// which in turn contains another such nested pull.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct NestedPull {
+    /// Reference attribute leading from the enclosing entities to
+    /// the entities to pull from.
+    pub ref_attribute: Aid,
+    /// Name under which the reference attribute is reported, if
+    /// different from the attribute itself.
+    #[serde(default)]
+    pub alias: Option<Aid>,
+    /// Attributes to pull for the referenced entities.
+    pub pull_attributes: Vec<Aid>,
+    /// Attributes to pull for the referenced entities, reported
+    /// under a different name, as (attribute, alias) pairs.
+    #[serde(default)]
+    pub aliased_attributes: Vec<(Aid, Aid)>,
+    /// Attributes to pull in reverse for the referenced entities.
+    #[serde(default)]
+    pub reverse_attributes: Vec<Aid>,
+    /// Pulls on entities referenced in turn.
+    #[serde(default)]
+    pub nested: Vec<NestedPull>,
 }
 
 /// A plan stage for pull queries split into individual paths. So
@@ -69,6 +155,82 @@ fn interleave(values: &[Value], constants: &[Aid]) -> Vec<Value> {
     }
 }
 
+/// A bound on the number of values pulled per entity and attribute.
+struct Bound<G: Scope> {
+    /// Maximum number of values to keep.
+    count: usize,
+    /// Optional (e, v) pairs to order values by.
+    sort: Option<Collection<G, (Value, Value), isize>>,
+    /// Keep the greatest values, rather than the least.
+    descending: bool,
+}
+
+/// Keeps only the first values in each group of pull tuples sharing
+/// everything but their value, i.e. in each group of values pulled
+/// for the same path and attribute.
+fn bound_values<'b, T, S>(
+    tuples: &Collection<Iterative<'b, S, u64>, Vec<Value>, isize>,
+    bound: &Bound<Iterative<'b, S, u64>>,
+) -> Collection<Iterative<'b, S, u64>, Vec<Value>, isize>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    S: Scope<Timestamp = T>,
+{
+    let count = bound.count;
+    let descending = bound.descending;
+
+    let keyed = tuples.map(|mut tuple| {
+        let v = tuple.pop().unwrap();
+        (tuple, v)
+    });
+
+    // Values are ranked by (missing?, sort key, value).
+    let ranked = match bound.sort {
+        None => keyed.map(|(key, v)| (key, (false, v.clone(), v))),
+        Some(ref sort) => {
+            let by_value = keyed.map(|(key, v)| (v, key));
+
+            let sorted = by_value.join_map(sort, |v, key, s| {
+                (key.clone(), (false, s.clone(), v.clone()))
+            });
+            let unsorted = by_value
+                .antijoin(&sort.map(|(e, _s)| e).distinct())
+                .map(|(v, key)| (key, (true, v.clone(), v)));
+
+            sorted.concat(&unsorted)
+        }
+    };
+
+    ranked
+        .reduce(move |_key, input, output| {
+            let (sorted, unsorted): (Vec<_>, Vec<_>) =
+                input.iter().partition(|(rank, _diff)| !rank.0);
+
+            let sorted: Box<dyn Iterator<Item = _>> = if descending {
+                Box::new(sorted.into_iter().rev())
+            } else {
+                Box::new(sorted.into_iter())
+            };
+
+            for (rank, diff) in sorted.chain(unsorted.into_iter()).take(count) {
+                output.push((rank.2.clone(), *diff));
+            }
+        })
+        .map(|(mut key, v)| {
+            key.push(v);
+            key
+        })
+}
+
+/// Name under which reverse pulls of an attribute are reported,
+/// e.g. `child/_parent` for `child/parent`.
+fn reverse_name(a: &str) -> Aid {
+    match a.rfind('/') {
+        None => Aid::from(format!("_{}", a)),
+        Some(idx) => Aid::from(format!("{}_{}", &a[..idx + 1], &a[idx + 1..])),
+    }
+}
+
 impl<P: Implementable> Implementable for PullLevel<P> {
     fn dependencies(&self) -> Dependencies {
         Dependencies::none()
@@ -79,23 +241,23 @@ impl<P: Implementable> Implementable for PullLevel<P> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        use differential_dataflow::operators::arrange::{Arrange, Arranged, TraceAgent};
-        use differential_dataflow::operators::JoinCore;
-        use differential_dataflow::trace::implementations::ord::OrdValSpine;
-        use differential_dataflow::trace::TraceReader;
-
-        let (input, shutdown_input) = self.plan.implement(nested, local_arrangements, context);
+        let (input, shutdown_input) = self.plan.implement(nested, local_arrangements, context)?;
 
-        if self.pull_attributes.is_empty() {
+        if self.pull_attributes.is_empty()
+            && self.aliased_attributes.is_empty()
+            && !self.wildcard
+            && self.reverse_attributes.is_empty()
+            && self.nested.is_empty()
+        {
             if self.path_attributes.is_empty() {
                 // nothing to pull
-                (input, shutdown_input)
+                Ok((input, shutdown_input))
             } else {
                 let path_attributes = self.path_attributes.clone();
                 let tuples = input
@@ -107,62 +269,82 @@ impl<P: Implementable> Implementable for PullLevel<P> {
                     tuples,
                 };
 
-                (relation, shutdown_input)
+                Ok((relation, shutdown_input))
             }
         } else {
-            // Arrange input entities by eid.
-            let paths = input.tuples();
-            let e_path: Arranged<
-                Iterative<S, u64>,
-                Value,
-                Vec<Value>,
-                isize,
-                TraceAgent<
-                    Value,
-                    Vec<Value>,
-                    Product<T, u64>,
-                    isize,
-                    OrdValSpine<Value, Vec<Value>, Product<T, u64>, isize>,
-                >,
-            > = paths.map(|t| (t.last().unwrap().clone(), t)).arrange();
-
             let mut shutdown_handle = shutdown_input;
-            let streams = self.pull_attributes.iter().map(|a| {
-                let e_v = match context.forward_index(a) {
-                    None => panic!("attribute {:?} does not exist", a),
-                    Some(index) => {
-                        let frontier: Vec<T> = index.propose_trace.advance_frontier().to_vec();
-                        let (arranged, shutdown_propose) =
-                            index.propose_trace.import_core(&nested.parent, a);
-
-                        let e_v = arranged.enter_at(nested, move |_, _, time| {
-                            let mut forwarded = time.clone();
-                            forwarded.advance_by(&frontier);
-                            Product::new(forwarded, 0)
-                        });
-
-                        shutdown_handle.add_button(shutdown_propose);
+            let mut streams = Vec::new();
 
-                        e_v
+            // Wildcard pulls are resolved once, attributes created
+            // later on will not show up in this dataflow.
+            let pull_attributes = if self.wildcard {
+                let mut pull_attributes = context.attributes();
+                for a in self.pull_attributes.iter() {
+                    if !pull_attributes.contains(a) {
+                        pull_attributes.push(a.clone());
                     }
-                };
+                }
+
+                pull_attributes
+            } else {
+                self.pull_attributes.clone()
+            };
+
+            let bound = match self.limit {
+                None => None,
+                Some(count) => {
+                    let sort = match self.sort_attribute {
+                        None => None,
+                        Some(ref a) => match context.forward_index(a) {
+                            None => {
+                                return Err(Error {
+                                    category: ErrorKind::NotFound,
+                                    message: format!("Attribute {} does not exist.", a),
+                                });
+                            }
+                            Some(index) => {
+                                let frontier: Vec<T> =
+                                    index.propose_trace.advance_frontier().to_vec();
+                                let (arranged, shutdown_propose) =
+                                    index.propose_trace.import_core(&nested.parent, a);
 
-                let attribute = Value::Aid(a.clone());
-                let path_attributes: Vec<Aid> = self.path_attributes.clone();
+                                let e_s = arranged
+                                    .enter_at(nested, move |_, _, time| {
+                                        let mut forwarded = time.clone();
+                                        forwarded.advance_by(&frontier);
+                                        Product::new(forwarded, 0)
+                                    })
+                                    .as_collection(|e, s| (e.clone(), s.clone()));
 
-                e_path
-                    .join_core(&e_v, move |_e, path: &Vec<Value>, v: &Value| {
-                        // Each result tuple must hold the interleaved
-                        // path, the attribute, and the value,
-                        // i.e. [?p "parent/child" ?c ?a ?v]
-                        let mut result = interleave(path, &path_attributes);
-                        result.push(attribute.clone());
-                        result.push(v.clone());
+                                shutdown_handle.add_button(shutdown_propose);
 
-                        Some(result)
+                                Some(e_s)
+                            }
+                        },
+                    };
+
+                    Some(Bound {
+                        count,
+                        sort,
+                        descending: self.sort_descending,
                     })
-                    .inner
-            });
+                }
+            };
+
+            pull_paths(
+                nested,
+                &input.tuples(),
+                &pull_attributes,
+                &self.aliased_attributes,
+                &self.reverse_attributes,
+                &self.path_attributes,
+                &self.nested,
+                bound.as_ref(),
+                self.collect_values,
+                context,
+                &mut shutdown_handle,
+                &mut streams,
+            )?;
 
             let tuples = nested.concatenate(streams).as_collection();
 
@@ -171,11 +353,212 @@ impl<P: Implementable> Implementable for PullLevel<P> {
                 tuples,
             };
 
-            (relation, shutdown_handle)
+            Ok((relation, shutdown_handle))
         }
     }
 }
 
+/// Collapses each group of pull tuples sharing everything but their
+/// value into a single tuple, holding all of the group's values as a
+/// sorted list.
+fn collect_values<'b, T, S>(
+    tuples: &Collection<Iterative<'b, S, u64>, Vec<Value>, isize>,
+) -> Collection<Iterative<'b, S, u64>, Vec<Value>, isize>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    S: Scope<Timestamp = T>,
+{
+    tuples
+        .map(|mut tuple| {
+            let v = tuple.pop().unwrap();
+            (tuple, v)
+        })
+        .reduce(|_key, input, output| {
+            // Values arrive in sorted order.
+            let mut values = Vec::new();
+            for (v, diff) in input.iter() {
+                for _ in 0..*diff {
+                    values.push((*v).clone());
+                }
+            }
+
+            output.push((Value::List(values), 1));
+        })
+        .map(|(mut key, v)| {
+            key.push(v);
+            key
+        })
+}
+
+/// Pulls the specified attributes (forward and reverse) for all
+/// entities at the end of the given paths, as well as all nested
+/// pulls on referenced entities, collecting the resulting tuple
+/// streams.
+#[allow(clippy::too_many_arguments)]
+fn pull_paths<'b, T, I, S>(
+    nested: &mut Iterative<'b, S, u64>,
+    paths: &Collection<Iterative<'b, S, u64>, Vec<Value>, isize>,
+    pull_attributes: &[Aid],
+    aliased_attributes: &[(Aid, Aid)],
+    reverse_attributes: &[Aid],
+    path_attributes: &[Aid],
+    children: &[NestedPull],
+    bound: Option<&Bound<Iterative<'b, S, u64>>>,
+    collect: bool,
+    context: &mut I,
+    shutdown_handle: &mut ShutdownHandle,
+    streams: &mut Vec<Stream<Iterative<'b, S, u64>, (Vec<Value>, Product<T, u64>, isize)>>,
+) -> Result<(), Error>
+where
+    T: Timestamp + Lattice + TotalOrder,
+    I: ImplContext<T>,
+    S: Scope<Timestamp = T>,
+{
+    // Arrange input entities by eid.
+    let e_path: Arranged<
+        Iterative<S, u64>,
+        Value,
+        Vec<Value>,
+        isize,
+        TraceAgent<
+            Value,
+            Vec<Value>,
+            Product<T, u64>,
+            isize,
+            OrdValSpine<Value, Vec<Value>, Product<T, u64>, isize>,
+        >,
+    > = paths.map(|t| (t.last().unwrap().clone(), t)).arrange();
+
+    let forward = pull_attributes.iter().map(|a| (a, a.clone(), false));
+    let aliased = aliased_attributes
+        .iter()
+        .map(|(a, alias)| (a, alias.clone(), false));
+    let reverse = reverse_attributes
+        .iter()
+        .map(|a| (a, reverse_name(a), true));
+
+    for (a, name, is_reverse) in forward.chain(aliased).chain(reverse) {
+        // Reverse indices map values to the eids referring to them.
+        let index = if is_reverse {
+            context.reverse_index(a, &nested.parent)
+        } else {
+            context.forward_index(a)
+        };
+
+        let e_v = match index {
+            None => {
+                return Err(Error {
+                    category: ErrorKind::NotFound,
+                    message: format!("Attribute {} does not exist.", a),
+                });
+            }
+            Some(index) => {
+                let frontier: Vec<T> = index.propose_trace.advance_frontier().to_vec();
+                let (arranged, shutdown_propose) =
+                    index.propose_trace.import_core(&nested.parent, a);
+
+                let e_v = arranged.enter_at(nested, move |_, _, time| {
+                    let mut forwarded = time.clone();
+                    forwarded.advance_by(&frontier);
+                    Product::new(forwarded, 0)
+                });
+
+                shutdown_handle.add_button(shutdown_propose);
+
+                e_v
+            }
+        };
+
+        let attribute = Value::Aid(name);
+        let path_attributes: Vec<Aid> = path_attributes.to_vec();
+
+        let tuples = e_path.join_core(&e_v, move |_e, path: &Vec<Value>, v: &Value| {
+            // Each result tuple must hold the interleaved
+            // path, the attribute, and the value,
+            // i.e. [?p "parent/child" ?c ?a ?v]
+            let mut result = interleave(path, &path_attributes);
+            result.push(attribute.clone());
+            result.push(v.clone());
+
+            Some(result)
+        });
+
+        let tuples = match bound {
+            None => tuples,
+            Some(bound) => bound_values(&tuples, bound),
+        };
+
+        let tuples = if collect {
+            collect_values(&tuples)
+        } else {
+            tuples
+        };
+
+        streams.push(tuples.inner);
+    }
+
+    for child in children.iter() {
+        let e_ref = match context.forward_index(&child.ref_attribute) {
+            None => {
+                return Err(Error {
+                    category: ErrorKind::NotFound,
+                    message: format!("Attribute {} does not exist.", child.ref_attribute),
+                });
+            }
+            Some(index) => {
+                let frontier: Vec<T> = index.propose_trace.advance_frontier().to_vec();
+                let (arranged, shutdown_propose) = index
+                    .propose_trace
+                    .import_core(&nested.parent, &child.ref_attribute);
+
+                let e_ref = arranged.enter_at(nested, move |_, _, time| {
+                    let mut forwarded = time.clone();
+                    forwarded.advance_by(&frontier);
+                    Product::new(forwarded, 0)
+                });
+
+                shutdown_handle.add_button(shutdown_propose);
+
+                e_ref
+            }
+        };
+
+        // Extend each path by the referenced entity, which will
+        // be the one to pull from on the next level.
+        let child_paths = e_path.join_core(&e_ref, |_e, path: &Vec<Value>, v: &Value| {
+            let mut child_path = path.clone();
+            child_path.push(v.clone());
+
+            Some(child_path)
+        });
+
+        let mut child_path_attributes = path_attributes.to_vec();
+        child_path_attributes.push(
+            child
+                .alias
+                .clone()
+                .unwrap_or_else(|| child.ref_attribute.clone()),
+        );
+
+        pull_paths(
+            nested,
+            &child_paths,
+            &child.pull_attributes,
+            &child.aliased_attributes,
+            &child.reverse_attributes,
+            &child_path_attributes,
+            &child.nested,
+            None,
+            false,
+            context,
+            shutdown_handle,
+            streams,
+        )?;
+    }
+
+    Ok(())
+}
+
 impl<P: Implementable> Implementable for Pull<P> {
     fn dependencies(&self) -> Dependencies {
         Dependencies::none()
@@ -186,7 +569,7 @@ impl<P: Implementable> Implementable for Pull<P> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
@@ -195,13 +578,14 @@ impl<P: Implementable> Implementable for Pull<P> {
         let mut scope = nested.clone();
         let mut shutdown_handle = ShutdownHandle::empty();
 
-        let streams = self.paths.iter().map(|path| {
-            let (relation, shutdown) = path.implement(&mut scope, local_arrangements, context);
+        let mut streams = Vec::with_capacity(self.paths.len());
+        for path in self.paths.iter() {
+            let (relation, shutdown) = path.implement(&mut scope, local_arrangements, context)?;
 
             shutdown_handle.merge_with(shutdown);
 
-            relation.tuples().inner
-        });
+            streams.push(relation.tuples().inner);
+        }
 
         let tuples = nested.concatenate(streams).as_collection();
 
@@ -210,6 +594,474 @@ impl<P: Implementable> Implementable for Pull<P> {
             tuples,
         };
 
-        (relation, shutdown_handle)
+        Ok((relation, shutdown_handle))
+    }
+}
+
+/// A plan for GraphQL queries, e.g. `{ hero(name: "Batman") { height
+/// mass } }`. Each top-level field names an attribute leading to the
+/// entities of interest, optionally constrained by arguments. Nested
+/// selections are pulled along reference attributes. So the above
+/// would be represented as:
+///
+/// (?root :hero ?hero) <- [:height :mass] | [?hero :name "Batman"]
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct GraphQl {
+    /// String representation of the GraphQL query.
+    pub query: String,
+}
+
+impl GraphQl {
+    /// Parses the query and splits it into individual pull paths.
+    pub fn paths(&self) -> Result<Vec<PullLevel<Plan>>, Error> {
+        match parse_query(&self.query) {
+            Err(error) => Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!("Invalid GraphQL query: {}", error),
+            }),
+            Ok(ast) => ast_to_paths(&ast),
+        }
+    }
+}
+
+/// Fragment definitions by name.
+type Fragments<'a> = HashMap<&'a str, &'a FragmentDefinition>;
+
+fn ast_to_paths(ast: &Document) -> Result<Vec<PullLevel<Plan>>, Error> {
+    // Fragments may be defined anywhere in the document, so we
+    // collect them before walking any selections.
+    let mut fragments = HashMap::new();
+    for definition in ast.definitions.iter() {
+        if let Definition::Fragment(fragment) = definition {
+            fragments.insert(fragment.name.as_str(), fragment);
+        }
+    }
+
+    let mut paths = Vec::new();
+    for definition in ast.definitions.iter() {
+        match definition {
+            Definition::Operation(OperationDefinition::SelectionSet(selection_set)) => {
+                paths.extend(selection_set_to_paths(selection_set, &fragments)?);
+            }
+            Definition::Operation(OperationDefinition::Query(query)) => {
+                paths.extend(selection_set_to_paths(&query.selection_set, &fragments)?);
+            }
+            Definition::Operation(_) => {
+                return Err(Error {
+                    category: ErrorKind::Unsupported,
+                    message: "Only GraphQL queries are supported.".to_string(),
+                });
+            }
+            Definition::Fragment(_) => {}
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Calls `logic` on every field of a selection set, expanding
+/// fragment spreads and inline fragments along the way. `visiting`
+/// holds the fragments currently being expanded, in order to detect
+/// cycles, and is passed on to `logic` for nested selections.
+fn for_each_field<'a, F>(
+    selection_set: &'a SelectionSet,
+    fragments: &Fragments<'a>,
+    visiting: &mut Vec<&'a str>,
+    logic: &mut F,
+) -> Result<(), Error>
+where
+    F: FnMut(&'a Field, &mut Vec<&'a str>) -> Result<(), Error>,
+{
+    for item in selection_set.items.iter() {
+        match item {
+            Selection::Field(field) => logic(field, visiting)?,
+            Selection::InlineFragment(fragment) => {
+                for_each_field(&fragment.selection_set, fragments, visiting, logic)?;
+            }
+            Selection::FragmentSpread(spread) => {
+                let name = spread.fragment_name.as_str();
+
+                if visiting.contains(&name) {
+                    return Err(Error {
+                        category: ErrorKind::Incorrect,
+                        message: format!("Fragment {} is cyclic.", name),
+                    });
+                }
+
+                match fragments.get(name) {
+                    None => {
+                        return Err(Error {
+                            category: ErrorKind::NotFound,
+                            message: format!("Fragment {} is not defined.", name),
+                        });
+                    }
+                    Some(&fragment) => {
+                        visiting.push(name);
+                        for_each_field(&fragment.selection_set, fragments, visiting, logic)?;
+                        visiting.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Translates each top-level field into a pull level on the entities
+/// it refers to. Field arguments constrain those entities to the
+/// ones holding the specified attribute values.
+fn selection_set_to_paths<'a>(
+    selection_set: &'a SelectionSet,
+    fragments: &Fragments<'a>,
+) -> Result<Vec<PullLevel<Plan>>, Error> {
+    let (root, e) = (0, 1);
+
+    let mut paths = Vec::with_capacity(selection_set.items.len());
+    for_each_field(
+        selection_set,
+        fragments,
+        &mut Vec::new(),
+        &mut |field, visiting| {
+            if field.selection_set.items.is_empty() {
+                return Err(Error {
+                    category: ErrorKind::Incorrect,
+                    message: format!("Top-level field {} requires a selection.", field.name),
+                });
+            }
+
+            let mut plan = Plan::MatchA(root, Aid::from(field.name.as_str()), e);
+            for (name, argument) in field.arguments.iter() {
+                plan = Plan::Project(Project {
+                    variables: vec![root, e],
+                    plan: Box::new(Plan::Join(Join {
+                        variables: vec![e],
+                        left_plan: Box::new(plan),
+                        right_plan: Box::new(Plan::MatchAV(
+                            e,
+                            Aid::from(name.as_str()),
+                            argument_to_value(name, argument)?,
+                        )),
+                    })),
+                });
+            }
+
+            let (pull_attributes, aliased_attributes, nested) =
+                selection_set_to_pulls(&field.selection_set, fragments, visiting)?;
+
+            let (order_by, order_descending) = field_to_order(field)?;
+
+            paths.push(PullLevel {
+                variables: vec![],
+                plan: Box::new(plan),
+                pull_attributes,
+                aliased_attributes,
+                wildcard: false,
+                limit: None,
+                sort_attribute: None,
+                sort_descending: false,
+                order_by,
+                order_descending,
+                collect_values: false,
+                path_attributes: vec![Aid::from(
+                    field.alias.as_ref().unwrap_or(&field.name).as_str(),
+                )],
+                reverse_attributes: vec![],
+                nested,
+            });
+
+            Ok(())
+        },
+    )?;
+
+    Ok(paths)
+}
+
+/// Reads the order of a top-level field's entities from an
+/// `@orderBy(attribute: "name", descending: true)` directive, if
+/// present. Other directives are ignored.
+fn field_to_order(field: &Field) -> Result<(Option<Aid>, bool), Error> {
+    use graphql_parser::query::Value as GqlValue;
+
+    let mut order_by = None;
+    let mut descending = false;
+
+    for directive in field.directives.iter().filter(|d| d.name == "orderBy") {
+        for (name, argument) in directive.arguments.iter() {
+            match (name.as_str(), argument) {
+                ("attribute", GqlValue::String(aid)) | ("attribute", GqlValue::Enum(aid)) => {
+                    order_by = Some(Aid::from(aid.as_str()));
+                }
+                ("descending", GqlValue::Boolean(boolean)) => descending = *boolean,
+                _ => {
+                    return Err(Error {
+                        category: ErrorKind::Incorrect,
+                        message: format!(
+                            "Invalid argument {} to @orderBy on field {}.",
+                            name, field.name
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok((order_by, descending))
+}
+
+/// Splits a nested selection set into the attributes to pull
+/// directly (with and without aliases), and the reference attributes
+/// to follow further.
+fn selection_set_to_pulls<'a>(
+    selection_set: &'a SelectionSet,
+    fragments: &Fragments<'a>,
+    visiting: &mut Vec<&'a str>,
+) -> Result<(Vec<Aid>, Vec<(Aid, Aid)>, Vec<NestedPull>), Error> {
+    let mut pull_attributes = Vec::new();
+    let mut aliased_attributes = Vec::new();
+    let mut nested = Vec::new();
+
+    for_each_field(
+        selection_set,
+        fragments,
+        visiting,
+        &mut |field, visiting| {
+            if !field.arguments.is_empty() {
+                return Err(Error {
+                    category: ErrorKind::Unsupported,
+                    message: format!(
+                        "Arguments on nested field {} are not supported.",
+                        field.name
+                    ),
+                });
+            }
+
+            if field.selection_set.items.is_empty() {
+                match field.alias {
+                    None => pull_attributes.push(Aid::from(field.name.as_str())),
+                    Some(ref alias) => aliased_attributes
+                        .push((Aid::from(field.name.as_str()), Aid::from(alias.as_str()))),
+                }
+            } else {
+                let (child_attributes, child_aliased, child_nested) =
+                    selection_set_to_pulls(&field.selection_set, fragments, visiting)?;
+
+                nested.push(NestedPull {
+                    ref_attribute: Aid::from(field.name.as_str()),
+                    alias: field.alias.as_ref().map(|alias| Aid::from(alias.as_str())),
+                    pull_attributes: child_attributes,
+                    aliased_attributes: child_aliased,
+                    reverse_attributes: vec![],
+                    nested: child_nested,
+                });
+            }
+
+            Ok(())
+        },
+    )?;
+
+    Ok((pull_attributes, aliased_attributes, nested))
+}
+
+/// Converts a GraphQL argument into the value it constrains its
+/// attribute to.
+fn argument_to_value(name: &str, argument: &graphql_parser::query::Value) -> Result<Value, Error> {
+    use graphql_parser::query::Value as GqlValue;
+
+    match argument {
+        GqlValue::String(string) => Ok(Value::String(string.clone())),
+        GqlValue::Boolean(boolean) => Ok(Value::Bool(*boolean)),
+        GqlValue::Enum(aid) => Ok(Value::Aid(Aid::from(aid.as_str()))),
+        GqlValue::Int(number) => match number.as_i64() {
+            None => Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!("Argument {} is out of range.", name),
+            }),
+            Some(number) => Ok(Value::Number(number)),
+        },
+        _ => Err(Error {
+            category: ErrorKind::Unsupported,
+            message: format!("Argument {} is of an unsupported type.", name),
+        }),
+    }
+}
+
+impl Implementable for GraphQl {
+    fn dependencies(&self) -> Dependencies {
+        Dependencies::none()
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let pull = Pull {
+            variables: vec![],
+            paths: self.paths()?,
+        };
+
+        pull.implement(nested, local_arrangements, context)
+    }
+}
+
+/// Converts flat pull paths, e.g. `[1 :hero 100 :name "Batman"]`,
+/// into a nested JSON object keyed by attributes and eids. Only
+/// additions are reflected.
+pub fn paths_to_nested<T>(paths: &[ResultDiff<T>]) -> serde_json::Value {
+    use serde_json::map::Map;
+
+    let mut acc = Map::new();
+    for (path, _time, diff) in paths.iter() {
+        if *diff <= 0 || path.len() < 2 {
+            continue;
+        }
+
+        let (value, keys) = path.split_last().unwrap();
+        let (attribute, keys) = keys.split_last().unwrap();
+
+        let mut current = &mut acc;
+        for key in keys.iter() {
+            current = current
+                .entry(value_to_key(key))
+                .or_insert_with(|| serde_json::Value::Object(Map::new()))
+                .as_object_mut()
+                .expect("path conflicts with a pulled value");
+        }
+
+        current.insert(value_to_key(attribute), value_to_json(value));
+    }
+
+    serde_json::Value::Object(acc)
+}
+
+/// Converts the results of a GraphQL query into a JSON object with
+/// one key per (aliased) root field. The entities holding the root
+/// fields are dropped, s.t. root fields held by different entities
+/// end up side by side, rather than in separate objects.
+pub fn graphql_to_json<T: Clone>(paths: &[ResultDiff<T>]) -> serde_json::Value {
+    let rooted: Vec<ResultDiff<T>> = paths
+        .iter()
+        .filter(|(path, _time, _diff)| !path.is_empty())
+        .map(|(path, time, diff)| (path[1..].to_vec(), time.clone(), *diff))
+        .collect();
+
+    squash_nested(paths_to_nested(&rooted))
+}
+
+/// Recursively replaces objects keyed by eids, as produced by
+/// `paths_to_nested`, with arrays of their values.
+pub fn squash_nested(nested: serde_json::Value) -> serde_json::Value {
+    match nested {
+        serde_json::Value::Object(map) => {
+            let keyed_by_eids = !map.is_empty() && map.keys().all(|key| key.parse::<Eid>().is_ok());
+
+            if keyed_by_eids {
+                serde_json::Value::Array(
+                    map.into_iter().map(|(_eid, v)| squash_nested(v)).collect(),
+                )
+            } else {
+                serde_json::Value::Object(
+                    map.into_iter()
+                        .map(|(k, v)| (k, squash_nested(v)))
+                        .collect(),
+                )
+            }
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(squash_nested).collect())
+        }
+        other => other,
+    }
+}
+
+/// Orders sibling entities in squashed results, as produced by
+/// `squash_nested`, according to the `order_by` attributes of the
+/// pull levels they were pulled by. The dataflow itself is unordered,
+/// so this has to happen on serialization. Entities lacking the
+/// attribute come last.
+pub fn order_nested<P: Implementable>(
+    mut nested: serde_json::Value,
+    levels: &[PullLevel<P>],
+) -> serde_json::Value {
+    for level in levels.iter() {
+        if let Some(ref attribute) = level.order_by {
+            order_at(
+                &mut nested,
+                &level.path_attributes,
+                attribute,
+                level.order_descending,
+            );
+        }
+    }
+
+    nested
+}
+
+/// Follows `path` through `nested`, descending into every element of
+/// the arrays along the way, and sorts the arrays found at its end.
+fn order_at(nested: &mut serde_json::Value, path: &[Aid], attribute: &Aid, descending: bool) {
+    match nested {
+        serde_json::Value::Array(values) => {
+            if path.is_empty() {
+                values.sort_by(|x, y| {
+                    match (x.get(attribute.as_str()), y.get(attribute.as_str())) {
+                        (None, None) => Ordering::Equal,
+                        (None, Some(_)) => Ordering::Greater,
+                        (Some(_), None) => Ordering::Less,
+                        (Some(x), Some(y)) if descending => compare_json(y, x),
+                        (Some(x), Some(y)) => compare_json(x, y),
+                    }
+                });
+            } else {
+                for value in values.iter_mut() {
+                    order_at(value, path, attribute, descending);
+                }
+            }
+        }
+        serde_json::Value::Object(map) => {
+            if let Some((key, path)) = path.split_first() {
+                if let Some(value) = map.get_mut(key.as_str()) {
+                    order_at(value, path, attribute, descending);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Orders numbers numerically and everything else by its
+/// serialization, which for strings is their lexicographic order.
+fn compare_json(x: &serde_json::Value, y: &serde_json::Value) -> Ordering {
+    match (x.as_f64(), y.as_f64()) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => x.to_string().cmp(&y.to_string()),
+    }
+}
+
+fn value_to_key(value: &Value) -> String {
+    match value {
+        Value::Aid(aid) => aid.to_string(),
+        Value::Eid(eid) => eid.to_string(),
+        other => value_to_json(other).to_string(),
+    }
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Aid(aid) => serde_json::Value::String(aid.to_string()),
+        Value::String(string) => serde_json::Value::String(string.clone()),
+        Value::Bool(boolean) => serde_json::Value::Bool(*boolean),
+        Value::Number(number) => serde_json::Value::from(*number),
+        Value::Eid(eid) => serde_json::Value::from(*eid),
+        Value::Instant(instant) => serde_json::Value::from(*instant),
+        Value::List(values) => serde_json::Value::Array(values.iter().map(value_to_json).collect()),
+        other => serde_json::to_value(other).expect("failed to serialize value"),
     }
 }
@@ -0,0 +1,119 @@
+//! Variable renaming expression plan.
+
+use std::collections::BTreeMap;
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::TotalOrder;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::binding::Binding;
+use crate::plan::{next_id, Dependencies, ImplContext, Implementable};
+use crate::{Aid, Eid, Value, Var};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, VariableMap};
+
+/// A plan stage relabeling the variables bound by its source, leaving
+/// the tuples themselves untouched. Variables not mentioned in the
+/// mapping keep their identifiers. Frontends are responsible for
+/// ensuring that the renamed variables don't collide with each other.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Rename<P: Implementable> {
+    /// Mapping from variables of the source to their new identifiers.
+    pub mapping: BTreeMap<Var, Var>,
+    /// Plan for the data source.
+    pub plan: Box<P>,
+}
+
+impl<P: Implementable> Rename<P> {
+    fn rename(&self, variable: Var) -> Var {
+        *self.mapping.get(&variable).unwrap_or(&variable)
+    }
+
+    fn rename_binding(&self, binding: Binding) -> Binding {
+        match binding {
+            Binding::Attribute(mut binding) => {
+                let (e, v) = binding.variables;
+                binding.variables = (self.rename(e), self.rename(v));
+                Binding::Attribute(binding)
+            }
+            Binding::Not(mut binding) => {
+                binding.binding = Box::new(self.rename_binding(*binding.binding));
+                Binding::Not(binding)
+            }
+            Binding::Constant(mut binding) => {
+                binding.variable = self.rename(binding.variable);
+                Binding::Constant(binding)
+            }
+            Binding::BinaryPredicate(mut binding) => {
+                let (x, y) = binding.variables;
+                binding.variables = (self.rename(x), self.rename(y));
+                Binding::BinaryPredicate(binding)
+            }
+            Binding::Relation(mut binding) => {
+                binding.variables = binding
+                    .variables
+                    .iter()
+                    .map(|variable| self.rename(*variable))
+                    .collect();
+                Binding::Relation(binding)
+            }
+        }
+    }
+}
+
+impl<P: Implementable> Implementable for Rename<P> {
+    fn dependencies(&self) -> Dependencies {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Vec<Binding> {
+        self.plan
+            .into_bindings()
+            .into_iter()
+            .map(|binding| self.rename_binding(binding))
+            .collect()
+    }
+
+    fn datafy(&self) -> Vec<(Eid, Aid, Value)> {
+        let eid = next_id();
+        let mut data = self.plan.datafy();
+
+        if data.is_empty() {
+            Vec::new()
+        } else {
+            let child_eid = data[0].0;
+
+            data.push((eid, Aid::from("df.rename/binding"), Value::Eid(child_eid)));
+
+            data
+        }
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let (relation, shutdown_handle) =
+            self.plan.implement(nested, local_arrangements, context)?;
+
+        let renamed = CollectionRelation {
+            variables: relation
+                .variables()
+                .into_iter()
+                .map(|variable| self.rename(variable))
+                .collect(),
+            tuples: relation.tuples(),
+        };
+
+        Ok((renamed, shutdown_handle))
+    }
+}
@@ -0,0 +1,102 @@
+//! Sampling expression plan.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::TotalOrder;
+use timely::progress::Timestamp;
+
+use timely_sort::Unsigned;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+use differential_dataflow::Hashable;
+
+use crate::binding::Binding;
+use crate::plan::{Dependencies, ImplContext, Implementable};
+use crate::{CollectionRelation, Error, ErrorKind, Relation, ShutdownHandle, Value, VariableMap};
+
+/// Number of groups the sample is first drawn from in parallel,
+/// before the final sample is drawn from their union.
+const BUCKETS: u64 = 64;
+
+/// A plan stage keeping a bounded sample of the distinct tuples
+/// of its source, for previewing large relations.
+///
+/// Each tuple is ranked by its hash and the `size` lowest ranked
+/// tuples make up the sample. As long as hashes look random, every
+/// tuple is equally likely to be sampled. The sample is maintained
+/// exactly under updates: a retracted tuple is replaced by the next
+/// lowest ranked one, and new tuples only enter if they outrank a
+/// sampled one. Being hash-based, the sample is the same on every
+/// run, rather than a fresh draw, and samples of related relations
+/// are correlated.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Sample<P: Implementable> {
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Maximum number of tuples in the sample.
+    pub size: usize,
+}
+
+impl<P: Implementable> Implementable for Sample<P> {
+    fn dependencies(&self) -> Dependencies {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Vec<Binding> {
+        self.plan.into_bindings()
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        if self.size == 0 {
+            return Err(Error {
+                category: ErrorKind::Incorrect,
+                message: "Samples must have a positive size.".to_string(),
+            });
+        }
+
+        let (relation, shutdown_handle) =
+            self.plan.implement(nested, local_arrangements, context)?;
+
+        let size = self.size;
+
+        // Values arrive sorted, i.e. by rank, so the sample is the
+        // first `size` of them.
+        let keep_lowest =
+            move |_key: &u64,
+                  input: &[(&(u64, Vec<Value>), isize)],
+                  output: &mut Vec<((u64, Vec<Value>), isize)>| {
+                for (ranked, _count) in input.iter().filter(|(_x, count)| *count > 0).take(size) {
+                    output.push(((*ranked).clone(), 1));
+                }
+            };
+
+        let tuples = relation
+            .tuples()
+            .map(|tuple| {
+                let rank = tuple.hashed().as_u64();
+                (rank % BUCKETS, (rank, tuple))
+            })
+            .reduce(keep_lowest)
+            .map(|(_bucket, ranked)| (0, ranked))
+            .reduce(keep_lowest)
+            .map(|(_key, (_rank, tuple))| tuple);
+
+        let sampled = CollectionRelation {
+            variables: relation.variables(),
+            tuples,
+        };
+
+        Ok((sampled, shutdown_handle))
+    }
+}
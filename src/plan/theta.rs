@@ -0,0 +1,154 @@
+//! Inequality join expression plan.
+
+use std::cmp::Ordering;
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::TotalOrder;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::JoinCore;
+
+use crate::binding::{AsBinding, BinaryPredicate as Predicate, Binding};
+use crate::plan::filter::{comparable, compare};
+use crate::plan::{Dependencies, ImplContext, Implementable};
+use crate::{
+    CollectionRelation, Error, ErrorKind, Relation, ShutdownHandle, Value, Var, VariableMap,
+};
+
+/// A plan stage joining two source relations on the specified
+/// variables, keeping only those pairs of tuples for which the
+/// predicate holds between a variable of the left relation and a
+/// variable of the right relation. Throws if any of the join variables
+/// isn't bound by both sources. Values are compared as by `Filter`,
+/// pairs binding incomparable values are dropped.
+///
+/// Without any join variables, every pair of tuples has to be
+/// compared. All tuples are then routed to a single worker, which
+/// should be avoided for all but small relations.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct ThetaJoin<P1: Implementable, P2: Implementable> {
+    /// Variables to join on by equality, possibly none.
+    pub variables: Vec<Var>,
+    /// Variables of the left and right relation, in the order they
+    /// are passed to the predicate.
+    pub predicate_variables: (Var, Var),
+    /// Logical predicate to apply.
+    pub predicate: Predicate,
+    /// Plan for the left input.
+    pub left_plan: Box<P1>,
+    /// Plan for the right input.
+    pub right_plan: Box<P2>,
+}
+
+impl<P1: Implementable, P2: Implementable> Implementable for ThetaJoin<P1, P2> {
+    fn dependencies(&self) -> Dependencies {
+        Dependencies::merge(
+            self.left_plan.dependencies(),
+            self.right_plan.dependencies(),
+        )
+    }
+
+    fn into_bindings(&self) -> Vec<Binding> {
+        let mut bindings = self.left_plan.into_bindings();
+        bindings.append(&mut self.right_plan.into_bindings());
+        bindings.push(Binding::binary_predicate(
+            self.predicate.clone(),
+            self.predicate_variables.0,
+            self.predicate_variables.1,
+        ));
+
+        bindings
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let (left, shutdown_left) =
+            self.left_plan
+                .implement(nested, local_arrangements, context)?;
+        let (right, shutdown_right) =
+            self.right_plan
+                .implement(nested, local_arrangements, context)?;
+
+        let (x, y) = self.predicate_variables;
+        if left.binds(x).is_none() || right.binds(y).is_none() {
+            return Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!(
+                    "Predicate variables {} and {} must be bound by the left and right input respectively.",
+                    x, y
+                ),
+            });
+        }
+
+        if self.variables.is_empty() {
+            warn!(
+                "Inequality join without join variables, all tuples will be compared on a single worker."
+            );
+        }
+
+        let variables: Vec<Var> = self
+            .variables
+            .iter()
+            .cloned()
+            .chain(
+                left.variables()
+                    .drain(..)
+                    .filter(|x| !self.variables.contains(x)),
+            )
+            .chain(
+                right
+                    .variables()
+                    .drain(..)
+                    .filter(|x| !self.variables.contains(x)),
+            )
+            .collect();
+
+        // Left variables come first, s.t. the first match is the one
+        // bound by the left input, even if both inputs bind it.
+        let left_offset = variables.binds(x).unwrap();
+        let right_offset = variables.iter().rposition(|v| *v == y).unwrap();
+
+        let predicate: fn(&Value, &Value) -> bool = match self.predicate {
+            Predicate::LT => |a, b| compare(a, b) == Ordering::Less,
+            Predicate::LTE => |a, b| compare(a, b) != Ordering::Greater,
+            Predicate::GT => |a, b| compare(a, b) == Ordering::Greater,
+            Predicate::GTE => |a, b| compare(a, b) != Ordering::Less,
+            Predicate::EQ => |a, b| compare(a, b) == Ordering::Equal,
+            Predicate::NEQ => |a, b| compare(a, b) != Ordering::Equal,
+        };
+
+        let tuples = left.arrange_by_variables(&self.variables).join_core(
+            &right.arrange_by_variables(&self.variables),
+            move |key, v1, v2| {
+                let tuple: Vec<Value> = key
+                    .iter()
+                    .cloned()
+                    .chain(v1.iter().cloned())
+                    .chain(v2.iter().cloned())
+                    .collect();
+
+                let (a, b) = (&tuple[left_offset], &tuple[right_offset]);
+                if comparable(a, b) && predicate(a, b) {
+                    Some(tuple)
+                } else {
+                    None
+                }
+            },
+        );
+
+        let shutdown_handle = ShutdownHandle::merge(shutdown_left, shutdown_right);
+
+        Ok((CollectionRelation { variables, tuples }, shutdown_handle))
+    }
+}
@@ -9,7 +9,7 @@ use differential_dataflow::lattice::Lattice;
 
 use crate::binding::{AsBinding, Binding};
 use crate::plan::{Dependencies, ImplContext, Implementable};
-use crate::{CollectionRelation, Relation, ShutdownHandle, Value, Var, VariableMap};
+use crate::{CollectionRelation, Error, Relation, ShutdownHandle, Value, Var, VariableMap};
 
 /// Permitted functions.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -54,13 +54,14 @@ impl<P: Implementable> Implementable for Transform<P> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
         S: Scope<Timestamp = T>,
     {
-        let (relation, shutdown_handle) = self.plan.implement(nested, local_arrangements, context);
+        let (relation, shutdown_handle) =
+            self.plan.implement(nested, local_arrangements, context)?;
 
         let key_offsets: Vec<usize> = self
             .variables
@@ -183,6 +184,6 @@ impl<P: Implementable> Implementable for Transform<P> {
             },
         };
 
-        (transformed, shutdown_handle)
+        Ok((transformed, shutdown_handle))
     }
 }
@@ -10,17 +10,25 @@ use differential_dataflow::operators::Threshold;
 
 use crate::binding::{AsBinding, Binding};
 use crate::plan::{Dependencies, ImplContext, Implementable};
-use crate::{CollectionRelation, Relation, ShutdownHandle, Var, VariableMap};
-
-/// A plan stage taking the union over its sources. Frontends are
-/// responsible to ensure that the sources are union-compatible
-/// (i.e. bind all of the same variables in the same order).
+use crate::{CollectionRelation, Error, ErrorKind, Relation, ShutdownHandle, Var, VariableMap};
+
+/// A plan stage taking the union over its sources. Every source must
+/// bind all of the union's variables, in any order. Variables bound
+/// beyond those are projected away before the sources are combined.
+///
+/// By default, duplicate tuples are removed (`UNION DISTINCT`), with
+/// or without the set-semantics feature. With `all` set, tuples are
+/// kept with the multiplicities they have across all sources
+/// (`UNION ALL`).
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Union<P: Implementable> {
-    /// TODO
+    /// Variables bound by the union, in order.
     pub variables: Vec<Var>,
     /// Plan for the data source.
     pub plans: Vec<P>,
+    /// Keep duplicate tuples?
+    #[serde(default)]
+    pub all: bool,
 }
 
 impl<P: Implementable> Implementable for Union<P> {
@@ -46,7 +54,7 @@ impl<P: Implementable> Implementable for Union<P> {
         nested: &mut Iterative<'b, S, u64>,
         local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
         context: &mut I,
-    ) -> (CollectionRelation<'b, S>, ShutdownHandle)
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
     where
         T: Timestamp + Lattice + TotalOrder,
         I: ImplContext<T>,
@@ -58,21 +66,36 @@ impl<P: Implementable> Implementable for Union<P> {
         let mut scope = nested.clone();
         let mut shutdown_handle = ShutdownHandle::empty();
 
-        let streams = self.plans.iter().map(|plan| {
-            let (relation, shutdown) = plan.implement(&mut scope, local_arrangements, context);
+        let mut streams = Vec::with_capacity(self.plans.len());
+        for plan in self.plans.iter() {
+            let (relation, shutdown) = plan.implement(&mut scope, local_arrangements, context)?;
 
             shutdown_handle.merge_with(shutdown);
 
-            relation.projected(&self.variables).inner
-        });
+            if let Some(variable) = self
+                .variables
+                .iter()
+                .find(|x| relation.binds(**x).is_none())
+            {
+                return Err(Error {
+                    category: ErrorKind::Incorrect,
+                    message: format!(
+                        "Union requires variable {}, which is not bound by all of its inputs.",
+                        variable
+                    ),
+                });
+            }
+
+            streams.push(relation.projected(&self.variables).inner);
+        }
 
         let concat = nested.concatenate(streams).as_collection();
 
         let concatenated = CollectionRelation {
             variables: self.variables.to_vec(),
-            tuples: concat.distinct(),
+            tuples: if self.all { concat } else { concat.distinct() },
         };
 
-        (concatenated, shutdown_handle)
+        Ok((concatenated, shutdown_handle))
     }
 }
@@ -0,0 +1,109 @@
+//! Valid time expression plan.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::{Product, TotalOrder};
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::Reduce;
+
+use crate::binding::Binding;
+use crate::plan::{Dependencies, ImplContext, Implementable};
+use crate::{Aid, CollectionRelation, Error, ErrorKind, ShutdownHandle, Value, Var, VariableMap};
+
+/// A plan stage reading a bitemporal attribute (see
+/// `AttributeConfig::valid_time`), binding each entity, value, and
+/// the instant from which the value is valid in the world. Valid
+/// times are bound as `Value::Instant`s and can be constrained by
+/// any of the usual predicates. System time remains the time of the
+/// attribute's domain, so corrections to the past are retracted and
+/// asserted as usual.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct ValidTime {
+    /// Variables bound to the entity, the value, and its valid time.
+    pub variables: (Var, Var, Var),
+    /// Bitemporal attribute to read.
+    pub attribute: Aid,
+    /// If set, only the values in effect at this instant are bound,
+    /// i.e. per entity those with the latest valid time not after
+    /// it. Otherwise, all versions are bound.
+    pub valid_at: Option<u64>,
+}
+
+impl Implementable for ValidTime {
+    fn dependencies(&self) -> Dependencies {
+        Dependencies::attribute(&self.attribute)
+    }
+
+    fn into_bindings(&self) -> Vec<Binding> {
+        panic!("Valid times can't be bound via Hector, implement the plan as it is instead.");
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        _local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let index = match context.forward_index(&self.attribute) {
+            None => {
+                return Err(Error {
+                    category: ErrorKind::NotFound,
+                    message: format!("Attribute {} does not exist.", self.attribute),
+                });
+            }
+            Some(index) => index,
+        };
+
+        let frontier: Vec<T> = index.validate_trace.advance_frontier().to_vec();
+        let (validate, shutdown_validate) = index
+            .validate_trace
+            .import_core(&nested.parent, &self.attribute);
+
+        // Values are validated on input, anything not holding a
+        // valid time can't be bound.
+        let versions = validate
+            .enter_at(nested, move |_, _, time| {
+                let mut forwarded = time.clone();
+                forwarded.advance_by(&frontier);
+                Product::new(forwarded, 0)
+            })
+            .as_collection(|(e, v), _| (e.clone(), v.clone()))
+            .flat_map(|(e, v)| match v.as_valid() {
+                None => None,
+                Some((v, valid_from)) => Some((e, (valid_from, v.clone()))),
+            });
+
+        let versions = match self.valid_at {
+            None => versions,
+            Some(valid_at) => versions
+                .filter(move |(_e, (valid_from, _v))| *valid_from <= valid_at)
+                .reduce(|_e, input, output| {
+                    let latest = input
+                        .iter()
+                        .filter(|(_version, count)| *count > 0)
+                        .map(|((valid_from, _v), _count)| *valid_from)
+                        .max();
+
+                    for (version, count) in input.iter() {
+                        if *count > 0 && Some(version.0) == latest {
+                            output.push(((*version).clone(), 1));
+                        }
+                    }
+                }),
+        };
+
+        let relation = CollectionRelation {
+            variables: vec![self.variables.0, self.variables.1, self.variables.2],
+            tuples: versions.map(|(e, (valid_from, v))| vec![e, v, Value::Instant(valid_from)]),
+        };
+
+        Ok((relation, ShutdownHandle::from_button(shutdown_validate)))
+    }
+}
@@ -0,0 +1,211 @@
+//! Windowed aggregate expression plan.
+
+use timely::dataflow::scopes::child::Iterative;
+use timely::dataflow::Scope;
+use timely::order::TotalOrder;
+use timely::progress::Timestamp;
+
+use differential_dataflow::lattice::Lattice;
+
+use crate::binding::{AsBinding, Binding};
+use crate::plan::{Aggregate, AggregationFn, Dependencies, ImplContext, Implementable};
+use crate::{
+    CollectionRelation, Error, ErrorKind, Relation, ShutdownHandle, Value, Var, VariableMap,
+};
+
+/// A plan stage applying an aggregation function to the bindings
+/// of a variable, separately for each group and each window of time
+/// the group's tuples fall into. Windows are aligned to multiples of
+/// their slide and are identified by the `Value::Instant` they start
+/// at, which is bound to the window variable.
+///
+/// Without a slide, windows are tumbling, i.e. every tuple falls
+/// into exactly one of them. Sliding windows overlap whenever the
+/// slide is shorter than their size.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Window<P: Implementable> {
+    /// Variables bound by the output, i.e. the key variables, the
+    /// window variable, and the aggregation variable in some order.
+    pub variables: Vec<Var>,
+    /// Plan for the data source.
+    pub plan: Box<P>,
+    /// Variable bound to the `Value::Instant` each tuple occurred at.
+    pub time_variable: Var,
+    /// Variable to bind the start of each window to.
+    pub window_variable: Var,
+    /// Relation variables that determine the grouping.
+    pub key_variables: Vec<Var>,
+    /// Aggregation function to apply.
+    pub aggregation_fn: AggregationFn,
+    /// Variable to aggregate over.
+    pub aggregation_variable: Var,
+    /// Variables distinguishing tuples that share a value within a
+    /// window, e.g. the entity of each event, s.t. each of them is
+    /// aggregated rather than the distinct values only.
+    #[serde(default)]
+    pub with_variables: Vec<Var>,
+    /// Length of each window, in milliseconds.
+    pub size: u64,
+    /// Distance between the starts of subsequent windows, in
+    /// milliseconds. Defaults to the window size.
+    #[serde(default)]
+    pub slide: Option<u64>,
+}
+
+impl<P: Implementable> Window<P> {
+    /// Returns the distance between the starts of subsequent windows.
+    pub fn slide(&self) -> u64 {
+        self.slide.unwrap_or(self.size)
+    }
+}
+
+/// Returns the starts of all windows containing the given instant.
+fn windows(instant: u64, size: u64, slide: u64) -> impl Iterator<Item = u64> {
+    let last = instant - (instant % slide);
+
+    (0..)
+        .map(move |i| i * slide)
+        .take_while(move |offset| *offset <= last && last - offset + size > instant)
+        .map(move |offset| last - offset)
+}
+
+/// The source of a windowed aggregation, binding the key variables,
+/// the window variable, the aggregation variable, and the with
+/// variables, in that order. A tuple appears once for every window it
+/// falls into. Tuples not bound to an instant fall into none.
+struct Buckets<'a, P: Implementable> {
+    window: &'a Window<P>,
+}
+
+impl<'a, P: Implementable> Implementable for Buckets<'a, P> {
+    fn dependencies(&self) -> Dependencies {
+        self.window.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Vec<Binding> {
+        self.window.plan.into_bindings()
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        let (relation, shutdown_handle) =
+            self.window
+                .plan
+                .implement(nested, local_arrangements, context)?;
+
+        let key_offsets: Vec<usize> = self
+            .window
+            .key_variables
+            .iter()
+            .map(|variable| relation.binds(*variable).expect("variable not found"))
+            .collect();
+
+        let time_offset = relation
+            .binds(self.window.time_variable)
+            .expect("variable not found");
+
+        let aggregation_offset = relation
+            .binds(self.window.aggregation_variable)
+            .expect("variable not found");
+
+        let with_offsets: Vec<usize> = self
+            .window
+            .with_variables
+            .iter()
+            .map(|variable| relation.binds(*variable).expect("variable not found"))
+            .collect();
+
+        let mut variables = self.window.key_variables.clone();
+        variables.push(self.window.window_variable);
+        variables.push(self.window.aggregation_variable);
+        variables.extend(self.window.with_variables.iter().cloned());
+
+        let size = self.window.size;
+        let slide = self.window.slide();
+
+        let tuples = relation.tuples().flat_map(move |tuple| {
+            let instant = match tuple[time_offset] {
+                Value::Instant(instant) => instant,
+                ref other => {
+                    warn!(
+                        "Skipping tuple {:?}, windows can only be formed over instants, not {:?}",
+                        tuple, other
+                    );
+                    return vec![];
+                }
+            };
+
+            let key: Vec<Value> = key_offsets
+                .iter()
+                .map(|offset| tuple[*offset].clone())
+                .collect();
+
+            let mut value = vec![tuple[aggregation_offset].clone()];
+            value.extend(with_offsets.iter().map(|offset| tuple[*offset].clone()));
+
+            windows(instant, size, slide)
+                .map(|start| {
+                    let mut bucketed = key.clone();
+                    bucketed.push(Value::Instant(start));
+                    bucketed.extend(value.iter().cloned());
+                    bucketed
+                })
+                .collect::<Vec<_>>()
+        });
+
+        Ok((CollectionRelation { variables, tuples }, shutdown_handle))
+    }
+}
+
+impl<P: Implementable> Implementable for Window<P> {
+    fn dependencies(&self) -> Dependencies {
+        self.plan.dependencies()
+    }
+
+    fn into_bindings(&self) -> Vec<Binding> {
+        self.plan.into_bindings()
+    }
+
+    fn implement<'b, T, I, S>(
+        &self,
+        nested: &mut Iterative<'b, S, u64>,
+        local_arrangements: &VariableMap<Iterative<'b, S, u64>>,
+        context: &mut I,
+    ) -> Result<(CollectionRelation<'b, S>, ShutdownHandle), Error>
+    where
+        T: Timestamp + Lattice + TotalOrder,
+        I: ImplContext<T>,
+        S: Scope<Timestamp = T>,
+    {
+        if self.size == 0 || self.slide() == 0 {
+            return Err(Error {
+                category: ErrorKind::Incorrect,
+                message: "Windows must have a positive size and slide.".to_string(),
+            });
+        }
+
+        let mut key_variables = self.key_variables.clone();
+        key_variables.push(self.window_variable);
+
+        let aggregate = Aggregate {
+            variables: self.variables.clone(),
+            plan: Box::new(Buckets { window: self }),
+            aggregation_fns: vec![self.aggregation_fn.clone()],
+            key_variables,
+            aggregation_variables: vec![self.aggregation_variable],
+            with_variables: self.with_variables.clone(),
+            count_empty: false,
+        };
+
+        aggregate.implement(nested, local_arrangements, context)
+    }
+}
@@ -0,0 +1,24 @@
+//! Extension point for restricting which requests clients may make.
+
+use crate::server::Request;
+
+/// Decides whether a client may have a request executed. Requests
+/// are authorized after they have been sequenced, by every worker,
+/// so implementations must come to the same decision on all of them.
+/// Clients are identified by the worker owning their connection along
+/// with their token, as tokens are only unique per worker.
+pub trait RequestAuthorizer<Token> {
+    /// Returns true iff the given client of the given worker may make
+    /// the given request.
+    fn authorize(&self, owner: usize, client: &Token, request: &Request) -> bool;
+}
+
+/// Authorizes every request.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllowAll;
+
+impl<Token> RequestAuthorizer<Token> for AllowAll {
+    fn authorize(&self, _owner: usize, _client: &Token, _request: &Request) -> bool {
+        true
+    }
+}
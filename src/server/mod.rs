@@ -1,27 +1,75 @@
 //! Server logic for driving the library via commands.
 
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
 use std::ops::Sub;
+use std::rc::{Rc, Weak};
 use std::time::{Duration, Instant};
 
+use timely::communication::Allocate;
+
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Filter, Operator};
 use timely::dataflow::{ProbeHandle, Scope};
 use timely::order::TotalOrder;
 use timely::progress::Timestamp;
+use timely::worker::Worker;
 
 use differential_dataflow::collection::Collection;
 use differential_dataflow::input::Input;
 use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::arrange::Arrange;
+use differential_dataflow::operators::Consolidate;
+use differential_dataflow::trace::TraceReader;
+use differential_dataflow::{AsCollection, Hashable};
+
+use timely_sort::Unsigned;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::domain::Domain;
-use crate::plan::{ImplContext, Implementable};
-use crate::sinks::{Sink, Sinkable};
+use crate::plan::{Explain, GraphQl, ImplContext, Implementable, Pull};
+use crate::sinks::{materialize, read_materialized, Sink, Sinkable};
 use crate::sources::{Source, Sourceable};
-use crate::Rule;
 use crate::{
-    implement, implement_neu, AttributeConfig, CollectionIndex, RelationHandle, ShutdownHandle,
+    check_cycles, collect_dependencies, collect_local_dependencies, implement, implement_neu,
+    is_tempid, AttributeConfig, CollectionIndex, CompactionPolicy, InputSemantics, Iterations,
+    RelationConfig, RelationHandle, RuleKind, RuleSemantics, ShutdownHandle,
 };
-use crate::{Aid, Error, Time, TxData, Value};
+use crate::{Aid, Eid, Error, ErrorKind, ResultDiff, Time, TxData, Value};
+use crate::{Plan, Rule};
+
+pub mod auth;
+pub mod symbols;
+
+pub use self::auth::{AllowAll, RequestAuthorizer};
+pub use self::symbols::Symbols;
+
+/// Entity id under which the internal domain describes itself, if
+/// meta queries are enabled.
+pub const META_DOMAIN: Eid = std::i64::MAX as Eid;
+
+/// Attribute holding the current time of the internal domain, as a
+/// `Value::Number` for logical and a `Value::Instant` for real times.
+pub const META_NOW: &str = "df.domain/now";
+
+/// Attribute holding the frontier of the server probe, as of the
+/// last time the internal domain was advanced.
+pub const META_FRONTIER: &str = "df.domain/frontier";
+
+/// Attribute naming the attribute an entity holds statistics on, as
+/// a `Value::Aid`.
+pub const META_IDENT: &str = "df.attribute/ident";
+
+/// Attribute holding the number of distinct entities of an
+/// attribute, as of the last Statistics request.
+pub const META_ENTITIES: &str = "df.attribute/entities";
+
+/// Attribute holding the number of datoms of an attribute, as of the
+/// last Statistics request.
+pub const META_DATOMS: &str = "df.attribute/datoms";
 
 /// Server configuration.
 #[derive(Clone, Debug)]
@@ -36,6 +84,32 @@ pub struct Config {
     pub enable_optimizer: bool,
     /// Should queries on the query graph be available?
     pub enable_meta: bool,
+    /// Should the history of relations be retained for queries at
+    /// past times?
+    pub enable_history: bool,
+    /// Number of result bytes that may be waiting to be written to a
+    /// single connection before its interest is dropped. Unbounded if
+    /// not set.
+    pub max_pending_bytes: Option<usize>,
+    /// File to which all sequenced commands are appended.
+    pub record: Option<String>,
+    /// File of recorded commands to replay, instead of accepting
+    /// client connections.
+    pub replay: Option<String>,
+    /// Total number of workers, across all processes. Inputs on
+    /// attributes partitioned by entity are spread across these.
+    pub peers: usize,
+    /// First entity id handed out in response to AllocateEids
+    /// requests. Clients choosing entity ids themselves should stay
+    /// below it.
+    pub eid_offset: Eid,
+    /// Should attribute identifiers in value position be interned?
+    pub intern_aids: bool,
+    /// How to choose the worker owning a newly accepted client
+    /// connection, and thus serializing all of its results.
+    pub affinity: Affinity,
+    /// Port at which this server answers liveness probes, if any.
+    pub health_port: Option<u16>,
 }
 
 impl Default for Config {
@@ -46,6 +120,44 @@ impl Default for Config {
             enable_cli: false,
             enable_optimizer: false,
             enable_meta: false,
+            enable_history: false,
+            max_pending_bytes: None,
+            record: None,
+            replay: None,
+            peers: 1,
+            eid_offset: 1 << 32,
+            intern_aids: false,
+            affinity: Affinity::Accepting,
+            health_port: None,
+        }
+    }
+}
+
+/// Policies for choosing the worker that owns a client connection,
+/// among the workers of the process accepting it. Connections can't
+/// be moved across processes.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Affinity {
+    /// The worker accepting a connection owns it.
+    Accepting,
+    /// Connections are dealt to all workers in turn.
+    RoundRobin,
+    /// Connections go to the worker owning the fewest open ones.
+    LeastLoaded,
+}
+
+impl std::str::FromStr for Affinity {
+    type Err = Error;
+
+    fn from_str(affinity: &str) -> Result<Affinity, Error> {
+        match affinity {
+            "accepting" => Ok(Affinity::Accepting),
+            "round-robin" => Ok(Affinity::RoundRobin),
+            "least-loaded" => Ok(Affinity::LeastLoaded),
+            _ => Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!("Unknown affinity {}.", affinity),
+            }),
         }
     }
 }
@@ -54,11 +166,63 @@ impl Default for Config {
 pub type TxId = u64;
 
 /// A request expressing interest in receiving results published under
-/// the specified name.
+/// the specified name. Results are interleaved with `{"frontier": [..]}`
+/// markers, after which no more results at times not in advance of
+/// the frontier will be delivered.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
 pub struct Interest {
     /// The name of a previously registered dataflow.
     pub name: String,
+    /// The form in which results should be delivered.
+    #[serde(default)]
+    pub delivery: Delivery,
+    /// The format in which results should be serialized.
+    #[serde(default)]
+    pub format: Format,
+    /// Milliseconds a newly created dataflow may take to catch up
+    /// with the domain. Past that, it is torn down and the client
+    /// receives a timeout error. Unbounded by default.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Possible forms of delivering results to interested clients.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Delivery {
+    /// Raw (tuple, time, diff) triples, as they are produced.
+    Raw,
+    /// Consolidated additions and removals for each completed time,
+    /// starting with a snapshot of the initial results. Changes at
+    /// a single time may arrive split across multiple messages.
+    Delta,
+    /// Consolidated (tuple, count) pairs for each completed time,
+    /// s.t. changes cancelling each other out aren't delivered at
+    /// all. As with deltas, changes at a single time may arrive split
+    /// across multiple messages.
+    Counts,
+}
+
+impl Default for Delivery {
+    fn default() -> Delivery {
+        Delivery::Raw
+    }
+}
+
+/// Possible formats for serializing results sent to interested
+/// clients.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Format {
+    /// JSON, sent as text frames.
+    Json,
+    /// MessagePack, sent as binary frames. Cheaper to produce and
+    /// parse than JSON, and keeps numbers at their exact types.
+    MessagePack,
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format::Json
+    }
 }
 
 /// A request with the intent of synthesising one or more new rules
@@ -81,6 +245,19 @@ pub struct RegisterSink {
     pub sink: Sink,
 }
 
+/// A request with the intent of keeping a named relation materialized
+/// on disk, s.t. snapshots of it can be served without running a
+/// dataflow of their own.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct Materialize {
+    /// The name of a previously registered relation.
+    pub name: String,
+    /// Path to the file holding the materialized view. It is written
+    /// by the first worker and must be readable by every worker
+    /// serving snapshots.
+    pub path: String,
+}
+
 /// A request with the intent of creating a new named, globally
 /// available input that can be transacted upon.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -90,6 +267,10 @@ pub struct CreateAttribute {
     pub name: String,
     /// Semantics enforced on this attribute by 3DF.
     pub config: AttributeConfig,
+    /// The named domain this attribute should live in. Attributes
+    /// without one live in the internal domain.
+    #[serde(default)]
+    pub domain: Option<String>,
 }
 
 /// Possible request types.
@@ -97,6 +278,15 @@ pub struct CreateAttribute {
 pub enum Request {
     /// Sends inputs via one or more registered handles.
     Transact(Vec<TxData>),
+    /// Sends many transactions at once, each introduced at its own,
+    /// explicitly chosen time.
+    TransactBatch(Vec<(Time, Vec<TxData>)>),
+    /// Retracts whatever values the given eids currently hold for the
+    /// given attributes, without having to name them.
+    RetractCurrent(Vec<(Eid, Aid)>),
+    /// Retracts all values the given eids currently hold, across all
+    /// attributes.
+    RetractEntities(Vec<Eid>),
     /// Expresses interest in a named relation.
     Interest(Interest),
     /// Expresses that the interest in a named relation has
@@ -104,8 +294,10 @@ pub enum Request {
     /// dataflow can be cleaned up.
     Uninterest(String),
     /// Expresses interest in a named relation, but directing results
-    /// to be forwarded to a sink.
-    Flow(String, String),
+    /// to be forwarded to one or more sinks, all fed by the same
+    /// dataflow. Unknown sinks are reported without affecting the
+    /// others.
+    Flow(String, Vec<String>),
     /// Registers one or more named relations.
     Register(Register),
     /// A request with the intent of attaching to an external data
@@ -113,14 +305,166 @@ pub enum Request {
     RegisterSource(Source),
     /// Registers an external data sink.
     RegisterSink(RegisterSink),
+    /// Keeps a named relation materialized on disk. Snapshots of it
+    /// are served from there from then on.
+    Materialize(Materialize),
     /// Creates a named input handle that can be `Transact`ed upon.
     CreateAttribute(CreateAttribute),
     /// Advances the specified domain to the specified time.
     AdvanceDomain(Option<String>, Time),
+    /// Changes the compaction policy (e.g. the trace slack) of an
+    /// existing attribute, taking effect on the next advance.
+    SetCompaction(Aid, CompactionPolicy<Time>),
+    /// Limits the number of iterations a recursive rule may take in
+    /// dataflows created from here on, via the configuration of its
+    /// relation.
+    SetIterationLimit(String, u64),
+    /// Fails a recursive rule that exceeded its iteration limit,
+    /// dropping all interests depending on it. Issued by the server
+    /// itself, s.t. all workers tear down the same dataflows.
+    FailRule(String),
+    /// Chooses between set and multiset semantics for a rule, in
+    /// dataflows created from here on.
+    SetRuleSemantics(String, RuleSemantics),
+    /// Registers the first name as an alias for the existing
+    /// attribute named second.
+    CreateAlias(Aid, Aid),
+    /// Changes the input semantics of an existing attribute,
+    /// re-indexing it under the new semantics.
+    MigrateAttribute(Aid, InputSemantics),
     /// Closes a named input handle.
     CloseInput(String),
     /// Requests orderly shutdown of the system.
     Shutdown,
+    /// Requests a description of the current server state.
+    Status,
+    /// Requests the current contents of a named relation, once.
+    Snapshot(String),
+    /// Requests the contents of a named relation as of a past time,
+    /// once.
+    QueryAt(String, Time),
+    /// Requests all registered rules, together with their
+    /// dependencies on other rules and on attributes.
+    ListRules,
+    /// Requests all rules and attributes a named relation depends
+    /// on, transitively.
+    Dependencies(String),
+    /// Requests a description of how a plan would be implemented,
+    /// without implementing it.
+    Explain(Plan),
+    /// Expresses interest in the results of a GraphQL query,
+    /// published under the specified name. Results are serialized
+    /// in the format of an interest in the same name, if any, and as
+    /// JSON otherwise.
+    GraphQl(String, String),
+    /// Requests operational metrics on rules and domains.
+    Metrics,
+    /// Requests the number of distinct entities and datoms held by
+    /// each attribute.
+    Statistics,
+    /// Requests a contiguous block of fresh entity ids of the
+    /// specified size.
+    AllocateEids(u64),
+    /// Streams the raw updates of a named relation to the client,
+    /// separately from any interest in it, but in the same format.
+    Debug(String),
+    /// Expresses that debugging output for a named relation is no
+    /// longer needed.
+    Undebug(String),
+}
+
+/// A description of the current server state, as returned in response
+/// to a Status request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Status<T> {
+    /// Attributes created via CreateAttribute, with their configurations.
+    pub attributes: BTreeMap<Aid, AttributeConfig>,
+    /// Attributes fed by external sources.
+    pub sources: Vec<Aid>,
+    /// Names of all registered rules.
+    pub rules: Vec<String>,
+    /// Names of relations with at least one interested client.
+    pub interests: Vec<String>,
+    /// Names of all registered sinks.
+    pub sinks: Vec<String>,
+    /// The current time of the internal domain.
+    pub time: T,
+    /// The current times of all named domains.
+    pub domains: BTreeMap<String, T>,
+}
+
+/// Operational metrics, as returned in response to a Metrics request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Metrics<T> {
+    /// The least current time across the internal and all named
+    /// domains.
+    pub time: T,
+    /// The frontier of the server probe. Results of interests are
+    /// complete up to this frontier, lagging behind `time` while
+    /// there is work left to do.
+    pub frontier: Vec<T>,
+    /// Metrics as datoms of the form [rule "df.metrics/interests" n],
+    /// [rule "df.metrics/iterations" n], and [domain
+    /// "df.metrics/transacted" n]. Iterations are the most any
+    /// recursive rule has taken to converge on this worker. The
+    /// internal domain is called "df.domain/internal".
+    pub datoms: Vec<(String, Aid, Value)>,
+}
+
+/// Attribute statistics, as returned in response to a Statistics
+/// request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Statistics<T> {
+    /// The least current time across the internal and all named
+    /// domains.
+    pub time: T,
+    /// Statistics as datoms of the form [aid "df.attribute/entities"
+    /// n] and [aid "df.attribute/datoms" n], across all domains and
+    /// sorted by attribute.
+    pub datoms: Vec<(Aid, Aid, Value)>,
+}
+
+/// A description of all registered rules, as returned in response to
+/// a ListRules request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RuleGraph {
+    /// All registered rules, sorted by name.
+    pub rules: Vec<Rule>,
+    /// Dependency edges of the form [rule "df.rule/depends-on" name]
+    /// and [rule "df.rule/attribute" aid].
+    pub edges: Vec<(String, Aid, Value)>,
+}
+
+/// Everything a named relation depends on, as returned in response to
+/// a Dependencies request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DependencyClosure {
+    /// The rule defining the relation itself, followed by all rules
+    /// it depends on, each appearing once.
+    pub rules: Vec<Rule>,
+    /// All attributes required by any of these rules, sorted.
+    pub attributes: Vec<Aid>,
+}
+
+/// A handle to a transaction, as returned by `transact_async`. It
+/// allows waiting for the transaction to become visible to all
+/// interests, without having to know anything about domain times.
+pub struct TxHandle<T: Timestamp> {
+    /// The time at which the transaction was introduced.
+    pub time: T,
+    /// The entity ids its placeholders resolved to.
+    pub tempids: Vec<(Eid, Eid)>,
+    probe: ProbeHandle<T>,
+}
+
+impl<T: Timestamp> TxHandle<T> {
+    /// Returns true iff the server probe has passed the time of the
+    /// transaction, i.e. iff all interests reflect it. Some worker
+    /// must keep stepping the dataflow, and the domains involved must
+    /// be advanced beyond the transaction time, for this to happen.
+    pub fn is_visible(&self) -> bool {
+        !self.probe.less_equal(&self.time)
+    }
 }
 
 /// Server context maintaining globally registered arrangements and
@@ -141,8 +485,21 @@ where
     pub interests: HashMap<String, HashSet<Token>>,
     /// Mapping from query names to their shutdown handles.
     pub shutdown_handles: HashMap<String, ShutdownHandle>,
+    /// Implementations of rules whose arrangements are registered
+    /// for re-use. They stay alive for as long as any dataflow
+    /// depends on them.
+    pub implementations: HashMap<String, Weak<ShutdownHandle>>,
     /// Probe keeping track of overall dataflow progress.
     pub probe: ProbeHandle<T>,
+    /// Next entity id to be handed out via AllocateEids.
+    pub next_eid: Eid,
+    /// Attribute identifiers interned so far.
+    pub symbols: Symbols,
+    /// Mapping from names of materialized relations to the paths of
+    /// their views.
+    pub materializations: HashMap<String, String>,
+    /// Decides which requests clients may make, see `authorize`.
+    pub authorizer: Box<dyn RequestAuthorizer<Token>>,
 }
 
 /// Implementation context.
@@ -151,11 +508,50 @@ where
     T: Timestamp + Lattice + TotalOrder,
 {
     /// Representation of named rules.
-    pub rules: HashMap<Aid, Rule>,
+    pub rules: HashMap<String, Rule>,
     /// Set of rules known to be underconstrained.
-    pub underconstrained: HashSet<Aid>,
+    pub underconstrained: HashSet<String>,
+    /// Iterations taken by recursive rules on this worker.
+    pub iterations: Rc<RefCell<Iterations>>,
+    /// Semantics per rule, where they deviate from the default.
+    pub rule_semantics: HashMap<String, RuleSemantics>,
     /// Internal domain of command sequence numbers.
     pub internal: Domain<T>,
+    /// Named domains, advancing independently of the internal one.
+    pub domains: HashMap<String, Domain<T>>,
+    /// Should plans be optimized based on statistics about the data?
+    pub enable_optimizer: bool,
+}
+
+impl<T> Context<T>
+where
+    T: Timestamp + Lattice + TotalOrder,
+{
+    /// Returns the name of the named domain holding the specified
+    /// attribute, or None if the attribute lives in the internal
+    /// domain (or doesn't exist at all).
+    fn domain_of(&self, aid: &str) -> Option<String> {
+        self.domains
+            .iter()
+            .find(|(_name, domain)| {
+                domain.forward.contains_key(aid) || domain.aliases.contains_key(aid)
+            })
+            .map(|(name, _domain)| name.clone())
+    }
+
+    /// Returns the configuration of the specified attribute, in
+    /// whichever domain it lives.
+    fn attribute_config(&self, aid: &str) -> Option<&AttributeConfig> {
+        self.internal
+            .attributes
+            .get(self.internal.resolve(aid))
+            .or_else(|| {
+                self.domains
+                    .values()
+                    .filter_map(|domain| domain.attributes.get(domain.resolve(aid)))
+                    .next()
+            })
+    }
 }
 
 impl<T> ImplContext<T> for Context<T>
@@ -171,26 +567,85 @@ where
     }
 
     fn has_attribute(&self, name: &str) -> bool {
-        self.internal.forward.contains_key(name)
+        let has = |domain: &Domain<T>| {
+            domain.forward.contains_key(name) || domain.aliases.contains_key(name)
+        };
+
+        has(&self.internal) || self.domains.values().any(has)
+    }
+
+    fn attributes(&self) -> Vec<Aid> {
+        let mut attributes: Vec<Aid> = self.internal.forward.keys().cloned().collect();
+        for domain in self.domains.values() {
+            attributes.extend(domain.forward.keys().cloned());
+        }
+        attributes.sort();
+
+        attributes
     }
 
     fn forward_index(&mut self, name: &str) -> Option<&mut CollectionIndex<Value, Value, T>> {
-        self.internal.forward.get_mut(name)
+        match self.domain_of(name) {
+            None => self.internal.forward_index(name),
+            Some(domain) => self.domains.get_mut(&domain).unwrap().forward_index(name),
+        }
+    }
+
+    fn reverse_index<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        scope: &S,
+    ) -> Option<&mut CollectionIndex<Value, Value, T>> {
+        match self.domain_of(name) {
+            None => self.internal.reverse_index(name, scope),
+            Some(domain) => self
+                .domains
+                .get_mut(&domain)
+                .unwrap()
+                .reverse_index(name, scope),
+        }
+    }
+
+    fn is_underconstrained(&self, name: &str) -> bool {
+        match self.rules.get(name) {
+            Some(rule) => rule.kind == RuleKind::Recursive,
+            None => true,
+        }
+    }
+
+    fn is_implemented(&self, name: &str) -> bool {
+        self.internal.arrangements.contains_key(name)
     }
 
-    fn reverse_index(&mut self, name: &str) -> Option<&mut CollectionIndex<Value, Value, T>> {
-        self.internal.reverse.get_mut(name)
+    fn iteration_limit(&self, name: &str) -> Option<u64> {
+        self.internal
+            .relations
+            .get(name)
+            .and_then(|config| config.iteration_limit)
     }
 
-    fn is_underconstrained(&self, _name: &str) -> bool {
-        // self.underconstrained.contains(name)
-        true
+    fn iterations(&self) -> Option<Rc<RefCell<Iterations>>> {
+        Some(self.iterations.clone())
+    }
+
+    fn rule_semantics(&self, name: &str) -> RuleSemantics {
+        self.rule_semantics.get(name).cloned().unwrap_or_default()
+    }
+
+    fn enable_optimizer(&self) -> bool {
+        self.enable_optimizer
     }
 }
 
 impl<T, Token> Server<T, Token>
 where
-    T: Timestamp + Lattice + TotalOrder + Default + Sub<Output = T> + std::convert::From<Time>,
+    T: Timestamp
+        + Lattice
+        + TotalOrder
+        + Default
+        + Sub<Output = T>
+        + std::convert::From<Time>
+        + Into<Time>,
     Token: Hash,
 {
     /// Creates a new server state from a configuration.
@@ -202,23 +657,78 @@ where
     /// additionally specified beginning of the computation: an
     /// instant in relation to which all durations will be measured.
     pub fn new_at(config: Config, t0: Instant) -> Self {
+        let enable_optimizer = config.enable_optimizer;
+
         Server {
+            next_eid: config.eid_offset,
             config,
             t0,
             context: Context {
                 rules: HashMap::new(),
                 internal: Domain::new(Default::default()),
+                domains: HashMap::new(),
                 underconstrained: HashSet::new(),
+                iterations: Rc::new(RefCell::new(Iterations::default())),
+                rule_semantics: HashMap::new(),
+                enable_optimizer,
             },
             interests: HashMap::new(),
             shutdown_handles: HashMap::new(),
+            implementations: HashMap::new(),
             probe: ProbeHandle::new(),
+            symbols: Symbols::default(),
+            materializations: HashMap::new(),
+            authorizer: Box::new(AllowAll),
+        }
+    }
+
+    /// Replaces the authorizer consulted before executing requests.
+    pub fn set_authorizer<A: RequestAuthorizer<Token> + 'static>(&mut self, authorizer: A) {
+        self.authorizer = Box::new(authorizer);
+    }
+
+    /// Checks whether the given client of the given worker may make
+    /// the given request, as decided by the configured authorizer.
+    /// All requests are allowed by default.
+    pub fn authorize(&self, owner: usize, client: &Token, request: &Request) -> Result<(), Error> {
+        if self.authorizer.authorize(owner, client, request) {
+            Ok(())
+        } else {
+            Err(Error {
+                category: ErrorKind::Forbidden,
+                message: "Client is not allowed to make this request.".to_string(),
+            })
         }
     }
 
     /// Returns commands to install built-in plans.
-    pub fn builtins() -> Vec<Request> {
-        vec![
+    pub fn builtins(config: &Config) -> Vec<Request> {
+        let mut builtins = Vec::new();
+
+        if config.enable_meta {
+            #[cfg(not(feature = "real-time"))]
+            let attribute_config = AttributeConfig::tx_time(InputSemantics::CardinalityOne);
+            #[cfg(feature = "real-time")]
+            let attribute_config = AttributeConfig::real_time(InputSemantics::CardinalityOne);
+
+            for name in [
+                META_NOW,
+                META_FRONTIER,
+                META_IDENT,
+                META_ENTITIES,
+                META_DATOMS,
+            ]
+            .iter()
+            {
+                builtins.push(Request::CreateAttribute(CreateAttribute {
+                    name: name.to_string(),
+                    config: attribute_config.clone(),
+                    domain: None,
+                }));
+            }
+        }
+
+        builtins.extend(vec![
             // Request::CreateAttribute(CreateAttribute {
             //     name: "df.pattern/e".to_string(),
             //     semantics: InputSemantics::Raw,
@@ -231,83 +741,813 @@ where
             //     name: "df.pattern/v".to_string(),
             //     semantics: InputSemantics::Raw,
             // }),
-        ]
+        ]);
+
+        builtins
     }
 
-    /// Handle a Transact request.
+    /// Handle a Transact request, returning the entity ids its
+    /// placeholders resolved to, as (tempid, eid) pairs.
     pub fn transact(
         &mut self,
         tx_data: Vec<TxData>,
         owner: usize,
         worker_index: usize,
+    ) -> Result<Vec<(Eid, Eid)>, Error> {
+        let (tx_data, tempids) = self.resolve_tempids(tx_data)?;
+        let tx_data = self.intern_values(tx_data);
+
+        // Each datom is introduced by a single worker, as chosen by
+        // its attribute's partitioning. Usually that is the owner.
+        let peers = self.config.peers;
+        let context = &self.context;
+        let tx_data: Vec<TxData> = tx_data
+            .into_iter()
+            .filter(|TxData(_op, e, a, _v)| {
+                let worker = match context.attribute_config(a) {
+                    // Unknown attributes are reported by the owner.
+                    None => owner,
+                    Some(config) => config.partitioning.worker(*e, owner, peers),
+                };
+
+                worker == worker_index
+            })
+            .collect();
+
+        if tx_data.is_empty() {
+            Ok(tempids)
+        } else if self.context.domains.is_empty() {
+            self.context.internal.transact(tx_data)?;
+            Ok(tempids)
+        } else {
+            // Each datom goes to the domain holding its attribute.
+            let mut by_domain: HashMap<Option<String>, Vec<TxData>> = HashMap::new();
+            for datom in tx_data.into_iter() {
+                by_domain
+                    .entry(self.context.domain_of(&datom.2))
+                    .or_insert_with(Vec::new)
+                    .push(datom);
+            }
+
+            for (domain, tx_data) in by_domain.drain() {
+                match domain {
+                    None => self.context.internal.transact(tx_data)?,
+                    Some(domain) => self
+                        .context
+                        .domains
+                        .get_mut(&domain)
+                        .unwrap()
+                        .transact(tx_data)?,
+                }
+            }
+
+            Ok(tempids)
+        }
+    }
+
+    /// Handle a RetractCurrent request. Retractions are introduced by
+    /// the same workers that would introduce datoms on the same
+    /// (e, a) pairs, s.t. they meet those datoms' values.
+    pub fn retract_current(
+        &mut self,
+        retractions: Vec<(Eid, Aid)>,
+        owner: usize,
+        worker_index: usize,
+    ) -> Result<(), Error> {
+        let peers = self.config.peers;
+        let context = &self.context;
+        let (retractions, others): (Vec<(Eid, Aid)>, Vec<(Eid, Aid)>) =
+            retractions.into_iter().partition(|(e, a)| {
+                let worker = match context.attribute_config(a) {
+                    // Unknown attributes are reported by the owner.
+                    None => owner,
+                    Some(config) => config.partitioning.worker(*e, owner, peers),
+                };
+
+                worker == worker_index
+            });
+
+        // Pending expiries are tracked by whichever worker introduced
+        // an assertion, so all of them have to forget about retracted
+        // values.
+        for (e, a) in others.iter() {
+            match self.context.domain_of(a) {
+                None => self.context.internal.cancel_expirations(*e, a),
+                Some(domain) => {
+                    if let Some(domain) = self.context.domains.get_mut(&domain) {
+                        domain.cancel_expirations(*e, a);
+                    }
+                }
+            }
+        }
+
+        let mut by_domain: HashMap<Option<String>, Vec<(Eid, Aid)>> = HashMap::new();
+        for (e, a) in retractions.into_iter() {
+            by_domain
+                .entry(self.context.domain_of(&a))
+                .or_insert_with(Vec::new)
+                .push((e, a));
+        }
+
+        for (domain, retractions) in by_domain.drain() {
+            match domain {
+                None => self.context.internal.retract_current(retractions)?,
+                Some(domain) => self
+                    .context
+                    .domains
+                    .get_mut(&domain)
+                    .unwrap()
+                    .retract_current(retractions)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a RetractEntities request.
+    pub fn retract_entities(&mut self, eids: Vec<Eid>, owner: usize, worker_index: usize) {
+        let peers = self.config.peers;
+
+        self.context
+            .internal
+            .retract_entities(&eids, owner, worker_index, peers);
+
+        for domain in self.context.domains.values_mut() {
+            domain.retract_entities(&eids, owner, worker_index, peers);
+        }
+    }
+
+    /// Handle a Transact request, returning a handle that can be
+    /// used to wait for the transaction to become visible. The
+    /// transaction time is the latest time amongst the domains it
+    /// touches, s.t. all workers agree on it, regardless of which
+    /// datoms they end up introducing.
+    pub fn transact_async(
+        &mut self,
+        tx_data: Vec<TxData>,
+        owner: usize,
+        worker_index: usize,
+    ) -> Result<TxHandle<T>, Error> {
+        let time = self.tx_time(tx_data.iter().map(|TxData(_op, _e, a, _v)| a.as_str()));
+
+        let tempids = self.transact(tx_data, owner, worker_index)?;
+
+        Ok(TxHandle {
+            time,
+            tempids,
+            probe: self.probe.clone(),
+        })
+    }
+
+    /// Returns the latest time amongst the domains holding the given
+    /// attributes, or the internal domain's time if none of them
+    /// lives in a named domain. Inputs to these attributes are
+    /// introduced at this time.
+    pub fn tx_time<'a, I: IntoIterator<Item = &'a str>>(&self, aids: I) -> T {
+        let mut time: Option<T> = None;
+        for aid in aids.into_iter() {
+            let domain_time = match self.context.domain_of(aid) {
+                None => self.context.internal.time(),
+                Some(domain) => self.context.domains[&domain].time(),
+            };
+
+            if time
+                .as_ref()
+                .map(|t| t.less_than(domain_time))
+                .unwrap_or(true)
+            {
+                time = Some(domain_time.clone());
+            }
+        }
+
+        time.unwrap_or_else(|| self.context.internal.time().clone())
+    }
+
+    /// Returns the least time amongst the domains holding attributes
+    /// the named relation depends on, or the internal domain's time
+    /// if none of them lives in a named domain. The relation can
+    /// only be complete up to this time.
+    pub fn relation_time(&self, name: &str) -> T {
+        let attributes: HashSet<Aid> = match collect_dependencies(&self.context, &[name]) {
+            Err(_) => HashSet::new(),
+            Ok(rules) => rules
+                .iter()
+                .flat_map(|rule| rule.plan.dependencies().attributes)
+                .collect(),
+        };
+
+        let mut time: Option<T> = None;
+        for aid in attributes.iter() {
+            let domain_time = match self.context.domain_of(aid) {
+                None => self.context.internal.time(),
+                Some(domain) => self.context.domains[&domain].time(),
+            };
+
+            if time
+                .as_ref()
+                .map(|t| domain_time.less_than(t))
+                .unwrap_or(true)
+            {
+                time = Some(domain_time.clone());
+            }
+        }
+
+        time.unwrap_or_else(|| self.context.internal.time().clone())
+    }
+
+    /// Handle a single transaction of a TransactBatch request. Every
+    /// domain the transaction touches is first advanced to `tx_time`,
+    /// s.t. its datoms are introduced at that time. Times must not go
+    /// backwards, neither within a batch nor across requests.
+    pub fn transact_at(
+        &mut self,
+        tx_time: T,
+        tx_data: Vec<TxData>,
+        owner: usize,
+        worker_index: usize,
+    ) -> Result<Vec<(Eid, Eid)>, Error> {
+        // Workers must agree on the domains to advance, regardless of
+        // which datoms they end up introducing.
+        let mut domains: Vec<Option<String>> = Vec::new();
+        for TxData(_op, _e, a, _v) in tx_data.iter() {
+            let domain = self.context.domain_of(a);
+            if !domains.contains(&domain) {
+                domains.push(domain);
+            }
+        }
+
+        for domain in domains.into_iter() {
+            self.advance_domain(domain, tx_time.clone())?;
+        }
+
+        self.transact(tx_data, owner, worker_index)
+    }
+
+    /// Replaces placeholder entity ids, in both entity and value
+    /// position, with freshly allocated ones. Each placeholder maps
+    /// to the same entity id throughout a transaction, but not across
+    /// transactions. All workers must resolve the same transactions,
+    /// in the same order, to agree on the resulting ids. Returns the
+    /// (tempid, eid) pairs alongside the resolved transaction, in
+    /// order of first appearance.
+    fn resolve_tempids(
+        &mut self,
+        mut tx_data: Vec<TxData>,
+    ) -> Result<(Vec<TxData>, Vec<(Eid, Eid)>), Error> {
+        // Placeholders are numbered in order of first appearance.
+        let mut resolved: HashMap<Eid, Eid> = HashMap::new();
+        for TxData(_op, e, _a, v) in tx_data.iter() {
+            if is_tempid(*e) {
+                let next = resolved.len() as Eid;
+                resolved.entry(*e).or_insert(next);
+            }
+
+            if let Value::Eid(e) = v {
+                if is_tempid(*e) {
+                    let next = resolved.len() as Eid;
+                    resolved.entry(*e).or_insert(next);
+                }
+            }
+        }
+
+        if resolved.is_empty() {
+            return Ok((tx_data, Vec::new()));
+        }
+
+        let (start, _end) = self.allocate_eids(resolved.len() as u64)?;
+        for eid in resolved.values_mut() {
+            *eid += start;
+        }
+
+        for TxData(_op, e, _a, v) in tx_data.iter_mut() {
+            if let Some(eid) = resolved.get(&*e) {
+                *e = *eid;
+            }
+
+            if let Value::Eid(e) = v {
+                if let Some(eid) = resolved.get(&*e) {
+                    *e = *eid;
+                }
+            }
+        }
+
+        let mut tempids: Vec<(Eid, Eid)> = resolved.into_iter().collect();
+        tempids.sort_by_key(|(_tempid, eid)| *eid);
+
+        Ok((tx_data, tempids))
+    }
+
+    /// Interns attribute identifiers in value position, if enabled.
+    /// This happens before datoms are assigned to workers, s.t. all
+    /// workers intern the same identifiers in the same order.
+    fn intern_values(&mut self, tx_data: Vec<TxData>) -> Vec<TxData> {
+        if !self.config.intern_aids {
+            return tx_data;
+        }
+
+        let symbols = &self.symbols;
+        tx_data
+            .into_iter()
+            .map(|TxData(op, e, a, v)| TxData(op, e, a, symbols.intern(v)))
+            .collect()
+    }
+
+    /// Interns attribute identifiers amongst the constants of a plan,
+    /// if enabled, s.t. they match interned values.
+    fn intern_constants(&self, plan: &mut Plan) {
+        if self.config.intern_aids {
+            let symbols = &self.symbols;
+            plan.map_constants(&mut |value: &mut Value| {
+                *value = symbols.intern(value.clone());
+            });
+        }
+    }
+
+    /// Replaces symbols by the attribute identifiers they stand for,
+    /// if interning is enabled. Results leave the server this way.
+    fn resolve_symbols<S: Scope<Timestamp = T>>(
+        &self,
+        relation: Collection<S, Vec<Value>, isize>,
+    ) -> Collection<S, Vec<Value>, isize> {
+        if !self.config.intern_aids {
+            return relation;
+        }
+
+        let symbols = self.symbols.clone();
+        relation.map(move |tuple| {
+            tuple
+                .into_iter()
+                .map(|value| symbols.resolve(value))
+                .collect()
+        })
+    }
+
+    /// Handle a CreateAttribute request.
+    pub fn create_attribute<S: Scope<Timestamp = T>>(
+        &mut self,
+        req: CreateAttribute,
+        scope: &mut S,
     ) -> Result<(), Error> {
-        // only the owner should actually introduce new inputs
-        if owner == worker_index {
-            self.context.internal.transact(tx_data)
+        let CreateAttribute {
+            name,
+            config,
+            domain,
+        } = req;
+
+        if self.context.has_attribute(&name) {
+            Err(Error {
+                category: ErrorKind::Conflict,
+                message: format!("An attribute of name {} already exists.", name),
+            })
         } else {
-            Ok(())
+            match domain {
+                None => self.context.internal.create_attribute(&name, config, scope),
+                Some(domain) => self
+                    .context
+                    .domains
+                    .entry(domain)
+                    .or_insert_with(|| Domain::new(Default::default()))
+                    .create_attribute(&name, config, scope),
+            }
         }
     }
 
-    /// Handles an Interest request.
-    pub fn interest<S: Scope<Timestamp = T>>(
+    /// Handle a CreateAlias request. Aliases live in the domain of
+    /// the attribute they refer to, and may not shadow any attribute
+    /// or alias, in any domain.
+    pub fn create_alias(&mut self, alias: &str, target: &str) -> Result<(), Error> {
+        if self.context.has_attribute(alias) {
+            return Err(Error {
+                category: ErrorKind::Conflict,
+                message: format!("An attribute or alias of name {} already exists.", alias),
+            });
+        }
+
+        match self.context.domain_of(target) {
+            None => self.context.internal.create_alias(alias, target),
+            Some(domain) => self
+                .context
+                .domains
+                .get_mut(&domain)
+                .unwrap()
+                .create_alias(alias, target),
+        }
+    }
+
+    /// Handle a SetCompaction request.
+    pub fn set_compaction(
         &mut self,
         name: &str,
+        compaction: CompactionPolicy<Time>,
+    ) -> Result<(), Error> {
+        match self.context.domain_of(name) {
+            None => self.context.internal.set_compaction(name, compaction),
+            Some(domain) => self
+                .context
+                .domains
+                .get_mut(&domain)
+                .unwrap()
+                .set_compaction(name, compaction),
+        }
+    }
+
+    /// Handle a MigrateAttribute request.
+    pub fn migrate_attribute<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        input_semantics: InputSemantics,
         scope: &mut S,
-    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
-        // We need to do a `contains_key` here to avoid taking
-        // a mut ref on context.
-        if self.context.internal.arrangements.contains_key(name) {
-            // Rule is already implemented.
-            let relation = self
+    ) -> Result<(), Error> {
+        match self.context.domain_of(name) {
+            None => self
                 .context
-                .global_arrangement(name)
+                .internal
+                .migrate_attribute(name, input_semantics, scope),
+            Some(domain) => self
+                .context
+                .domains
+                .get_mut(&domain)
                 .unwrap()
-                .import_named(scope, name)
-                .as_collection(|tuple, _| tuple.clone());
+                .migrate_attribute(name, input_semantics, scope),
+        }
+    }
+
+    /// Handle a CloseInput request.
+    pub fn close_input(&mut self, name: String) -> Result<(), Error> {
+        match self.context.domain_of(&name) {
+            None => self.context.internal.close_input(name),
+            Some(domain) => self
+                .context
+                .domains
+                .get_mut(&domain)
+                .unwrap()
+                .close_input(name),
+        }
+    }
+
+    /// Implements the named rule and registers its arrangement for
+    /// re-use, unless that has happened already. Cached and already
+    /// implemented rules it depends on are shared rather than
+    /// synthesized again. Returns a handle keeping the implementation
+    /// alive.
+    fn share_rule<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        scope: &mut S,
+    ) -> Result<Rc<ShutdownHandle>, Error> {
+        if let Some(shared) = self.implementations.get(name).and_then(Weak::upgrade) {
+            return Ok(shared);
+        }
+
+        // Shared rules are imported from their global arrangements
+        // by everything depending on them, so they have to be
+        // implemented first. Cycles would keep cached rules from
+        // ever becoming available.
+        check_cycles(
+            &self.context,
+            &collect_dependencies(&self.context, &[name])?,
+        )?;
+
+        let mut shared: Vec<String> = collect_local_dependencies(&self.context, &[name])?
+            .iter()
+            .flat_map(|rule| rule.plan.dependencies().names)
+            .filter(|dependency| {
+                !self.context.is_underconstrained(dependency)
+                    || self.context.is_implemented(dependency)
+            })
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+        shared.sort();
 
-            Ok(relation)
+        let mut shutdown_handle = ShutdownHandle::empty();
+        for dependency in shared.iter() {
+            shutdown_handle.add_shared(self.share_rule(dependency, scope)?);
+        }
+
+        let (mut rel_map, implemented) = if self.config.enable_optimizer {
+            implement_neu(name, scope, &mut self.context)?
         } else {
-            let (mut rel_map, shutdown_handle) = if self.config.enable_optimizer {
-                implement_neu(name, scope, &mut self.context)?
-            } else {
-                implement(name, scope, &mut self.context)?
-            };
+            implement(name, scope, &mut self.context)?
+        };
 
-            // @TODO when do we actually want to register result traces for re-use?
-            // for (name, relation) in rel_map.into_iter() {
-            // let trace = relation.map(|t| (t, ())).arrange_named(name).trace;
-            //     self.context.register_arrangement(name, config, trace);
-            // }
+        shutdown_handle.merge_with(implemented);
 
-            match rel_map.remove(name) {
-                None => Err(Error {
-                    category: "df.error.category/fault",
-                    message: format!(
-                        "Relation of interest ({}) wasn't actually implemented.",
-                        name
-                    ),
-                }),
-                Some(relation) => {
-                    self.shutdown_handles
-                        .insert(name.to_string(), shutdown_handle);
+        match rel_map.remove(name) {
+            None => Err(Error {
+                category: ErrorKind::Fault,
+                message: format!(
+                    "Relation of interest ({}) wasn't actually implemented.",
+                    name
+                ),
+            }),
+            Some(relation) => {
+                // The relation is registered s.t. it can be re-used
+                // by dataflows starting later on. Its full history is
+                // only retained if it may be queried at past times.
+                // Otherwise it is compacted up to one tick behind the
+                // domain, s.t. snapshots can still tell updates before
+                // the current time from those at it.
+                let compaction = if self.config.enable_history {
+                    CompactionPolicy::None
+                } else {
+                    let now: Time = self.context.internal.time().clone().into();
+                    CompactionPolicy::Slack(now.tick().into())
+                };
+
+                let trace = relation.map(|tuple| (tuple, ())).arrange_named(name).trace;
+
+                let iteration_limit = self.context.iteration_limit(name);
+
+                self.context.internal.register_arrangement(
+                    name.to_string(),
+                    RelationConfig {
+                        compaction,
+                        iteration_limit,
+                    },
+                    trace,
+                );
+
+                let shared = Rc::new(shutdown_handle);
+                self.implementations
+                    .insert(name.to_string(), Rc::downgrade(&shared));
+
+                Ok(shared)
+            }
+        }
+    }
+
+    /// Forgets about rule implementations nothing depends on anymore,
+    /// s.t. their arrangements aren't re-used once their dataflows
+    /// have shut down.
+    fn release_implementations(&mut self) {
+        let released: Vec<String> = self
+            .implementations
+            .iter()
+            .filter(|(_name, shared)| shared.upgrade().is_none())
+            .map(|(name, _shared)| name.clone())
+            .collect();
+
+        for name in released.iter() {
+            self.implementations.remove(name);
+            self.context.internal.unregister_arrangement(name);
+        }
+    }
+
+    /// Implements the named relation, returning it together with the
+    /// handle controlling the dataflow it lives in. Rules are
+    /// implemented only once and their arrangements re-used, for as
+    /// long as anything depends on them.
+    fn implement_relation<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        scope: &mut S,
+    ) -> Result<(Collection<S, Vec<Value>, isize>, ShutdownHandle), Error> {
+        self.release_implementations();
+
+        let shared = self.share_rule(name, scope)?;
+
+        let (arranged, shutdown_button) = self
+            .context
+            .global_arrangement(name)
+            .unwrap()
+            .import_core(scope, name);
+
+        let relation = arranged.as_collection(|tuple, _| tuple.clone());
+
+        let mut shutdown_handle = ShutdownHandle::from_button(shutdown_button);
+        shutdown_handle.add_shared(shared);
+
+        Ok((self.resolve_symbols(relation), shutdown_handle))
+    }
+
+    /// Handles an Interest request.
+    pub fn interest<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        let (relation, shutdown_handle) = self.implement_relation(name, scope)?;
+
+        self.shutdown_handles
+            .insert(name.to_string(), shutdown_handle);
+
+        Ok(relation)
+    }
+
+    /// Handles an Uninterest request, once no client is interested in
+    /// the named relation anymore. Shuts down the corresponding
+    /// dataflow explicitly, instead of relying on its handle being
+    /// dropped.
+    pub fn uninterest(&mut self, name: &str) {
+        if let Some(shutdown_handle) = self.shutdown_handles.remove(name) {
+            shutdown_handle.shutdown();
+        }
+
+        self.release_implementations();
+    }
+
+    /// Handles a Debug request. Implements the named relation in a
+    /// dataflow of its own, whose shutdown handle is kept under
+    /// `debug_name(name)`, s.t. it can be torn down without affecting
+    /// interests in the relation.
+    pub fn debug<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        let (relation, shutdown_handle) = self.implement_relation(name, scope)?;
+
+        self.shutdown_handles
+            .insert(debug_name(name), shutdown_handle);
+
+        Ok(relation)
+    }
+
+    /// Handles a Snapshot request. Returns the consolidated contents
+    /// of the named relation as of `relation_time(name)`, all placed
+    /// at that time. The snapshot is complete once the domains
+    /// involved have advanced beyond it, at which point its dataflow
+    /// shuts itself down.
+    pub fn snapshot<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        let at = self.relation_time(name);
+        let (relation, shutdown_handle) = self.implement_relation(name, scope)?;
+
+        let (at1, at2, at3) = (at.clone(), at.clone(), at);
+        let snapshot = relation
+            .inner
+            .filter(move |(_tuple, time, _diff)| time.less_than(&at1))
+            .as_collection()
+            .delay(move |_time| at2.clone())
+            .consolidate();
+
+        shutdown_when_complete(&snapshot, at3, shutdown_handle);
+
+        Ok(snapshot)
+    }
+
+    /// Handles a Materialize request. The materializing dataflow's
+    /// shutdown handle is kept under `materialized_name(name)`.
+    pub fn materialize<S: Scope<Timestamp = T>>(
+        &mut self,
+        req: Materialize,
+        scope: &mut S,
+    ) -> Result<(), Error>
+    where
+        T: Serialize,
+    {
+        let Materialize { name, path } = req;
+        let (relation, shutdown_handle) = self.implement_relation(&name, scope)?;
+
+        materialize(&relation, &path);
+
+        self.shutdown_handles
+            .insert(materialized_name(&name), shutdown_handle);
+        self.materializations.insert(name, path);
+
+        Ok(())
+    }
+
+    /// Serves a Snapshot request for a materialized relation from
+    /// disk. Returns None while the materialized view hasn't caught
+    /// up with `at` yet. Otherwise the snapshot reflects exactly the
+    /// changes at times before `at`, and its tuples are placed at
+    /// `at`, unless the view has been compacted beyond `at` (see
+    /// `MaterializedView::snapshot`).
+    pub fn materialized_snapshot(
+        &self,
+        name: &str,
+        at: &T,
+    ) -> Result<Option<Vec<ResultDiff<T>>>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let path = match self.materializations.get(name) {
+            None => {
+                return Err(Error {
+                    category: ErrorKind::NotFound,
+                    message: format!("Relation {} is not materialized.", name),
+                });
+            }
+            Some(path) => path,
+        };
+
+        match read_materialized::<T>(path)? {
+            None => Ok(None),
+            Some(view) => Ok(view.snapshot(at)),
+        }
+    }
+
+    /// Handles a QueryAt request. Returns the consolidated contents
+    /// of the named relation as of the specified time, all placed at
+    /// that time. This requires history to be enabled and the
+    /// relation's trace not to have been compacted beyond `time`.
+    pub fn query_at<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        time: T,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        if !self.config.enable_history {
+            return Err(Error {
+                category: ErrorKind::Unsupported,
+                message: "Historical queries require history to be enabled.".to_string(),
+            });
+        }
+
+        let relation = match self.context.global_arrangement(name) {
+            None => Err(Error {
+                category: ErrorKind::NotFound,
+                message: format!("No history available for relation {}.", name),
+            }),
+            Some(trace) => {
+                let frontier: Vec<T> = trace.advance_frontier().to_vec();
+
+                if !frontier.iter().any(|t| t.less_equal(&time)) {
+                    Err(Error {
+                        category: ErrorKind::NotFound,
+                        message: format!(
+                            "Relation {} has been compacted up to {:?}, data as of {:?} is no longer available.",
+                            name, frontier, time
+                        ),
+                    })
+                } else {
+                    let (arranged, shutdown_button) = trace.import_core(scope, name);
+
+                    let (at1, at2, at3) = (time.clone(), time.clone(), time);
+                    let relation = arranged
+                        .as_collection(|tuple, _| tuple.clone())
+                        .inner
+                        .filter(move |(_tuple, time, _diff)| time.less_equal(&at1))
+                        .as_collection()
+                        .delay(move |_time| at2.clone())
+                        .consolidate();
+
+                    shutdown_when_complete(
+                        &relation,
+                        at3,
+                        ShutdownHandle::from_button(shutdown_button),
+                    );
 
                     Ok(relation)
                 }
             }
-        }
+        }?;
+
+        Ok(self.resolve_symbols(relation))
+    }
+
+    /// Handles a GraphQl request. Returns the flat pull paths
+    /// matching the query, to be nested by the consumer.
+    pub fn graphql<S: Scope<Timestamp = T>>(
+        &mut self,
+        name: &str,
+        query: GraphQl,
+        scope: &mut S,
+    ) -> Result<Collection<S, Vec<Value>, isize>, Error> {
+        use crate::{Relation, VariableMap};
+
+        // Arguments are matched against stored values, which might
+        // have been interned.
+        let mut plan = Plan::Pull(Pull {
+            variables: vec![],
+            paths: query.paths()?,
+        });
+        self.intern_constants(&mut plan);
+
+        let context = &mut self.context;
+        let (paths, shutdown_handle) =
+            scope.iterative::<u64, _, _>(|nested| -> Result<_, Error> {
+                let (relation, shutdown_handle) =
+                    plan.implement(nested, &VariableMap::new(), context)?;
+
+                Ok((relation.tuples().leave(), shutdown_handle))
+            })?;
+
+        self.shutdown_handles
+            .insert(name.to_string(), shutdown_handle);
+
+        Ok(self.resolve_symbols(paths))
     }
 
     /// Handle a Register request.
     pub fn register(&mut self, req: Register) -> Result<(), Error> {
         let Register { rules, .. } = req;
 
-        for rule in rules.into_iter() {
+        for mut rule in rules.into_iter() {
             if self.context.rules.contains_key(&rule.name) {
                 // @TODO panic if hashes don't match
                 // panic!("Attempted to re-register a named relation");
                 continue;
             } else {
+                rule.plan.validate()?;
+
+                self.intern_constants(&mut rule.plan);
+
                 if self.config.enable_meta {
                     let mut data = rule.plan.datafy();
                     let tx_data: Vec<TxData> =
@@ -323,21 +1563,535 @@ where
         Ok(())
     }
 
-    /// Handle an AdvanceDomain request.
+    /// Registers the given rule and computes its consolidated
+    /// contents as of the current frontier, stepping the worker until
+    /// they are complete. All results are placed at the frontier,
+    /// reflecting everything that happened before it. The dataflow
+    /// is shut down afterwards. Each worker only sees its share of
+    /// the results.
+    ///
+    /// The rule is unregistered again once its results are
+    /// collected, s.t. repeated queries don't pile up rules. If a
+    /// rule of the same name is registered already, that rule is
+    /// queried instead and kept around.
+    ///
+    /// Meant for embedding, where there are no clients to express
+    /// interest in a rule.
+    pub fn query<A: Allocate>(
+        &mut self,
+        worker: &mut Worker<A>,
+        rule: Rule,
+    ) -> Result<Vec<ResultDiff<T>>, Error> {
+        let name = rule.name.clone();
+        let temporary = !self.context.rules.contains_key(&name);
+
+        self.register(Register {
+            rules: vec![rule],
+            publish: vec![],
+        })?;
+
+        let results = self.collect(worker, &name);
+
+        if temporary {
+            self.unregister(&name)?;
+        }
+
+        results
+    }
+
+    /// Computes the consolidated contents of the named rule for
+    /// `query`.
+    fn collect<A: Allocate>(
+        &mut self,
+        worker: &mut Worker<A>,
+        name: &str,
+    ) -> Result<Vec<ResultDiff<T>>, Error> {
+        let frontier = self.frontier();
+        let results = Rc::new(RefCell::new(Vec::new()));
+        let mut probe = ProbeHandle::new();
+
+        let shutdown_handle = worker.dataflow::<T, _, _>(|scope| {
+            let (relation, shutdown_handle) = self.implement_relation(name, scope)?;
+
+            let results = results.clone();
+            let frontier = frontier.clone();
+            relation
+                .inspect(move |(tuple, time, diff)| {
+                    if time.less_than(&frontier) {
+                        results.borrow_mut().push((tuple.clone(), *diff));
+                    }
+                })
+                .probe_with(&mut probe);
+
+            Ok(shutdown_handle)
+        })?;
+
+        worker.step_while(|| probe.less_than(&frontier));
+
+        shutdown_handle.shutdown();
+
+        let mut consolidated = BTreeMap::new();
+        for (tuple, diff) in results.borrow_mut().drain(..) {
+            *consolidated.entry(tuple).or_insert(0) += diff;
+        }
+
+        Ok(consolidated
+            .into_iter()
+            .filter(|(_tuple, diff)| *diff != 0)
+            .map(|(tuple, diff)| (tuple, frontier.clone(), diff))
+            .collect())
+    }
+
+    /// Removes a rule registered by `query`, together with its
+    /// implementation and, if meta queries are enabled, the datoms
+    /// describing it.
+    fn unregister(&mut self, name: &str) -> Result<(), Error> {
+        self.implementations.remove(name);
+        self.context.internal.unregister_arrangement(name);
+
+        if let Some(rule) = self.context.rules.remove(name) {
+            if self.config.enable_meta {
+                let mut data = rule.plan.datafy();
+                let tx_data: Vec<TxData> = data
+                    .drain(..)
+                    .map(|(e, a, v)| TxData(-1, e, a, v))
+                    .collect();
+
+                self.transact(tx_data, 0, 0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a SetIterationLimit request.
+    pub fn set_iteration_limit(&mut self, name: &str, limit: u64) -> Result<(), Error> {
+        if !self.context.rules.contains_key(name) {
+            Err(Error {
+                category: ErrorKind::NotFound,
+                message: format!("Unknown rule {}.", name),
+            })
+        } else if limit == 0 {
+            Err(Error {
+                category: ErrorKind::Incorrect,
+                message: format!("Rule {} must be allowed at least one iteration.", name),
+            })
+        } else {
+            // The compaction policy is decided once the relation is
+            // registered.
+            self.context
+                .internal
+                .relations
+                .entry(name.to_string())
+                .or_insert_with(|| RelationConfig {
+                    compaction: CompactionPolicy::None,
+                    iteration_limit: None,
+                })
+                .iteration_limit = Some(limit);
+
+            Ok(())
+        }
+    }
+
+    /// Returns the names of all rules that exceeded their iteration
+    /// limit on this worker since the last call. These have to be
+    /// failed via `fail_rule` on all workers.
+    pub fn take_exceeded(&mut self) -> Vec<String> {
+        let mut exceeded: Vec<String> = self
+            .context
+            .iterations
+            .borrow_mut()
+            .exceeded
+            .drain()
+            .collect();
+        exceeded.sort();
+
+        exceeded
+    }
+
+    /// Handle a FailRule request. Drops all interests in relations
+    /// depending on the named rule, shutting down their dataflows.
+    /// Returns the names of these relations, along with the clients
+    /// that were interested in them.
+    pub fn fail_rule(&mut self, name: &str) -> Vec<(String, HashSet<Token>)> {
+        let mut failing: Vec<String> = self
+            .interests
+            .keys()
+            .filter(|interest| {
+                collect_dependencies(&self.context, &[interest.as_str()])
+                    .map(|rules| rules.iter().any(|rule| rule.name == name))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+        failing.sort();
+
+        let mut failed = Vec::with_capacity(failing.len());
+        for interest in failing.into_iter() {
+            if let Some(tokens) = self.interests.remove(&interest) {
+                self.uninterest(&interest);
+                failed.push((interest, tokens));
+            }
+        }
+
+        failed
+    }
+
+    /// Handle a SetRuleSemantics request.
+    pub fn set_rule_semantics(
+        &mut self,
+        name: &str,
+        semantics: RuleSemantics,
+    ) -> Result<(), Error> {
+        if !self.context.rules.contains_key(name) {
+            Err(Error {
+                category: ErrorKind::NotFound,
+                message: format!("Unknown rule {}.", name),
+            })
+        } else {
+            self.context
+                .rule_semantics
+                .insert(name.to_string(), semantics);
+            Ok(())
+        }
+    }
+
+    /// Handle an AllocateEids request, returning the half-open range
+    /// of entity ids allocated. As all workers process requests in
+    /// the same order, they agree on the ids handed out. Ids from
+    /// 2^63 upwards are placeholders and never handed out.
+    pub fn allocate_eids(&mut self, count: u64) -> Result<(Eid, Eid), Error> {
+        if count == 0 {
+            return Err(Error {
+                category: ErrorKind::Incorrect,
+                message: "At least one entity id must be requested.".to_string(),
+            });
+        }
+
+        match self
+            .next_eid
+            .checked_add(count)
+            .filter(|end| !is_tempid(*end - 1))
+        {
+            None => Err(Error {
+                category: ErrorKind::Fault,
+                message: "Entity ids exhausted.".to_string(),
+            }),
+            Some(end) => {
+                let start = self.next_eid;
+                self.next_eid = end;
+
+                Ok((start, end))
+            }
+        }
+    }
+
+    /// Handle an AdvanceDomain request. With meta queries enabled,
+    /// advancing the internal domain also asserts its new time, and
+    /// the probe frontier as of the advance, on the `META_NOW` and
+    /// `META_FRONTIER` attributes of `META_DOMAIN`.
     pub fn advance_domain(&mut self, name: Option<String>, next: T) -> Result<(), Error> {
         match name {
-            None => self.context.internal.advance_to(next),
-            Some(_) => Err(Error {
-                category: "df.error.category/unsupported",
-                message: "Named domains are not yet supported.".to_string(),
-            }),
+            None => {
+                self.context.internal.advance_to(next.clone())?;
+
+                if self.config.enable_meta {
+                    let now: Time = next.into();
+                    let mut tx_data = vec![TxData(
+                        1,
+                        META_DOMAIN,
+                        Aid::from(META_NOW),
+                        Value::from(now),
+                    )];
+
+                    let frontier = self
+                        .probe
+                        .with_frontier(|frontier| frontier.iter().min().cloned());
+
+                    if let Some(frontier) = frontier {
+                        let frontier: Time = frontier.into();
+                        tx_data.push(TxData(
+                            1,
+                            META_DOMAIN,
+                            Aid::from(META_FRONTIER),
+                            Value::from(frontier),
+                        ));
+                    }
+
+                    self.transact(tx_data, 0, 0)?;
+                }
+
+                Ok(())
+            }
+            Some(name) => match self.context.domains.get_mut(&name) {
+                None => Err(Error {
+                    category: ErrorKind::NotFound,
+                    message: format!("Domain {} does not exist.", name),
+                }),
+                Some(domain) => domain.advance_to(next),
+            },
+        }
+    }
+
+    /// Returns the least time across the internal and all named
+    /// domains. Queries spanning multiple domains can only produce
+    /// complete results up to this time.
+    pub fn frontier(&self) -> T {
+        let mut frontier = self.context.internal.time().clone();
+        for domain in self.context.domains.values() {
+            if domain.time().less_than(&frontier) {
+                frontier = domain.time().clone();
+            }
+        }
+
+        frontier
+    }
+
+    /// Handle a Shutdown request. Closes all inputs and shuts down
+    /// all dataflows that were created on behalf of interests.
+    pub fn shutdown(&mut self) -> Result<(), Error> {
+        self.context.internal.close_inputs()?;
+        for domain in self.context.domains.values_mut() {
+            domain.close_inputs()?;
+        }
+
+        for (_name, shutdown_handle) in self.shutdown_handles.drain() {
+            shutdown_handle.shutdown();
+        }
+
+        self.release_implementations();
+        self.interests.clear();
+
+        Ok(())
+    }
+
+    /// Handle a Status request.
+    pub fn status(&self) -> Status<T> {
+        let domain = &self.context.internal;
+
+        let domains: Vec<&Domain<T>> = std::iter::once(domain)
+            .chain(self.context.domains.values())
+            .collect();
+
+        let attributes: BTreeMap<Aid, AttributeConfig> = domains
+            .iter()
+            .flat_map(|domain| domain.attributes.iter())
+            .map(|(aid, config)| (aid.clone(), config.clone()))
+            .collect();
+
+        let mut sources: Vec<Aid> = domains
+            .iter()
+            .flat_map(|domain| domain.forward.keys())
+            .filter(|aid| !attributes.contains_key(*aid))
+            .cloned()
+            .collect();
+        sources.sort();
+
+        let mut rules: Vec<String> = self.context.rules.keys().cloned().collect();
+        rules.sort();
+
+        let mut interests: Vec<String> = self
+            .interests
+            .iter()
+            .filter(|(_name, tokens)| !tokens.is_empty())
+            .map(|(name, _tokens)| name.clone())
+            .collect();
+        interests.sort();
+
+        let mut sinks: Vec<String> = domains
+            .iter()
+            .flat_map(|domain| domain.sinks.keys())
+            .cloned()
+            .collect();
+        sinks.sort();
+
+        Status {
+            attributes,
+            sources,
+            rules,
+            interests,
+            sinks,
+            time: domain.time().clone(),
+            domains: self
+                .context
+                .domains
+                .iter()
+                .map(|(name, domain)| (name.clone(), domain.time().clone()))
+                .collect(),
         }
     }
 
+    /// Handle a ListRules request.
+    pub fn list_rules(&self) -> RuleGraph {
+        let mut rules: Vec<Rule> = self.context.rules.values().cloned().collect();
+        rules.sort_by(|x, y| x.name.cmp(&y.name));
+
+        let mut edges = Vec::new();
+        for rule in rules.iter() {
+            let dependencies = rule.plan.dependencies();
+
+            let mut names: Vec<String> = dependencies.names.into_iter().collect();
+            names.sort();
+
+            let mut attributes: Vec<Aid> = dependencies.attributes.into_iter().collect();
+            attributes.sort();
+
+            for name in names.drain(..) {
+                edges.push((
+                    rule.name.clone(),
+                    Aid::from("df.rule/depends-on"),
+                    Value::String(name),
+                ));
+            }
+
+            for aid in attributes.drain(..) {
+                edges.push((
+                    rule.name.clone(),
+                    Aid::from("df.rule/attribute"),
+                    Value::Aid(aid),
+                ));
+            }
+        }
+
+        RuleGraph { rules, edges }
+    }
+
+    /// Handle a Dependencies request.
+    pub fn dependencies(&self, name: &str) -> Result<DependencyClosure, Error> {
+        let rules = collect_dependencies(&self.context, &[name])?;
+
+        let mut attributes: Vec<Aid> = rules
+            .iter()
+            .flat_map(|rule| rule.plan.dependencies().attributes)
+            .collect::<HashSet<Aid>>()
+            .into_iter()
+            .collect();
+        attributes.sort();
+
+        Ok(DependencyClosure { rules, attributes })
+    }
+
+    /// Handle an Explain request.
+    pub fn explain(&self, plan: &Plan) -> Explain {
+        plan.explain(&self.context)
+    }
+
+    /// Handle a Metrics request.
+    pub fn metrics(&self) -> Metrics<T> {
+        let mut rules: Vec<&String> = self.context.rules.keys().collect();
+        rules.sort();
+
+        let mut datoms = Vec::new();
+        let iterations = self.context.iterations.borrow();
+        for name in rules.into_iter() {
+            let interests = self
+                .interests
+                .get(name)
+                .map(|tokens| tokens.len())
+                .unwrap_or(0);
+
+            datoms.push((
+                name.clone(),
+                Aid::from("df.metrics/interests"),
+                Value::Number(interests as i64),
+            ));
+
+            if let Some(reached) = iterations.reached.get(name) {
+                datoms.push((
+                    name.clone(),
+                    Aid::from("df.metrics/iterations"),
+                    Value::Number(*reached as i64),
+                ));
+            }
+        }
+
+        datoms.push((
+            "df.domain/internal".to_string(),
+            Aid::from("df.metrics/transacted"),
+            Value::Number(self.context.internal.transacted() as i64),
+        ));
+
+        let mut domains: Vec<(&String, &Domain<T>)> = self.context.domains.iter().collect();
+        domains.sort_by(|x, y| x.0.cmp(y.0));
+
+        for (name, domain) in domains.into_iter() {
+            datoms.push((
+                name.clone(),
+                Aid::from("df.metrics/transacted"),
+                Value::Number(domain.transacted() as i64),
+            ));
+        }
+
+        Metrics {
+            time: self.frontier(),
+            frontier: self.probe.with_frontier(|frontier| frontier.to_vec()),
+            datoms,
+        }
+    }
+
+    /// Handle a Statistics request. Counts are read from the count
+    /// traces of the attributes and only cover this worker's shard.
+    /// With meta queries enabled, they are also asserted on the
+    /// `META_ENTITIES` and `META_DATOMS` attributes of an entity per
+    /// attribute, which is named via `META_IDENT`. Only the first
+    /// worker's statistics are asserted.
+    pub fn statistics(&mut self) -> Result<Statistics<T>, Error> {
+        let mut statistics = self.context.internal.statistics();
+        for domain in self.context.domains.values_mut() {
+            statistics.extend(domain.statistics());
+        }
+        statistics.sort();
+
+        if self.config.enable_meta {
+            let mut tx_data = Vec::with_capacity(3 * statistics.len());
+            for (aid, entities, datoms) in statistics.iter() {
+                // Entities are derived from attribute names, s.t.
+                // repeated requests update the same entities.
+                let eid = aid.hashed().as_u64();
+
+                tx_data.push(TxData(
+                    1,
+                    eid,
+                    Aid::from(META_IDENT),
+                    Value::Aid(aid.clone()),
+                ));
+                tx_data.push(TxData(
+                    1,
+                    eid,
+                    Aid::from(META_ENTITIES),
+                    Value::Number(*entities as i64),
+                ));
+                tx_data.push(TxData(
+                    1,
+                    eid,
+                    Aid::from(META_DATOMS),
+                    Value::Number(*datoms as i64),
+                ));
+            }
+
+            self.transact(tx_data, 0, 0)?;
+        }
+
+        let mut datoms = Vec::with_capacity(2 * statistics.len());
+        for (aid, entities, count) in statistics.into_iter() {
+            datoms.push((
+                aid.clone(),
+                Aid::from(META_ENTITIES),
+                Value::Number(entities as i64),
+            ));
+            datoms.push((aid, Aid::from(META_DATOMS), Value::Number(count as i64)));
+        }
+
+        Ok(Statistics {
+            time: self.frontier(),
+            datoms,
+        })
+    }
+
     /// Returns true iff the probe is behind any input handle. Mostly
     /// used as a convenience method during testing.
     pub fn is_any_outdated(&self) -> bool {
-        if self.probe.less_than(self.context.internal.time()) {
+        if self.probe.less_than(&self.frontier()) {
             return true;
         }
 
@@ -435,3 +2189,36 @@ impl<Token: Hash> Server<Duration, Token> {
         Ok(())
     }
 }
+
+/// Returns the name under which debugging output for the named
+/// relation is published.
+pub fn debug_name(name: &str) -> String {
+    format!("df.debug/{}", name)
+}
+
+/// Returns the name under which the dataflow materializing the named
+/// relation is kept.
+pub fn materialized_name(name: &str) -> String {
+    format!("df.materialized/{}", name)
+}
+
+/// Holds on to the shutdown handle of a one-off query until its
+/// results are complete as of `at`, then shuts its dataflow down.
+fn shutdown_when_complete<S, T>(
+    results: &Collection<S, Vec<Value>, isize>,
+    at: T,
+    shutdown_handle: ShutdownHandle,
+) where
+    S: Scope<Timestamp = T>,
+    T: Timestamp + Lattice + TotalOrder,
+{
+    let mut shutdown_handle = Some(shutdown_handle);
+    results.inner.sink(Pipeline, "QueryShutdown", move |input| {
+        input.for_each(|_time, _data| {});
+
+        if !input.frontier().less_equal(&at) {
+            // Dropping the handle presses all of its buttons.
+            shutdown_handle.take();
+        }
+    });
+}
@@ -0,0 +1,76 @@
+//! Interning of attribute identifiers in value position.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{Aid, Value};
+
+#[derive(Default)]
+struct Table {
+    ids: HashMap<Aid, u32>,
+    names: Vec<Aid>,
+}
+
+/// An append-only table of attribute identifiers appearing as
+/// values. Interned identifiers are stored as `Value::Symbol`s, s.t.
+/// repeated occurrences don't each carry a string of their own.
+///
+/// Clones share the same table, which allows symbols to be resolved
+/// from within dataflows. Workers must intern the same identifiers,
+/// in the same order, to agree on their symbols.
+#[derive(Clone, Default)]
+pub struct Symbols {
+    table: Rc<RefCell<Table>>,
+}
+
+impl Symbols {
+    /// Replaces an attribute identifier by its symbol, allocating a
+    /// new one if it hasn't been seen before. Other values are
+    /// returned as they are.
+    pub fn intern(&self, value: Value) -> Value {
+        match value {
+            Value::Aid(aid) => {
+                let mut table = self.table.borrow_mut();
+                let next = table.names.len() as u32;
+                let symbol = match table.ids.get(&aid) {
+                    Some(symbol) => *symbol,
+                    None => {
+                        table.ids.insert(aid.clone(), next);
+                        table.names.push(aid);
+                        next
+                    }
+                };
+
+                Value::Symbol(symbol)
+            }
+            other => other,
+        }
+    }
+
+    /// Replaces a symbol by the attribute identifier it stands
+    /// for, also within lists. Other values, as well as unknown
+    /// symbols, are returned as they are.
+    pub fn resolve(&self, value: Value) -> Value {
+        match value {
+            Value::Symbol(symbol) => match self.table.borrow().names.get(symbol as usize) {
+                None => Value::Symbol(symbol),
+                Some(aid) => Value::Aid(aid.clone()),
+            },
+            Value::List(values) => {
+                Value::List(values.into_iter().map(|x| self.resolve(x)).collect())
+            }
+            other => other,
+        }
+    }
+
+    /// Returns the number of interned attribute identifiers.
+    pub fn len(&self) -> usize {
+        self.table.borrow().names.len()
+    }
+
+    /// Returns true iff no attribute identifiers have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
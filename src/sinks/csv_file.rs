@@ -5,7 +5,7 @@ use timely::dataflow::operators::Operator;
 use timely::dataflow::{Scope, Stream};
 
 use super::Sinkable;
-use crate::{Error, ResultDiff};
+use crate::{Error, ErrorKind, ResultDiff};
 
 /// A local filesystem data sink.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
@@ -32,7 +32,7 @@ impl Sinkable<u64> for CsvFile {
 
         match writer_result {
             Err(error) => Err(Error {
-                category: "df.error.category/fault",
+                category: ErrorKind::Fault,
                 message: format!("Failed to create writer: {}", error),
             }),
             Ok(mut writer) => {
@@ -0,0 +1,272 @@
+//! Operator and utilities to keep the contents of a relation
+//! materialized on disk.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::Operator;
+use timely::dataflow::Scope;
+use timely::order::TotalOrder;
+use timely::progress::Timestamp;
+
+use differential_dataflow::collection::Collection;
+use differential_dataflow::lattice::Lattice;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Error, ErrorKind, ResultDiff, Value};
+
+/// Minimum number of logged updates before a materialized view is
+/// compacted.
+const COMPACTION_THRESHOLD: usize = 1024;
+
+/// A single line of the on-disk representation of a materialized
+/// relation. Each file starts with a base, followed by any number of
+/// batches appended to it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Record<T> {
+    /// The consolidated contents as of `since`, i.e. reflecting all
+    /// changes at times not in advance of it.
+    Base {
+        /// Frontier the contents have been compacted to.
+        since: Vec<T>,
+        /// Tuples with non-zero counts, sorted.
+        tuples: Vec<(Vec<Value>, isize)>,
+    },
+    /// Changes at times in advance of the previous upper, but not in
+    /// advance of this one.
+    Batch {
+        /// Frontier up to which changes are complete.
+        upper: Vec<T>,
+        /// Changes along with their times.
+        updates: Vec<ResultDiff<T>>,
+    },
+}
+
+/// A materialized relation as read from disk.
+#[derive(Clone, Debug)]
+pub struct MaterializedView<T> {
+    /// Frontier the base has been compacted to.
+    pub since: Vec<T>,
+    /// Changes at times not in advance of the frontier are reflected
+    /// in the view, all others aren't.
+    pub frontier: Vec<T>,
+    /// Consolidated contents as of `since`.
+    pub tuples: Vec<(Vec<Value>, isize)>,
+    /// Changes since `since`, with their times.
+    pub updates: Vec<ResultDiff<T>>,
+}
+
+impl<T> MaterializedView<T>
+where
+    T: Timestamp + Lattice + TotalOrder,
+{
+    /// Returns the consolidated contents of the relation as of `at`,
+    /// i.e. reflecting all changes at times before `at`, or None if
+    /// the view hasn't caught up with `at` yet. Views compacted
+    /// beyond `at` can no longer tell the changes before `at` apart
+    /// from later ones, and are served as of their base instead. The
+    /// returned tuples are placed at the time they are valid as of.
+    pub fn snapshot(&self, at: &T) -> Option<Vec<ResultDiff<T>>> {
+        if self.frontier.iter().any(|t| t.less_than(at)) {
+            return None;
+        }
+
+        let at = match self.since.iter().find(|t| at.less_than(t)) {
+            None => at,
+            Some(since) => since,
+        };
+
+        let mut contents: BTreeMap<&Vec<Value>, isize> = BTreeMap::new();
+        for (tuple, count) in self.tuples.iter() {
+            *contents.entry(tuple).or_insert(0) += count;
+        }
+
+        for (tuple, time, diff) in self.updates.iter() {
+            if time.less_than(at) {
+                *contents.entry(tuple).or_insert(0) += diff;
+            }
+        }
+
+        Some(
+            contents
+                .into_iter()
+                .filter(|(_tuple, count)| *count != 0)
+                .map(|(tuple, count)| (tuple.clone(), at.clone(), count))
+                .collect(),
+        )
+    }
+}
+
+/// Keeps the contents of a relation in a file at the given path.
+/// Whenever the relation's frontier advances, the changes completed
+/// by the advance are appended to the file, along with their times.
+/// Once more changes have been logged than there are tuples in the
+/// relation, the file is replaced atomically by a compacted one. All
+/// tuples are routed to the first worker, which is the only one
+/// writing.
+pub fn materialize<S, T>(results: &Collection<S, Vec<Value>, isize>, path: &str)
+where
+    S: Scope<Timestamp = T>,
+    T: Timestamp + Lattice + TotalOrder + Serialize,
+{
+    let path = path.to_string();
+    let is_writer = results.inner.scope().index() == 0;
+
+    let mut vector = Vec::new();
+    let mut pending: Vec<ResultDiff<T>> = Vec::new();
+    let mut contents: BTreeMap<Vec<Value>, isize> = BTreeMap::new();
+    let mut written: Option<Vec<T>> = None;
+    let mut logged = 0;
+
+    results.inner.sink(
+        Exchange::new(|_| 0),
+        &format!("Materialize({})", &path),
+        move |input| {
+            input.for_each(|_time, data| {
+                data.swap(&mut vector);
+                pending.extend(vector.drain(..));
+            });
+
+            let frontier = input.frontier().frontier().to_vec();
+
+            if !is_writer || written.as_ref() == Some(&frontier) {
+                return;
+            }
+
+            let (complete, incomplete): (Vec<_>, Vec<_>) = pending
+                .drain(..)
+                .partition(|(_tuple, time, _diff)| !input.frontier().less_equal(time));
+
+            pending = incomplete;
+
+            for (tuple, _time, diff) in complete.iter() {
+                let count = contents.entry(tuple.clone()).or_insert(0);
+                *count += diff;
+
+                if *count == 0 {
+                    contents.remove(tuple);
+                }
+            }
+
+            // Empty batches count as well, s.t. frontier advances
+            // alone don't grow the file indefinitely.
+            logged += complete.len().max(1);
+
+            let result = if written.is_none() || logged >= contents.len().max(COMPACTION_THRESHOLD)
+            {
+                let base = Record::Base {
+                    since: frontier.clone(),
+                    tuples: contents
+                        .iter()
+                        .map(|(tuple, count)| (tuple.clone(), *count))
+                        .collect(),
+                };
+
+                logged = 0;
+                replace(&path, &base)
+            } else {
+                let batch = Record::Batch {
+                    upper: frontier.clone(),
+                    updates: complete,
+                };
+
+                append(&path, &batch)
+            };
+
+            match result {
+                Err(error) => {
+                    error!("failed to write materialized view {}: {}", &path, error);
+
+                    // The view is rewritten from scratch next time,
+                    // as it might be missing the changes just lost.
+                    written = None;
+                }
+                Ok(_) => written = Some(frontier),
+            }
+        },
+    );
+}
+
+/// Atomically replaces the file at the given path by one holding
+/// only the given record.
+fn replace<T: Serialize>(path: &str, record: &Record<T>) -> io::Result<()> {
+    let staging = format!("{}.tmp", path);
+    let mut serialized = serde_json::to_vec(record).expect("failed to serialize record");
+    serialized.push(b'\n');
+
+    fs::write(&staging, serialized).and_then(|_| fs::rename(&staging, path))
+}
+
+/// Appends the given record to the file at the given path.
+fn append<T: Serialize>(path: &str, record: &Record<T>) -> io::Result<()> {
+    let mut serialized = serde_json::to_vec(record).expect("failed to serialize record");
+    serialized.push(b'\n');
+
+    fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&serialized))
+}
+
+/// Reads the materialized view at the given path, if it has been
+/// written yet. A trailing line that is still being appended is
+/// ignored.
+pub fn read_materialized<T: DeserializeOwned>(
+    path: &str,
+) -> Result<Option<MaterializedView<T>>, Error> {
+    let serialized = match fs::read_to_string(path) {
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(Error {
+                category: ErrorKind::Fault,
+                message: format!("Failed to read materialized view {}: {}", path, error),
+            });
+        }
+        Ok(serialized) => serialized,
+    };
+
+    // Whatever follows the last newline is still being appended.
+    let mut lines: Vec<&str> = serialized.split('\n').collect();
+    lines.pop();
+
+    let mut view: Option<MaterializedView<T>> = None;
+
+    for line in lines.into_iter() {
+        let record = match serde_json::from_str::<Record<T>>(line) {
+            Err(error) => {
+                return Err(Error {
+                    category: ErrorKind::Fault,
+                    message: format!("Failed to parse materialized view {}: {}", path, error),
+                });
+            }
+            Ok(record) => record,
+        };
+
+        match (record, view.as_mut()) {
+            (Record::Base { since, tuples }, None) => {
+                view = Some(MaterializedView {
+                    frontier: since.clone(),
+                    since,
+                    tuples,
+                    updates: Vec::new(),
+                });
+            }
+            (Record::Batch { upper, mut updates }, Some(view)) => {
+                view.frontier = upper;
+                view.updates.append(&mut updates);
+            }
+            _ => {
+                return Err(Error {
+                    category: ErrorKind::Fault,
+                    message: format!("Materialized view {} is malformed.", path),
+                });
+            }
+        }
+    }
+
+    Ok(view)
+}
@@ -14,9 +14,11 @@ use crate::{Error, ResultDiff};
 
 #[cfg(feature = "csv-source")]
 pub mod csv_file;
+pub mod materialized;
 
 #[cfg(feature = "csv-source")]
 pub use self::csv_file::CsvFile;
+pub use self::materialized::{materialize, read_materialized, MaterializedView, Record};
 
 /// An external system that wants to receive result diffs.
 pub trait Sinkable<T>
@@ -1,6 +1,8 @@
 //! Operator and utilities to source data from csv files.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Take};
 use std::time::{Duration, Instant};
 
 use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
@@ -8,7 +10,7 @@ use timely::dataflow::{Scope, Stream};
 
 use chrono::DateTime;
 
-use crate::sources::Sourceable;
+use crate::sources::{composite_eid, Sourceable};
 use crate::{Aid, Eid, Value};
 
 /// A local filesystem data source.
@@ -26,11 +28,192 @@ pub struct CsvFile {
     pub flexible: bool,
     /// Special column offset for the entity id.
     pub eid_offset: usize,
+    /// Column offsets making up a composite key, from which entity
+    /// ids are derived via `composite_eid`. Takes precedence over
+    /// `eid_offset` if non-empty.
+    #[serde(default)]
+    pub key_offsets: Vec<usize>,
     /// Special column offset for the timestamp.
     pub timestamp_offset: Option<usize>,
     /// Specifies the column offsets and their value types, that
     /// should be introduced.
     pub schema: Vec<(Aid, (usize, Value))>,
+    /// Attributes whose columns pack multiple values into a single
+    /// cell, together with the separator splitting them. Each
+    /// non-empty part is introduced as a separate datom.
+    #[serde(default)]
+    pub multi_valued: BTreeMap<Aid, char>,
+    /// What to do about records that can't be read or parsed.
+    #[serde(default)]
+    pub on_error: OnError,
+}
+
+/// Policies for dealing with malformed records.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub enum OnError {
+    /// Panic, aborting the ingestion.
+    Fail,
+    /// Skip the record and log it.
+    Skip,
+    /// Skip the record and introduce a description of the problem
+    /// into the specified attribute, keyed by the byte offset at
+    /// which the record starts.
+    Quarantine(Aid),
+}
+
+impl Default for OnError {
+    fn default() -> Self {
+        OnError::Fail
+    }
+}
+
+/// Parses a single field into a value of the hinted type.
+fn parse_value(type_hint: &Value, field: &str) -> Result<Value, String> {
+    match type_hint {
+        Value::String(_) => Ok(Value::String(field.to_string())),
+        Value::Number(_) => field
+            .parse::<i64>()
+            .map(Value::Number)
+            .map_err(|_| format!("{:?} is not a number", field)),
+        Value::Eid(_) => field
+            .parse::<Eid>()
+            .map(Value::Eid)
+            .map_err(|_| format!("{:?} is not an eid", field)),
+        _ => panic!("Only String, Number, and Eid are supported at the moment."),
+    }
+}
+
+/// Parses the entity id and all values described by a record, the
+/// latter tagged with the index of the schema entry they belong
+/// to. Nothing is returned if any of the fields is malformed.
+fn parse_record(
+    record: &csv::StringRecord,
+    eid_offset: usize,
+    key_offsets: &[usize],
+    schema: &[(Aid, (usize, Value))],
+    multi_valued: &BTreeMap<Aid, char>,
+) -> Result<(Value, Vec<(usize, Value)>), String> {
+    let field = |offset: usize| {
+        record
+            .get(offset)
+            .ok_or_else(|| format!("missing column {}", offset))
+    };
+
+    let eid = if key_offsets.is_empty() {
+        parse_value(&Value::Eid(0), field(eid_offset)?)?
+    } else {
+        let key = key_offsets
+            .iter()
+            .map(|offset| field(*offset))
+            .collect::<Result<Vec<&str>, String>>()?;
+
+        Value::Eid(composite_eid(key))
+    };
+
+    let mut values = Vec::with_capacity(schema.len());
+
+    for (idx, (aid, (offset, type_hint))) in schema.iter().enumerate() {
+        match multi_valued.get(aid) {
+            None => values.push((idx, parse_value(type_hint, field(*offset)?)?)),
+            Some(separator) => {
+                // Empty cells and trailing separators yield no
+                // values.
+                for part in field(*offset)?
+                    .split(*separator)
+                    .filter(|part| !part.is_empty())
+                {
+                    values.push((idx, parse_value(type_hint, part)?));
+                }
+            }
+        }
+    }
+
+    Ok((eid, values))
+}
+
+/// Moves the given offset forward to the start of the next
+/// record, unless a record begins right there. Alignment looks
+/// for the next line break, which assumes that quoted fields
+/// don't contain any.
+fn align(file: &mut File, offset: u64, data_start: u64) -> u64 {
+    if offset <= data_start {
+        return data_start;
+    }
+
+    file.seek(SeekFrom::Start(offset - 1))
+        .expect("failed to seek");
+
+    let skipped = BufReader::new(&*file)
+        .read_until(b'\n', &mut Vec::new())
+        .expect("read error");
+
+    offset - 1 + (skipped as u64)
+}
+
+impl CsvFile {
+    fn reader_builder(&self, has_headers: bool) -> csv::ReaderBuilder {
+        let mut builder = csv::ReaderBuilder::new();
+        builder
+            .has_headers(has_headers)
+            .delimiter(self.delimiter)
+            .comment(self.comment);
+
+        builder
+    }
+
+    /// Opens a reader over the share of the file assigned to the
+    /// given worker. The file is split into byte ranges of equal
+    /// size, both ends of which are then aligned to record
+    /// boundaries, s.t. every record is read by the worker whose
+    /// range contains its first byte. The reader sees nothing beyond
+    /// the aligned end of the range, regardless of how line endings
+    /// are consumed. Returns the reader, and the offsets at which it
+    /// starts and ends.
+    fn open_range(
+        &self,
+        worker_index: usize,
+        num_workers: usize,
+    ) -> (csv::Reader<Take<File>>, u64, u64) {
+        let len = std::fs::metadata(&self.path)
+            .expect("failed to read file metadata")
+            .len();
+
+        // Headers are skipped by everyone.
+        let data_start = if self.has_headers {
+            let mut reader = self
+                .reader_builder(true)
+                .from_path(&self.path)
+                .expect("failed to create reader");
+
+            reader.headers().expect("failed to read headers");
+            reader.position().byte()
+        } else {
+            0
+        };
+
+        let share = (len - data_start) / (num_workers as u64);
+        let start = data_start + share * (worker_index as u64);
+        let end = if worker_index + 1 == num_workers {
+            len
+        } else {
+            start + share
+        };
+
+        let mut file = File::open(&self.path).expect("failed to open file");
+
+        // A record straddling our start belongs to the previous
+        // worker, one straddling our end to us.
+        let offset = align(&mut file, start, data_start);
+        let end = align(&mut file, end, data_start);
+
+        file.seek(SeekFrom::Start(offset)).expect("failed to seek");
+
+        let reader = self
+            .reader_builder(false)
+            .from_reader(file.take(end - offset));
+
+        (reader, offset, end)
+    }
 }
 
 impl Sourceable<Duration> for CsvFile {
@@ -62,44 +245,49 @@ impl Sourceable<Duration> for CsvFile {
             streams.push(stream);
         }
 
+        // Quarantined records go to an additional, final output.
+        if let OnError::Quarantine(_) = self.on_error {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.push(wrapper);
+            streams.push(stream);
+        }
+
         demux.build(move |mut capabilities| {
             let activator = scope.activator_for(&operator_info.address[..]);
 
             let worker_index = scope.index();
             let num_workers = scope.peers();
 
-            let reader = csv::ReaderBuilder::new()
-                .has_headers(self.has_headers)
-                .delimiter(self.delimiter)
-                .comment(self.comment)
-                .from_path(&filename)
-                .expect("failed to create reader");
-
-            let mut iterator = reader.into_records();
+            let (mut reader, start, end) = self.open_range(worker_index, num_workers);
+            let mut record = csv::StringRecord::new();
+            let mut done = false;
 
             let mut num_datums_read = 0;
-            let mut datum_index = 0;
+            let mut num_skipped = 0;
 
             let schema = self.schema.clone();
+            let multi_valued = self.multi_valued.clone();
             let eid_offset = self.eid_offset;
+            let key_offsets = self.key_offsets.clone();
             let timestamp_offset = self.timestamp_offset;
+            let on_error = self.on_error.clone();
 
             move |_frontiers| {
-                if iterator.reader().is_done() {
+                if done {
                     info!(
-                        "[WORKER {}] read {} out of {} datums",
-                        worker_index, num_datums_read, datum_index
+                        "[WORKER {}] read {} datums from bytes {} to {}, skipped {} records",
+                        worker_index, num_datums_read, start, end, num_skipped
                     );
                     capabilities.drain(..);
                 } else {
                     // let mut fuel = 256;
 
-                    let mut handles = Vec::with_capacity(schema.len());
+                    let mut handles = Vec::with_capacity(wrappers.len());
                     for wrapper in wrappers.iter_mut() {
                         handles.push(wrapper.activate());
                     }
 
-                    let mut sessions = Vec::with_capacity(schema.len());
+                    let mut sessions = Vec::with_capacity(wrappers.len());
                     for (idx, handle) in handles.iter_mut().enumerate() {
                         sessions.push(handle.session(capabilities.get(idx).unwrap()));
                     }
@@ -108,50 +296,80 @@ impl Sourceable<Duration> for CsvFile {
 
                     info!("Ingesting at {:?}", time);
 
-                    while let Some(result) = iterator.next() {
-                        let record = result.expect("read error");
-
-                        if datum_index % num_workers == worker_index {
-                            let eid =
-                                Value::Eid(record[eid_offset].parse::<Eid>().expect("not a eid"));
-                            // let time = match timestamp_offset {
-                            //     None => Default::default(),
-                            //     Some(timestamp_offset) => {
-                            //         let epoch =
-                            //             DateTime::parse_from_rfc3339(&record[timestamp_offset])
-                            //                 .expect("not a valid rfc3339 datetime")
-                            //                 .timestamp();
-
-                            //         if epoch >= 0 {
-                            //             epoch as u64
-                            //         } else {
-                            //             panic!("invalid epoch");
-                            //         }
-                            //     }
-                            // };
-
-                            for (idx, (_aid, (offset, type_hint))) in schema.iter().enumerate() {
-                                let v = match type_hint {
-                                    Value::String(_) => Value::String(record[*offset].to_string()),
-                                    Value::Number(_) => Value::Number(
-                                        record[*offset].parse::<i64>().expect("not a number"),
-                                    ),
-                                    Value::Eid(_) => Value::Eid(
-                                        record[*offset].parse::<Eid>().expect("not a eid"),
-                                    ),
-                                    _ => panic!(
-                                        "Only String, Number, and Eid are supported at the moment."
-                                    ),
-                                };
-
-                                let tuple = (eid.clone(), v);
-                                sessions.get_mut(idx).unwrap().give((tuple, time, 1));
+                    while !done {
+                        let position = start + reader.position().byte();
+
+                        let parsed = match reader.read_record(&mut record) {
+                            Err(ref error) if error.is_io_error() => {
+                                panic!("read error: {}", error)
                             }
+                            Err(error) => Some(Err(error.to_string())),
+                            Ok(false) => None,
+                            Ok(true) => Some(parse_record(
+                                &record,
+                                eid_offset,
+                                &key_offsets,
+                                &schema,
+                                &multi_valued,
+                            )),
+                        };
 
-                            num_datums_read += 1;
-                        }
+                        match parsed {
+                            None => {
+                                done = true;
+                            }
+                            Some(Ok((eid, values))) => {
+                                // let time = match timestamp_offset {
+                                //     None => Default::default(),
+                                //     Some(timestamp_offset) => {
+                                //         let epoch =
+                                //             DateTime::parse_from_rfc3339(&record[timestamp_offset])
+                                //                 .expect("not a valid rfc3339 datetime")
+                                //                 .timestamp();
+
+                                //         if epoch >= 0 {
+                                //             epoch as u64
+                                //         } else {
+                                //             panic!("invalid epoch");
+                                //         }
+                                //     }
+                                // };
 
-                        datum_index += 1;
+                                for (idx, v) in values {
+                                    let tuple = (eid.clone(), v);
+                                    sessions.get_mut(idx).unwrap().give((tuple, time, 1));
+                                }
+
+                                num_datums_read += 1;
+                            }
+                            Some(Err(error)) => {
+                                let message = format!("byte {}: {}", position, error);
+
+                                match on_error {
+                                    OnError::Fail => panic!("malformed record at {}", message),
+                                    OnError::Skip => {
+                                        debug!(
+                                            "[WORKER {}] skipping record at {}",
+                                            worker_index, message
+                                        );
+                                    }
+                                    OnError::Quarantine(_) => {
+                                        let tuple =
+                                            (Value::Number(position as i64), Value::String(error));
+                                        sessions.last_mut().unwrap().give((tuple, time, 1));
+                                    }
+                                }
+
+                                num_skipped += 1;
+
+                                if num_skipped % 1000 == 0 {
+                                    warn!(
+                                        "[WORKER {}] skipped {} malformed records so far",
+                                        worker_index, num_skipped
+                                    );
+                                }
+                            }
+                        }
 
                         // fuel -= 1;
                         // if fuel <= 0 {
@@ -159,10 +377,10 @@ impl Sourceable<Duration> for CsvFile {
                         // }
                     }
 
-                    if iterator.reader().is_done() {
+                    if done {
                         info!(
-                            "[WORKER {}] read {} out of {} datums",
-                            worker_index, num_datums_read, datum_index
+                            "[WORKER {}] read {} datums from bytes {} to {}, skipped {} records",
+                            worker_index, num_datums_read, start, end, num_skipped
                         );
                         capabilities.drain(..);
                     } else {
@@ -175,8 +393,14 @@ impl Sourceable<Duration> for CsvFile {
 
         let mut out = HashMap::new();
         for (idx, stream) in streams.drain(..).enumerate() {
-            let aid = self.schema[idx].0.clone();
-            out.insert(aid.to_string(), stream);
+            let aid = match self.schema.get(idx) {
+                Some((aid, _)) => aid,
+                None => match self.on_error {
+                    OnError::Quarantine(ref aid) => aid,
+                    _ => unreachable!(),
+                },
+            };
+            out.insert(aid.clone(), stream);
         }
 
         out
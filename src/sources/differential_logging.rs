@@ -52,8 +52,8 @@ impl Sourceable<Duration> for DifferentialLogging {
 
         for aid in self.attributes.iter() {
             let (wrapper, stream) = demux.new_output();
-            wrappers.insert(aid.to_string(), wrapper);
-            streams.insert(aid.to_string(), stream);
+            wrappers.insert(aid.clone(), wrapper);
+            streams.insert(aid.clone(), stream);
         }
 
         let mut demux_buffer = Vec::new();
@@ -63,7 +63,7 @@ impl Sourceable<Duration> for DifferentialLogging {
             move |_frontiers| {
                 let mut handles = HashMap::with_capacity(num_interests);
                 for (aid, wrapper) in wrappers.iter_mut() {
-                    handles.insert(aid.to_string(), wrapper.activate());
+                    handles.insert(aid.clone(), wrapper.activate());
                 }
 
                 input.for_each(|time, data| {
@@ -71,7 +71,7 @@ impl Sourceable<Duration> for DifferentialLogging {
 
                     let mut sessions = HashMap::with_capacity(num_interests);
                     for (aid, handle) in handles.iter_mut() {
-                        sessions.insert(aid.to_string(), handle.session(&time));
+                        sessions.insert(aid.clone(), handle.session(&time));
                     }
 
                     for (time, _worker, datum) in demux_buffer.drain(..) {
@@ -0,0 +1,197 @@
+//! Operator and utilities to source data from HTTP endpoints serving
+//! JSON arrays, by periodically polling them.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use crate::sources::Sourceable;
+use crate::{Aid, Eid, Value};
+use Value::{Bool, Number};
+
+/// An HTTP endpoint returning a JSON array of objects, each of which
+/// describes an entity identified by the value of a key field. The
+/// endpoint is polled periodically, and only the differences to the
+/// previous response are introduced.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct HttpPoll {
+    /// URL to poll.
+    pub url: String,
+    /// Time to wait between polls.
+    pub interval: Duration,
+    /// Name of the integer field holding entity ids.
+    pub key_field: String,
+    /// Attributes to ingest.
+    pub attributes: Vec<Aid>,
+}
+
+/// Fetches the endpoint and returns all (aid, e, v) triples it
+/// currently holds.
+fn fetch(
+    url: &str,
+    key_field: &str,
+    attributes: &[Aid],
+) -> Result<HashSet<(Aid, Eid, Value)>, String> {
+    let response: serde_json::Value = reqwest::get(url)
+        .and_then(|mut response| response.json())
+        .map_err(|error| error.to_string())?;
+
+    let objects = response
+        .as_array()
+        .ok_or_else(|| "expected an array of objects".to_string())?;
+
+    let mut datoms = HashSet::new();
+
+    for obj in objects.iter() {
+        let obj_map = obj
+            .as_object()
+            .ok_or_else(|| "expected an array of objects".to_string())?;
+
+        let eid = obj_map
+            .get(key_field)
+            .and_then(|key| key.as_u64())
+            .ok_or_else(|| format!("{} doesn't hold an entity id", key_field))?;
+
+        for aid in attributes.iter() {
+            let v = match obj_map.get(aid.as_str()) {
+                None | Some(serde_json::Value::Null) => continue,
+                Some(serde_json::Value::String(s)) => Value::String(s.to_string()),
+                Some(serde_json::Value::Number(num)) => match num.as_i64() {
+                    None => return Err("only i64 supported at the moment".to_string()),
+                    Some(num) => Number(num),
+                },
+                Some(serde_json::Value::Bool(b)) => Bool(*b),
+                Some(_) => {
+                    return Err(
+                        "only strings, booleans, and i64 types supported at the moment".to_string(),
+                    );
+                }
+            };
+
+            datoms.insert((aid.clone(), eid, v));
+        }
+    }
+
+    Ok(datoms)
+}
+
+impl Sourceable<Duration> for HttpPoll {
+    fn source<S: Scope<Timestamp = Duration>>(
+        &self,
+        scope: &mut S,
+        t0: Instant,
+    ) -> HashMap<Aid, Stream<S, ((Value, Value), Duration, isize)>> {
+        let url = self.url.clone();
+
+        let mut demux = OperatorBuilder::new(format!("HttpPoll({})", url), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        let mut wrappers = HashMap::with_capacity(self.attributes.len());
+        let mut streams = HashMap::with_capacity(self.attributes.len());
+
+        for aid in self.attributes.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.insert(aid.clone(), wrapper);
+            streams.insert(aid.clone(), stream);
+        }
+
+        let scope_handle = scope.clone();
+        let attributes = self.attributes.clone();
+        let key_field = self.key_field.clone();
+        let interval = self.interval;
+
+        demux.build(move |mut capabilities| {
+            let scope = scope_handle;
+
+            // A single worker polls, s.t. the endpoint is hit only
+            // once per interval.
+            let mut cap = if scope.index() == 0 {
+                Some(capabilities.pop().unwrap())
+            } else {
+                None
+            };
+
+            // Requests block, so they are made from a thread of their
+            // own, which hands responses over and schedules the
+            // operator once they arrive. It stops polling once the
+            // operator has been dropped.
+            let (send_responses, responses) = mpsc::channel();
+
+            if cap.is_some() {
+                let activator = scope.sync_activator_for(&operator_info.address[..]);
+                let url = url.clone();
+                let key_field = key_field.clone();
+                let attributes = attributes.clone();
+
+                thread::spawn(move || loop {
+                    let polled_at = Instant::now();
+                    let response = fetch(&url, &key_field, &attributes);
+
+                    if send_responses.send((polled_at, response)).is_err()
+                        || activator.activate().is_err()
+                    {
+                        break;
+                    }
+
+                    thread::sleep(interval);
+                });
+            }
+
+            let mut previous = HashSet::new();
+
+            move |_frontiers| {
+                if cap.is_none() {
+                    return;
+                }
+
+                while let Ok((polled_at, response)) = responses.try_recv() {
+                    match response {
+                        Err(error) => {
+                            error!("Failed to poll {}: {}", url, error);
+                        }
+                        Ok(current) => {
+                            let time = polled_at.duration_since(t0);
+                            cap.as_mut().unwrap().downgrade(&time);
+
+                            let mut handles = HashMap::with_capacity(attributes.len());
+                            for (aid, wrapper) in wrappers.iter_mut() {
+                                handles.insert(aid.clone(), wrapper.activate());
+                            }
+
+                            let cap_ref = cap.as_ref().unwrap();
+                            let mut sessions = HashMap::with_capacity(attributes.len());
+                            for (aid, handle) in handles.iter_mut() {
+                                sessions.insert(aid.clone(), handle.session(&cap_ref));
+                            }
+
+                            for (aid, e, v) in previous.difference(&current) {
+                                sessions.get_mut(aid).unwrap().give((
+                                    (Value::Eid(*e), v.clone()),
+                                    time,
+                                    -1,
+                                ));
+                            }
+
+                            for (aid, e, v) in current.difference(&previous) {
+                                sessions.get_mut(aid).unwrap().give((
+                                    (Value::Eid(*e), v.clone()),
+                                    time,
+                                    1,
+                                ));
+                            }
+
+                            previous = current;
+                        }
+                    }
+                }
+            }
+        });
+
+        streams
+    }
+}
@@ -12,7 +12,7 @@ use timely::dataflow::{Scope, Stream};
 
 // use sources::json_file::flate2::read::GzDecoder;
 
-use crate::sources::Sourceable;
+use crate::sources::{composite_eid, Sourceable};
 use crate::{Aid, Eid, Value};
 use Value::{Bool, Number};
 
@@ -23,6 +23,22 @@ pub struct JsonFile {
     pub path: String,
     /// Attributes to ingest.
     pub attributes: Vec<Aid>,
+    /// Optional field holding the difference of each object, either
+    /// as a number (+1 / -1), or as a boolean indicating a
+    /// retraction. Objects without it are asserted.
+    #[serde(default)]
+    pub diff_field: Option<String>,
+    /// Optional field holding the time of each object, as
+    /// milliseconds since the epoch. Objects are placed at the time
+    /// they are read, if not set. Domains fed from such a file have
+    /// to be advanced in the same terms.
+    #[serde(default)]
+    pub time_field: Option<String>,
+    /// Fields making up a composite key, from which entity ids are
+    /// derived via `composite_eid`. Objects are identified by their
+    /// position in the file, if empty.
+    #[serde(default)]
+    pub key_fields: Vec<String>,
 }
 
 impl Sourceable<Duration> for JsonFile {
@@ -46,12 +62,15 @@ impl Sourceable<Duration> for JsonFile {
 
         for aid in self.attributes.iter() {
             let (wrapper, stream) = demux.new_output();
-            wrappers.insert(aid.to_string(), wrapper);
-            streams.insert(aid.to_string(), stream);
+            wrappers.insert(aid.clone(), wrapper);
+            streams.insert(aid.clone(), stream);
         }
 
         let scope_handle = scope.clone();
         let attributes = self.attributes.clone();
+        let diff_field = self.diff_field.clone();
+        let time_field = self.time_field.clone();
+        let key_fields = self.key_fields.clone();
 
         demux.build(move |mut capabilities| {
 
@@ -75,17 +94,17 @@ impl Sourceable<Duration> for JsonFile {
             move |_frontiers| {
                 let mut handles = HashMap::with_capacity(attributes.len());
                 for (aid, wrapper) in wrappers.iter_mut() {
-                    handles.insert(aid.to_string(), wrapper.activate());
+                    handles.insert(aid.clone(), wrapper.activate());
                 }
 
                 if iterator.peek().is_some() {
                     let cap_ref = cap.as_ref().unwrap();
                     let mut sessions = HashMap::with_capacity(attributes.len());
                     for (aid, handle) in handles.iter_mut() {
-                        sessions.insert(aid.to_string(), handle.session(&cap_ref));
+                        sessions.insert(aid.clone(), handle.session(&cap_ref));
                     }
 
-                    let time = Instant::now().duration_since(t0);
+                    let read_at = Instant::now().duration_since(t0);
 
                     for readline in iterator.by_ref().take(256 - 1) {
                         let line = readline.expect("read error");
@@ -104,8 +123,43 @@ impl Sourceable<Duration> for JsonFile {
                             // otherwise:
                             // for (k, v) in obj.as_object().unwrap() {
 
+                            let time = match time_field.as_ref() {
+                                None => read_at,
+                                Some(field) => match obj_map.get(field).and_then(|time| time.as_u64()) {
+                                    None => panic!("objects must hold their time as a non-negative integer"),
+                                    Some(millis) => Duration::from_millis(millis),
+                                },
+                            };
+
+                            let diff = match diff_field.as_ref().and_then(|field| obj_map.get(field)) {
+                                None => 1,
+                                Some(serde_json::Value::Number(num)) => {
+                                    match num.as_i64() {
+                                        None => panic!("only i64 differences supported"),
+                                        Some(num) => num as isize,
+                                    }
+                                },
+                                Some(serde_json::Value::Bool(deleted)) => if *deleted { -1 } else { 1 },
+                                _ => panic!("differences must be numbers or booleans"),
+                            };
+
+                            let eid = if key_fields.is_empty() {
+                                object_index as Eid
+                            } else {
+                                let key: Vec<String> = key_fields
+                                    .iter()
+                                    .map(|field| match obj_map.get(field) {
+                                        None => panic!("objects must hold all key fields"),
+                                        Some(serde_json::Value::String(ref s)) => s.to_string(),
+                                        Some(other) => other.to_string(),
+                                    })
+                                    .collect();
+
+                                composite_eid(key.iter().map(|part| part.as_str()))
+                            };
+
                             for aid in attributes.iter() {
-                                match obj_map.get(aid) {
+                                match obj_map.get(aid.as_str()) {
                                     None => {}
                                     Some(json_value) => {
                                         let v = match *json_value {
@@ -120,11 +174,11 @@ impl Sourceable<Duration> for JsonFile {
                                             _ => panic!("only strings, booleans, and i64 types supported at the moment"),
                                         };
 
-                                        let tuple = (Value::Eid(object_index as Eid), v);
+                                        let tuple = (Value::Eid(eid), v);
 
                                         sessions.get_mut(aid)
                                             .unwrap()
-                                            .give((tuple, time, 1));
+                                            .give((tuple, time, diff));
                                     }
                                 }
                             }
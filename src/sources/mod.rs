@@ -9,17 +9,51 @@ use timely::progress::Timestamp;
 
 use differential_dataflow::lattice::Lattice;
 
-use crate::{Aid, Value};
+use crate::{Aid, Eid, Value};
 
 #[cfg(feature = "csv-source")]
 pub mod csv_file;
 pub mod differential_logging;
+#[cfg(feature = "http-source")]
+pub mod http_poll;
 pub mod json_file;
+#[cfg(feature = "parquet-source")]
+pub mod parquet;
 pub mod timely_logging;
 
 #[cfg(feature = "csv-source")]
-pub use self::csv_file::CsvFile;
+pub use self::csv_file::{CsvFile, OnError};
+#[cfg(feature = "http-source")]
+pub use self::http_poll::HttpPoll;
 pub use self::json_file::JsonFile;
+#[cfg(feature = "parquet-source")]
+pub use self::parquet::ParquetFile;
+
+/// Derives an entity id from the parts of a composite key, by hashing
+/// them with FNV-1a. Unlike the standard library's hashers, this
+/// yields the same id on every worker and across builds. The highest
+/// bit is cleared, s.t. derived ids are never mistaken for tempids.
+pub fn composite_eid<'a, I: IntoIterator<Item = &'a str>>(parts: I) -> Eid {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+
+    for part in parts {
+        // Prefixing each part with its length keeps e.g. ("ab", "c")
+        // and ("a", "bc") apart.
+        feed(&(part.len() as u64).to_le_bytes());
+        feed(part.as_bytes());
+    }
+
+    hash & (u64::max_value() >> 1)
+}
 
 /// An external data source that can provide Datoms.
 pub trait Sourceable<T>
@@ -47,6 +81,12 @@ pub enum Source {
     CsvFile(CsvFile),
     /// Files containing json objects
     JsonFile(JsonFile),
+    /// Parquet files
+    #[cfg(feature = "parquet-source")]
+    ParquetFile(ParquetFile),
+    /// Periodically polled HTTP endpoints
+    #[cfg(feature = "http-source")]
+    HttpPoll(HttpPoll),
 }
 
 #[cfg(feature = "real-time")]
@@ -61,6 +101,10 @@ impl Sourceable<Duration> for Source {
             Source::DifferentialLogging(ref source) => source.source(scope, t0),
             #[cfg(feature = "csv-source")]
             Source::CsvFile(ref source) => source.source(scope, t0),
+            #[cfg(feature = "parquet-source")]
+            Source::ParquetFile(ref source) => source.source(scope, t0),
+            #[cfg(feature = "http-source")]
+            Source::HttpPoll(ref source) => source.source(scope, t0),
             _ => unimplemented!(),
         }
     }
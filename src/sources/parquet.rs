@@ -0,0 +1,160 @@
+//! Operator and utilities to source data from Parquet files.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::{Scope, Stream};
+
+use ::parquet::file::reader::{FileReader, RowGroupReader, SerializedFileReader};
+use ::parquet::record::Field;
+
+use crate::sources::Sourceable;
+use crate::{Aid, Eid, Value};
+
+/// A local filesystem data source containing Parquet files. Each row
+/// describes an entity, identified by the value of a key column. The
+/// remaining columns are introduced as attributes of the same name.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize)]
+pub struct ParquetFile {
+    /// Path to a file on each workers local filesystem.
+    pub path: String,
+    /// Name of the integer column holding entity ids.
+    pub eid_column: String,
+    /// Columns to ingest as attributes.
+    pub attributes: Vec<Aid>,
+}
+
+/// Converts a Parquet field into a value of the corresponding
+/// type. Nulls have no value.
+fn field_to_value(field: &Field) -> Option<Value> {
+    match *field {
+        Field::Null => None,
+        Field::Bool(x) => Some(Value::Bool(x)),
+        Field::Byte(x) => Some(Value::Number(i64::from(x))),
+        Field::Short(x) => Some(Value::Number(i64::from(x))),
+        Field::Int(x) => Some(Value::Number(i64::from(x))),
+        Field::Long(x) => Some(Value::Number(x)),
+        Field::UByte(x) => Some(Value::Number(i64::from(x))),
+        Field::UShort(x) => Some(Value::Number(i64::from(x))),
+        Field::UInt(x) => Some(Value::Number(i64::from(x))),
+        Field::Str(ref x) => Some(Value::String(x.to_string())),
+        Field::Timestamp(millis) => Some(Value::Instant(millis)),
+        Field::Date(days) => Some(Value::Instant(u64::from(days) * 24 * 60 * 60 * 1000)),
+        _ => panic!(
+            "Only booleans, integers, strings, dates, and timestamps are supported at the moment."
+        ),
+    }
+}
+
+impl Sourceable<Duration> for ParquetFile {
+    fn source<S: Scope<Timestamp = Duration>>(
+        &self,
+        scope: &mut S,
+        t0: Instant,
+    ) -> HashMap<Aid, Stream<S, ((Value, Value), Duration, isize)>> {
+        let filename = self.path.clone();
+
+        let mut demux = OperatorBuilder::new(format!("ParquetFile({})", filename), scope.clone());
+        let operator_info = demux.operator_info();
+        demux.set_notify(false);
+
+        let mut wrappers = HashMap::with_capacity(self.attributes.len());
+        let mut streams = HashMap::with_capacity(self.attributes.len());
+
+        for aid in self.attributes.iter() {
+            let (wrapper, stream) = demux.new_output();
+            wrappers.insert(aid.clone(), wrapper);
+            streams.insert(aid.clone(), stream);
+        }
+
+        let scope_handle = scope.clone();
+        let attributes = self.attributes.clone();
+        let eid_column = self.eid_column.clone();
+
+        demux.build(move |mut capabilities| {
+            let scope = scope_handle;
+            let activator = scope.activator_for(&operator_info.address[..]);
+
+            let mut cap = Some(capabilities.pop().unwrap());
+
+            let worker_index = scope.index();
+            let num_workers = scope.peers();
+
+            let file = File::open(&filename).expect("failed to open file");
+            let reader = SerializedFileReader::new(file).expect("failed to create reader");
+
+            // Row groups are divided among workers round-robin.
+            let mut row_groups =
+                (0..reader.num_row_groups()).filter(move |i| i % num_workers == worker_index);
+
+            let mut num_rows_read = 0;
+
+            move |_frontiers| {
+                let mut handles = HashMap::with_capacity(attributes.len());
+                for (aid, wrapper) in wrappers.iter_mut() {
+                    handles.insert(aid.clone(), wrapper.activate());
+                }
+
+                // We read one row group per activation.
+                match row_groups.next() {
+                    None => {
+                        if cap.take().is_some() {
+                            info!("[WORKER {}] read {} rows", worker_index, num_rows_read);
+                        }
+                    }
+                    Some(i) => {
+                        let cap_ref = cap.as_ref().unwrap();
+                        let mut sessions = HashMap::with_capacity(attributes.len());
+                        for (aid, handle) in handles.iter_mut() {
+                            sessions.insert(aid.clone(), handle.session(&cap_ref));
+                        }
+
+                        let time = Instant::now().duration_since(t0);
+
+                        let row_group = reader.get_row_group(i).expect("failed to read row group");
+                        let rows = row_group.get_row_iter(None).expect("failed to read rows");
+
+                        for row in rows {
+                            let mut eid = None;
+                            let mut values = Vec::with_capacity(attributes.len());
+
+                            for (column, field) in row.get_column_iter() {
+                                if *column == eid_column {
+                                    eid = match *field {
+                                        Field::Int(x) if x >= 0 => Some(x as Eid),
+                                        Field::Long(x) if x >= 0 => Some(x as Eid),
+                                        Field::UInt(x) => Some(Eid::from(x)),
+                                        Field::ULong(x) => Some(x),
+                                        _ => panic!("{} doesn't hold entity ids", eid_column),
+                                    };
+                                } else if attributes.iter().any(|aid| aid == column.as_str()) {
+                                    if let Some(v) = field_to_value(field) {
+                                        values.push((column.to_string(), v));
+                                    }
+                                }
+                            }
+
+                            let eid = Value::Eid(eid.expect("missing entity id"));
+
+                            for (aid, v) in values.drain(..) {
+                                sessions.get_mut(aid.as_str()).unwrap().give((
+                                    (eid.clone(), v),
+                                    time,
+                                    1,
+                                ));
+                            }
+
+                            num_rows_read += 1;
+                        }
+
+                        activator.activate();
+                    }
+                }
+            }
+        });
+
+        streams
+    }
+}
@@ -0,0 +1,532 @@
+//! Trace storage that can spill to disk.
+//!
+//! Arrangements built via the `StoredKeySpine` and `StoredValSpine`
+//! traces keep each batch either in memory or in a file, as decided
+//! by the `Storage` in effect when the arrangement was created (see
+//! `with_storage`). Batches spilled to disk are read back in only
+//! while a cursor or a merge is using them.
+//!
+//! All stored traces share a single type regardless of their storage,
+//! s.t. indices of attributes kept on disk can be used wherever those
+//! of attributes kept in memory can.
+
+use std::cell::{Cell, OnceCell};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Deserialize;
+
+use differential_dataflow::difference::Semigroup;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::arrange::TraceAgent;
+use differential_dataflow::trace::description::Description;
+use differential_dataflow::trace::implementations::ord::{OrdKeyBatch, OrdValBatch};
+use differential_dataflow::trace::implementations::spine_fueled::Spine;
+use differential_dataflow::trace::{Batch, BatchReader, Batcher, Builder, Cursor, Merger};
+use differential_dataflow::ExchangeData;
+
+/// Where the batches of an arranged trace are kept.
+#[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Storage {
+    /// All batches are kept in memory.
+    Memory,
+    /// Batches holding more than `resident` updates are spilled to a
+    /// file in the temporary directory, smaller ones are kept in
+    /// memory. Spilled batches are read back in while in use, which
+    /// trades throughput for a bounded memory footprint.
+    Disk {
+        /// Maximum number of updates in batches kept in memory.
+        resident: usize,
+    },
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Storage::Memory
+    }
+}
+
+thread_local! {
+    /// Storage for the batches of traces created on this thread.
+    static STORAGE: Cell<Storage> = Cell::new(Storage::Memory);
+}
+
+/// Counter for unique names of spill files within this process.
+static SPILLED: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs `build`, keeping the batches of all stored traces it creates
+/// in the given storage. Traces created elsewhere keep theirs in
+/// memory.
+pub fn with_storage<F: FnOnce() -> X, X>(storage: Storage, build: F) -> X {
+    let previous = STORAGE.with(|current| current.replace(storage));
+    let built = build();
+    STORAGE.with(|current| current.set(previous));
+
+    built
+}
+
+/// A trace of values indexed by self, with stored batches.
+pub type StoredKeySpine<K, T, R> = Spine<K, (), T, R, StoredBatch<OrdKeyBatch<K, T, R>, T>>;
+
+/// A trace of (K, V) pairs indexed by key, with stored batches.
+pub type StoredValSpine<K, V, T, R> = Spine<K, V, T, R, StoredBatch<OrdValBatch<K, V, T, R>, T>>;
+
+/// A handle to a trace of values indexed by self, with stored batches.
+pub type StoredKeyHandle<K, T, R> = TraceAgent<K, (), T, R, StoredKeySpine<K, T, R>>;
+
+/// A handle to a trace of (K, V) pairs indexed by key, with stored
+/// batches.
+pub type StoredValHandle<K, V, T, R> = TraceAgent<K, V, T, R, StoredValSpine<K, V, T, R>>;
+
+/// The updates of a batch, written to a file.
+struct Spill<T> {
+    /// Location of the file, removed along with the spill.
+    path: PathBuf,
+    /// The open file.
+    file: File,
+    /// Description of the spilled batch.
+    description: Description<T>,
+    /// Number of updates in the spilled batch.
+    len: usize,
+}
+
+impl<T> Drop for Spill<T> {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.path) {
+            warn!("failed to remove {}: {}", self.path.display(), error);
+        }
+    }
+}
+
+impl<T: ExchangeData + Lattice> Spill<T> {
+    /// Writes all updates of the given batch to a new file.
+    fn write<K, V, R, B>(batch: &B) -> io::Result<Self>
+    where
+        K: ExchangeData,
+        V: ExchangeData,
+        R: ExchangeData + Semigroup,
+        B: BatchReader<K, V, T, R>,
+    {
+        let path = env::temp_dir().join(format!(
+            "declarative-dataflow-{}-{}.spill",
+            process::id(),
+            SPILLED.fetch_add(1, Ordering::SeqCst)
+        ));
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        let mut spill = Spill {
+            path,
+            file,
+            description: batch.description().clone(),
+            len: 0,
+        };
+
+        let mut len = 0;
+        let mut written = Ok(());
+        {
+            let mut writer = BufWriter::new(&spill.file);
+            let mut cursor = batch.cursor();
+
+            while cursor.key_valid(batch) {
+                while cursor.val_valid(batch) {
+                    let key = cursor.key(batch);
+                    let val = cursor.val(batch);
+
+                    cursor.map_times(batch, |time, diff| {
+                        if written.is_ok() {
+                            written =
+                                rmp_serde::encode::write(&mut writer, &(key, val, time, diff));
+                            len += 1;
+                        }
+                    });
+
+                    cursor.step_val(batch);
+                }
+
+                cursor.step_key(batch);
+            }
+
+            written.map_err(|error| io::Error::new(io::ErrorKind::Other, error.to_string()))?;
+            writer.flush()?;
+        }
+
+        spill.len = len;
+
+        Ok(spill)
+    }
+
+    /// Reads the spilled batch back in.
+    fn read<K, V, R, B>(&self) -> B
+    where
+        K: ExchangeData,
+        V: ExchangeData,
+        R: ExchangeData + Semigroup,
+        B: Batch<K, V, T, R>,
+    {
+        (&self.file)
+            .seek(SeekFrom::Start(0))
+            .expect("failed to rewind spilled batch");
+
+        let mut deserializer = rmp_serde::Deserializer::new(BufReader::new(&self.file));
+        let mut builder = <B::Builder as Builder<K, V, T, R, B>>::with_capacity(self.len);
+
+        for _ in 0..self.len {
+            let update: (K, V, T, R) =
+                Deserialize::deserialize(&mut deserializer).unwrap_or_else(|error| {
+                    panic!("failed to read {}: {}", self.path.display(), error)
+                });
+
+            builder.push(update);
+        }
+
+        builder.done(
+            self.description.lower(),
+            self.description.upper(),
+            self.description.since(),
+        )
+    }
+}
+
+/// Where the contents of a batch are.
+enum Contents<B, T> {
+    /// In memory.
+    Resident(Rc<B>),
+    /// In a file, along with the batch as read from there by this
+    /// handle, if it has been in use.
+    Spilled(Rc<Spill<T>>, OnceCell<Rc<B>>),
+}
+
+/// A batch kept in memory or on disk.
+pub struct StoredBatch<B, T> {
+    /// Storage for batches merged from this one.
+    storage: Storage,
+    contents: Contents<B, T>,
+}
+
+impl<B, T> Clone for StoredBatch<B, T> {
+    /// Clones are read back in independently of the original, s.t.
+    /// a spilled batch is only held in memory while some clone of it
+    /// is in use, e.g. as the storage of a cursor.
+    fn clone(&self) -> Self {
+        let contents = match self.contents {
+            Contents::Resident(ref batch) => Contents::Resident(batch.clone()),
+            Contents::Spilled(ref spill, _) => Contents::Spilled(spill.clone(), OnceCell::new()),
+        };
+
+        StoredBatch {
+            storage: self.storage,
+            contents,
+        }
+    }
+}
+
+impl<B, T> StoredBatch<B, T>
+where
+    T: ExchangeData + Lattice,
+{
+    /// Keeps the given batch in the given storage. Batches that
+    /// can't be spilled are kept in memory.
+    fn new<K, V, R>(storage: Storage, batch: B) -> Self
+    where
+        K: ExchangeData,
+        V: ExchangeData,
+        R: ExchangeData + Semigroup,
+        B: BatchReader<K, V, T, R>,
+    {
+        let contents = match storage {
+            Storage::Disk { resident } if batch.len() > resident => {
+                match Spill::write::<K, V, R, B>(&batch) {
+                    Ok(spill) => Contents::Spilled(Rc::new(spill), OnceCell::new()),
+                    Err(error) => {
+                        error!("failed to spill batch: {}", error);
+                        Contents::Resident(Rc::new(batch))
+                    }
+                }
+            }
+            _ => Contents::Resident(Rc::new(batch)),
+        };
+
+        StoredBatch { storage, contents }
+    }
+
+    /// Returns the batch, reading it back in if necessary.
+    fn batch<K, V, R>(&self) -> &B
+    where
+        K: ExchangeData,
+        V: ExchangeData,
+        R: ExchangeData + Semigroup,
+        B: Batch<K, V, T, R>,
+    {
+        match self.contents {
+            Contents::Resident(ref batch) => &**batch,
+            Contents::Spilled(ref spill, ref read) => {
+                &**read.get_or_init(|| Rc::new(spill.read::<K, V, R, B>()))
+            }
+        }
+    }
+}
+
+impl<K, V, T, R, B> BatchReader<K, V, T, R> for StoredBatch<B, T>
+where
+    K: ExchangeData,
+    V: ExchangeData,
+    T: ExchangeData + Lattice,
+    R: ExchangeData + Semigroup,
+    B: Batch<K, V, T, R>,
+{
+    type Cursor = StoredCursor<K, V, T, R, B>;
+
+    fn cursor(&self) -> Self::Cursor {
+        StoredCursor {
+            phantom: PhantomData,
+            cursor: OnceCell::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.contents {
+            Contents::Resident(ref batch) => (&**batch).len(),
+            Contents::Spilled(ref spill, _) => spill.len,
+        }
+    }
+
+    fn description(&self) -> &Description<T> {
+        match self.contents {
+            Contents::Resident(ref batch) => (&**batch).description(),
+            Contents::Spilled(ref spill, _) => &spill.description,
+        }
+    }
+}
+
+/// A cursor over a stored batch. The batch is only read back in
+/// once the cursor is first used, which is why the cursor proper is
+/// created lazily as well.
+pub struct StoredCursor<K, V, T, R, B: BatchReader<K, V, T, R>> {
+    phantom: PhantomData<(K, V, T, R)>,
+    cursor: OnceCell<B::Cursor>,
+}
+
+impl<K, V, T, R, B> StoredCursor<K, V, T, R, B>
+where
+    B: BatchReader<K, V, T, R>,
+{
+    fn cursor(&self, batch: &B) -> &B::Cursor {
+        self.cursor.get_or_init(|| batch.cursor())
+    }
+
+    fn cursor_mut(&mut self, batch: &B) -> &mut B::Cursor {
+        self.cursor.get_or_init(|| batch.cursor());
+        self.cursor.get_mut().unwrap()
+    }
+}
+
+impl<K, V, T, R, B> Cursor<K, V, T, R> for StoredCursor<K, V, T, R, B>
+where
+    K: ExchangeData,
+    V: ExchangeData,
+    T: ExchangeData + Lattice,
+    R: ExchangeData + Semigroup,
+    B: Batch<K, V, T, R>,
+{
+    type Storage = StoredBatch<B, T>;
+
+    #[inline]
+    fn key_valid(&self, storage: &Self::Storage) -> bool {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor(batch).key_valid(batch)
+    }
+    #[inline]
+    fn val_valid(&self, storage: &Self::Storage) -> bool {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor(batch).val_valid(batch)
+    }
+
+    #[inline]
+    fn key<'a>(&self, storage: &'a Self::Storage) -> &'a K {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor(batch).key(batch)
+    }
+    #[inline]
+    fn val<'a>(&self, storage: &'a Self::Storage) -> &'a V {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor(batch).val(batch)
+    }
+
+    #[inline]
+    fn map_times<L: FnMut(&T, &R)>(&mut self, storage: &Self::Storage, logic: L) {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor_mut(batch).map_times(batch, logic)
+    }
+
+    #[inline]
+    fn step_key(&mut self, storage: &Self::Storage) {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor_mut(batch).step_key(batch)
+    }
+    #[inline]
+    fn seek_key(&mut self, storage: &Self::Storage, key: &K) {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor_mut(batch).seek_key(batch, key)
+    }
+
+    #[inline]
+    fn step_val(&mut self, storage: &Self::Storage) {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor_mut(batch).step_val(batch)
+    }
+    #[inline]
+    fn seek_val(&mut self, storage: &Self::Storage, val: &V) {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor_mut(batch).seek_val(batch, val)
+    }
+
+    #[inline]
+    fn rewind_keys(&mut self, storage: &Self::Storage) {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor_mut(batch).rewind_keys(batch)
+    }
+    #[inline]
+    fn rewind_vals(&mut self, storage: &Self::Storage) {
+        let batch = storage.batch::<K, V, R>();
+        self.cursor_mut(batch).rewind_vals(batch)
+    }
+}
+
+impl<K, V, T, R, B> Batch<K, V, T, R> for StoredBatch<B, T>
+where
+    K: ExchangeData,
+    V: ExchangeData,
+    T: ExchangeData + Lattice,
+    R: ExchangeData + Semigroup,
+    B: Batch<K, V, T, R>,
+{
+    type Batcher = StoredBatcher<K, V, T, R, B>;
+    type Builder = StoredBuilder<K, V, T, R, B>;
+    type Merger = StoredMerger<K, V, T, R, B>;
+
+    fn begin_merge(&self, other: &Self) -> Self::Merger {
+        // Merges involving spilled batches are spilled as well.
+        let storage = match self.storage {
+            Storage::Memory => other.storage,
+            Storage::Disk { .. } => self.storage,
+        };
+
+        StoredMerger {
+            storage,
+            merger: self
+                .batch::<K, V, R>()
+                .begin_merge(other.batch::<K, V, R>()),
+        }
+    }
+}
+
+/// Wrapper to provide batcher behavior to `StoredBatch`.
+pub struct StoredBatcher<K, V, T, R, B: Batch<K, V, T, R>> {
+    storage: Storage,
+    batcher: B::Batcher,
+}
+
+impl<K, V, T, R, B> Batcher<K, V, T, R, StoredBatch<B, T>> for StoredBatcher<K, V, T, R, B>
+where
+    K: ExchangeData,
+    V: ExchangeData,
+    T: ExchangeData + Lattice,
+    R: ExchangeData + Semigroup,
+    B: Batch<K, V, T, R>,
+{
+    /// Batchers are created along with the arrangement they feed,
+    /// which is how the storage in effect then reaches its batches.
+    fn new() -> Self {
+        StoredBatcher {
+            storage: STORAGE.with(|current| current.get()),
+            batcher: <B::Batcher as Batcher<K, V, T, R, B>>::new(),
+        }
+    }
+    fn push_batch(&mut self, batch: &mut Vec<((K, V), T, R)>) {
+        self.batcher.push_batch(batch)
+    }
+    fn seal(&mut self, upper: &[T]) -> StoredBatch<B, T> {
+        StoredBatch::new::<K, V, R>(self.storage, self.batcher.seal(upper))
+    }
+    fn frontier(&mut self) -> &[T] {
+        self.batcher.frontier()
+    }
+}
+
+/// Wrapper to provide builder behavior to `StoredBatch`.
+pub struct StoredBuilder<K, V, T, R, B: Batch<K, V, T, R>> {
+    storage: Storage,
+    builder: B::Builder,
+}
+
+impl<K, V, T, R, B> Builder<K, V, T, R, StoredBatch<B, T>> for StoredBuilder<K, V, T, R, B>
+where
+    K: ExchangeData,
+    V: ExchangeData,
+    T: ExchangeData + Lattice,
+    R: ExchangeData + Semigroup,
+    B: Batch<K, V, T, R>,
+{
+    fn new() -> Self {
+        StoredBuilder {
+            storage: STORAGE.with(|current| current.get()),
+            builder: <B::Builder as Builder<K, V, T, R, B>>::new(),
+        }
+    }
+    fn with_capacity(cap: usize) -> Self {
+        StoredBuilder {
+            storage: STORAGE.with(|current| current.get()),
+            builder: <B::Builder as Builder<K, V, T, R, B>>::with_capacity(cap),
+        }
+    }
+    fn push(&mut self, element: (K, V, T, R)) {
+        self.builder.push(element)
+    }
+    fn done(self, lower: &[T], upper: &[T], since: &[T]) -> StoredBatch<B, T> {
+        StoredBatch::new::<K, V, R>(self.storage, self.builder.done(lower, upper, since))
+    }
+}
+
+/// Wrapper to provide merger behavior to `StoredBatch`.
+pub struct StoredMerger<K, V, T, R, B: Batch<K, V, T, R>> {
+    storage: Storage,
+    merger: B::Merger,
+}
+
+impl<K, V, T, R, B> Merger<K, V, T, R, StoredBatch<B, T>> for StoredMerger<K, V, T, R, B>
+where
+    K: ExchangeData,
+    V: ExchangeData,
+    T: ExchangeData + Lattice,
+    R: ExchangeData + Semigroup,
+    B: Batch<K, V, T, R>,
+{
+    fn work(
+        &mut self,
+        source1: &StoredBatch<B, T>,
+        source2: &StoredBatch<B, T>,
+        frontier: &Option<Vec<T>>,
+        fuel: &mut isize,
+    ) {
+        self.merger.work(
+            source1.batch::<K, V, R>(),
+            source2.batch::<K, V, R>(),
+            frontier,
+            fuel,
+        )
+    }
+    fn done(self) -> StoredBatch<B, T> {
+        StoredBatch::new::<K, V, R>(self.storage, self.merger.done())
+    }
+}
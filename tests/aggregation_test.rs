@@ -7,11 +7,11 @@ use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
 use declarative_dataflow::binding::Binding;
-use declarative_dataflow::plan::{Aggregate, AggregationFn, Implementable, Join, Project};
+use declarative_dataflow::plan::{Aggregate, AggregationFn, Implementable, Join, Project, Window};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::{Aid, Value};
-use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, TxData};
-use Value::{Eid, Number, Rational32, String};
+use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, RuleKind, TxData};
+use Value::{Eid, Instant, List, Number, Rational32, String};
 
 use num_rational::Ratio;
 
@@ -59,6 +59,7 @@ fn run_cases(mut cases: Vec<Case>) {
                         scope,
                         Rule {
                             name: "hector".to_string(),
+                            kind: RuleKind::Recursive,
                             plan,
                         },
                     )
@@ -114,12 +115,12 @@ fn run_cases(mut cases: Vec<Case>) {
 fn count() {
     let (e, amount) = (1, 2);
     let data = vec![
-        TxData(1, 1, ":amount".to_string(), Number(5)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 1, ":amount".to_string(), Number(2)),
-        TxData(1, 1, ":amount".to_string(), Number(4)),
-        TxData(1, 1, ":amount".to_string(), Number(6)),
+        TxData(1, 1, ":amount".into(), Number(5)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 1, ":amount".into(), Number(2)),
+        TxData(1, 1, ":amount".into(), Number(4)),
+        TxData(1, 1, ":amount".into(), Number(6)),
     ];
 
     run_cases(vec![
@@ -129,12 +130,13 @@ fn count() {
                 variables: vec![amount],
                 plan: Box::new(Plan::Project(Project {
                     variables: vec![amount],
-                    plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                    plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 })),
                 aggregation_fns: vec![AggregationFn::COUNT],
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(6)], 0, 1)]],
@@ -147,11 +149,12 @@ fn count() {
             description: "[:find ?e (count ?amount) :where [?e :amount ?amount]]",
             plan: Plan::Aggregate(Aggregate {
                 variables: vec![e, amount],
-                plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 aggregation_fns: vec![AggregationFn::COUNT],
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -169,16 +172,45 @@ fn count() {
     ]);
 }
 
+#[test]
+fn count_empty() {
+    let (e, amount) = (1, 2);
+
+    run_cases(vec![Case {
+        description: "[:find (count ?amount) :where [?e :amount ?amount]]",
+        plan: Plan::Aggregate(Aggregate {
+            variables: vec![amount],
+            plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
+            aggregation_fns: vec![AggregationFn::COUNT],
+            key_variables: vec![],
+            aggregation_variables: vec![amount],
+            with_variables: vec![],
+            count_empty: true,
+        }),
+        transactions: vec![
+            vec![],
+            vec![
+                TxData(1, 1, ":amount".into(), Number(5)),
+                TxData(1, 2, ":amount".into(), Number(10)),
+            ],
+        ],
+        expectations: vec![
+            vec![(vec![Number(0)], 0, 1)],
+            vec![(vec![Number(0)], 1, -1), (vec![Number(2)], 1, 1)],
+        ],
+    }]);
+}
+
 #[test]
 fn max() {
     let (e, amount) = (1, 2);
     let data = vec![
-        TxData(1, 1, ":amount".to_string(), Number(5)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 1, ":amount".to_string(), Number(2)),
-        TxData(1, 1, ":amount".to_string(), Number(4)),
-        TxData(1, 1, ":amount".to_string(), Number(6)),
+        TxData(1, 1, ":amount".into(), Number(5)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 1, ":amount".into(), Number(2)),
+        TxData(1, 1, ":amount".into(), Number(4)),
+        TxData(1, 1, ":amount".into(), Number(6)),
     ];
 
     run_cases(vec![
@@ -188,12 +220,13 @@ fn max() {
                 variables: vec![amount],
                 plan: Box::new(Plan::Project(Project {
                     variables: vec![amount],
-                    plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                    plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 })),
                 aggregation_fns: vec![AggregationFn::MAX],
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(10)], 0, 1)]],
@@ -202,11 +235,12 @@ fn max() {
             description: "[:find ?e (max ?amount) :where [?e :amount ?amount]]",
             plan: Plan::Aggregate(Aggregate {
                 variables: vec![e, amount],
-                plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 aggregation_fns: vec![AggregationFn::MAX],
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -221,12 +255,12 @@ fn max() {
 fn min() {
     let (e, amount) = (1, 2);
     let data = vec![
-        TxData(1, 1, ":amount".to_string(), Number(5)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 1, ":amount".to_string(), Number(2)),
-        TxData(1, 1, ":amount".to_string(), Number(4)),
-        TxData(1, 1, ":amount".to_string(), Number(6)),
+        TxData(1, 1, ":amount".into(), Number(5)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 1, ":amount".into(), Number(2)),
+        TxData(1, 1, ":amount".into(), Number(4)),
+        TxData(1, 1, ":amount".into(), Number(6)),
     ];
 
     run_cases(vec![
@@ -236,12 +270,13 @@ fn min() {
                 variables: vec![amount],
                 plan: Box::new(Plan::Project(Project {
                     variables: vec![amount],
-                    plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                    plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 })),
                 aggregation_fns: vec![AggregationFn::MIN],
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(2)], 0, 1)]],
@@ -250,11 +285,12 @@ fn min() {
             description: "[:find ?e (min ?amount) :where [?e :amount ?amount]]",
             plan: Plan::Aggregate(Aggregate {
                 variables: vec![e, amount],
-                plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 aggregation_fns: vec![AggregationFn::MIN],
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -269,12 +305,12 @@ fn min() {
 fn sum() {
     let (e, amount) = (1, 2);
     let data = vec![
-        TxData(1, 1, ":amount".to_string(), Number(5)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 1, ":amount".to_string(), Number(2)),
-        TxData(1, 1, ":amount".to_string(), Number(4)),
-        TxData(1, 1, ":amount".to_string(), Number(6)),
+        TxData(1, 1, ":amount".into(), Number(5)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 1, ":amount".into(), Number(2)),
+        TxData(1, 1, ":amount".into(), Number(4)),
+        TxData(1, 1, ":amount".into(), Number(6)),
     ];
 
     run_cases(vec![
@@ -284,12 +320,13 @@ fn sum() {
                 variables: vec![amount],
                 plan: Box::new(Plan::Project(Project {
                     variables: vec![amount],
-                    plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                    plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 })),
                 aggregation_fns: vec![AggregationFn::SUM],
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(37)], 0, 1)]],
@@ -302,11 +339,12 @@ fn sum() {
             description: "[:find ?e (sum ?amount) :where [?e :amount ?amount]]",
             plan: Plan::Aggregate(Aggregate {
                 variables: vec![e, amount],
-                plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 aggregation_fns: vec![AggregationFn::SUM],
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -328,12 +366,12 @@ fn sum() {
 fn avg() {
     let (e, amount) = (1, 2);
     let data = vec![
-        TxData(1, 1, ":amount".to_string(), Number(5)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 1, ":amount".to_string(), Number(2)),
-        TxData(1, 1, ":amount".to_string(), Number(4)),
-        TxData(1, 1, ":amount".to_string(), Number(6)),
+        TxData(1, 1, ":amount".into(), Number(5)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 1, ":amount".into(), Number(2)),
+        TxData(1, 1, ":amount".into(), Number(4)),
+        TxData(1, 1, ":amount".into(), Number(6)),
     ];
 
     run_cases(vec![
@@ -343,12 +381,13 @@ fn avg() {
                 variables: vec![amount],
                 plan: Box::new(Plan::Project(Project {
                     variables: vec![amount],
-                    plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                    plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 })),
                 aggregation_fns: vec![AggregationFn::AVG],
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Rational32(Ratio::new(37, 6))], 0, 1)]],
@@ -361,11 +400,12 @@ fn avg() {
             description: "[:find ?e (avg ?amount) :where [?e :amount ?amount]]",
             plan: Plan::Aggregate(Aggregate {
                 variables: vec![e, amount],
-                plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 aggregation_fns: vec![AggregationFn::AVG],
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -387,12 +427,12 @@ fn avg() {
 fn variance() {
     let (e, amount) = (1, 2);
     let data = vec![
-        TxData(1, 1, ":amount".to_string(), Number(5)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 1, ":amount".to_string(), Number(2)),
-        TxData(1, 1, ":amount".to_string(), Number(4)),
-        TxData(1, 1, ":amount".to_string(), Number(6)),
+        TxData(1, 1, ":amount".into(), Number(5)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 1, ":amount".into(), Number(2)),
+        TxData(1, 1, ":amount".into(), Number(4)),
+        TxData(1, 1, ":amount".into(), Number(6)),
     ];
 
     run_cases(vec![
@@ -402,12 +442,13 @@ fn variance() {
                 variables: vec![amount],
                 plan: Box::new(Plan::Project(Project {
                     variables: vec![amount],
-                    plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                    plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 })),
                 aggregation_fns: vec![AggregationFn::VARIANCE],
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Rational32(Ratio::new(317, 36))], 0, 1)]],
@@ -420,11 +461,12 @@ fn variance() {
             description: "[:find ?e (variance ?amount) :where [?e :amount ?amount]]",
             plan: Plan::Aggregate(Aggregate {
                 variables: vec![e, amount],
-                plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 aggregation_fns: vec![AggregationFn::VARIANCE],
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -439,12 +481,12 @@ fn variance() {
 fn median() {
     let (e, amount) = (1, 2);
     let data = vec![
-        TxData(1, 1, ":amount".to_string(), Number(5)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 2, ":amount".to_string(), Number(10)),
-        TxData(1, 1, ":amount".to_string(), Number(2)),
-        TxData(1, 1, ":amount".to_string(), Number(4)),
-        TxData(1, 1, ":amount".to_string(), Number(6)),
+        TxData(1, 1, ":amount".into(), Number(5)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 1, ":amount".into(), Number(2)),
+        TxData(1, 1, ":amount".into(), Number(4)),
+        TxData(1, 1, ":amount".into(), Number(6)),
     ];
 
     run_cases(vec![
@@ -454,12 +496,13 @@ fn median() {
                 variables: vec![amount],
                 plan: Box::new(Plan::Project(Project {
                     variables: vec![amount],
-                    plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                    plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 })),
                 aggregation_fns: vec![AggregationFn::MEDIAN],
                 key_variables: vec![],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Number(5)], 0, 1)]],
@@ -468,11 +511,12 @@ fn median() {
             description: "[:find ?e (median ?amount) :where [?e :amount ?amount]]",
             plan: Plan::Aggregate(Aggregate {
                 variables: vec![e, amount],
-                plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
+                plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
                 aggregation_fns: vec![AggregationFn::MEDIAN],
                 key_variables: vec![e],
                 aggregation_variables: vec![amount],
                 with_variables: vec![],
+                count_empty: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -498,8 +542,8 @@ fn multiple_aggregations() {
                         variables: vec![amount, debt],
                         plan: Box::new(Plan::Join(Join {
                             variables: vec![e],
-                            left_plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
-                            right_plan: Box::new(Plan::MatchA(e, ":debt".to_string(), debt)),
+                            left_plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
+                            right_plan: Box::new(Plan::MatchA(e, ":debt".into(), debt)),
                         })),
                     })),
                     aggregation_fns: vec![
@@ -511,24 +555,25 @@ fn multiple_aggregations() {
                     key_variables: vec![],
                     aggregation_variables: vec![amount, debt, amount, debt],
                     with_variables: vec![],
+                    count_empty: false,
                 })
             },
             transactions: vec![
                 vec![
-                    TxData(1, 1, ":amount".to_string(), Number(5)),
-                    TxData(1, 1, ":amount".to_string(), Number(2)),
-                    TxData(1, 1, ":amount".to_string(), Number(6)),
-                    TxData(1, 1, ":amount".to_string(), Number(9)),
-                    TxData(1, 1, ":amount".to_string(), Number(10)),
-                    TxData(1, 1, ":debt".to_string(), Number(13)),
-                    TxData(1, 1, ":debt".to_string(), Number(4)),
-                    TxData(1, 1, ":debt".to_string(), Number(9)),
-                    TxData(1, 1, ":debt".to_string(), Number(15)),
-                    TxData(1, 1, ":debt".to_string(), Number(10)),
-                    TxData(1, 2, ":amount".to_string(), Number(2)),
-                    TxData(1, 2, ":amount".to_string(), Number(4)),
-                    TxData(1, 2, ":debt".to_string(), Number(5)),
-                    TxData(1, 2, ":debt".to_string(), Number(42)),
+                    TxData(1, 1, ":amount".into(), Number(5)),
+                    TxData(1, 1, ":amount".into(), Number(2)),
+                    TxData(1, 1, ":amount".into(), Number(6)),
+                    TxData(1, 1, ":amount".into(), Number(9)),
+                    TxData(1, 1, ":amount".into(), Number(10)),
+                    TxData(1, 1, ":debt".into(), Number(13)),
+                    TxData(1, 1, ":debt".into(), Number(4)),
+                    TxData(1, 1, ":debt".into(), Number(9)),
+                    TxData(1, 1, ":debt".into(), Number(15)),
+                    TxData(1, 1, ":debt".into(), Number(10)),
+                    TxData(1, 2, ":amount".into(), Number(2)),
+                    TxData(1, 2, ":amount".into(), Number(4)),
+                    TxData(1, 2, ":debt".into(), Number(5)),
+                    TxData(1, 2, ":debt".into(), Number(42)),
                 ],
             ],
             expectations: vec![
@@ -555,8 +600,8 @@ fn multiple_aggregations() {
                         variables: vec![e, amount, debt],
                         plan: Box::new(Plan::Join(Join {
                             variables: vec![e],
-                            left_plan: Box::new(Plan::MatchA(e, ":amount".to_string(), amount)),
-                            right_plan: Box::new(Plan::MatchA(e, ":debt".to_string(), debt)),
+                            left_plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
+                            right_plan: Box::new(Plan::MatchA(e, ":debt".into(), debt)),
                         })),
                     })),
                     aggregation_fns: vec![
@@ -572,24 +617,25 @@ fn multiple_aggregations() {
                     key_variables: vec![e],
                     aggregation_variables: vec![amount, amount, amount, amount, debt, debt, debt, debt],
                     with_variables: vec![],
+                    count_empty: false,
                 })
             },
             transactions: vec![
                 vec![
-                    TxData(1, 1, ":amount".to_string(), Number(5)),
-                    TxData(1, 1, ":amount".to_string(), Number(2)),
-                    TxData(1, 1, ":amount".to_string(), Number(6)),
-                    TxData(1, 1, ":amount".to_string(), Number(9)),
-                    TxData(1, 1, ":amount".to_string(), Number(10)),
-                    TxData(1, 1, ":debt".to_string(), Number(13)),
-                    TxData(1, 1, ":debt".to_string(), Number(4)),
-                    TxData(1, 1, ":debt".to_string(), Number(9)),
-                    TxData(1, 1, ":debt".to_string(), Number(15)),
-                    TxData(1, 1, ":debt".to_string(), Number(10)),
-                    TxData(1, 2, ":amount".to_string(), Number(2)),
-                    TxData(1, 2, ":amount".to_string(), Number(4)),
-                    TxData(1, 2, ":debt".to_string(), Number(5)),
-                    TxData(1, 2, ":debt".to_string(), Number(42)),
+                    TxData(1, 1, ":amount".into(), Number(5)),
+                    TxData(1, 1, ":amount".into(), Number(2)),
+                    TxData(1, 1, ":amount".into(), Number(6)),
+                    TxData(1, 1, ":amount".into(), Number(9)),
+                    TxData(1, 1, ":amount".into(), Number(10)),
+                    TxData(1, 1, ":debt".into(), Number(13)),
+                    TxData(1, 1, ":debt".into(), Number(4)),
+                    TxData(1, 1, ":debt".into(), Number(9)),
+                    TxData(1, 1, ":debt".into(), Number(15)),
+                    TxData(1, 1, ":debt".into(), Number(10)),
+                    TxData(1, 2, ":amount".into(), Number(2)),
+                    TxData(1, 2, ":amount".into(), Number(4)),
+                    TxData(1, 2, ":debt".into(), Number(5)),
+                    TxData(1, 2, ":debt".into(), Number(42)),
                 ],
             ],
             expectations: vec![
@@ -619,26 +665,27 @@ fn multiple_aggregations() {
                         variables: vec![heads, monster],
                         plan: Box::new(Plan::Join(Join {
                             variables: vec![e],
-                            left_plan: Box::new(Plan::MatchA(e, ":monster".to_string(), monster)),
-                            right_plan: Box::new(Plan::MatchA(e, ":heads".to_string(), heads)),
+                            left_plan: Box::new(Plan::MatchA(e, ":monster".into(), monster)),
+                            right_plan: Box::new(Plan::MatchA(e, ":heads".into(), heads)),
                         })),
                     })),
                     aggregation_fns: vec![AggregationFn::SUM],
                     key_variables: vec![],
                     aggregation_variables: vec![heads],
                     with_variables: vec![monster],
+                    count_empty: false,
                 })
             },
             transactions: vec![
                 vec![
-                    TxData(1, 1, ":monster".to_string(), String("Cerberus".to_string())),
-                    TxData(1, 1, ":heads".to_string(), Number(3)),
-                    TxData(1, 2, ":monster".to_string(), String("Medusa".to_string())),
-                    TxData(1, 2, ":heads".to_string(), Number(1)),
-                    TxData(1, 3, ":monster".to_string(), String("Cyclops".to_string())),
-                    TxData(1, 3, ":heads".to_string(), Number(1)),
-                    TxData(1, 4, ":monster".to_string(), String("Chimera".to_string())),
-                    TxData(1, 4, ":heads".to_string(), Number(1)),
+                    TxData(1, 1, ":monster".into(), String("Cerberus".to_string())),
+                    TxData(1, 1, ":heads".into(), Number(3)),
+                    TxData(1, 2, ":monster".into(), String("Medusa".to_string())),
+                    TxData(1, 2, ":heads".into(), Number(1)),
+                    TxData(1, 3, ":monster".into(), String("Cyclops".to_string())),
+                    TxData(1, 3, ":heads".into(), Number(1)),
+                    TxData(1, 4, ":monster".into(), String("Chimera".to_string())),
+                    TxData(1, 4, ":heads".into(), Number(1)),
                 ],
             ],
             expectations: vec![
@@ -647,3 +694,180 @@ fn multiple_aggregations() {
         },
     ]);
 }
+
+#[test]
+fn list() {
+    let (e, amount) = (1, 2);
+    let data = vec![
+        TxData(1, 1, ":amount".into(), Number(5)),
+        TxData(1, 2, ":amount".into(), Number(10)),
+        TxData(1, 1, ":amount".into(), Number(2)),
+        TxData(1, 1, ":amount".into(), Number(4)),
+    ];
+
+    run_cases(vec![Case {
+        description: "[:find ?e (list ?amount) :where [?e :amount ?amount]]",
+        plan: Plan::Aggregate(Aggregate {
+            variables: vec![e, amount],
+            plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
+            aggregation_fns: vec![AggregationFn::LIST],
+            key_variables: vec![e],
+            aggregation_variables: vec![amount],
+            with_variables: vec![],
+            count_empty: false,
+        }),
+        transactions: vec![data],
+        expectations: vec![vec![
+            (
+                vec![Eid(1), List(vec![Number(2), Number(4), Number(5)])],
+                0,
+                1,
+            ),
+            (vec![Eid(2), List(vec![Number(10)])], 0, 1),
+        ]],
+    }]);
+}
+
+#[test]
+fn windows() {
+    let (e, sensor, time, value, window) = (1, 2, 3, 4, 5);
+    let data = vec![
+        TxData(1, 1, ":sensor".into(), String("a".to_string())),
+        TxData(1, 1, ":time".into(), Instant(1000)),
+        TxData(1, 1, ":value".into(), Number(1)),
+        TxData(1, 2, ":sensor".into(), String("a".to_string())),
+        TxData(1, 2, ":time".into(), Instant(1500)),
+        TxData(1, 2, ":value".into(), Number(2)),
+        TxData(1, 3, ":sensor".into(), String("a".to_string())),
+        TxData(1, 3, ":time".into(), Instant(2500)),
+        TxData(1, 3, ":value".into(), Number(4)),
+        TxData(1, 4, ":sensor".into(), String("b".to_string())),
+        TxData(1, 4, ":time".into(), Instant(1200)),
+        TxData(1, 4, ":value".into(), Number(8)),
+    ];
+
+    let readings = Plan::Join(Join {
+        variables: vec![e],
+        left_plan: Box::new(Plan::MatchA(e, ":sensor".into(), sensor)),
+        right_plan: Box::new(Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::MatchA(e, ":time".into(), time)),
+            right_plan: Box::new(Plan::MatchA(e, ":value".into(), value)),
+        })),
+    });
+
+    run_cases(vec![
+        Case {
+            description: "tumbling (sum ?value) per ?sensor over 1s windows of ?time",
+            plan: Plan::Window(Window {
+                variables: vec![sensor, window, value],
+                plan: Box::new(readings.clone()),
+                time_variable: time,
+                window_variable: window,
+                key_variables: vec![sensor],
+                aggregation_fn: AggregationFn::SUM,
+                aggregation_variable: value,
+                with_variables: vec![],
+                size: 1000,
+                slide: None,
+            }),
+            transactions: vec![data.clone()],
+            expectations: vec![vec![
+                (
+                    vec![String("a".to_string()), Instant(1000), Number(3)],
+                    0,
+                    1,
+                ),
+                (
+                    vec![String("a".to_string()), Instant(2000), Number(4)],
+                    0,
+                    1,
+                ),
+                (
+                    vec![String("b".to_string()), Instant(1000), Number(8)],
+                    0,
+                    1,
+                ),
+            ]],
+        },
+        Case {
+            description: "sliding (sum ?value) over 2s windows of ?time every 1s",
+            plan: Plan::Window(Window {
+                variables: vec![window, value],
+                plan: Box::new(readings),
+                time_variable: time,
+                window_variable: window,
+                key_variables: vec![],
+                aggregation_fn: AggregationFn::SUM,
+                aggregation_variable: value,
+                with_variables: vec![],
+                size: 2000,
+                slide: Some(1000),
+            }),
+            transactions: vec![data],
+            expectations: vec![vec![
+                (vec![Instant(0), Number(11)], 0, 1),
+                (vec![Instant(1000), Number(15)], 0, 1),
+                (vec![Instant(2000), Number(4)], 0, 1),
+            ]],
+        },
+    ]);
+}
+
+#[test]
+fn windows_with_duplicate_values() {
+    let (e, time, value, window) = (1, 2, 3, 4);
+    let data = vec![
+        TxData(1, 1, ":time".into(), Instant(1000)),
+        TxData(1, 1, ":value".into(), Number(5)),
+        TxData(1, 2, ":time".into(), Instant(1500)),
+        TxData(1, 2, ":value".into(), Number(5)),
+        TxData(1, 3, ":time".into(), Instant(2500)),
+        TxData(1, 3, ":value".into(), Number(5)),
+        // Not an instant, thus not part of any window.
+        TxData(1, 4, ":time".into(), Number(1200)),
+        TxData(1, 4, ":value".into(), Number(5)),
+    ];
+
+    let readings = Plan::Join(Join {
+        variables: vec![e],
+        left_plan: Box::new(Plan::MatchA(e, ":time".into(), time)),
+        right_plan: Box::new(Plan::MatchA(e, ":value".into(), value)),
+    });
+
+    let windowed = |aggregation_fn| {
+        Plan::Window(Window {
+            variables: vec![window, value],
+            plan: Box::new(readings.clone()),
+            time_variable: time,
+            window_variable: window,
+            key_variables: vec![],
+            aggregation_fn,
+            aggregation_variable: value,
+            with_variables: vec![e],
+            size: 1000,
+            slide: None,
+        })
+    };
+
+    run_cases(vec![
+        Case {
+            description: "tumbling (sum ?value) over 1s windows of ?time :with ?e",
+            plan: windowed(AggregationFn::SUM),
+            transactions: vec![data.clone()],
+            expectations: vec![vec![
+                (vec![Instant(1000), Number(10)], 0, 1),
+                (vec![Instant(2000), Number(5)], 0, 1),
+            ]],
+        },
+        Case {
+            description: "tumbling (count ?value) over 1s windows of ?time :with ?e",
+            plan: windowed(AggregationFn::COUNT),
+            transactions: vec![data],
+            expectations: vec![vec![
+                (vec![Instant(1000), Number(2)], 0, 1),
+                (vec![Instant(2000), Number(1)], 0, 1),
+            ]],
+        },
+    ]);
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{AttributeConfig, CompactionPolicy, ErrorKind, InputSemantics, Time};
+
+#[test]
+fn compaction_frontier() {
+    let uncompacted: CompactionPolicy<Time> = CompactionPolicy::None;
+    assert_eq!(uncompacted.frontier(&10u64), None);
+
+    let slack = CompactionPolicy::Slack(Time::TxId(3));
+    assert_eq!(slack.frontier(&10u64), Some(7));
+
+    let real_slack = CompactionPolicy::Slack(Time::Real(Duration::from_secs(1)));
+    assert_eq!(
+        real_slack.frontier(&Duration::from_secs(5)),
+        Some(Duration::from_secs(4))
+    );
+
+    let keep_since = CompactionPolicy::KeepSince(Time::TxId(5));
+    assert_eq!(keep_since.frontier(&3u64), Some(3));
+    assert_eq!(keep_since.frontier(&10u64), Some(5));
+}
+
+#[test]
+fn set_compaction() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(
+                    ":name",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                    scope,
+                )
+                .unwrap();
+        });
+
+        server.advance_domain(None, 10).unwrap();
+
+        // Retaining more history than was already kept is impossible.
+        for compaction in vec![
+            CompactionPolicy::None,
+            CompactionPolicy::Slack(Time::TxId(5)),
+            CompactionPolicy::KeepSince(Time::TxId(3)),
+        ] {
+            match server.set_compaction(":name", compaction) {
+                Ok(_) => panic!("Expected uncompaction to be rejected."),
+                Err(error) => assert_eq!(error.category, ErrorKind::Conflict),
+            }
+        }
+
+        server
+            .set_compaction(":name", CompactionPolicy::Slack(Time::TxId(0)))
+            .unwrap();
+
+        assert_eq!(
+            server.context.internal.attributes[":name"].compaction,
+            CompactionPolicy::Slack(Time::TxId(0))
+        );
+
+        match server.set_compaction(":unknown", CompactionPolicy::None) {
+            Ok(_) => panic!("Expected unknown attribute to be rejected."),
+            Err(error) => assert_eq!(error.category, ErrorKind::NotFound),
+        }
+    });
+}
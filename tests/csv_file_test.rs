@@ -0,0 +1,261 @@
+#![cfg(feature = "csv-source")]
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::Inspect;
+
+use declarative_dataflow::sources::{composite_eid, CsvFile, OnError, Sourceable};
+use declarative_dataflow::{Aid, Value};
+use Value::{Eid, Number, String};
+
+/// Largest number of workers each file is read with.
+const MAX_PEERS: usize = 8;
+
+/// Writes the given contents to a file in the temporary directory.
+fn write_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "declarative-dataflow-{}-{}.csv",
+        name,
+        std::process::id()
+    ));
+
+    std::fs::write(&path, contents).expect("failed to write file");
+
+    path
+}
+
+/// A source reading names from the second column of the given file.
+fn names(path: &PathBuf, has_headers: bool) -> CsvFile {
+    CsvFile {
+        path: path.to_str().unwrap().to_string(),
+        has_headers,
+        delimiter: b',',
+        comment: None,
+        flexible: false,
+        eid_offset: 0,
+        key_offsets: vec![],
+        timestamp_offset: None,
+        schema: vec![(":name".into(), (1, String("".to_string())))],
+        multi_valued: BTreeMap::new(),
+        on_error: OnError::Fail,
+    }
+}
+
+/// Reads the file with the given number of workers, returning the
+/// datoms introduced by all of them in sorted order.
+fn read(source: &CsvFile, peers: usize) -> Vec<(Aid, Value, Value)> {
+    let source = source.clone();
+    let guards = timely::execute(timely::Configuration::Process(peers), move |worker| {
+        let (send_results, results) = channel();
+
+        worker.dataflow::<Duration, _, _>(|scope| {
+            for (aid, stream) in source.source(scope, Instant::now()) {
+                let send_results = send_results.clone();
+                stream.inspect(move |((e, v), _t, diff)| {
+                    assert_eq!(*diff, 1);
+                    send_results
+                        .send((aid.clone(), e.clone(), v.clone()))
+                        .unwrap();
+                });
+            }
+        });
+
+        while worker.step() {}
+
+        results.try_iter().collect::<Vec<_>>()
+    })
+    .unwrap();
+
+    let mut datoms: Vec<(Aid, Value, Value)> = guards
+        .join()
+        .into_iter()
+        .flat_map(|result| result.unwrap())
+        .collect();
+
+    datoms.sort();
+    datoms
+}
+
+/// Asserts that every worker count reads exactly the expected
+/// datoms, i.e. that every record is read by exactly one worker.
+fn assert_read(source: &CsvFile, mut expected: Vec<(Aid, Value, Value)>) {
+    expected.sort();
+
+    for peers in 1..=MAX_PEERS {
+        assert_eq!(read(source, peers), expected, "with {} workers", peers);
+    }
+}
+
+fn name_datoms(records: &[(u64, &str)]) -> Vec<(Aid, Value, Value)> {
+    records
+        .iter()
+        .map(|(e, name)| (":name".into(), Eid(*e), String(name.to_string())))
+        .collect()
+}
+
+#[test]
+fn worker_ranges() {
+    let records: Vec<(u64, &str)> = (10..22)
+        .zip(vec![
+            "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l",
+        ])
+        .collect();
+
+    // Records are five bytes long, s.t. across worker counts range
+    // boundaries fall on line breaks, right after them, and within
+    // records.
+    let contents: std::string::String = records
+        .iter()
+        .map(|(e, name)| format!("{},{}\n", e, name))
+        .collect();
+
+    // With eight workers, the third range starts exactly on the line
+    // break ending the third record.
+    assert_eq!(contents.as_bytes()[contents.len() / 8 * 2], b'\n');
+
+    let path = write_file("worker-ranges", &contents);
+    assert_read(&names(&path, false), name_datoms(&records));
+}
+
+#[test]
+fn crlf_line_endings() {
+    let records: Vec<(u64, &str)> = (10..22)
+        .zip(vec![
+            "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l",
+        ])
+        .collect();
+
+    let contents: std::string::String = std::iter::once("id,name\r\n".to_string())
+        .chain(
+            records
+                .iter()
+                .map(|(e, name)| format!("{},{}\r\n", e, name)),
+        )
+        .collect();
+
+    let path = write_file("crlf-line-endings", &contents);
+    assert_read(&names(&path, true), name_datoms(&records));
+}
+
+#[test]
+fn headers() {
+    let records: Vec<(u64, &str)> = vec![(10, "Dipper"), (11, "Mabel"), (12, "Soos")];
+
+    let path = write_file("headers", "id,name\n10,Dipper\n11,Mabel\n12,Soos\n");
+    assert_read(&names(&path, true), name_datoms(&records));
+
+    // Without a trailing line break.
+    let path = write_file(
+        "headers-no-newline",
+        "id,name\n10,Dipper\n11,Mabel\n12,Soos",
+    );
+    assert_read(&names(&path, true), name_datoms(&records));
+}
+
+#[test]
+fn fewer_bytes_than_workers() {
+    let path = write_file("fewer-bytes", "1,a\n");
+    assert_read(&names(&path, false), name_datoms(&[(1, "a")]));
+
+    let path = write_file("fewer-bytes-headers", "id,name\n1,a\n");
+    assert_read(&names(&path, true), name_datoms(&[(1, "a")]));
+
+    // Nothing but headers.
+    let path = write_file("only-headers", "id,name\n");
+    assert_read(&names(&path, true), vec![]);
+}
+
+#[test]
+fn multi_valued_cells() {
+    let path = write_file("multi-valued", "1,a|b|\n2,\n3,||c\n4,d\n5,e|e\n");
+
+    let mut multi_valued: BTreeMap<Aid, char> = BTreeMap::new();
+    multi_valued.insert(":tag".into(), '|');
+
+    let source = CsvFile {
+        schema: vec![(":tag".into(), (1, String("".to_string())))],
+        multi_valued,
+        ..names(&path, false)
+    };
+
+    // Empty cells and trailing separators yield no values, repeated
+    // parts yield one datom each.
+    let tag =
+        |e, tag: &str| -> (Aid, Value, Value) { (":tag".into(), Eid(e), String(tag.to_string())) };
+    assert_read(
+        &source,
+        vec![
+            tag(1, "a"),
+            tag(1, "b"),
+            tag(3, "c"),
+            tag(4, "d"),
+            tag(5, "e"),
+            tag(5, "e"),
+        ],
+    );
+}
+
+#[test]
+fn malformed_records() {
+    let path = write_file("malformed", "1,12\n2,x\n3,15\n4,\n5,22\n");
+
+    let ages = |on_error| CsvFile {
+        schema: vec![(":age".into(), (1, Number(0)))],
+        on_error,
+        ..names(&path, false)
+    };
+
+    let age = |e, age| -> (Aid, Value, Value) { (":age".into(), Eid(e), Number(age)) };
+    let valid = vec![age(1, 12), age(3, 15), age(5, 22)];
+
+    assert_read(&ages(OnError::Skip), valid.clone());
+
+    // Problems are keyed by the byte offset at which the record
+    // starts, no matter which worker reads it.
+    let problem = |offset, error: &str| -> (Aid, Value, Value) {
+        (":errors".into(), Number(offset), String(error.to_string()))
+    };
+
+    let mut expected = valid;
+    expected.push(problem(5, "\"x\" is not a number"));
+    expected.push(problem(14, "\"\" is not a number"));
+
+    assert_read(&ages(OnError::Quarantine(":errors".into())), expected);
+}
+
+#[test]
+fn composite_keys() {
+    let path = write_file(
+        "composite-keys",
+        "gravity,falls,Dipper\nmystery,shack,Soos\ngravity,fall,Mabel\ngravity,falls,Stan\n",
+    );
+
+    let source = CsvFile {
+        key_offsets: vec![0, 1],
+        schema: vec![(":name".into(), (2, String("".to_string())))],
+        ..names(&path, false)
+    };
+
+    // Records sharing a key are read by different workers, but are
+    // still assigned the same entity id.
+    let name = |key: &[&str], name: &str| -> (Aid, Value, Value) {
+        (
+            ":name".into(),
+            Eid(composite_eid(key.iter().cloned())),
+            String(name.to_string()),
+        )
+    };
+
+    assert_read(
+        &source,
+        vec![
+            name(&["gravity", "falls"], "Dipper"),
+            name(&["mystery", "shack"], "Soos"),
+            name(&["gravity", "fall"], "Mabel"),
+            name(&["gravity", "falls"], "Stan"),
+        ],
+    );
+}
@@ -0,0 +1,119 @@
+use std::collections::{HashMap, HashSet};
+
+use timely::dataflow::Scope;
+
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::{
+    check_cycles, Aid, CollectionIndex, ErrorKind, ImplContext, Plan, RelationHandle, Rule,
+    RuleKind, Value,
+};
+
+/// A context knowing only about rules, some of which are recursive.
+struct Rules {
+    rules: HashMap<String, Rule>,
+    recursive: HashSet<String>,
+}
+
+impl ImplContext<u64> for Rules {
+    fn rule(&self, name: &str) -> Option<&Rule> {
+        self.rules.get(name)
+    }
+
+    fn global_arrangement(&mut self, _name: &str) -> Option<&mut RelationHandle<u64>> {
+        None
+    }
+
+    fn has_attribute(&self, _name: &str) -> bool {
+        true
+    }
+
+    fn attributes(&self) -> Vec<Aid> {
+        vec![]
+    }
+
+    fn forward_index(&mut self, _name: &str) -> Option<&mut CollectionIndex<Value, Value, u64>> {
+        None
+    }
+
+    fn reverse_index<S: Scope<Timestamp = u64>>(
+        &mut self,
+        _name: &str,
+        _scope: &S,
+    ) -> Option<&mut CollectionIndex<Value, Value, u64>> {
+        None
+    }
+
+    fn is_underconstrained(&self, name: &str) -> bool {
+        self.recursive.contains(name)
+    }
+
+    fn iteration_limit(&self, _name: &str) -> Option<u64> {
+        None
+    }
+}
+
+fn rules(recursive: &[&str]) -> (Rules, Vec<Rule>) {
+    let e = 1;
+    let rule = |name: &str, left: &str, right: &str| Rule {
+        name: name.to_string(),
+        kind: RuleKind::Recursive,
+        plan: Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::NameExpr(vec![e], left.to_string())),
+            right_plan: Box::new(Plan::NameExpr(vec![e], right.to_string())),
+        }),
+    };
+
+    // a -> b -> c -> b, a -> d -> d
+    let all = vec![
+        rule("a", "b", "d"),
+        rule("b", "c", "c"),
+        rule("c", "b", "b"),
+        rule("d", "d", "d"),
+    ];
+
+    let context = Rules {
+        rules: all
+            .iter()
+            .map(|rule| (rule.name.clone(), rule.clone()))
+            .collect(),
+        recursive: recursive.iter().map(|name| name.to_string()).collect(),
+    };
+
+    (context, all)
+}
+
+#[test]
+fn recursive_cycles() {
+    let (context, all) = rules(&["a", "b", "c", "d"]);
+    assert!(check_cycles(&context, &all).is_ok());
+
+    // Cycles among recursive rules are fine, no matter what the
+    // rules outside of them are.
+    let (context, all) = rules(&["b", "c", "d"]);
+    assert!(check_cycles(&context, &all).is_ok());
+}
+
+#[test]
+fn non_recursive_cycles() {
+    let (context, all) = rules(&["b", "d"]);
+    match check_cycles(&context, &all) {
+        Ok(_) => panic!("Expected the cycle between b and c to be rejected."),
+        Err(error) => {
+            assert_eq!(error.category, ErrorKind::Unsupported);
+            assert_eq!(
+                error.message,
+                "Rules b, c depend on each other cyclically, but are not all recursive."
+            );
+        }
+    }
+
+    let (context, all) = rules(&["b", "c"]);
+    match check_cycles(&context, &all) {
+        Ok(_) => panic!("Expected the self-reference of d to be rejected."),
+        Err(error) => assert_eq!(
+            error.message,
+            "Rule d depends on itself, but is not recursive."
+        ),
+    }
+}
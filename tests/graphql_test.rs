@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use declarative_dataflow::plan::{
+    graphql_to_json, order_nested, paths_to_nested, squash_nested, GraphQl,
+};
+use declarative_dataflow::server::{Register, Server};
+use declarative_dataflow::{
+    AttributeConfig, ErrorKind, InputSemantics, Plan, Rule, RuleKind, TxData, Value,
+};
+use InputSemantics::Raw;
+use Value::{Aid, Eid, Number, String};
+
+#[test]
+fn graphql_arguments() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let query = GraphQl {
+            query: "{ hero(name: \"Batman\") { height mass } }".to_string(),
+        };
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for a in ["hero", "name", "height", "mass"].iter() {
+                server
+                    .context
+                    .internal
+                    .create_attribute(a, AttributeConfig::tx_time(Raw), scope)
+                    .unwrap();
+            }
+
+            server
+                .graphql("heroes", query, scope)
+                .unwrap()
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 1, "hero".into(), Eid(100)),
+                    TxData(1, 1, "hero".into(), Eid(200)),
+                    TxData(1, 100, "name".into(), String("Batman".to_string())),
+                    TxData(1, 100, "height".into(), Number(188)),
+                    TxData(1, 100, "mass".into(), Number(95)),
+                    TxData(1, 200, "name".into(), String("Robin".to_string())),
+                    TxData(1, 200, "height".into(), Number(178)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((
+            vec![
+                Eid(1),
+                Aid("hero".into()),
+                Eid(100),
+                Aid("height".into()),
+                Number(188),
+            ],
+            1,
+        ));
+        expected.insert((
+            vec![
+                Eid(1),
+                Aid("hero".into()),
+                Eid(100),
+                Aid("mass".into()),
+                Number(95),
+            ],
+            1,
+        ));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn graphql_rule() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let plan = Plan::GraphQl(GraphQl {
+            query: "{ hero { name } }".to_string(),
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for a in ["hero", "name"].iter() {
+                server
+                    .context
+                    .internal
+                    .create_attribute(a, AttributeConfig::tx_time(Raw), scope)
+                    .unwrap();
+            }
+
+            server
+                .register(Register {
+                    rules: vec![Rule {
+                        name: "heroes".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    }],
+                    publish: vec!["heroes".to_string()],
+                })
+                .unwrap();
+
+            server.interest("heroes", scope).unwrap().inspect(move |x| {
+                send_results.send((x.0.clone(), x.2)).unwrap();
+            });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 1, "hero".into(), Eid(100)),
+                    TxData(1, 100, "name".into(), String("Batman".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (
+                vec![
+                    Eid(1),
+                    Aid("hero".into()),
+                    Eid(100),
+                    Aid("name".into()),
+                    String("Batman".to_string()),
+                ],
+                1
+            )
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn graphql_nested_arguments() {
+    let query = GraphQl {
+        query: "{ hero { friends(name: \"Robin\") { name } } }".to_string(),
+    };
+
+    match query.paths() {
+        Ok(_) => panic!("Expected nested arguments to be rejected."),
+        Err(error) => assert_eq!(error.category, ErrorKind::Unsupported),
+    }
+}
+
+#[test]
+fn graphql_aliases() {
+    let query = GraphQl {
+        query: "{ villains: hero { fullName: name realName: name height } }".to_string(),
+    };
+
+    let paths = query.paths().unwrap();
+
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path_attributes, vec!["villains"]);
+    assert_eq!(paths[0].pull_attributes, vec!["height"]);
+    assert_eq!(
+        paths[0].aliased_attributes,
+        vec![
+            ("name".into(), "fullName".into()),
+            ("name".into(), "realName".into()),
+        ]
+    );
+}
+
+#[test]
+fn graphql_fragments() {
+    let query = GraphQl {
+        query: "{ hero { ...heroFields friends { ...heroFields } } }
+                fragment heroFields on Hero { name height }"
+            .to_string(),
+    };
+
+    let paths = query.paths().unwrap();
+
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].pull_attributes, vec!["name", "height"]);
+    assert_eq!(paths[0].nested.len(), 1);
+    assert_eq!(paths[0].nested[0].ref_attribute, "friends");
+    assert_eq!(paths[0].nested[0].pull_attributes, vec!["name", "height"]);
+}
+
+#[test]
+fn graphql_fragment_errors() {
+    let undefined = GraphQl {
+        query: "{ hero { ...heroFields } }".to_string(),
+    };
+
+    match undefined.paths() {
+        Ok(_) => panic!("Expected undefined fragment to be rejected."),
+        Err(error) => assert_eq!(error.category, ErrorKind::NotFound),
+    }
+
+    let cyclic = GraphQl {
+        query: "{ hero { ...heroFields } }
+                fragment heroFields on Hero { name friends { ...heroFields } }"
+            .to_string(),
+    };
+
+    match cyclic.paths() {
+        Ok(_) => panic!("Expected cyclic fragment to be rejected."),
+        Err(error) => assert_eq!(error.category, ErrorKind::Incorrect),
+    }
+}
+
+#[test]
+fn graphql_squash_nested() {
+    let path = |friend: u64, a: &str, v: Value| {
+        (
+            vec![
+                Eid(1),
+                Aid("hero".into()),
+                Eid(100),
+                Aid("friends".into()),
+                Eid(friend),
+                Aid(a.into()),
+                v,
+            ],
+            0,
+            1,
+        )
+    };
+
+    let paths = vec![
+        (
+            vec![
+                Eid(1),
+                Aid("hero".into()),
+                Eid(100),
+                Aid("name".into()),
+                String("Batman".to_string()),
+            ],
+            0,
+            1,
+        ),
+        path(200, "name", String("Robin".to_string())),
+        path(300, "name", String("Alfred".to_string())),
+        path(300, "age", Number(70)),
+    ];
+
+    assert_eq!(
+        squash_nested(paths_to_nested(&paths)),
+        serde_json::json!([{
+            "hero": [{
+                "name": "Batman",
+                "friends": [
+                    { "name": "Robin" },
+                    { "name": "Alfred", "age": 70 }
+                ]
+            }]
+        }])
+    );
+}
+
+#[test]
+fn graphql_multiple_roots() {
+    let path = |root: u64, field: &str, e: u64, v: &str| {
+        (
+            vec![
+                Eid(root),
+                Aid(field.into()),
+                Eid(e),
+                Aid("name".into()),
+                String(v.to_string()),
+            ],
+            0,
+            1,
+        )
+    };
+
+    let paths = vec![
+        path(1, "heroes", 100, "Batman"),
+        path(1, "heroes", 200, "Robin"),
+        path(2, "villains", 300, "Joker"),
+    ];
+
+    assert_eq!(
+        graphql_to_json(&paths),
+        serde_json::json!({
+            "heroes": [{ "name": "Batman" }, { "name": "Robin" }],
+            "villains": [{ "name": "Joker" }]
+        })
+    );
+}
+
+#[test]
+fn graphql_order_by() {
+    let query = GraphQl {
+        query: "{ heroes @orderBy(attribute: \"name\", descending: true) { name } }".to_string(),
+    };
+
+    let levels = query.paths().unwrap();
+    assert_eq!(levels[0].order_by, Some("name".into()));
+    assert!(levels[0].order_descending);
+
+    let path = |e: u64, v: &str| {
+        (
+            vec![
+                Eid(1),
+                Aid("heroes".into()),
+                Eid(e),
+                Aid("name".into()),
+                String(v.to_string()),
+            ],
+            0,
+            1,
+        )
+    };
+
+    let paths = vec![path(100, "Batman"), path(200, "Robin"), path(300, "Alfred")];
+
+    assert_eq!(
+        order_nested(graphql_to_json(&paths), &levels),
+        serde_json::json!({
+            "heroes": [{ "name": "Robin" }, { "name": "Batman" }, { "name": "Alfred" }]
+        })
+    );
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
 use std::sync::mpsc::channel;
 use std::time::Duration;
@@ -8,10 +8,10 @@ use timely::dataflow::operators::Operator;
 
 use declarative_dataflow::binding::BinaryPredicate::LT;
 use declarative_dataflow::binding::{AsBinding, Binding};
-use declarative_dataflow::plan::hector::{plan_order, source_conflicts};
+use declarative_dataflow::plan::hector::{plan_order, plan_order_by_cost, source_conflicts};
 use declarative_dataflow::plan::{Hector, Implementable};
 use declarative_dataflow::server::Server;
-use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, TxData, Value};
+use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, RuleKind, TxData, Value};
 use Value::{Bool, Eid, Number, String};
 
 struct Case {
@@ -162,6 +162,43 @@ fn ordering() {
     }
 }
 
+/// Ensures that smaller attributes are preferred when estimates on
+/// their cardinalities are available.
+#[test]
+fn cost_based_ordering() {
+    let (e, c, e2, a, n) = (0, 1, 2, 3, 4);
+    let bindings = vec![
+        Binding::attribute(e2, ":age", a),
+        Binding::attribute(e, ":age", a),
+        Binding::attribute(e, ":name", c),
+        Binding::attribute(e2, ":name", n),
+        Binding::constant(c, String("Ivan".to_string())),
+    ];
+
+    let mut cardinalities = HashMap::new();
+    cardinalities.insert(":age".into(), 1);
+    cardinalities.insert(":name".into(), 100);
+
+    let (variable_order, binding_order) = plan_order_by_cost(1, &bindings, &cardinalities);
+
+    assert_eq!(variable_order, vec![e, a, e2, c, n]);
+    assert_eq!(
+        binding_order,
+        vec![
+            Binding::attribute(e2, ":age", a),
+            Binding::attribute(e, ":name", c),
+            Binding::attribute(e2, ":name", n),
+            Binding::constant(c, String("Ivan".to_string())),
+        ]
+    );
+
+    // Without estimates, the order is the same as before.
+    assert_eq!(
+        plan_order_by_cost(1, &bindings, &HashMap::new()),
+        plan_order(1, &bindings)
+    );
+}
+
 #[test]
 fn run_hector_cases() {
     let mut cases: Vec<Case> =
@@ -173,9 +210,9 @@ fn run_hector_cases() {
                 bindings: vec![Binding::attribute(0, ":name", 1)],
             },
             transactions: vec![vec![
-                TxData(1, 1, ":name".to_string(), String("Dipper".to_string())),
-                TxData(1, 2, ":name".to_string(), String("Mabel".to_string())),
-                TxData(1, 3, ":name".to_string(), String("Soos".to_string())),
+                TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+                TxData(1, 2, ":name".into(), String("Mabel".to_string())),
+                TxData(1, 3, ":name".into(), String("Soos".to_string())),
             ]],
             expectations: vec![vec![
                 (vec![Eid(1), String("Dipper".to_string())], 0, 1),
@@ -193,9 +230,9 @@ fn run_hector_cases() {
                 ],
             },
             transactions: vec![vec![
-                TxData(1, 1, ":name".to_string(), String("Dipper".to_string())),
-                TxData(1, 2, ":name".to_string(), String("Mabel".to_string())),
-                TxData(1, 3, ":name".to_string(), String("Soos".to_string())),
+                TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+                TxData(1, 2, ":name".into(), String("Mabel".to_string())),
+                TxData(1, 3, ":name".into(), String("Soos".to_string())),
             ]],
             expectations: vec![vec![(vec![Eid(1), String("Dipper".to_string())], 0, 1)]],
         },
@@ -211,11 +248,11 @@ fn run_hector_cases() {
                     ],
                 },
                 transactions: vec![vec![
-                    TxData(1, 1, ":name".to_string(), String("Dipper".to_string())),
-                    TxData(1, 1, ":age".to_string(), Number(12)),
-                    TxData(1, 2, ":name".to_string(), String("Mabel".to_string())),
-                    TxData(1, 2, ":age".to_string(), Number(13)),
-                    TxData(1, 3, ":name".to_string(), String("Soos".to_string())),
+                    TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 1, ":age".into(), Number(12)),
+                    TxData(1, 2, ":name".into(), String("Mabel".to_string())),
+                    TxData(1, 2, ":age".into(), Number(13)),
+                    TxData(1, 3, ":name".into(), String("Soos".to_string())),
                 ]],
                 expectations: vec![vec![
                     (vec![Eid(1), Number(12), String("Dipper".to_string())], 0, 1),
@@ -236,12 +273,12 @@ fn run_hector_cases() {
                     ],
                 },
                 transactions: vec![vec![
-                    TxData(1, 100, "edge".to_string(), Eid(200)),
-                    TxData(1, 200, "edge".to_string(), Eid(300)),
-                    TxData(1, 100, "edge".to_string(), Eid(300)),
-                    TxData(1, 100, "edge".to_string(), Eid(400)),
-                    TxData(1, 400, "edge".to_string(), Eid(500)),
-                    TxData(1, 500, "edge".to_string(), Eid(100)),
+                    TxData(1, 100, "edge".into(), Eid(200)),
+                    TxData(1, 200, "edge".into(), Eid(300)),
+                    TxData(1, 100, "edge".into(), Eid(300)),
+                    TxData(1, 100, "edge".into(), Eid(400)),
+                    TxData(1, 400, "edge".into(), Eid(500)),
+                    TxData(1, 500, "edge".into(), Eid(100)),
                 ]],
                 expectations: vec![vec![(vec![Eid(100), Eid(200), Eid(300)], 0, 1)]],
             }
@@ -260,13 +297,13 @@ fn run_hector_cases() {
                     ],
                 },
                 transactions: vec![vec![
-                    TxData(1, 100, ":name".to_string(), String("Dipper".to_string())),
-                    TxData(1, 100, ":age".to_string(), Number(12)),
-                    TxData(1, 100, ":likes".to_string(), Eid(200)),
-                    TxData(1, 100, ":fears".to_string(), Eid(300)),
-                    TxData(1, 200, ":name".to_string(), String("Mabel".to_string())),
-                    TxData(1, 200, ":age".to_string(), Number(13)),
-                    TxData(1, 300, ":name".to_string(), String("Soos".to_string())),
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, ":age".into(), Number(12)),
+                    TxData(1, 100, ":likes".into(), Eid(200)),
+                    TxData(1, 100, ":fears".into(), Eid(300)),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                    TxData(1, 200, ":age".into(), Number(13)),
+                    TxData(1, 300, ":name".into(), String("Soos".to_string())),
                 ]],
                 expectations: vec![vec![(
                     vec![
@@ -292,9 +329,9 @@ fn run_hector_cases() {
                 ],
             },
             transactions: vec![vec![
-                TxData(1, 100, ":num".to_string(), Number(1)),
-                TxData(1, 100, ":num".to_string(), Number(2)),
-                TxData(1, 100, ":num".to_string(), Number(3)),
+                TxData(1, 100, ":num".into(), Number(1)),
+                TxData(1, 100, ":num".into(), Number(2)),
+                TxData(1, 100, ":num".into(), Number(3)),
             ]],
             expectations: vec![vec![
                 (vec![Eid(100), Number(2), Number(1)], 0, 1),
@@ -316,9 +353,9 @@ fn run_hector_cases() {
                 ],
             },
             transactions: vec![vec![
-                TxData(1, 100, ":num".to_string(), Number(1)),
-                TxData(1, 100, ":num".to_string(), Number(10)),
-                TxData(1, 100, ":num".to_string(), Number(20)),
+                TxData(1, 100, ":num".into(), Number(1)),
+                TxData(1, 100, ":num".into(), Number(10)),
+                TxData(1, 100, ":num".into(), Number(20)),
             ]],
             expectations: vec![vec![(
                 vec![Eid(100), Number(10), Number(18), Number(20)],
@@ -341,12 +378,12 @@ fn run_hector_cases() {
                     ],
                 },
                 transactions: vec![vec![
-                    TxData(1, 100, ":name".to_string(), String("Dipper".to_string())),
-                    TxData(1, 100, ":age".to_string(), Number(12)),
-                    TxData(1, 100, ":admin?".to_string(), Bool(true)),
-                    TxData(1, 200, ":name".to_string(), String("Mabel".to_string())),
-                    TxData(1, 100, ":age".to_string(), Number(12)),
-                    TxData(1, 100, ":admin?".to_string(), Bool(false)),
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, ":age".into(), Number(12)),
+                    TxData(1, 100, ":admin?".into(), Bool(true)),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                    TxData(1, 100, ":age".into(), Number(12)),
+                    TxData(1, 100, ":admin?".into(), Bool(false)),
                 ]],
                 expectations: vec![vec![
                     (
@@ -374,9 +411,9 @@ fn run_hector_cases() {
                     ],
                 },
                 transactions: vec![vec![
-                    TxData(1, 100, ":name".to_string(), String("Dipper".to_string())),
-                    TxData(1, 100, ":admin?".to_string(), Bool(true)),
-                    TxData(1, 200, ":name".to_string(), String("Mabel".to_string())),
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, ":admin?".into(), Bool(true)),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
                 ]],
                 expectations: vec![vec![
                     (vec![Eid(100), String("Dipper".to_string()), Bool(true)], 0, 1),
@@ -410,6 +447,7 @@ fn run_hector_cases() {
                         scope,
                         Rule {
                             name: "hector".to_string(),
+                            kind: RuleKind::Recursive,
                             plan,
                         },
                     )
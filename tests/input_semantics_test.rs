@@ -0,0 +1,790 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::ValidTime;
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{
+    AttributeConfig, ErrorKind, InputSemantics, Plan, Rule, RuleKind, Time, TxData, Value,
+};
+use InputSemantics::{CardinalityMany, CardinalityOne, Raw, Unique};
+use Value::{Eid, Instant, Number, String};
+
+#[test]
+fn cardinality_one_same_batch() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::MatchA(e, ":name".into(), n);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(CardinalityOne), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "cardinality_one".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Mabel".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn cardinality_one_across_batches() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::MatchA(e, ":name".into(), n);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(CardinalityOne), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "cardinality_one".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 1)
+        );
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Alias".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(100), String("Dipper".to_string())], -1));
+        expected.insert((vec![Eid(100), String("Alias".to_string())], 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn retract_current() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::MatchA(e, ":name".into(), n);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(CardinalityOne), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "retract_current".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 1)
+        );
+
+        // Eid 200 doesn't hold a value, which is fine.
+        server
+            .retract_current(vec![(100, ":name".into()), (200, ":name".into())], 0, 0)
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], -1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn retract_entities() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, v) = (1, 2);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(CardinalityOne), scope)
+                .unwrap();
+
+            server
+                .context
+                .internal
+                .create_attribute(":tag", AttributeConfig::tx_time(CardinalityMany), scope)
+                .unwrap();
+
+            for (name, a) in vec![("names", ":name"), ("tags", ":tag")] {
+                let send_results = send_results.clone();
+
+                server
+                    .test_single(
+                        scope,
+                        Rule {
+                            name: name.to_string(),
+                            kind: RuleKind::Recursive,
+                            plan: Plan::MatchA(e, a.into(), v),
+                        },
+                    )
+                    .inspect(move |x| {
+                        send_results.send((x.0.clone(), x.2)).unwrap();
+                    });
+            }
+        });
+
+        // Asserting a tag twice must not keep it around after
+        // retracting the entity.
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, ":tag".into(), String("twin".to_string())),
+                    TxData(1, 100, ":tag".into(), String("twin".to_string())),
+                    TxData(1, 100, ":tag".into(), String("hat".to_string())),
+                    TxData(1, 200, ":tag".into(), String("twin".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(100), String("Dipper".to_string())], 1));
+        expected.insert((vec![Eid(100), String("twin".to_string())], 1));
+        expected.insert((vec![Eid(100), String("hat".to_string())], 1));
+        expected.insert((vec![Eid(200), String("twin".to_string())], 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        server.retract_entities(vec![100], 0, 0);
+
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(100), String("Dipper".to_string())], -1));
+        expected.insert((vec![Eid(100), String("twin".to_string())], -1));
+        expected.insert((vec![Eid(100), String("hat".to_string())], -1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        // Re-asserting the tag once brings it back.
+        server
+            .transact(
+                vec![TxData(1, 100, ":tag".into(), String("twin".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 3).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("twin".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn migrate_to_cardinality_one() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::MatchA(e, ":name".into(), n);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, ":name".into(), String("Mabel".to_string())),
+                    TxData(1, 200, ":name".into(), String("Soos".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .migrate_attribute(":name", CardinalityOne, scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "migrated".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        assert_eq!(
+            server.context.internal.attributes[":name"].input_semantics,
+            CardinalityOne
+        );
+
+        server
+            .transact(
+                vec![TxData(1, 200, ":name".into(), String("Wendy".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut state = HashMap::new();
+        while let Ok((tuple, diff)) = results.try_recv() {
+            *state.entry(tuple).or_insert(0) += diff;
+        }
+        state.retain(|_tuple, count| *count != 0);
+
+        // The historical violation on 100 is resolved in favour of
+        // the largest value, the new input on 200 replaces Soos.
+        let expected: HashMap<Vec<Value>, isize> = vec![
+            (vec![Eid(100), String("Mabel".to_string())], 1),
+            (vec![Eid(200), String("Wendy".to_string())], 1),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(state, expected);
+    });
+}
+
+#[test]
+fn migrate_unknown_attribute() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            match server.migrate_attribute(":name", CardinalityOne, scope) {
+                Ok(_) => panic!("Expected migration to fail."),
+                Err(error) => assert_eq!(error.category, ErrorKind::NotFound),
+            }
+        });
+    });
+}
+
+#[test]
+fn unique_values() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, m) = (1, 2);
+        let plan = Plan::MatchA(e, ":email".into(), m);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":email", AttributeConfig::tx_time(Unique), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "unique".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let email = || String("dipper@mysteryshack.com".to_string());
+
+        // Conflicts within a single transaction are rejected outright.
+        match server.transact(
+            vec![
+                TxData(1, 100, ":email".into(), email()),
+                TxData(1, 200, ":email".into(), email()),
+            ],
+            0,
+            0,
+        ) {
+            Ok(_) => panic!("Expected conflicting transaction to fail."),
+            Err(error) => assert_eq!(error.category, ErrorKind::Conflict),
+        }
+
+        server
+            .transact(vec![TxData(1, 100, ":email".into(), email())], 0, 0)
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(results.recv().unwrap(), (vec![Eid(100), email()], 1));
+
+        // As are conflicts with earlier transactions.
+        match server.transact(vec![TxData(1, 200, ":email".into(), email())], 0, 0) {
+            Ok(_) => panic!("Expected transaction of a held value to fail."),
+            Err(error) => assert_eq!(error.category, ErrorKind::Conflict),
+        }
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(results.try_recv().is_err());
+
+        // Once retracted, the value is up for grabs again.
+        server
+            .transact(
+                vec![
+                    TxData(-1, 100, ":email".into(), email()),
+                    TxData(1, 200, ":email".into(), email()),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received = vec![results.recv().unwrap(), results.recv().unwrap()];
+        received.sort();
+
+        assert_eq!(
+            received,
+            vec![(vec![Eid(100), email()], -1), (vec![Eid(200), email()], 1),]
+        );
+        assert!(results.try_recv().is_err());
+
+        // Conflicts with transactions not yet indexed are dropped by
+        // the attribute's dataflow, the smallest eid wins.
+        let other = || String("mabel@mysteryshack.com".to_string());
+
+        server
+            .transact(vec![TxData(1, 400, ":email".into(), other())], 0, 0)
+            .unwrap();
+        server
+            .transact(vec![TxData(1, 300, ":email".into(), other())], 0, 0)
+            .unwrap();
+
+        server.advance_domain(None, 4).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(results.recv().unwrap(), (vec![Eid(300), other()], 1));
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn ttl() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::MatchA(e, ":name".into(), n);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(
+                    ":name",
+                    AttributeConfig {
+                        ttl: Some(Time::TxId(2)),
+                        ..AttributeConfig::tx_time(Raw)
+                    },
+                    scope,
+                )
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "ttl".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 0, 1)
+        );
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                    TxData(-1, 200, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(results.try_recv().is_err());
+
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        // Mabel was retracted explicitly and must not expire again.
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 3, -1)
+        );
+
+        server.advance_domain(None, 4).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn ttl_retract_current() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::MatchA(e, ":name".into(), n);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(
+                    ":name",
+                    AttributeConfig {
+                        ttl: Some(Time::TxId(2)),
+                        ..AttributeConfig::tx_time(Raw)
+                    },
+                    scope,
+                )
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "ttl_retract_current".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 0, 1)
+        );
+
+        server
+            .retract_current(vec![(100, ":name".into())], 0, 0)
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 1, -1)
+        );
+
+        // Dipper is gone already and must not expire again.
+        server.advance_domain(None, 3).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+        server.advance_domain(None, 4).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn valid_time() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, s, t) = (1, 2, 3);
+        let plan = Plan::ValidTime(ValidTime {
+            variables: (e, s, t),
+            attribute: ":salary".into(),
+            valid_at: Some(150),
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let config = AttributeConfig {
+                valid_time: true,
+                ..AttributeConfig::tx_time(Raw)
+            };
+
+            match server.context.internal.create_attribute(
+                ":salary/current",
+                AttributeConfig {
+                    input_semantics: CardinalityOne,
+                    ..config.clone()
+                },
+                scope,
+            ) {
+                Ok(_) => panic!("Expected bitemporal CardinalityOne attribute to be rejected."),
+                Err(error) => assert_eq!(error.category, ErrorKind::Incorrect),
+            }
+
+            server
+                .context
+                .internal
+                .create_attribute(":salary", config, scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "valid_time".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        match server.transact(vec![TxData(1, 100, ":salary".into(), Number(10))], 0, 0) {
+            Ok(_) => panic!("Expected value without a valid time to be rejected."),
+            Err(error) => assert_eq!(error.category, ErrorKind::Incorrect),
+        }
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":salary".into(), Value::valid(Number(10), 100)),
+                    TxData(1, 100, ":salary".into(), Value::valid(Number(20), 200)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), Number(10), Instant(100)], 1)
+        );
+        assert!(results.try_recv().is_err());
+
+        // Learning about a raise in the past supersedes what was
+        // valid at the time.
+        server
+            .transact(
+                vec![TxData(
+                    1,
+                    100,
+                    ":salary".into(),
+                    Value::valid(Number(15), 140),
+                )],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received = vec![results.recv().unwrap(), results.recv().unwrap()];
+        received.sort();
+
+        assert_eq!(
+            received,
+            vec![
+                (vec![Eid(100), Number(10), Instant(100)], -1),
+                (vec![Eid(100), Number(15), Instant(140)], 1),
+            ]
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
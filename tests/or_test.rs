@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::iter::FromIterator;
 use std::sync::mpsc::channel;
 use std::time::Duration;
@@ -10,7 +10,7 @@ use declarative_dataflow::binding::Binding;
 use declarative_dataflow::plan::{Hector, Implementable, Union};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::{Aid, Value};
-use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, TxData};
+use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, RuleKind, TxData};
 use Value::{Eid, Number, String};
 
 struct Case {
@@ -63,6 +63,7 @@ fn run_cases(mut cases: Vec<Case>) {
                         scope,
                         Rule {
                             name: "query".to_string(),
+                            kind: RuleKind::Recursive,
                             plan,
                         },
                     )
@@ -117,18 +118,18 @@ fn run_cases(mut cases: Vec<Case>) {
 #[test]
 fn or() {
     let data = vec![
-        TxData(1, 1, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 1, ":age".to_string(), Number(10)),
-        TxData(1, 2, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 2, ":age".to_string(), Number(20)),
-        TxData(1, 3, ":name".to_string(), String("Oleg".to_string())),
-        TxData(1, 3, ":age".to_string(), Number(10)),
-        TxData(1, 4, ":name".to_string(), String("Oleg".to_string())),
-        TxData(1, 4, ":age".to_string(), Number(20)),
-        TxData(1, 5, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 5, ":age".to_string(), Number(10)),
-        TxData(1, 6, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 6, ":age".to_string(), Number(20)),
+        TxData(1, 1, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 1, ":age".into(), Number(10)),
+        TxData(1, 2, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 2, ":age".into(), Number(20)),
+        TxData(1, 3, ":name".into(), String("Oleg".to_string())),
+        TxData(1, 3, ":age".into(), Number(10)),
+        TxData(1, 4, ":name".into(), String("Oleg".to_string())),
+        TxData(1, 4, ":age".into(), Number(20)),
+        TxData(1, 5, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 5, ":age".into(), Number(10)),
+        TxData(1, 6, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 6, ":age".into(), Number(20)),
     ];
 
     run_cases(vec![
@@ -152,6 +153,7 @@ fn or() {
                         ],
                     }),
                 ],
+                all: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -181,6 +183,7 @@ fn or() {
                         ],
                     }),
                 ],
+                all: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Eid(3)], 0, 1), (vec![Eid(4)], 0, 1)]],
@@ -205,6 +208,7 @@ fn or() {
                         ],
                     }),
                 ],
+                all: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![]],
@@ -272,6 +276,7 @@ fn or() {
                         ],
                     }),
                 ],
+                all: false,
             }),
             transactions: vec![data.clone()],
             expectations: vec![vec![
@@ -286,18 +291,18 @@ fn or() {
 #[test]
 fn or_join() {
     let data = vec![
-        TxData(1, 1, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 1, ":age".to_string(), Number(10)),
-        TxData(1, 2, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 2, ":age".to_string(), Number(20)),
-        TxData(1, 3, ":name".to_string(), String("Oleg".to_string())),
-        TxData(1, 3, ":age".to_string(), Number(10)),
-        TxData(1, 4, ":name".to_string(), String("Oleg".to_string())),
-        TxData(1, 4, ":age".to_string(), Number(20)),
-        TxData(1, 5, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 5, ":age".to_string(), Number(10)),
-        TxData(1, 6, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 6, ":age".to_string(), Number(20)),
+        TxData(1, 1, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 1, ":age".into(), Number(10)),
+        TxData(1, 2, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 2, ":age".into(), Number(20)),
+        TxData(1, 3, ":name".into(), String("Oleg".to_string())),
+        TxData(1, 3, ":age".into(), Number(10)),
+        TxData(1, 4, ":name".into(), String("Oleg".to_string())),
+        TxData(1, 4, ":age".into(), Number(20)),
+        TxData(1, 5, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 5, ":age".into(), Number(10)),
+        TxData(1, 6, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 6, ":age".into(), Number(20)),
     ];
 
     run_cases(vec![Case {
@@ -321,6 +326,7 @@ fn or_join() {
                     ],
                 }),
             ],
+            all: false,
         }),
         transactions: vec![data.clone()],
         expectations: vec![vec![
@@ -333,3 +339,97 @@ fn or_join() {
         ]],
     }]);
 }
+
+#[test]
+fn union_modes() {
+    let data = vec![
+        TxData(1, 1, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 1, ":age".into(), Number(10)),
+        TxData(1, 3, ":name".into(), String("Oleg".to_string())),
+        TxData(1, 3, ":age".into(), Number(10)),
+        TxData(1, 4, ":name".into(), String("Oleg".to_string())),
+        TxData(1, 4, ":age".into(), Number(20)),
+    ];
+
+    let union = |all: bool| {
+        Plan::Union(Union {
+            variables: vec![0],
+            plans: vec![
+                Plan::Hector(Hector {
+                    variables: vec![0],
+                    bindings: vec![
+                        Binding::attribute(0, ":name", 1),
+                        Binding::constant(1, String("Oleg".to_string())),
+                    ],
+                }),
+                Plan::Hector(Hector {
+                    variables: vec![0],
+                    bindings: vec![
+                        Binding::attribute(0, ":age", 1),
+                        Binding::constant(1, Number(10)),
+                    ],
+                }),
+            ],
+            all,
+        })
+    };
+
+    for (all, expected) in vec![
+        (
+            false,
+            vec![(vec![Eid(1)], 1), (vec![Eid(3)], 1), (vec![Eid(4)], 1)],
+        ),
+        (
+            true,
+            vec![(vec![Eid(1)], 1), (vec![Eid(3)], 2), (vec![Eid(4)], 1)],
+        ),
+    ] {
+        let plan = union(all);
+        let data = data.clone();
+
+        timely::execute_directly(move |worker| {
+            let mut server = Server::<u64, u64>::new(Default::default());
+            let (send_results, results) = channel();
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                for dep in [":name", ":age"].iter() {
+                    server
+                        .context
+                        .internal
+                        .create_attribute(dep, AttributeConfig::tx_time(InputSemantics::Raw), scope)
+                        .unwrap();
+                }
+
+                server
+                    .test_single(
+                        scope,
+                        Rule {
+                            name: "query".to_string(),
+                            kind: RuleKind::Recursive,
+                            plan,
+                        },
+                    )
+                    .inner
+                    .sink(Pipeline, "Results", move |input| {
+                        input.for_each(|_time, data| {
+                            for datum in data.iter() {
+                                send_results.send(datum.clone()).unwrap()
+                            }
+                        });
+                    });
+            });
+
+            server.transact(data, 0, 0).unwrap();
+            server.advance_domain(None, 1).unwrap();
+
+            worker.step_while(|| server.is_any_outdated());
+
+            let mut counts: BTreeMap<Vec<Value>, isize> = BTreeMap::new();
+            while let Ok((tuple, _time, diff)) = results.recv_timeout(Duration::from_millis(400)) {
+                *counts.entry(tuple).or_insert(0) += diff;
+            }
+
+            assert_eq!(counts.into_iter().collect::<Vec<_>>(), expected);
+        });
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::binding::Binding;
+use declarative_dataflow::parser::{parse, parse_plan};
+use declarative_dataflow::server::Server;
+use declarative_dataflow::{
+    q, AttributeConfig, ErrorKind, InputSemantics, Rule, RuleKind, TxData, Value,
+};
+use InputSemantics::Raw;
+use Value::{Aid, Bool, Eid, Number, String};
+
+#[test]
+fn parse_patterns() {
+    let query = parse(
+        "select ?e ?name
+         where {
+           ?e :name ?name .
+           ?e :age 30 .
+         }",
+    )
+    .unwrap();
+
+    assert_eq!(query.variables, vec![0, 1]);
+    assert_eq!(query.symbols[..2], ["e".to_string(), "name".to_string()]);
+    assert_eq!(
+        query.into_plan(),
+        q(
+            vec![0, 1],
+            vec![
+                Binding::attribute(0, ":name", 1),
+                Binding::constant(2, Number(30)),
+                Binding::attribute(0, ":age", 2),
+            ]
+        )
+    );
+}
+
+#[test]
+fn parse_constants() {
+    let query = parse(
+        "find ?v
+         100 :name ?v
+         ?x :admin? true
+         ?x :role :role/admin
+         ?x :motto \"Gravity \\\"Falls\\\"\"",
+    )
+    .unwrap();
+
+    assert_eq!(
+        query.bindings,
+        vec![
+            Binding::constant(1, Eid(100)),
+            Binding::attribute(1, ":name", 0),
+            Binding::constant(3, Bool(true)),
+            Binding::attribute(2, ":admin?", 3),
+            Binding::constant(4, Aid(":role/admin".into())),
+            Binding::attribute(2, ":role", 4),
+            Binding::constant(5, String("Gravity \"Falls\"".to_string())),
+            Binding::attribute(2, ":motto", 5),
+        ]
+    );
+}
+
+#[test]
+fn parse_errors() {
+    let cases = vec![
+        (
+            "?e :name ?n",
+            "Line 1: queries must start with select or find",
+        ),
+        (
+            "select where { ?e :name ?n }",
+            "Line 1: expected at least one target variable",
+        ),
+        (
+            "select ?e\n?e :name",
+            "Line 2: expected a value, found end of input",
+        ),
+        (
+            "select ?e\n?e ?a ?n",
+            "Line 2: expected an attribute, found ?a",
+        ),
+        ("select ?e { ?e :name ?n", "Line 1: expected }"),
+        (
+            "select ?e ?x\n?e :name ?n",
+            "Line 2: ?x is not bound by any pattern",
+        ),
+        (
+            "select ?e\n?e :name \"Dipper",
+            "Line 2: unterminated string literal",
+        ),
+    ];
+
+    for (input, message) in cases.into_iter() {
+        match parse(input) {
+            Ok(_) => panic!("Expected {:?} to be rejected.", input),
+            Err(error) => {
+                assert_eq!(error.category, ErrorKind::Incorrect);
+                assert_eq!(error.message, message);
+            }
+        }
+    }
+}
+
+#[test]
+fn parsed_query() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let plan = parse_plan("select ?e where { ?e :name \"Mabel\" }").unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "parsed".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(results.recv().unwrap(), (vec![Eid(200)], 1));
+        assert!(results.try_recv().is_err());
+    });
+}
@@ -2,11 +2,11 @@ use std::collections::HashSet;
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
-use declarative_dataflow::plan::{Pull, PullLevel};
+use declarative_dataflow::plan::{NestedPull, Project, Pull, PullLevel};
 use declarative_dataflow::server::Server;
-use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, TxData, Value};
+use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, RuleKind, TxData, Value};
 use InputSemantics::Raw;
-use Value::{Aid, Bool, Eid, Number, String};
+use Value::{Aid, Bool, Eid, List, Number, String};
 
 #[test]
 fn pull_level() {
@@ -17,9 +17,19 @@ fn pull_level() {
         let (e,) = (1,);
         let plan = Plan::PullLevel(PullLevel {
             variables: vec![],
-            plan: Box::new(Plan::MatchAV(e, "admin?".to_string(), Bool(false))),
-            pull_attributes: vec!["name".to_string(), "age".to_string()],
+            plan: Box::new(Plan::MatchAV(e, "admin?".into(), Bool(false))),
+            pull_attributes: vec!["name".into(), "age".into()],
+            wildcard: false,
+            limit: None,
+            sort_attribute: None,
+            sort_descending: false,
+            order_by: None,
+            order_descending: false,
+            collect_values: false,
             path_attributes: vec![],
+            aliased_attributes: vec![],
+            reverse_attributes: vec![],
+            nested: vec![],
         });
 
         worker.dataflow::<u64, _, _>(|scope| {
@@ -44,6 +54,7 @@ fn pull_level() {
                     scope,
                     Rule {
                         name: "pull_level".to_string(),
+                        kind: RuleKind::Recursive,
                         plan,
                     },
                 )
@@ -55,14 +66,14 @@ fn pull_level() {
         server
             .transact(
                 vec![
-                    TxData(1, 100, "admin?".to_string(), Bool(true)),
-                    TxData(1, 200, "admin?".to_string(), Bool(false)),
-                    TxData(1, 300, "admin?".to_string(), Bool(false)),
-                    TxData(1, 100, "name".to_string(), String("Mabel".to_string())),
-                    TxData(1, 200, "name".to_string(), String("Dipper".to_string())),
-                    TxData(1, 300, "name".to_string(), String("Soos".to_string())),
-                    TxData(1, 100, "age".to_string(), Number(12)),
-                    TxData(1, 200, "age".to_string(), Number(13)),
+                    TxData(1, 100, "admin?".into(), Bool(true)),
+                    TxData(1, 200, "admin?".into(), Bool(false)),
+                    TxData(1, 300, "admin?".into(), Bool(false)),
+                    TxData(1, 100, "name".into(), String("Mabel".to_string())),
+                    TxData(1, 200, "name".into(), String("Dipper".to_string())),
+                    TxData(1, 300, "name".into(), String("Soos".to_string())),
+                    TxData(1, 100, "age".into(), Number(12)),
+                    TxData(1, 200, "age".into(), Number(13)),
                 ],
                 0,
                 0,
@@ -74,21 +85,13 @@ fn pull_level() {
         worker.step_while(|| server.is_any_outdated());
 
         let mut expected = HashSet::new();
-        expected.insert((vec![Eid(200), Aid("age".to_string()), Number(13)], 1));
+        expected.insert((vec![Eid(200), Aid("age".into()), Number(13)], 1));
         expected.insert((
-            vec![
-                Eid(200),
-                Aid("name".to_string()),
-                String("Dipper".to_string()),
-            ],
+            vec![Eid(200), Aid("name".into()), String("Dipper".to_string())],
             1,
         ));
         expected.insert((
-            vec![
-                Eid(300),
-                Aid("name".to_string()),
-                String("Soos".to_string()),
-            ],
+            vec![Eid(300), Aid("name".into()), String("Soos".to_string())],
             1,
         ));
 
@@ -112,9 +115,19 @@ fn pull_children() {
         let (parent, child) = (1, 2);
         let plan = Plan::PullLevel(PullLevel {
             variables: vec![],
-            plan: Box::new(Plan::MatchA(parent, "parent/child".to_string(), child)),
-            pull_attributes: vec!["name".to_string(), "age".to_string()],
-            path_attributes: vec!["parent/child".to_string()],
+            plan: Box::new(Plan::MatchA(parent, "parent/child".into(), child)),
+            pull_attributes: vec!["name".into(), "age".into()],
+            wildcard: false,
+            limit: None,
+            sort_attribute: None,
+            sort_descending: false,
+            order_by: None,
+            order_descending: false,
+            collect_values: false,
+            path_attributes: vec!["parent/child".into()],
+            aliased_attributes: vec![],
+            reverse_attributes: vec![],
+            nested: vec![],
         });
 
         worker.dataflow::<u64, _, _>(|scope| {
@@ -139,6 +152,7 @@ fn pull_children() {
                     scope,
                     Rule {
                         name: "pull_children".to_string(),
+                        kind: RuleKind::Recursive,
                         plan,
                     },
                 )
@@ -150,14 +164,14 @@ fn pull_children() {
         server
             .transact(
                 vec![
-                    TxData(1, 100, "name".to_string(), String("Alice".to_string())),
-                    TxData(1, 100, "parent/child".to_string(), Eid(300)),
-                    TxData(1, 200, "name".to_string(), String("Bob".to_string())),
-                    TxData(1, 200, "parent/child".to_string(), Eid(400)),
-                    TxData(1, 300, "name".to_string(), String("Mabel".to_string())),
-                    TxData(1, 300, "age".to_string(), Number(13)),
-                    TxData(1, 400, "name".to_string(), String("Dipper".to_string())),
-                    TxData(1, 400, "age".to_string(), Number(12)),
+                    TxData(1, 100, "name".into(), String("Alice".to_string())),
+                    TxData(1, 100, "parent/child".into(), Eid(300)),
+                    TxData(1, 200, "name".into(), String("Bob".to_string())),
+                    TxData(1, 200, "parent/child".into(), Eid(400)),
+                    TxData(1, 300, "name".into(), String("Mabel".to_string())),
+                    TxData(1, 300, "age".into(), Number(13)),
+                    TxData(1, 400, "name".into(), String("Dipper".to_string())),
+                    TxData(1, 400, "age".into(), Number(12)),
                 ],
                 0,
                 0,
@@ -172,9 +186,9 @@ fn pull_children() {
         expected.insert((
             vec![
                 Eid(100),
-                Aid("parent/child".to_string()),
+                Aid("parent/child".into()),
                 Eid(300),
-                Aid("age".to_string()),
+                Aid("age".into()),
                 Number(13),
             ],
             1,
@@ -182,9 +196,9 @@ fn pull_children() {
         expected.insert((
             vec![
                 Eid(100),
-                Aid("parent/child".to_string()),
+                Aid("parent/child".into()),
                 Eid(300),
-                Aid("name".to_string()),
+                Aid("name".into()),
                 String("Mabel".to_string()),
             ],
             1,
@@ -192,9 +206,9 @@ fn pull_children() {
         expected.insert((
             vec![
                 Eid(200),
-                Aid("parent/child".to_string()),
+                Aid("parent/child".into()),
                 Eid(400),
-                Aid("age".to_string()),
+                Aid("age".into()),
                 Number(12),
             ],
             1,
@@ -202,9 +216,9 @@ fn pull_children() {
         expected.insert((
             vec![
                 Eid(200),
-                Aid("parent/child".to_string()),
+                Aid("parent/child".into()),
                 Eid(400),
-                Aid("name".to_string()),
+                Aid("name".into()),
                 String("Dipper".to_string()),
             ],
             1,
@@ -233,19 +247,39 @@ fn pull() {
             paths: vec![
                 PullLevel {
                     variables: vec![],
-                    plan: Box::new(Plan::MatchA(a, "join/binding".to_string(), b)),
+                    plan: Box::new(Plan::MatchA(a, "join/binding".into(), b)),
                     pull_attributes: vec![
-                        "pattern/e".to_string(),
-                        "pattern/a".to_string(),
-                        "pattern/v".to_string(),
+                        "pattern/e".into(),
+                        "pattern/a".into(),
+                        "pattern/v".into(),
                     ],
-                    path_attributes: vec!["join/binding".to_string()],
+                    wildcard: false,
+                    limit: None,
+                    sort_attribute: None,
+                    sort_descending: false,
+                    order_by: None,
+                    order_descending: false,
+                    collect_values: false,
+                    path_attributes: vec!["join/binding".into()],
+                    aliased_attributes: vec![],
+                    reverse_attributes: vec![],
+                    nested: vec![],
                 },
                 PullLevel {
                     variables: vec![],
-                    plan: Box::new(Plan::MatchA(a, "name".to_string(), c)),
+                    plan: Box::new(Plan::MatchA(a, "name".into(), c)),
                     pull_attributes: vec![],
-                    path_attributes: vec!["name".to_string()],
+                    wildcard: false,
+                    limit: None,
+                    sort_attribute: None,
+                    sort_descending: false,
+                    order_by: None,
+                    order_descending: false,
+                    collect_values: false,
+                    path_attributes: vec!["name".into()],
+                    aliased_attributes: vec![],
+                    reverse_attributes: vec![],
+                    nested: vec![],
                 },
             ],
         });
@@ -282,6 +316,7 @@ fn pull() {
                     scope,
                     Rule {
                         name: "pull".to_string(),
+                        kind: RuleKind::Recursive,
                         plan,
                     },
                 )
@@ -293,12 +328,12 @@ fn pull() {
         server
             .transact(
                 vec![
-                    TxData(1, 100, "name".to_string(), String("rule".to_string())),
-                    TxData(1, 100, "join/binding".to_string(), Eid(200)),
-                    TxData(1, 100, "join/binding".to_string(), Eid(300)),
-                    TxData(1, 200, "pattern/a".to_string(), Aid("xyz".to_string())),
-                    TxData(1, 300, "pattern/e".to_string(), Eid(12345)),
-                    TxData(1, 300, "pattern/a".to_string(), Aid("asd".to_string())),
+                    TxData(1, 100, "name".into(), String("rule".to_string())),
+                    TxData(1, 100, "join/binding".into(), Eid(200)),
+                    TxData(1, 100, "join/binding".into(), Eid(300)),
+                    TxData(1, 200, "pattern/a".into(), Aid("xyz".into())),
+                    TxData(1, 300, "pattern/e".into(), Eid(12345)),
+                    TxData(1, 300, "pattern/a".into(), Aid("asd".into())),
                 ],
                 0,
                 0,
@@ -311,29 +346,25 @@ fn pull() {
 
         let mut expected = HashSet::new();
         expected.insert((
-            vec![
-                Eid(100),
-                Aid("name".to_string()),
-                String("rule".to_string()),
-            ],
+            vec![Eid(100), Aid("name".into()), String("rule".to_string())],
             1,
         ));
         expected.insert((
             vec![
                 Eid(100),
-                Aid("join/binding".to_string()),
+                Aid("join/binding".into()),
                 Eid(200),
-                Aid("pattern/a".to_string()),
-                Aid("xyz".to_string()),
+                Aid("pattern/a".into()),
+                Aid("xyz".into()),
             ],
             1,
         ));
         expected.insert((
             vec![
                 Eid(100),
-                Aid("join/binding".to_string()),
+                Aid("join/binding".into()),
                 Eid(300),
-                Aid("pattern/e".to_string()),
+                Aid("pattern/e".into()),
                 Eid(12345),
             ],
             1,
@@ -341,10 +372,10 @@ fn pull() {
         expected.insert((
             vec![
                 Eid(100),
-                Aid("join/binding".to_string()),
+                Aid("join/binding".into()),
                 Eid(300),
-                Aid("pattern/a".to_string()),
-                Aid("asd".to_string()),
+                Aid("pattern/a".into()),
+                Aid("asd".into()),
             ],
             1,
         ));
@@ -359,3 +390,492 @@ fn pull() {
         assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
     });
 }
+
+#[test]
+fn pull_nested() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        // {:parent/child [:name {:parent/child [:name]}]}
+        let (e,) = (1,);
+        let plan = Plan::PullLevel(PullLevel {
+            variables: vec![],
+            plan: Box::new(Plan::MatchAV(e, "name".into(), String("Stan".to_string()))),
+            pull_attributes: vec![],
+            wildcard: false,
+            limit: None,
+            sort_attribute: None,
+            sort_descending: false,
+            order_by: None,
+            order_descending: false,
+            collect_values: false,
+            path_attributes: vec![],
+            aliased_attributes: vec![],
+            reverse_attributes: vec![],
+            nested: vec![NestedPull {
+                ref_attribute: "parent/child".into(),
+                alias: None,
+                pull_attributes: vec!["name".into()],
+                aliased_attributes: vec![],
+                reverse_attributes: vec![],
+                nested: vec![NestedPull {
+                    ref_attribute: "parent/child".into(),
+                    alias: None,
+                    pull_attributes: vec!["name".into()],
+                    aliased_attributes: vec![],
+                    reverse_attributes: vec![],
+                    nested: vec![],
+                }],
+            }],
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute("parent/child", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute("name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "pull_nested".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, "name".into(), String("Stan".to_string())),
+                    TxData(1, 100, "parent/child".into(), Eid(200)),
+                    TxData(1, 200, "name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, "parent/child".into(), Eid(300)),
+                    TxData(1, 300, "name".into(), String("Waddles".to_string())),
+                    TxData(1, 300, "parent/child".into(), Eid(400)),
+                    TxData(1, 400, "name".into(), String("Too deep".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((
+            vec![
+                Eid(100),
+                Aid("parent/child".into()),
+                Eid(200),
+                Aid("name".into()),
+                String("Dipper".to_string()),
+            ],
+            1,
+        ));
+        expected.insert((
+            vec![
+                Eid(100),
+                Aid("parent/child".into()),
+                Eid(200),
+                Aid("parent/child".into()),
+                Eid(300),
+                Aid("name".into()),
+                String("Waddles".to_string()),
+            ],
+            1,
+        ));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn pull_reverse() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        // [:name :child/_parent]
+        let (e,) = (1,);
+        let plan = Plan::PullLevel(PullLevel {
+            variables: vec![],
+            plan: Box::new(Plan::MatchAV(e, "name".into(), String("Stan".to_string()))),
+            pull_attributes: vec!["name".into()],
+            wildcard: false,
+            limit: None,
+            sort_attribute: None,
+            sort_descending: false,
+            order_by: None,
+            order_descending: false,
+            collect_values: false,
+            path_attributes: vec![],
+            aliased_attributes: vec![],
+            reverse_attributes: vec!["child/parent".into()],
+            nested: vec![],
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute("child/parent", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute("name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "pull_reverse".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, "name".into(), String("Stan".to_string())),
+                    TxData(1, 200, "name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, "child/parent".into(), Eid(100)),
+                    TxData(1, 300, "name".into(), String("Mabel".to_string())),
+                    TxData(1, 300, "child/parent".into(), Eid(100)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((
+            vec![Eid(100), Aid("name".into()), String("Stan".to_string())],
+            1,
+        ));
+        expected.insert((vec![Eid(100), Aid("child/_parent".into()), Eid(200)], 1));
+        expected.insert((vec![Eid(100), Aid("child/_parent".into()), Eid(300)], 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn pull_wildcard() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        // [*]
+        let (e,) = (1,);
+        let plan = Plan::PullLevel(PullLevel {
+            variables: vec![],
+            plan: Box::new(Plan::MatchAV(e, "admin?".into(), Bool(false))),
+            pull_attributes: vec![],
+            wildcard: true,
+            limit: None,
+            sort_attribute: None,
+            sort_descending: false,
+            order_by: None,
+            order_descending: false,
+            collect_values: false,
+            path_attributes: vec![],
+            aliased_attributes: vec![],
+            reverse_attributes: vec![],
+            nested: vec![],
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute("admin?", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute("name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute("age", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "pull_wildcard".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        // Attributes created after the fact must not show up.
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute("nickname", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, "admin?".into(), Bool(true)),
+                    TxData(1, 200, "admin?".into(), Bool(false)),
+                    TxData(1, 100, "name".into(), String("Mabel".to_string())),
+                    TxData(1, 200, "name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, "age".into(), Number(12)),
+                    TxData(1, 200, "nickname".into(), String("Dip".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(200), Aid("admin?".into()), Bool(false)], 1));
+        expected.insert((
+            vec![Eid(200), Aid("name".into()), String("Dipper".to_string())],
+            1,
+        ));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn pull_limit() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e,) = (1,);
+        let plan = Plan::PullLevel(PullLevel {
+            variables: vec![],
+            plan: Box::new(Plan::MatchA(e, "post/title".into(), 2)),
+            pull_attributes: vec!["post/title".into(), "post/comment".into()],
+            wildcard: false,
+            limit: Some(2),
+            sort_attribute: Some("comment/time".into()),
+            sort_descending: true,
+            order_by: None,
+            order_descending: false,
+            collect_values: false,
+            path_attributes: vec![],
+            aliased_attributes: vec![],
+            reverse_attributes: vec![],
+            nested: vec![],
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for a in ["post/title", "post/comment", "comment/time"].iter() {
+                server
+                    .context
+                    .internal
+                    .create_attribute(a, AttributeConfig::tx_time(Raw), scope)
+                    .unwrap();
+            }
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "pull_limit".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, "post/title".into(), String("Gravity".to_string())),
+                    TxData(1, 100, "post/comment".into(), Eid(201)),
+                    TxData(1, 100, "post/comment".into(), Eid(202)),
+                    TxData(1, 100, "post/comment".into(), Eid(203)),
+                    TxData(1, 201, "comment/time".into(), Number(3)),
+                    TxData(1, 202, "comment/time".into(), Number(1)),
+                    TxData(1, 203, "comment/time".into(), Number(2)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((
+            vec![
+                Eid(100),
+                Aid("post/title".into()),
+                String("Gravity".to_string()),
+            ],
+            1,
+        ));
+        expected.insert((vec![Eid(100), Aid("post/comment".into()), Eid(201)], 1));
+        expected.insert((vec![Eid(100), Aid("post/comment".into()), Eid(203)], 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
+
+#[test]
+fn pull_collect_values() {
+    timely::execute_directly(|worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::PullLevel(PullLevel {
+            variables: vec![],
+            plan: Box::new(Plan::Project(Project {
+                variables: vec![e],
+                plan: Box::new(Plan::MatchA(e, "name".into(), n)),
+            })),
+            pull_attributes: vec!["alias".into()],
+            wildcard: false,
+            limit: None,
+            sort_attribute: None,
+            sort_descending: false,
+            order_by: None,
+            order_descending: false,
+            collect_values: true,
+            path_attributes: vec![],
+            aliased_attributes: vec![],
+            reverse_attributes: vec![],
+            nested: vec![],
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute("name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute("alias", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "pull_collect_values".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, "name".into(), String("Stanford".to_string())),
+                    TxData(1, 100, "alias".into(), String("Stan".to_string())),
+                    TxData(1, 100, "alias".into(), String("Mr. Mystery".to_string())),
+                    TxData(1, 100, "alias".into(), String("Grunkle Stan".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (
+                vec![
+                    Eid(100),
+                    Aid("alias".into()),
+                    List(vec![
+                        String("Grunkle Stan".to_string()),
+                        String("Mr. Mystery".to_string()),
+                        String("Stan".to_string()),
+                    ]),
+                ],
+                1
+            )
+        );
+        assert!(results.recv_timeout(Duration::from_millis(400)).is_err());
+    });
+}
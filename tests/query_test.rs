@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::iter::FromIterator;
 use std::sync::mpsc::channel;
@@ -6,11 +7,18 @@ use std::time::Duration;
 use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::Operator;
 
+use declarative_dataflow::binding::BinaryPredicate::LT;
 use declarative_dataflow::binding::Binding;
-use declarative_dataflow::plan::{Implementable, Join, Project};
-use declarative_dataflow::server::Server;
-use declarative_dataflow::{q, Aid, AttributeConfig, InputSemantics, Plan, Rule, TxData, Value};
-use Value::{Eid, Number, String};
+use declarative_dataflow::plan::{
+    Constant, Filter, Implementable, Join, NotExists, Predicate, Project, Rename, Sample, ThetaJoin,
+};
+use declarative_dataflow::server::{Config, Register, Server};
+use declarative_dataflow::{
+    q, Aid, AttributeConfig, ErrorKind, InputSemantics, Plan, Rule, RuleKind, TxData, Value,
+};
+use Value::{Eid, Number, Rational32, String};
+
+use num_rational::Ratio;
 
 struct Case {
     description: &'static str,
@@ -62,6 +70,7 @@ fn run_cases(mut cases: Vec<Case>) {
                         scope,
                         Rule {
                             name: "query".to_string(),
+                            kind: RuleKind::Recursive,
                             plan,
                         },
                     )
@@ -116,15 +125,15 @@ fn run_cases(mut cases: Vec<Case>) {
 #[test]
 fn base_patterns() {
     let data = vec![
-        TxData(1, 100, ":name".to_string(), String("Dipper".to_string())),
-        TxData(1, 100, ":name".to_string(), String("Alias".to_string())),
-        TxData(1, 200, ":name".to_string(), String("Mabel".to_string())),
+        TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+        TxData(1, 100, ":name".into(), String("Alias".to_string())),
+        TxData(1, 200, ":name".into(), String("Mabel".to_string())),
     ];
 
     run_cases(vec![
         Case {
             description: "[:find ?e ?n :where [?e :name ?n]]",
-            plan: Plan::MatchA(0, ":name".to_string(), 1),
+            plan: Plan::MatchA(0, ":name".into(), 1),
             transactions: vec![data.clone()],
             expectations: vec![vec![
                 (vec![Eid(100), String("Dipper".to_string())], 0, 1),
@@ -134,7 +143,7 @@ fn base_patterns() {
         },
         Case {
             description: "[:find ?n :where [100 :name ?n]]",
-            plan: Plan::MatchEA(100, ":name".to_string(), 0),
+            plan: Plan::MatchEA(100, ":name".into(), 0),
             transactions: vec![data.clone()],
             expectations: vec![vec![
                 (vec![String("Alias".to_string())], 0, 1),
@@ -143,7 +152,7 @@ fn base_patterns() {
         },
         Case {
             description: "[:find ?e :where [?e :name Mabel]]",
-            plan: Plan::MatchAV(0, ":name".to_string(), String("Mabel".to_string())),
+            plan: Plan::MatchAV(0, ":name".into(), String("Mabel".to_string())),
             transactions: vec![data.clone()],
             expectations: vec![vec![(vec![Eid(200)], 0, 1)]],
         },
@@ -153,9 +162,9 @@ fn base_patterns() {
 #[test]
 fn wco_base_patterns() {
     let data = vec![
-        TxData(1, 100, ":name".to_string(), String("Dipper".to_string())),
-        TxData(1, 100, ":name".to_string(), String("Alias".to_string())),
-        TxData(1, 200, ":name".to_string(), String("Mabel".to_string())),
+        TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+        TxData(1, 100, ":name".into(), String("Alias".to_string())),
+        TxData(1, 200, ":name".into(), String("Mabel".to_string())),
     ];
 
     run_cases(vec![
@@ -209,13 +218,13 @@ fn joins() {
                 variables: vec![e, n, a],
                 plan: Box::new(Plan::Join(Join {
                     variables: vec![e],
-                    left_plan: Box::new(Plan::MatchA(e, ":name".to_string(), n)),
-                    right_plan: Box::new(Plan::MatchA(e, ":age".to_string(), a)),
+                    left_plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
+                    right_plan: Box::new(Plan::MatchA(e, ":age".into(), a)),
                 })),
             }),
             transactions: vec![vec![
-                TxData(1, 1, ":name".to_string(), String("Dipper".to_string())),
-                TxData(1, 1, ":age".to_string(), Number(12)),
+                TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+                TxData(1, 1, ":age".into(), Number(12)),
             ]],
             expectations: vec![vec![(
                 vec![Eid(1), String("Dipper".to_string()), Number(12)],
@@ -226,16 +235,207 @@ fn joins() {
     }]);
 }
 
+#[test]
+fn distinct_projection() {
+    let (e, a) = (1, 2);
+    run_cases(vec![Case {
+        description: "[:find ?a :where [?e :age ?a]]",
+        plan: Plan::ProjectDistinct(Project {
+            variables: vec![a],
+            plan: Box::new(Plan::MatchA(e, ":age".into(), a)),
+        }),
+        transactions: vec![vec![
+            TxData(1, 1, ":age".into(), Number(12)),
+            TxData(1, 2, ":age".into(), Number(12)),
+            TxData(1, 3, ":age".into(), Number(13)),
+        ]],
+        expectations: vec![vec![(vec![Number(12)], 0, 1), (vec![Number(13)], 0, 1)]],
+    }]);
+}
+
+#[test]
+fn renamed_joins() {
+    // Both sub-plans bind their values to variable 2.
+    let (e, v, renamed) = (1, 2, 3);
+    let names = Plan::MatchA(e, ":name".into(), v);
+    let ages = Plan::MatchA(e, ":age".into(), v);
+
+    let transactions = vec![vec![
+        TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+        TxData(1, 1, ":age".into(), Number(12)),
+    ]];
+
+    let expectations = vec![vec![(
+        vec![Eid(1), String("Dipper".to_string()), Number(12)],
+        0,
+        1,
+    )]];
+
+    run_cases(vec![
+        Case {
+            description: "[:find ?e ?n ?a :where [?e :name ?n] [?e :age ?a]] (join)",
+            plan: Plan::Project(Project {
+                variables: vec![e, v, renamed],
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![e],
+                    left_plan: Box::new(names.clone()),
+                    right_plan: Box::new(Plan::Rename(Rename {
+                        mapping: vec![(v, renamed)].into_iter().collect(),
+                        plan: Box::new(ages.clone()),
+                    })),
+                })),
+            }),
+            transactions: transactions.clone(),
+            expectations: expectations.clone(),
+        },
+        Case {
+            description: "[:find ?e ?n ?a :where [?e :name ?n] [?e :age ?a]] (hector)",
+            plan: {
+                let mut bindings = names.into_bindings();
+                bindings.extend(
+                    Plan::Rename(Rename {
+                        mapping: vec![(v, renamed)].into_iter().collect(),
+                        plan: Box::new(ages),
+                    })
+                    .into_bindings(),
+                );
+
+                q(vec![e, v, renamed], bindings)
+            },
+            transactions,
+            expectations,
+        },
+    ]);
+}
+
+#[test]
+fn theta_joins() {
+    let data = vec![
+        TxData(1, 1, ":time".into(), Number(10)),
+        TxData(1, 1, ":group".into(), Number(1)),
+        TxData(1, 2, ":time".into(), Number(20)),
+        TxData(1, 2, ":group".into(), Number(2)),
+        TxData(1, 3, ":time".into(), Number(30)),
+        TxData(1, 3, ":group".into(), Number(1)),
+    ];
+
+    run_cases(vec![
+        {
+            let (e1, t1, e2, t2) = (1, 2, 3, 4);
+            Case {
+                description: "[:find ?e1 ?e2 :where [?e1 :time ?t1] [?e2 :time ?t2] [(< ?t1 ?t2)]]",
+                plan: Plan::ThetaJoin(ThetaJoin {
+                    variables: vec![],
+                    predicate_variables: (t1, t2),
+                    predicate: LT,
+                    left_plan: Box::new(Plan::MatchA(e1, ":time".into(), t1)),
+                    right_plan: Box::new(Plan::MatchA(e2, ":time".into(), t2)),
+                }),
+                transactions: vec![data.clone()],
+                expectations: vec![vec![
+                    (vec![Eid(1), Number(10), Eid(2), Number(20)], 0, 1),
+                    (vec![Eid(1), Number(10), Eid(3), Number(30)], 0, 1),
+                    (vec![Eid(2), Number(20), Eid(3), Number(30)], 0, 1),
+                ]],
+            }
+        },
+        {
+            let (e1, t1, e2, t2, g) = (1, 2, 3, 4, 5);
+            Case {
+                description: "[:find ?e1 ?e2 :where [?e1 :group ?g] [?e1 :time ?t1] [?e2 :group ?g] [?e2 :time ?t2] [(< ?t1 ?t2)]]",
+                plan: Plan::ThetaJoin(ThetaJoin {
+                    variables: vec![g],
+                    predicate_variables: (t1, t2),
+                    predicate: LT,
+                    left_plan: Box::new(q(
+                        vec![e1, g, t1],
+                        vec![
+                            Binding::attribute(e1, ":group", g),
+                            Binding::attribute(e1, ":time", t1),
+                        ],
+                    )),
+                    right_plan: Box::new(q(
+                        vec![e2, g, t2],
+                        vec![
+                            Binding::attribute(e2, ":group", g),
+                            Binding::attribute(e2, ":time", t2),
+                        ],
+                    )),
+                }),
+                transactions: vec![data.clone()],
+                expectations: vec![vec![(
+                    vec![Number(1), Eid(1), Number(10), Eid(3), Number(30)],
+                    0,
+                    1,
+                )]],
+            }
+        },
+        {
+            // Numbers are compared by magnitude, strings never match numbers.
+            let (e1, s1, e2, s2) = (1, 2, 3, 4);
+            Case {
+                description: "[:find ?e1 ?e2 :where [?e1 :size ?s1] [?e2 :size ?s2] [(< ?s1 ?s2)]]",
+                plan: Plan::ThetaJoin(ThetaJoin {
+                    variables: vec![],
+                    predicate_variables: (s1, s2),
+                    predicate: LT,
+                    left_plan: Box::new(Plan::MatchA(e1, ":size".into(), s1)),
+                    right_plan: Box::new(Plan::MatchA(e2, ":size".into(), s2)),
+                }),
+                transactions: vec![vec![
+                    TxData(1, 10, ":size".into(), Number(1)),
+                    TxData(1, 11, ":size".into(), Rational32(Ratio::new(3, 2))),
+                    TxData(1, 12, ":size".into(), Rational32(Ratio::new(1, 2))),
+                    TxData(1, 13, ":size".into(), String("big".to_string())),
+                    TxData(1, 14, ":size".into(), String("huge".to_string())),
+                ]],
+                expectations: vec![vec![
+                    (
+                        vec![Eid(10), Number(1), Eid(11), Rational32(Ratio::new(3, 2))],
+                        0,
+                        1,
+                    ),
+                    (
+                        vec![Eid(12), Rational32(Ratio::new(1, 2)), Eid(10), Number(1)],
+                        0,
+                        1,
+                    ),
+                    (
+                        vec![
+                            Eid(12),
+                            Rational32(Ratio::new(1, 2)),
+                            Eid(11),
+                            Rational32(Ratio::new(3, 2)),
+                        ],
+                        0,
+                        1,
+                    ),
+                    (
+                        vec![
+                            Eid(13),
+                            String("big".to_string()),
+                            Eid(14),
+                            String("huge".to_string()),
+                        ],
+                        0,
+                        1,
+                    ),
+                ]],
+            }
+        },
+    ]);
+}
+
 #[test]
 fn wco_joins() {
     let data = vec![
-        TxData(1, 1, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 1, ":age".to_string(), Number(15)),
-        TxData(1, 2, ":name".to_string(), String("Petr".to_string())),
-        TxData(1, 2, ":age".to_string(), Number(37)),
-        TxData(1, 3, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 3, ":age".to_string(), Number(37)),
-        TxData(1, 4, ":age".to_string(), Number(15)),
+        TxData(1, 1, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 1, ":age".into(), Number(15)),
+        TxData(1, 2, ":name".into(), String("Petr".to_string())),
+        TxData(1, 2, ":age".into(), Number(37)),
+        TxData(1, 3, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 3, ":age".into(), Number(37)),
+        TxData(1, 4, ":age".into(), Number(15)),
     ];
 
     run_cases(vec![
@@ -308,12 +508,12 @@ fn wco_joins() {
 #[test]
 fn wco_join_many() {
     let data = vec![
-        TxData(1, 1, ":name".to_string(), String("Ivan".to_string())),
-        TxData(1, 1, ":aka".to_string(), String("ivolga".to_string())),
-        TxData(1, 1, ":aka".to_string(), String("pi".to_string())),
-        TxData(1, 2, ":name".to_string(), String("Petr".to_string())),
-        TxData(1, 2, ":aka".to_string(), String("porosenok".to_string())),
-        TxData(1, 2, ":aka".to_string(), String("pi".to_string())),
+        TxData(1, 1, ":name".into(), String("Ivan".to_string())),
+        TxData(1, 1, ":aka".into(), String("ivolga".to_string())),
+        TxData(1, 1, ":aka".into(), String("pi".to_string())),
+        TxData(1, 2, ":name".into(), String("Petr".to_string())),
+        TxData(1, 2, ":aka".into(), String("porosenok".to_string())),
+        TxData(1, 2, ":aka".into(), String("pi".to_string())),
     ];
 
     let (e1, x, e2, n1, n2) = (0, 1, 2, 3, 4);
@@ -369,21 +569,21 @@ fn wco_join_many() {
 //             variables: vec![e, n, a],
 //             plan: Box::new(Plan::Join(Join {
 //                 variables: vec![e],
-//                 left_plan: Box::new(Plan::MatchA(e, ":name".to_string(), n)),
+//                 left_plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
 //                 right_plan: Box::new(Plan::Filter(Filter {
 //                     variables: vec![a],
 //                     predicate: Predicate::LTE,
-//                     plan: Box::new(Plan::MatchA(e, ":age".to_string(), a)),
+//                     plan: Box::new(Plan::MatchA(e, ":age".into(), a)),
 //                     constants: constants,
 //                 })),
 //             })),
 //         }),
 //         transactions: vec![
 //             vec![
-//                 TxData(1, 100, ":name".to_string(), String("Dipper".to_string())),
-//                 TxData(1, 100, ":age".to_string(), Number(12)),
-//                 TxData(1, 100, ":name".to_string(), String("Soos".to_string())),
-//                 TxData(1, 100, ":age".to_string(), Number(30)),
+//                 TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+//                 TxData(1, 100, ":age".into(), Number(12)),
+//                 TxData(1, 100, ":name".into(), String("Soos".to_string())),
+//                 TxData(1, 100, ":age".into(), Number(30)),
 //             ],
 //         ],
 //         expectations: vec![
@@ -391,3 +591,487 @@ fn wco_join_many() {
 //         ],
 //     }
 // },
+
+#[test]
+fn real_time_plans() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<Duration, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n, a) = (1, 2, 3);
+        let plan = q(
+            vec![e, n, a],
+            vec![
+                Binding::attribute(e, ":name", n),
+                Binding::attribute(e, ":age", a),
+            ],
+        );
+
+        worker.dataflow::<Duration, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(
+                    ":name",
+                    AttributeConfig::real_time(InputSemantics::Raw),
+                    scope,
+                )
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute(
+                    ":age",
+                    AttributeConfig::real_time(InputSemantics::Raw),
+                    scope,
+                )
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "real_time".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.1, x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, ":age".into(), Number(12)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, Duration::from_secs(1)).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (
+                vec![Eid(100), String("Dipper".to_string()), Number(12)],
+                Duration::from_secs(0),
+                1
+            )
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn numeric_filters() {
+    assert_eq!(
+        Number(2).numeric_cmp(&Rational32(Ratio::new(3, 2))),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(
+        Rational32(Ratio::new(4, 2)).numeric_cmp(&Number(2)),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(Number(2).numeric_cmp(&String("2".to_string())), None);
+
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, amount) = (1, 2);
+
+        // [:find ?e ?amount :where [?e :amount ?amount] [(> ?amount 1)]]
+        let plan = Plan::Filter(Filter {
+            variables: vec![amount],
+            predicate: Predicate::GT,
+            plan: Box::new(Plan::MatchA(e, ":amount".into(), amount)),
+            constants: vec![None, Some(Number(1))],
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(
+                    ":amount",
+                    AttributeConfig::tx_time(InputSemantics::Raw),
+                    scope,
+                )
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "numeric_filter".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":amount".into(), Number(2)),
+                    TxData(1, 200, ":amount".into(), Rational32(Ratio::new(3, 2))),
+                    TxData(1, 300, ":amount".into(), Rational32(Ratio::new(1, 2))),
+                    TxData(1, 400, ":amount".into(), Number(1)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received = vec![results.recv().unwrap(), results.recv().unwrap()];
+        received.sort();
+
+        assert_eq!(
+            received,
+            vec![
+                (vec![Eid(100), Number(2)], 1),
+                (vec![Eid(200), Rational32(Ratio::new(3, 2))], 1),
+            ]
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn variable_filters() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, start, end) = (1, 2, 3);
+
+        // [:find ?e ?start ?end :where [?e :start ?start] [?e :end ?end] [(< ?start ?end)]]
+        let plan = Plan::Project(Project {
+            variables: vec![e, start, end],
+            plan: Box::new(Plan::Filter(Filter {
+                variables: vec![start, end],
+                predicate: Predicate::LT,
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![e],
+                    left_plan: Box::new(Plan::MatchA(e, ":start".into(), start)),
+                    right_plan: Box::new(Plan::MatchA(e, ":end".into(), end)),
+                })),
+                constants: vec![None, None],
+            })),
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for name in [":start", ":end"].iter() {
+                server
+                    .context
+                    .internal
+                    .create_attribute(name, AttributeConfig::tx_time(InputSemantics::Raw), scope)
+                    .unwrap();
+            }
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "variable_filter".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":start".into(), Number(1)),
+                    TxData(1, 100, ":end".into(), Rational32(Ratio::new(3, 2))),
+                    TxData(1, 200, ":start".into(), Number(2)),
+                    TxData(1, 200, ":end".into(), Number(2)),
+                    TxData(1, 300, ":start".into(), Number(1)),
+                    TxData(1, 300, ":end".into(), String("2".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), Number(1), Rational32(Ratio::new(3, 2))], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn not_exists() {
+    let (user, name, order) = (1, 2, 3);
+    run_cases(vec![Case {
+        description:
+            "[:find ?user ?name :where [?user :user/name ?name] (not [?order :order/user ?user])]",
+        plan: Plan::NotExists(NotExists {
+            left_plan: Box::new(Plan::MatchA(user, ":user/name".into(), name)),
+            right_plan: Box::new(Plan::MatchA(order, ":order/user".into(), user)),
+        }),
+        transactions: vec![
+            vec![
+                TxData(1, 100, ":user/name".into(), String("Dipper".to_string())),
+                TxData(1, 200, ":user/name".into(), String("Mabel".to_string())),
+                TxData(1, 300, ":order/user".into(), Eid(100)),
+            ],
+            vec![TxData(1, 400, ":order/user".into(), Eid(200))],
+        ],
+        expectations: vec![
+            vec![(vec![Eid(200), String("Mabel".to_string())], 0, 1)],
+            vec![(vec![Eid(200), String("Mabel".to_string())], 1, -1)],
+        ],
+    }]);
+}
+
+#[test]
+fn not_exists_conjunction() {
+    timely::execute_directly(move |worker| {
+        // The optimizer implements rules via Hector, which can't
+        // express the negation of a conjunction.
+        let config = Config {
+            enable_optimizer: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+        let (send_results, results) = channel();
+
+        let (user, name, order, paid) = (1, 2, 3, 4);
+        let plan = Plan::NotExists(NotExists {
+            left_plan: Box::new(Plan::MatchA(user, ":user/name".into(), name)),
+            right_plan: Box::new(Plan::Join(Join {
+                variables: vec![order],
+                left_plan: Box::new(Plan::MatchA(order, ":order/user".into(), user)),
+                right_plan: Box::new(Plan::MatchA(order, ":order/paid".into(), paid)),
+            })),
+        });
+
+        assert!(plan.negates_conjunction());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for aid in &[":user/name", ":order/user", ":order/paid"] {
+                server
+                    .context
+                    .internal
+                    .create_attribute(aid, AttributeConfig::tx_time(InputSemantics::Raw), scope)
+                    .unwrap();
+            }
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "unpaid".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":user/name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, ":user/name".into(), String("Mabel".to_string())),
+                    TxData(1, 300, ":order/user".into(), Eid(100)),
+                    TxData(1, 300, ":order/paid".into(), Value::Bool(true)),
+                    TxData(1, 400, ":order/user".into(), Eid(200)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(200), String("Mabel".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn sample() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, v) = (1, 2);
+        let plan = Plan::Sample(Sample {
+            plan: Box::new(Plan::MatchA(e, ":num".into(), v)),
+            size: 3,
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":num", AttributeConfig::tx_time(InputSemantics::Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "sample".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let tx_data: Vec<TxData> = (0..10)
+            .map(|i| TxData(1, 100 + i, ":num".into(), Number(i as i64)))
+            .collect();
+
+        server.transact(tx_data, 0, 0).unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let sampled: Vec<(Vec<Value>, isize)> = results.try_iter().collect();
+
+        assert_eq!(sampled.len(), 3);
+        assert!(sampled.iter().all(|(_tuple, diff)| *diff == 1));
+
+        // Retracting a sampled tuple has another one take its place.
+        let retracted = sampled[0].0.clone();
+        let retracted_e = match retracted[0] {
+            Eid(e) => e,
+            _ => panic!("expected an eid"),
+        };
+
+        server
+            .transact(
+                vec![TxData(-1, retracted_e, ":num".into(), retracted[1].clone())],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let changes: Vec<(Vec<Value>, isize)> = results.try_iter().collect();
+
+        assert_eq!(changes.len(), 2);
+        assert!(changes.contains(&(retracted, -1)));
+        assert!(changes
+            .iter()
+            .any(|(tuple, diff)| *diff == 1 && !sampled.contains(&(tuple.clone(), 1))));
+    });
+}
+
+#[test]
+fn constant() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, v, label) = (1, 2, 3);
+        let plan = Plan::Join(Join {
+            variables: vec![v],
+            left_plan: Box::new(Plan::MatchA(e, ":num".into(), v)),
+            right_plan: Box::new(Plan::Constant(Constant {
+                variables: vec![v, label],
+                tuples: vec![
+                    vec![Number(1), String("one".to_string())],
+                    vec![Number(2), String("two".to_string())],
+                ],
+            })),
+        });
+
+        assert_eq!(
+            server
+                .register(Register {
+                    rules: vec![Rule {
+                        name: "malformed".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::Constant(Constant {
+                            variables: vec![v, label],
+                            tuples: vec![vec![Number(1)]],
+                        }),
+                    }],
+                    publish: vec![],
+                })
+                .unwrap_err()
+                .category,
+            ErrorKind::Incorrect
+        );
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":num", AttributeConfig::tx_time(InputSemantics::Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "constant".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":num".into(), Number(1)),
+                    TxData(1, 200, ":num".into(), Number(3)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Number(1), Eid(100), String("one".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
@@ -1,10 +1,13 @@
 use std::sync::mpsc::channel;
 
-use declarative_dataflow::plan::{Join, Project};
-use declarative_dataflow::server::Server;
-use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, TxData, Value};
+use declarative_dataflow::binding::Binding;
+use declarative_dataflow::plan::{Hector, Join, Project, PullLevel};
+use declarative_dataflow::server::{Config, Register, Server};
+use declarative_dataflow::{
+    AttributeConfig, ErrorKind, InputSemantics, Plan, Rule, RuleKind, TxData, Value,
+};
 use InputSemantics::Raw;
-use Value::{Eid, String};
+use Value::{Bool, Eid, Number, String};
 
 #[test]
 fn match_ea_after_input() {
@@ -13,7 +16,7 @@ fn match_ea_after_input() {
         let (send_results, results) = channel();
 
         // [:find ?v :where [1 :name ?n]]
-        let plan = Plan::MatchEA(1, ":name".to_string(), 1);
+        let plan = Plan::MatchEA(1, ":name".into(), 1);
 
         worker.dataflow::<u64, _, _>(|scope| {
             server
@@ -24,9 +27,9 @@ fn match_ea_after_input() {
         });
 
         let tx_data = vec![
-            TxData(1, 1, ":name".to_string(), String("Dipper".to_string())),
-            TxData(1, 1, ":name".to_string(), String("Alias".to_string())),
-            TxData(1, 2, ":name".to_string(), String("Mabel".to_string())),
+            TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+            TxData(1, 1, ":name".into(), String("Alias".to_string())),
+            TxData(1, 2, ":name".into(), String("Mabel".to_string())),
         ];
 
         server.transact(tx_data, 0, 0).unwrap();
@@ -41,6 +44,7 @@ fn match_ea_after_input() {
                     scope,
                     Rule {
                         name: "match_ea".to_string(),
+                        kind: RuleKind::Recursive,
                         plan,
                     },
                 )
@@ -91,7 +95,7 @@ fn join_after_input() {
             let tx_data = vec![TxData(
                 1,
                 1,
-                ":user/id".to_string(),
+                ":user/id".into(),
                 String("123-456-789".to_string()),
             )];
 
@@ -106,7 +110,7 @@ fn join_after_input() {
             let tx_data = vec![TxData(
                 1,
                 101,
-                ":transfer/from".to_string(),
+                ":transfer/from".into(),
                 String("123-456-789".to_string()),
             )];
 
@@ -123,8 +127,8 @@ fn join_after_input() {
                 variables: vec![transfer, sender],
                 plan: Box::new(Plan::Join(Join {
                     variables: vec![uuid],
-                    left_plan: Box::new(Plan::MatchA(transfer, ":transfer/from".to_string(), uuid)),
-                    right_plan: Box::new(Plan::MatchA(sender, ":user/id".to_string(), uuid)),
+                    left_plan: Box::new(Plan::MatchA(transfer, ":transfer/from".into(), uuid)),
+                    right_plan: Box::new(Plan::MatchA(sender, ":user/id".into(), uuid)),
                 })),
             });
 
@@ -133,6 +137,7 @@ fn join_after_input() {
                     scope,
                     Rule {
                         name: "join".to_string(),
+                        kind: RuleKind::Recursive,
                         plan,
                     },
                 )
@@ -148,3 +153,427 @@ fn join_after_input() {
         assert_eq!(results.recv().unwrap(), (vec![Eid(101), Eid(1)], 1));
     });
 }
+
+#[test]
+fn match_ea_optimized() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            enable_optimizer: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+        let (send_results, results) = channel();
+
+        // [:find ?n :where [1 :name ?n]]
+        let plan = Plan::MatchEA(1, ":name".into(), 1);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "match_ea_optimized".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let tx_data = vec![
+            TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+            TxData(1, 2, ":name".into(), String("Mabel".to_string())),
+        ];
+
+        server.transact(tx_data, 0, 0).unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![String("Dipper".to_string())], 1)
+        );
+    });
+}
+
+#[test]
+fn match_av_optimized() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            enable_optimizer: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+        let (send_results, results) = channel();
+
+        // [:find ?e :where [?e :name "Mabel"]]
+        let plan = Plan::MatchAV(1, ":name".into(), String("Mabel".to_string()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "match_av_optimized".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let tx_data = vec![
+            TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+            TxData(1, 2, ":name".into(), String("Mabel".to_string())),
+        ];
+
+        server.transact(tx_data, 0, 0).unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(results.recv().unwrap(), (vec![Eid(2)], 1));
+    });
+}
+
+#[test]
+fn pull_unknown_attribute() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        let (e,) = (1,);
+        let plan = Plan::PullLevel(PullLevel {
+            variables: vec![],
+            plan: Box::new(Plan::MatchA(e, ":name".into(), 2)),
+            pull_attributes: vec![":age".into()],
+            wildcard: false,
+            limit: None,
+            sort_attribute: None,
+            sort_descending: false,
+            order_by: None,
+            order_descending: false,
+            collect_values: false,
+            path_attributes: vec![],
+            aliased_attributes: vec![],
+            reverse_attributes: vec![],
+            nested: vec![],
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .register(Register {
+                    rules: vec![Rule {
+                        name: "pull_unknown".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    }],
+                    publish: vec!["pull_unknown".to_string()],
+                })
+                .unwrap();
+
+            match server.interest("pull_unknown", scope) {
+                Ok(_) => panic!("Expected interest to fail."),
+                Err(error) => assert_eq!(error.category, ErrorKind::NotFound),
+            }
+        });
+    });
+}
+
+#[test]
+fn join_rule_optimized() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            enable_optimizer: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":admin?", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .register(Register {
+                    rules: vec![Rule {
+                        name: "admins".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::Project(Project {
+                            variables: vec![e],
+                            plan: Box::new(Plan::MatchAV(e, ":admin?".into(), Bool(true))),
+                        }),
+                    }],
+                    publish: vec![],
+                })
+                .unwrap();
+
+            // [:find ?e ?n :where (admins ?e) [?e :name ?n]]
+            let plan = Plan::Project(Project {
+                variables: vec![e, n],
+                plan: Box::new(Plan::Join(Join {
+                    variables: vec![e],
+                    left_plan: Box::new(Plan::NameExpr(vec![e], "admins".to_string())),
+                    right_plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
+                })),
+            });
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "admin_names".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let tx_data = vec![
+            TxData(1, 100, ":admin?".into(), Bool(true)),
+            TxData(1, 200, ":admin?".into(), Bool(false)),
+            TxData(1, 100, ":name".into(), String("Mabel".to_string())),
+            TxData(1, 200, ":name".into(), String("Dipper".to_string())),
+        ];
+
+        server.transact(tx_data, 0, 0).unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Mabel".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn reverse_index_on_demand() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 1, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 2, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert!(server.context.internal.reverse.is_empty());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            // [:find ?e :where [?e :name "Mabel"]]
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "mabel".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::MatchAV(1, ":name".into(), String("Mabel".to_string())),
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        assert!(server.context.internal.reverse.contains_key(":name"));
+
+        server
+            .transact(
+                vec![TxData(1, 3, ":name".into(), String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(results.recv().unwrap(), (vec![Eid(2)], 1));
+        assert_eq!(results.recv().unwrap(), (vec![Eid(3)], 1));
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn register_unbound_join_variable() {
+    let mut server = Server::<u64, u64>::new(Default::default());
+
+    let (e, n, a) = (1, 2, 3);
+
+    // The right side doesn't bind the join variable ?n.
+    let plan = Plan::Project(Project {
+        variables: vec![e],
+        plan: Box::new(Plan::Join(Join {
+            variables: vec![n],
+            left_plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
+            right_plan: Box::new(Plan::MatchA(e, ":age".into(), a)),
+        })),
+    });
+
+    let result = server.register(Register {
+        rules: vec![Rule {
+            name: "unbound".to_string(),
+            kind: RuleKind::Recursive,
+            plan,
+        }],
+        publish: vec![],
+    });
+
+    match result {
+        Ok(_) => panic!("Expected registration to fail."),
+        Err(error) => assert_eq!(error.category, ErrorKind::Incorrect),
+    }
+
+    assert!(!server.context.rules.contains_key("unbound"));
+}
+
+#[test]
+fn constrained_rules_optimized() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            enable_optimizer: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+        let (send_results, results) = channel();
+
+        let (e, n, a) = (1, 2, 3);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for aid in &[":admin?", ":name", ":age"] {
+                server
+                    .context
+                    .internal
+                    .create_attribute(aid, AttributeConfig::tx_time(Raw), scope)
+                    .unwrap();
+            }
+
+            server
+                .register(Register {
+                    rules: vec![
+                        Rule {
+                            name: "admins".to_string(),
+                            kind: RuleKind::Recursive,
+                            plan: Plan::Project(Project {
+                                variables: vec![e],
+                                plan: Box::new(Plan::MatchAV(e, ":admin?".to_string(), Bool(true))),
+                            }),
+                        },
+                        Rule {
+                            name: "ages".to_string(),
+                            kind: RuleKind::Recursive,
+                            plan: Plan::MatchA(e, ":age".to_string(), a),
+                        },
+                    ],
+                    publish: vec![],
+                })
+                .unwrap();
+
+            // [:find ?e ?n :where [?e :name ?n] (not (admins ?e)) (ages ?e 12)]
+            let plan = Plan::Hector(Hector {
+                variables: vec![e, n],
+                bindings: vec![
+                    Binding::attribute(e, ":name", n),
+                    Binding::not(Binding::relation("admins", vec![e])),
+                    Binding::relation("ages", vec![e, a]),
+                    Binding::constant(a, Number(12)),
+                ],
+            });
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "young_non_admins".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let tx_data = vec![
+            TxData(1, 100, ":admin?".to_string(), Bool(true)),
+            TxData(1, 100, ":name".to_string(), String("Mabel".to_string())),
+            TxData(1, 100, ":age".to_string(), Number(12)),
+            TxData(1, 200, ":name".to_string(), String("Dipper".to_string())),
+            TxData(1, 200, ":age".to_string(), Number(12)),
+            TxData(1, 300, ":name".to_string(), String("Soos".to_string())),
+            TxData(1, 300, ":age".to_string(), Number(22)),
+        ];
+
+        server.transact(tx_data, 0, 0).unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(200), String("Dipper".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
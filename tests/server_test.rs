@@ -0,0 +1,1983 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::binding::Binding;
+use declarative_dataflow::plan::{Function, Hector, Join, Project, Transform, Union};
+use declarative_dataflow::server::{
+    debug_name, Config, CreateAttribute, Materialize, Register, Request, RequestAuthorizer, Server,
+    META_DATOMS, META_DOMAIN, META_ENTITIES, META_IDENT, META_NOW,
+};
+use declarative_dataflow::{
+    is_tempid, tempid, AttributeConfig, ErrorKind, InputSemantics, Partitioning, Plan, Rule,
+    RuleKind, RuleSemantics, TxData, Value, ValueType,
+};
+use InputSemantics::{CardinalityOne, Raw};
+use Value::{Aid, Eid, Number, String};
+
+#[test]
+fn status() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(CardinalityOne), scope)
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute(":age", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":name".into(), 2),
+                }],
+                publish: vec![],
+            })
+            .unwrap();
+
+        let mut tokens = HashSet::new();
+        tokens.insert(1);
+        server.interests.insert("names".to_string(), tokens);
+
+        server.advance_domain(None, 3).unwrap();
+
+        let status = server.status();
+
+        assert_eq!(
+            status.attributes.keys().cloned().collect::<Vec<_>>(),
+            vec![":age", ":name"]
+        );
+        assert_eq!(
+            status.attributes[":name"],
+            AttributeConfig::tx_time(CardinalityOne)
+        );
+        assert!(status.sources.is_empty());
+        assert_eq!(status.rules, vec!["names".to_string()]);
+        assert_eq!(status.interests, vec!["names".to_string()]);
+        assert!(status.sinks.is_empty());
+        assert_eq!(status.time, 3);
+    });
+}
+
+#[test]
+fn list_rules() {
+    let mut server = Server::<u64, u64>::new(Default::default());
+
+    let (e, n) = (1, 2);
+    let names = Rule {
+        name: "names".to_string(),
+        kind: RuleKind::Recursive,
+        plan: Plan::MatchA(e, ":name".into(), n),
+    };
+    let named = Rule {
+        name: "named".to_string(),
+        kind: RuleKind::Recursive,
+        plan: Plan::Project(Project {
+            variables: vec![e],
+            plan: Box::new(Plan::NameExpr(vec![e, n], "names".to_string())),
+        }),
+    };
+
+    server
+        .register(Register {
+            rules: vec![names.clone(), named.clone()],
+            publish: vec![],
+        })
+        .unwrap();
+
+    let graph = server.list_rules();
+
+    assert_eq!(graph.rules, vec![named, names]);
+    assert_eq!(
+        graph.edges,
+        vec![
+            (
+                "named".to_string(),
+                "df.rule/depends-on".into(),
+                String("names".to_string())
+            ),
+            (
+                "names".to_string(),
+                "df.rule/attribute".into(),
+                Aid(":name".into())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn dependencies() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        let (e, n) = (1, 2);
+        let names = Rule {
+            name: "names".to_string(),
+            kind: RuleKind::Recursive,
+            plan: Plan::MatchA(e, ":name".into(), n),
+        };
+        let named = Rule {
+            name: "named".to_string(),
+            kind: RuleKind::Recursive,
+            plan: Plan::Project(Project {
+                variables: vec![e],
+                plan: Box::new(Plan::NameExpr(vec![e, n], "names".to_string())),
+            }),
+        };
+
+        server
+            .register(Register {
+                rules: vec![names.clone(), named.clone()],
+                publish: vec![],
+            })
+            .unwrap();
+
+        let closure = server.dependencies("named").unwrap();
+
+        assert_eq!(closure.rules, vec![named, names]);
+        assert_eq!(closure.attributes, vec![":name"]);
+
+        match server.dependencies("unknown") {
+            Ok(_) => panic!("Expected an unknown rule to be rejected."),
+            Err(error) => {
+                assert_eq!(error.category, ErrorKind::NotFound);
+                assert_eq!(error.category.to_string(), "df.error.category/not-found");
+            }
+        }
+    });
+}
+
+#[test]
+fn explain() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        let (e, n, a) = (1, 2, 3);
+
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(e, ":name".into(), n),
+                }],
+                publish: vec![],
+            })
+            .unwrap();
+
+        let plan = Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::NameExpr(vec![e, n], "names".to_string())),
+            right_plan: Box::new(Plan::MatchA(e, ":age".into(), a)),
+        });
+
+        let explanation = server.explain(&plan);
+
+        assert_eq!(explanation.operator, "Join");
+        assert_eq!(
+            explanation.notes,
+            vec!["arranges both inputs by join variables"]
+        );
+        assert_eq!(explanation.inputs[0].operator, "NameExpr");
+        assert_eq!(explanation.inputs[1].operator, "MatchA");
+        assert_eq!(
+            explanation.inputs[1].notes,
+            vec!["attribute :age does not exist"]
+        );
+
+        assert_eq!(
+            explanation.to_string(),
+            "Join
+  - arranges both inputs by join variables
+  NameExpr
+    - re-uses local relation names, but not its arrangement
+  MatchA
+    - attribute :age does not exist
+"
+        );
+    });
+}
+
+#[test]
+fn shutdown() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.test_single(
+                scope,
+                Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":name".into(), 2),
+                },
+            );
+        });
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        server.shutdown().unwrap();
+
+        worker.step_while(|| !server.probe.done());
+
+        assert!(server.shutdown_handles.is_empty());
+        assert!(server
+            .transact(
+                vec![TxData(1, 200, ":name".into(), String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .is_err());
+    });
+}
+
+#[test]
+fn snapshot() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":name".into(), 2),
+                }],
+                publish: vec!["names".to_string()],
+            })
+            .unwrap();
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        server
+            .transact(
+                vec![TxData(-1, 200, ":name".into(), String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .snapshot("names", scope)
+                .unwrap()
+                .inspect(move |x| {
+                    send_results.send(x.clone()).unwrap();
+                })
+                .probe_with(&mut server.probe);
+        });
+
+        // Not part of the snapshot anymore.
+        server
+            .transact(
+                vec![TxData(1, 300, ":name".into(), String("Soos".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 3).unwrap();
+
+        worker.step_while(|| !server.probe.done());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 2, 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn materialize() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let path = std::env::temp_dir()
+            .join(format!("df-materialize-{}.json", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":name".into(), 2),
+                }],
+                publish: vec!["names".to_string()],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .materialize(
+                    Materialize {
+                        name: "names".to_string(),
+                        path: path.clone(),
+                    },
+                    scope,
+                )
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        while server.materialized_snapshot("names", &1).unwrap().is_none() {
+            worker.step();
+        }
+
+        server
+            .transact(
+                vec![TxData(-1, 200, ":name".into(), String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+
+        let mut snapshot = None;
+        while snapshot.is_none() {
+            worker.step();
+            snapshot = server.materialized_snapshot("names", &2).unwrap();
+        }
+
+        assert_eq!(
+            snapshot.unwrap(),
+            vec![(vec![Eid(100), String("Dipper".to_string())], 2, 1)]
+        );
+
+        // Snapshots as of earlier times don't see later changes.
+        assert_eq!(
+            server.materialized_snapshot("names", &1).unwrap().unwrap(),
+            vec![
+                (vec![Eid(100), String("Dipper".to_string())], 1, 1),
+                (vec![Eid(200), String("Mabel".to_string())], 1, 1),
+            ]
+        );
+
+        // Nor can they be served before the view has caught up.
+        assert!(server.materialized_snapshot("names", &3).unwrap().is_none());
+
+        match server.materialized_snapshot("unknown", &2) {
+            Ok(_) => panic!("Expected an unmaterialized relation to be rejected."),
+            Err(error) => assert_eq!(error.category, ErrorKind::NotFound),
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    });
+}
+
+#[test]
+fn aliases() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server.create_alias(":person/name", ":name").unwrap();
+
+        match server.create_alias(":name", ":person/name") {
+            Ok(_) => panic!("Expected an alias shadowing an attribute to be rejected."),
+            Err(error) => assert_eq!(error.category, ErrorKind::Conflict),
+        }
+
+        match server.create_alias(":person/age", ":age") {
+            Ok(_) => panic!("Expected an alias of an unknown attribute to be rejected."),
+            Err(error) => assert_eq!(error.category, ErrorKind::NotFound),
+        }
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let req = CreateAttribute {
+                name: ":person/name".to_string(),
+                config: AttributeConfig::tx_time(Raw),
+                domain: None,
+            };
+
+            match server.create_attribute(req, scope) {
+                Ok(_) => panic!("Expected an attribute shadowing an alias to be rejected."),
+                Err(error) => assert_eq!(error.category, ErrorKind::Conflict),
+            }
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":person/name".into(), 2),
+                }],
+                publish: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.interest("names", scope).unwrap().inspect(move |x| {
+                send_results.send(x.clone()).unwrap();
+            });
+        });
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 0, 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn named_domains() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig::tx_time(Raw),
+                        domain: Some("feed".to_string()),
+                    },
+                    scope,
+                )
+                .unwrap();
+
+            assert!(server
+                .create_attribute(
+                    CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig::tx_time(Raw),
+                        domain: None,
+                    },
+                    scope,
+                )
+                .is_err());
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "names".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::MatchA(1, ":name".into(), 2),
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send(x.clone()).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        // Advancing the internal domain leaves the feed untouched.
+        server.advance_domain(None, 5).unwrap();
+        assert_eq!(server.frontier(), 0);
+
+        // Writes and relations over the feed follow its time.
+        assert_eq!(server.tx_time(vec![":name"]), 0);
+        assert_eq!(server.relation_time("names"), 0);
+        assert_eq!(server.tx_time(vec![":unknown"]), 5);
+
+        server.advance_domain(Some("feed".to_string()), 1).unwrap();
+        assert_eq!(server.frontier(), 1);
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 0, 1)
+        );
+
+        assert!(server
+            .advance_domain(Some("unknown".to_string()), 1)
+            .is_err());
+    });
+}
+
+#[test]
+fn query_at() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            enable_history: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .register(Register {
+                    rules: vec![Rule {
+                        name: "names".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::MatchA(1, ":name".into(), 2),
+                    }],
+                    publish: vec!["names".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("names", scope)
+                .unwrap()
+                .probe_with(&mut server.probe);
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        server
+            .transact(
+                vec![TxData(-1, 200, ":name".into(), String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .query_at("names", 0, scope)
+                .unwrap()
+                .inspect(move |x| {
+                    send_results.send(x.clone()).unwrap();
+                })
+                .probe_with(&mut server.probe);
+
+            assert!(server.query_at("unknown", 0, scope).is_err());
+        });
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut names = vec![results.recv().unwrap(), results.recv().unwrap()];
+        names.sort();
+
+        assert_eq!(
+            names,
+            vec![
+                (vec![Eid(100), String("Dipper".to_string())], 0, 1),
+                (vec![Eid(200), String("Mabel".to_string())], 0, 1),
+            ]
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn query_at_without_history() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| match server.query_at("names", 0, scope) {
+            Ok(_) => panic!("Expected historical query to fail."),
+            Err(error) => assert_eq!(error.category, ErrorKind::Unsupported),
+        });
+    });
+}
+
+#[test]
+fn metrics() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .register(Register {
+                    rules: vec![Rule {
+                        name: "names".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::MatchA(1, ":name".into(), 2),
+                    }],
+                    publish: vec!["names".to_string()],
+                })
+                .unwrap();
+
+            server
+                .interest("names", scope)
+                .unwrap()
+                .probe_with(&mut server.probe);
+        });
+
+        let mut tokens = HashSet::new();
+        tokens.insert(1);
+        tokens.insert(2);
+        server.interests.insert("names".to_string(), tokens);
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let metrics = server.metrics();
+
+        assert_eq!(metrics.time, 1);
+        assert_eq!(metrics.frontier, vec![1]);
+        assert_eq!(
+            metrics.datoms,
+            vec![
+                (
+                    "names".to_string(),
+                    "df.metrics/interests".into(),
+                    Number(2)
+                ),
+                (
+                    "df.domain/internal".to_string(),
+                    "df.metrics/transacted".into(),
+                    Number(2)
+                ),
+            ]
+        );
+    });
+}
+
+#[test]
+fn statistics() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            enable_meta: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config.clone());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::Project(Project {
+            variables: vec![n],
+            plan: Box::new(Plan::Join(Join {
+                variables: vec![e],
+                left_plan: Box::new(Plan::MatchAV(e, META_IDENT.into(), Aid(":name".into()))),
+                right_plan: Box::new(Plan::MatchA(e, META_DATOMS.into(), n)),
+            })),
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for req in Server::<u64, u64>::builtins(&config).into_iter() {
+                if let Request::CreateAttribute(req) = req {
+                    server.create_attribute(req, scope).unwrap();
+                }
+            }
+
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "datoms".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 100, ":name".into(), String("Pines".to_string())),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let statistics = server.statistics().unwrap();
+
+        assert_eq!(statistics.time, 1);
+        assert!(statistics
+            .datoms
+            .contains(&(":name".into(), META_ENTITIES.into(), Number(2))));
+        assert!(statistics
+            .datoms
+            .contains(&(":name".into(), META_DATOMS.into(), Number(3))));
+
+        // Statistics are asserted as datoms, to be queried like any
+        // other attribute.
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(results.recv().unwrap(), (vec![Number(3)], 1));
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn partition_by_entity() {
+    let guards = timely::execute(timely::Configuration::Process(2), move |worker| {
+        let config = Config {
+            peers: worker.peers(),
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    CreateAttribute {
+                        name: ":name".to_string(),
+                        config: AttributeConfig {
+                            partitioning: Partitioning::Entity,
+                            ..AttributeConfig::tx_time(Raw)
+                        },
+                        domain: None,
+                    },
+                    scope,
+                )
+                .unwrap();
+        });
+
+        let tx_data: Vec<TxData> = (0..10)
+            .map(|e| TxData(1, e, ":name".into(), Number(e as i64)))
+            .collect();
+
+        // Every worker sees the transaction, as if sequenced, but
+        // only introduces the datoms on its own eids.
+        server.transact(tx_data, 0, worker.index()).unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let expected = (0..10)
+            .filter(|e| Partitioning::Entity.worker(*e, 0, worker.peers()) == worker.index())
+            .count();
+
+        assert_eq!(server.context.internal.transacted(), expected);
+
+        expected
+    })
+    .unwrap();
+
+    let transacted: Vec<usize> = guards
+        .join()
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect();
+
+    assert_eq!(transacted.iter().sum::<usize>(), 10);
+    assert!(transacted.iter().all(|count| *count > 0));
+}
+
+#[test]
+fn iteration_limit() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        // (counter ?y) :- [?e :start ?y]
+        // (counter ?y) :- (counter ?x) [(+ ?x 1) ?y]
+        let (e, x, y) = (1, 2, 3);
+        let plan = Plan::Union(Union {
+            variables: vec![y],
+            plans: vec![
+                Plan::MatchA(e, ":start".into(), y),
+                Plan::Transform(Transform {
+                    variables: vec![x],
+                    result_variable: y,
+                    plan: Box::new(Plan::NameExpr(vec![x], "counter".to_string())),
+                    function: Function::ADD,
+                    constants: vec![Some(Number(1))],
+                }),
+            ],
+            all: false,
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "counter".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan,
+                }],
+                publish: vec!["counter".to_string()],
+            })
+            .unwrap();
+
+        match server.set_iteration_limit("unknown", 5) {
+            Ok(_) => panic!("Expected limiting an unknown rule to fail."),
+            Err(error) => assert_eq!(error.category, ErrorKind::NotFound),
+        }
+
+        server.set_iteration_limit("counter", 5).unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":start", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .interest("counter", scope)
+                .unwrap()
+                .probe_with(&mut server.probe)
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        server
+            .transact(vec![TxData(1, 100, ":start".into(), Number(0))], 0, 0)
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut counts = HashSet::new();
+        while let Ok((tuple, diff)) = results.try_recv() {
+            assert_eq!(diff, 1);
+            counts.insert(tuple);
+        }
+
+        let expected: HashSet<Vec<Value>> = (0..5).map(|i| vec![Number(i)]).collect();
+
+        assert_eq!(counts, expected);
+
+        // The iterations reached are exposed as metrics.
+        assert!(server.metrics().datoms.contains(&(
+            "counter".to_string(),
+            "df.metrics/iterations".into(),
+            Number(5)
+        )));
+
+        // The rule has to be failed, dropping all interests in it.
+        assert_eq!(server.take_exceeded(), vec!["counter".to_string()]);
+        assert!(server.take_exceeded().is_empty());
+
+        server
+            .interests
+            .insert("counter".to_string(), vec![7].into_iter().collect());
+
+        let failed = server.fail_rule("counter");
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, "counter");
+        assert!(failed[0].1.contains(&7));
+        assert!(server.interests.is_empty());
+    });
+}
+
+#[test]
+fn allocate_eids() {
+    let config = Config {
+        eid_offset: 100,
+        ..Default::default()
+    };
+    let mut server = Server::<u64, u64>::new(config);
+
+    assert_eq!(server.allocate_eids(10).unwrap(), (100, 110));
+    assert_eq!(server.allocate_eids(1).unwrap(), (110, 111));
+
+    match server.allocate_eids(0) {
+        Ok(_) => panic!("Expected empty allocation to fail."),
+        Err(error) => assert_eq!(error.category, ErrorKind::Incorrect),
+    }
+
+    assert_eq!(server.allocate_eids(5).unwrap(), (111, 116));
+
+    // Ids from 2^63 upwards are reserved for placeholders.
+    let config = Config {
+        eid_offset: i64::max_value() as u64,
+        ..Default::default()
+    };
+    let mut server = Server::<u64, u64>::new(config);
+
+    match server.allocate_eids(2) {
+        Ok(_) => panic!("Expected allocation of placeholder ids to fail."),
+        Err(error) => assert_eq!(error.category, ErrorKind::Fault),
+    }
+
+    let (start, _end) = server.allocate_eids(1).unwrap();
+    assert_eq!(start, i64::max_value() as u64);
+    assert!(!is_tempid(start));
+    assert!(server.allocate_eids(1).is_err());
+}
+
+#[test]
+fn meta_time() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            enable_meta: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config.clone());
+        let (send_results, results) = channel();
+
+        let (e, t) = (1, 2);
+        let plan = Plan::MatchA(e, META_NOW.into(), t);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            for req in Server::<u64, u64>::builtins(&config).into_iter() {
+                if let Request::CreateAttribute(req) = req {
+                    server.create_attribute(req, scope).unwrap();
+                }
+            }
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "now".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        // Each time becomes visible once the domain has moved past it.
+        server.advance_domain(None, 1).unwrap();
+        server.advance_domain(None, 2).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(META_DOMAIN), Number(1)], 1)
+        );
+        assert!(results.try_recv().is_err());
+
+        server.advance_domain(None, 3).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut expected = HashSet::new();
+        expected.insert((vec![Eid(META_DOMAIN), Number(1)], -1));
+        expected.insert((vec![Eid(META_DOMAIN), Number(2)], 1));
+
+        for _i in 0..expected.len() {
+            let result = results.recv().unwrap();
+            if !expected.remove(&result) {
+                panic!("unknown result {:?}", result);
+            }
+        }
+
+        assert!(results.try_recv().is_err());
+    });
+}
+
+/// Only lets client 0 of worker 0 transact.
+struct ReadOnlyClients;
+
+impl RequestAuthorizer<u64> for ReadOnlyClients {
+    fn authorize(&self, owner: usize, client: &u64, request: &Request) -> bool {
+        match request {
+            Request::Transact(_) | Request::TransactBatch(_) => owner == 0 && *client == 0,
+            _ => true,
+        }
+    }
+}
+
+#[test]
+fn authorize() {
+    let mut server = Server::<u64, u64>::new(Default::default());
+    let transact = Request::Transact(vec![]);
+    let uninterest = Request::Uninterest("q".to_string());
+
+    assert!(server.authorize(0, &1, &transact).is_ok());
+
+    server.set_authorizer(ReadOnlyClients);
+
+    assert!(server.authorize(0, &0, &transact).is_ok());
+    assert!(server.authorize(0, &1, &uninterest).is_ok());
+
+    match server.authorize(0, &1, &transact) {
+        Ok(_) => panic!("Expected authorization to fail."),
+        Err(error) => assert_eq!(error.category, ErrorKind::Forbidden),
+    }
+
+    // Tokens are only unique per worker.
+    match server.authorize(1, &0, &transact) {
+        Ok(_) => panic!("Expected authorization to fail."),
+        Err(error) => assert_eq!(error.category, ErrorKind::Forbidden),
+    }
+}
+
+#[test]
+fn resolve_tempids() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            eid_offset: 100,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":parent/child", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "children".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::MatchA(1, ":parent/child".into(), 2),
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let tempids = server
+            .transact(
+                vec![
+                    TxData(1, tempid(-1), ":parent/child".into(), Eid(tempid(-2))),
+                    TxData(1, tempid(-2), ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        assert_eq!(tempids, vec![(tempid(-1), 100), (tempid(-2), 101)]);
+
+        // Placeholders are resolved anew in each transaction.
+        let tempids = server
+            .transact(
+                vec![TxData(1, tempid(-2), ":parent/child".into(), Eid(101))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        assert_eq!(tempids, vec![(tempid(-2), 102)]);
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received = vec![results.recv().unwrap(), results.recv().unwrap()];
+        received.sort();
+
+        assert_eq!(
+            received,
+            vec![(vec![Eid(100), Eid(101)], 1), (vec![Eid(102), Eid(101)], 1),]
+        );
+        assert_eq!(server.next_eid, 103);
+    });
+}
+
+#[test]
+fn value_types() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .create_attribute(
+                    CreateAttribute {
+                        name: ":age".to_string(),
+                        config: AttributeConfig {
+                            value_type: Some(ValueType::Number),
+                            ..AttributeConfig::tx_time(Raw)
+                        },
+                        domain: None,
+                    },
+                    scope,
+                )
+                .unwrap();
+        });
+
+        server
+            .transact(vec![TxData(1, 100, ":age".into(), Number(12))], 0, 0)
+            .unwrap();
+
+        match server.transact(
+            vec![
+                TxData(1, 200, ":age".into(), Number(12)),
+                TxData(1, 200, ":age".into(), String("twelve".to_string())),
+            ],
+            0,
+            0,
+        ) {
+            Ok(_) => panic!("Expected ill-typed transaction to fail."),
+            Err(error) => assert_eq!(error.category, ErrorKind::Incorrect),
+        }
+
+        assert_eq!(server.context.internal.transacted(), 1);
+    });
+}
+
+#[test]
+fn debug() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":name".into(), 2),
+                }],
+                publish: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.debug("names", scope).unwrap().inspect(move |x| {
+                send_results.send((x.0.clone(), x.2)).unwrap();
+            });
+        });
+
+        assert!(server.shutdown_handles.contains_key(&debug_name("names")));
+        assert!(!server.shutdown_handles.contains_key("names"));
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 1)
+        );
+    });
+}
+
+#[test]
+fn transact_async() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "names".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::MatchA(1, ":name".into(), 2),
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        let handle = server
+            .transact_async(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        assert_eq!(handle.time, 0);
+        assert!(!handle.is_visible());
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| !handle.is_visible());
+
+        assert_eq!(
+            results.try_recv().unwrap(),
+            (vec![Eid(100), String("Dipper".to_string())], 1)
+        );
+    });
+}
+
+#[test]
+fn transact_at() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "names".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::MatchA(1, ":name".into(), 2),
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send(x.clone()).unwrap();
+                });
+        });
+
+        server
+            .transact_at(
+                2,
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+        server
+            .transact_at(
+                5,
+                vec![TxData(1, 200, ":name".into(), String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        assert_eq!(server.context.internal.time(), &5);
+
+        // Times can't go backwards.
+        assert!(server
+            .transact_at(
+                3,
+                vec![TxData(1, 300, ":name".into(), String("Soos".to_string()))],
+                0,
+                0,
+            )
+            .is_err());
+
+        server.advance_domain(None, 6).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        let mut received: Vec<_> = results.try_iter().collect();
+        received.sort();
+
+        assert_eq!(
+            received,
+            vec![
+                (vec![Eid(100), String("Dipper".to_string())], 2, 1),
+                (vec![Eid(200), String("Mabel".to_string())], 5, 1),
+            ]
+        );
+    });
+}
+
+#[test]
+fn query() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, ":name".into(), String("Mabel".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        server
+            .transact(
+                vec![TxData(-1, 200, ":name".into(), String("Mabel".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 2).unwrap();
+
+        let results = server
+            .query(
+                worker,
+                Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":name".into(), 2),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            results,
+            vec![(vec![Eid(100), String("Dipper".to_string())], 2, 1)]
+        );
+
+        // Queried rules don't outlive the query, s.t. the name can be
+        // reused for a different plan.
+        assert!(!server.context.rules.contains_key("names"));
+
+        let results = server
+            .query(
+                worker,
+                Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchEA(100, ":name".into(), 2),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(results, vec![(vec![String("Dipper".to_string())], 2, 1)]);
+
+        // Rules registered beforehand are queried as they are, and
+        // kept around.
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "registered".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":name".into(), 2),
+                }],
+                publish: vec![],
+            })
+            .unwrap();
+
+        let results = server
+            .query(
+                worker,
+                Rule {
+                    name: "registered".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchEA(200, ":name".into(), 2),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            results,
+            vec![(vec![Eid(100), String("Dipper".to_string())], 2, 1)]
+        );
+        assert!(server.context.rules.contains_key("registered"));
+    });
+}
+
+#[test]
+fn intern_aids() {
+    timely::execute_directly(move |worker| {
+        let config = Config {
+            intern_aids: true,
+            ..Default::default()
+        };
+        let mut server = Server::<u64, u64>::new(config);
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":depends-on", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":depends-on".into(), Aid(":name".into())),
+                    TxData(1, 200, ":depends-on".into(), Aid(":name".into())),
+                    TxData(1, 300, ":depends-on".into(), Aid(":age".into())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        // Repeated identifiers share a single symbol.
+        assert_eq!(server.symbols.len(), 2);
+
+        server.advance_domain(None, 1).unwrap();
+
+        let mut results = server
+            .query(
+                worker,
+                Rule {
+                    name: "dependents".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchAV(1, ":depends-on".into(), Aid(":name".into())),
+                },
+            )
+            .unwrap();
+        results.sort();
+
+        assert_eq!(
+            results,
+            vec![(vec![Eid(100)], 1, 1), (vec![Eid(200)], 1, 1)]
+        );
+
+        let results = server
+            .query(
+                worker,
+                Rule {
+                    name: "dependencies".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchEA(300, ":depends-on".into(), 1),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(results, vec![(vec![Aid(":age".into())], 1, 1)]);
+    });
+}
+
+#[test]
+fn uninterest() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server.test_single(
+                scope,
+                Rule {
+                    name: "names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::MatchA(1, ":name".into(), 2),
+                },
+            );
+        });
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        server.uninterest("names");
+
+        assert!(server.shutdown_handles.is_empty());
+
+        // The attribute input remains open, so the probe can only
+        // complete once the interest dataflow has released its
+        // imported traces.
+        worker.step_while(|| !server.probe.done());
+
+        assert!(server.probe.done());
+    });
+}
+
+#[test]
+fn rule_semantics() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        let (e, n) = (1, 2);
+        server
+            .register(Register {
+                rules: vec![Rule {
+                    name: "distinct_names".to_string(),
+                    kind: RuleKind::Recursive,
+                    plan: Plan::Project(Project {
+                        variables: vec![n],
+                        plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
+                    }),
+                }],
+                publish: vec![],
+            })
+            .unwrap();
+
+        assert!(server
+            .set_rule_semantics("unknown", RuleSemantics::Set)
+            .is_err());
+        server
+            .set_rule_semantics("distinct_names", RuleSemantics::Set)
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("distinct_names", scope)
+                .unwrap()
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                })
+                .probe_with(&mut server.probe);
+        });
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":name".into(), String("Dipper".to_string())),
+                    TxData(1, 200, ":name".into(), String("Dipper".to_string())),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![String("Dipper".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn cached_rules() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        let (e, n) = (1, 2);
+        server
+            .register(Register {
+                rules: vec![
+                    Rule {
+                        name: "names".to_string(),
+                        kind: RuleKind::Cached,
+                        plan: Plan::Project(Project {
+                            variables: vec![n],
+                            plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
+                        }),
+                    },
+                    Rule {
+                        name: "greetings".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::NameExpr(vec![n], "names".to_string()),
+                    },
+                    Rule {
+                        name: "loop".to_string(),
+                        kind: RuleKind::Cached,
+                        plan: Plan::Join(Join {
+                            variables: vec![e],
+                            left_plan: Box::new(Plan::NameExpr(vec![e], "loop".to_string())),
+                            right_plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
+                        }),
+                    },
+                ],
+                publish: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("greetings", scope)
+                .unwrap()
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                })
+                .probe_with(&mut server.probe);
+
+            assert_eq!(
+                server.interest("loop", scope).unwrap_err().category,
+                ErrorKind::Unsupported
+            );
+        });
+
+        // The cached rule was implemented once, to be re-used. The
+        // relation of interest is shared as well, rather than
+        // implemented privately.
+        assert!(server.context.internal.arrangements.contains_key("names"));
+        assert!(server.implementations.contains_key("greetings"));
+        assert!(server.implementations.contains_key("names"));
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![String("Dipper".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
+
+#[test]
+fn shared_rules() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":name", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        let (e, n) = (1, 2);
+        server
+            .register(Register {
+                rules: vec![
+                    Rule {
+                        name: "names".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::Project(Project {
+                            variables: vec![n],
+                            plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
+                        }),
+                    },
+                    Rule {
+                        name: "greetings".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::NameExpr(vec![n], "names".to_string()),
+                    },
+                    Rule {
+                        name: "farewells".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::NameExpr(vec![n], "names".to_string()),
+                    },
+                ],
+                publish: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("names", scope)
+                .unwrap()
+                .probe_with(&mut server.probe);
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("greetings", scope)
+                .unwrap()
+                .probe_with(&mut server.probe);
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("farewells", scope)
+                .unwrap()
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                })
+                .probe_with(&mut server.probe);
+        });
+
+        // Both dependents re-use the implementation of names, which
+        // outlives the interest it was implemented for.
+        server.uninterest("names");
+        assert!(server.implementations.contains_key("names"));
+
+        server
+            .transact(
+                vec![TxData(1, 100, ":name".into(), String("Dipper".to_string()))],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![String("Dipper".to_string())], 1)
+        );
+        assert!(results.try_recv().is_err());
+
+        server.uninterest("greetings");
+        assert!(server.implementations.contains_key("names"));
+
+        server.uninterest("farewells");
+        assert!(server.implementations.is_empty());
+        assert!(server.context.internal.arrangements.is_empty());
+    });
+}
+
+#[test]
+fn hector_over_cached_relations() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .context
+                .internal
+                .create_attribute(":edge", AttributeConfig::tx_time(Raw), scope)
+                .unwrap();
+        });
+
+        let (a, b, c) = (1, 2, 3);
+        server
+            .register(Register {
+                rules: vec![
+                    Rule {
+                        name: "edges".to_string(),
+                        kind: RuleKind::Cached,
+                        plan: Plan::Project(Project {
+                            variables: vec![a, b],
+                            plan: Box::new(Plan::MatchA(a, ":edge".into(), b)),
+                        }),
+                    },
+                    Rule {
+                        name: "paths".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan: Plan::Hector(Hector {
+                            variables: vec![a, b, c],
+                            bindings: vec![
+                                Binding::relation("edges", vec![a, b]),
+                                Binding::attribute(b, ":edge", c),
+                            ],
+                        }),
+                    },
+                ],
+                publish: vec![],
+            })
+            .unwrap();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            server
+                .interest("paths", scope)
+                .unwrap()
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                })
+                .probe_with(&mut server.probe);
+        });
+
+        // The relation was implemented globally, to be indexed.
+        assert!(server.context.internal.arrangements.contains_key("edges"));
+
+        server
+            .transact(
+                vec![
+                    TxData(1, 100, ":edge".into(), Eid(200)),
+                    TxData(1, 200, ":edge".into(), Eid(300)),
+                ],
+                0,
+                0,
+            )
+            .unwrap();
+
+        server.advance_domain(None, 1).unwrap();
+
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(100), Eid(200), Eid(300)], 1)
+        );
+        assert!(results.try_recv().is_err());
+    });
+}
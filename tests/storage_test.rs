@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+use std::sync::mpsc::channel;
+
+use declarative_dataflow::plan::Join;
+use declarative_dataflow::server::Server;
+use declarative_dataflow::storage::Storage;
+use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, RuleKind, TxData, Value};
+use InputSemantics::{CardinalityOne, Raw};
+use Value::{Eid, Number, String};
+
+#[test]
+fn disk_storage() {
+    timely::execute_directly(move |worker| {
+        let mut server = Server::<u64, u64>::new(Default::default());
+        let (send_results, results) = channel();
+
+        let (e, n) = (1, 2);
+        let plan = Plan::Join(Join {
+            variables: vec![e],
+            left_plan: Box::new(Plan::MatchA(e, ":name".into(), n)),
+            right_plan: Box::new(Plan::MatchAV(e, ":age".into(), Number(12))),
+        });
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            // Every batch is spilled, no matter how small.
+            let on_disk = |input_semantics| AttributeConfig {
+                storage: Storage::Disk { resident: 0 },
+                ..AttributeConfig::tx_time(input_semantics)
+            };
+
+            server
+                .context
+                .internal
+                .create_attribute(":name", on_disk(Raw), scope)
+                .unwrap();
+
+            server
+                .context
+                .internal
+                .create_attribute(":age", on_disk(CardinalityOne), scope)
+                .unwrap();
+
+            server
+                .test_single(
+                    scope,
+                    Rule {
+                        name: "disk_storage".to_string(),
+                        kind: RuleKind::Recursive,
+                        plan,
+                    },
+                )
+                .inspect(move |x| {
+                    send_results.send((x.0.clone(), x.2)).unwrap();
+                });
+        });
+
+        // Many small transactions make for many batches to merge.
+        for (t, (eid, name, age)) in vec![
+            (100, "Dipper", 12),
+            (200, "Mabel", 12),
+            (300, "Soos", 22),
+            (400, "Wendy", 15),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            server
+                .transact(
+                    vec![
+                        TxData(1, eid, ":name".into(), String(name.to_string())),
+                        TxData(1, eid, ":age".into(), Number(age)),
+                    ],
+                    0,
+                    0,
+                )
+                .unwrap();
+
+            server.advance_domain(None, t as u64 + 1).unwrap();
+            worker.step_while(|| server.is_any_outdated());
+        }
+
+        let expected: HashSet<(Vec<Value>, isize)> = vec![
+            (vec![Eid(100), String("Dipper".to_string())], 1),
+            (vec![Eid(200), String("Mabel".to_string())], 1),
+        ]
+        .into_iter()
+        .collect();
+
+        let received: HashSet<(Vec<Value>, isize)> = results.try_iter().collect();
+        assert_eq!(received, expected);
+
+        // Superseding a spilled value retracts it as usual.
+        server
+            .transact(vec![TxData(1, 200, ":age".into(), Number(13))], 0, 0)
+            .unwrap();
+
+        server.advance_domain(None, 5).unwrap();
+        worker.step_while(|| server.is_any_outdated());
+
+        assert_eq!(
+            results.recv().unwrap(),
+            (vec![Eid(200), String("Mabel".to_string())], -1)
+        );
+        assert!(results.try_recv().is_err());
+
+        let index = server.context.internal.forward_index(":name").unwrap();
+        assert_eq!(index.statistics(), (4, 4));
+    });
+}
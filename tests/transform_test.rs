@@ -10,7 +10,7 @@ use declarative_dataflow::binding::Binding;
 use declarative_dataflow::plan::{Function, Implementable, Transform};
 use declarative_dataflow::server::Server;
 use declarative_dataflow::{Aid, Value};
-use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, TxData};
+use declarative_dataflow::{AttributeConfig, InputSemantics, Plan, Rule, RuleKind, TxData};
 use Value::{Eid, Instant};
 
 struct Case {
@@ -43,14 +43,14 @@ fn run_transform_cases() {
             Plan::Transform(Transform {
                 variables: vec![t],
                 result_variable: h,
-                plan: Box::new(Plan::MatchA(e, ":timestamp".to_string(), t)),
+                plan: Box::new(Plan::MatchA(e, ":timestamp".into(), t)),
                 function: Function::TRUNCATE,
                 constants,
             })
         },
         transactions: vec![vec![
-            TxData(1, 1, ":timestamp".to_string(), Instant(1_540_048_515_500)),
-            TxData(1, 2, ":timestamp".to_string(), Instant(1_540_048_515_616)),
+            TxData(1, 1, ":timestamp".into(), Instant(1_540_048_515_500)),
+            TxData(1, 2, ":timestamp".into(), Instant(1_540_048_515_616)),
         ]],
         expectations: vec![vec![
             (
@@ -98,6 +98,7 @@ fn run_transform_cases() {
                         scope,
                         Rule {
                             name: "hector".to_string(),
+                            kind: RuleKind::Recursive,
                             plan,
                         },
                     )